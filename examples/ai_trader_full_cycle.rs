@@ -93,7 +93,7 @@ async fn main() {
         }
 
         // Generate signals
-        let signals = signal_engine.generate_signals(symbol, &indicators, &prices);
+        let signals = signal_engine.generate_signal_episodes(symbol, &indicators, &prices);
 
         // Save signals to DB
         let mut signal_count = 0;
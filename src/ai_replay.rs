@@ -0,0 +1,1292 @@
+//! AI Trader Replay
+//!
+//! Backtests the AI trader over past dates. A simulated clock steps through
+//! the replay window; on each decision day the trader's market context is
+//! rebuilt from the data visible at that day's close, formatted into the live
+//! prompt and answered by a pluggable [`DecisionModel`] (Ollama, recorded
+//! responses or a rule-based stand-in). Decisions execute against an isolated
+//! in-memory portfolio - the paper account is never touched - and the run is
+//! reported as a regular `BacktestResult`.
+//!
+//! Differences from live trading:
+//! - Orders fill at the decision day's close, without execution costs
+//! - Signals are regenerated from stored indicators and carry no scorecard
+//!   track record; prediction accuracy is not tracked (both would leak later
+//!   outcomes into the prompt)
+//! - Stops and exit rules are checked on each day's bar after entry
+//! - Guardrails, when enforced, apply to buys only and skip the circuit
+//!   breaker and blocked hours
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::future::Future;
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::ai_trader::{
+    AiDecisionResponse, AiTrader, GuardrailChecks, MacroSignalSummary, MarketContext,
+    ParsedDecision, PortfolioSnapshot, PositionInfo, ProposedTrade, RecentTrade, SignalSummary,
+    SymbolContext, TradeResult, AI_TRADER_SYSTEM_PROMPT, BANKRUPTCY_THRESHOLD,
+};
+use crate::backtest::symbol_trade_stats;
+use crate::db::Database;
+use crate::macro_signals::{publication_lag_days, MARKET_SIGNAL_SYMBOL};
+use crate::metrics::{self, Benchmark};
+use crate::models::{
+    BacktestResult, BacktestTrade, DailyPrice, EquityPoint, ExecutionCosts, ExitRules,
+    MarketRegime, PositionSizing, RegimeRecord, Signal, Strategy, StrategyConditionType,
+    StrategyDirection, TechnicalIndicator, TradeDirection,
+};
+use crate::ollama::OllamaClient;
+use crate::regime::MARKET_REGIME_SYMBOL;
+use crate::signal_lifecycle::{apply_lifecycle, SignalLifecycleConfig};
+use crate::signals::configured_signal_engine;
+use crate::stops::{FixedStops, StopTracker};
+use crate::volatility::{VolatilityRegime, VOL_REGIME_INDICATOR};
+
+/// Calendar days a signal stays in the context after its episode ends
+pub const DEFAULT_SIGNAL_LOOKBACK_DAYS: i64 = 5;
+
+/// Response of a model with nothing to do
+const NO_DECISIONS: &str = r#"{"decisions":[]}"#;
+
+/// Window and cadence of a replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayConfig {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    /// Symbols in the context each day (the live trader's watchlist)
+    pub symbols: Vec<String>,
+    /// Trading days between decisions (1 = every day)
+    pub step_days: usize,
+    /// Calendar days a signal stays in the context after its episode ends
+    pub signal_lookback_days: i64,
+    /// Check buys against the trading mode's guardrails
+    pub enforce_guardrails: bool,
+}
+
+impl ReplayConfig {
+    pub fn new(start: NaiveDate, end: NaiveDate, symbols: Vec<String>) -> Self {
+        Self {
+            start,
+            end,
+            symbols,
+            step_days: 1,
+            signal_lookback_days: DEFAULT_SIGNAL_LOOKBACK_DAYS,
+            enforce_guardrails: true,
+        }
+    }
+}
+
+/// History of one replayed symbol
+#[derive(Debug, Clone, Default)]
+pub struct ReplaySeries {
+    pub symbol: String,
+    /// Sorted by date
+    pub prices: Vec<DailyPrice>,
+    /// Sorted by date
+    pub indicators: Vec<TechnicalIndicator>,
+    /// Raw signals regenerated from the indicators, sorted by date
+    pub signals: Vec<Signal>,
+    /// Sorted by date
+    pub regimes: Vec<RegimeRecord>,
+}
+
+impl ReplaySeries {
+    /// Signal episodes built from the signals and bars up to `date` only, so
+    /// an episode's strength and end never include later bars
+    fn signals_as_of(
+        &self,
+        date: NaiveDate,
+        lifecycle: Option<&SignalLifecycleConfig>,
+    ) -> Vec<Signal> {
+        let raw = self.signals[..self.signals.partition_point(|s| s.timestamp <= date)].to_vec();
+        match lifecycle {
+            Some(lifecycle) => {
+                let bars = self.prices.partition_point(|p| p.date <= date);
+                let calendar: Vec<NaiveDate> = self.prices[..bars].iter().map(|p| p.date).collect();
+                apply_lifecycle(raw, &calendar, lifecycle)
+            }
+            None => raw,
+        }
+    }
+}
+
+/// Everything a replay reads, loaded up front so the run itself holds no
+/// database connection
+#[derive(Debug, Clone, Default)]
+pub struct ReplayData {
+    pub series: Vec<ReplaySeries>,
+    /// Broad market regimes, sorted by date
+    pub market_regimes: Vec<RegimeRecord>,
+    /// Market-wide macro signals, sorted by date
+    pub macro_signals: Vec<Signal>,
+    /// Episode merging applied to each series' signals as of the replay date
+    pub lifecycle: Option<SignalLifecycleConfig>,
+    pub benchmark: Option<Benchmark>,
+    /// Annual risk-free rate over the window (%)
+    pub risk_free_rate_percent: f64,
+}
+
+impl ReplayData {
+    /// Load the replay's symbols, regimes, macro signals and benchmark
+    pub fn load(db: &Database, config: &ReplayConfig, benchmark_symbol: &str) -> Result<Self> {
+        let engine = configured_signal_engine(db)?;
+        let mut series = Vec::new();
+        for symbol in &config.symbols {
+            let mut prices = db.get_prices(symbol)?;
+            prices.sort_by_key(|p| p.date);
+            let indicators = db.get_all_indicators(symbol)?;
+            let mut signals = engine.generate_signals(symbol, &indicators, &prices);
+            signals.sort_by_key(|s| s.timestamp);
+            series.push(ReplaySeries {
+                symbol: symbol.clone(),
+                prices,
+                indicators,
+                signals,
+                regimes: db.get_regime_history(symbol)?,
+            });
+        }
+
+        let mut macro_signals = db.get_signals(MARKET_SIGNAL_SYMBOL, false)?;
+        macro_signals.sort_by_key(|s| s.timestamp);
+
+        Ok(Self {
+            series,
+            market_regimes: db.get_regime_history(MARKET_REGIME_SYMBOL)?,
+            macro_signals,
+            lifecycle: engine.config().lifecycle.clone(),
+            benchmark: metrics::load_benchmark(db, benchmark_symbol)?,
+            risk_free_rate_percent: metrics::risk_free_rate_percent(db, config.start, config.end)?,
+        })
+    }
+}
+
+// ============================================================================
+// Decision Models
+// ============================================================================
+
+/// Source of a replay's decisions: the raw reply to each day's prompt, parsed
+/// exactly like a live model response
+pub trait DecisionModel {
+    /// Name recorded on the backtest
+    fn name(&self) -> String;
+
+    /// Reply to the prompt built for `date`
+    fn respond(
+        &mut self,
+        date: NaiveDate,
+        prompt: &str,
+        context: &MarketContext,
+    ) -> impl Future<Output = Result<String>> + Send;
+}
+
+/// Queries Ollama with the live system prompt, trying each model in turn
+#[derive(Debug, Clone)]
+pub struct OllamaDecisionModel {
+    client: OllamaClient,
+    models: Vec<String>,
+}
+
+impl OllamaDecisionModel {
+    pub fn new(models: Vec<String>) -> Self {
+        Self {
+            client: OllamaClient::new(),
+            models,
+        }
+    }
+}
+
+impl DecisionModel for OllamaDecisionModel {
+    fn name(&self) -> String {
+        self.models
+            .first()
+            .cloned()
+            .unwrap_or_else(|| "ollama".to_string())
+    }
+
+    async fn respond(
+        &mut self,
+        date: NaiveDate,
+        prompt: &str,
+        _context: &MarketContext,
+    ) -> Result<String> {
+        for model in &self.models {
+            match self
+                .client
+                .query_with_thinking(prompt, Some(AI_TRADER_SYSTEM_PROMPT), Some(model))
+                .await
+            {
+                Ok((response, _)) => return Ok(response),
+                Err(e) => eprintln!("[AI Replay] {} on {}: {}, trying next", model, date, e),
+            }
+        }
+        anyhow::bail!("All models failed to respond for {}", date)
+    }
+}
+
+/// Replays responses recorded by date; days without one make no decisions
+#[derive(Debug, Clone, Default)]
+pub struct RecordedModel {
+    pub name: String,
+    pub responses: HashMap<NaiveDate, String>,
+}
+
+impl RecordedModel {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            responses: HashMap::new(),
+        }
+    }
+
+    /// Add the response given on `date`
+    pub fn with_response(mut self, date: NaiveDate, response: &str) -> Self {
+        self.responses.insert(date, response.to_string());
+        self
+    }
+}
+
+impl DecisionModel for RecordedModel {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    async fn respond(
+        &mut self,
+        date: NaiveDate,
+        _prompt: &str,
+        _context: &MarketContext,
+    ) -> Result<String> {
+        Ok(self
+            .responses
+            .get(&date)
+            .cloned()
+            .unwrap_or_else(|| NO_DECISIONS.to_string()))
+    }
+}
+
+/// Deterministic stand-in for an LLM: buys symbols it doesn't hold on
+/// bullish confluence and sells held symbols on bearish confluence
+#[derive(Debug, Clone)]
+pub struct RuleBasedModel {
+    /// Percent of cash per buy
+    pub buy_percent: f64,
+    /// Weakest confluence acted on
+    pub min_strength: f64,
+}
+
+impl Default for RuleBasedModel {
+    fn default() -> Self {
+        Self {
+            buy_percent: 10.0,
+            min_strength: 0.0,
+        }
+    }
+}
+
+impl RuleBasedModel {
+    fn decide(&self, context: &MarketContext) -> Vec<ParsedDecision> {
+        let held = |symbol: &str| {
+            context
+                .portfolio
+                .positions
+                .iter()
+                .any(|p| p.symbol == symbol)
+        };
+        context
+            .symbols_data
+            .iter()
+            .filter_map(|s| {
+                let confluence = s
+                    .confluence
+                    .as_ref()
+                    .filter(|c| c.strength >= self.min_strength)?;
+                let (action, quantity_percent) = match confluence.direction.as_str() {
+                    "Bullish" if !held(&s.symbol) => ("BUY", self.buy_percent),
+                    "Bearish" if held(&s.symbol) => ("SELL", 100.0),
+                    _ => return None,
+                };
+                Some(ParsedDecision {
+                    action: action.to_string(),
+                    symbol: s.symbol.clone(),
+                    quantity_percent,
+                    confidence: confluence.strength,
+                    reasoning: format!(
+                        "{} confluence of {} indicators",
+                        confluence.direction, confluence.agreeing_indicators
+                    ),
+                    prediction: None,
+                })
+            })
+            .collect()
+    }
+}
+
+impl DecisionModel for RuleBasedModel {
+    fn name(&self) -> String {
+        "rule-based".to_string()
+    }
+
+    async fn respond(
+        &mut self,
+        _date: NaiveDate,
+        _prompt: &str,
+        context: &MarketContext,
+    ) -> Result<String> {
+        let response = AiDecisionResponse {
+            decisions: self.decide(context),
+            market_outlook: None,
+            session_notes: None,
+        };
+        Ok(serde_json::to_string(&response)?)
+    }
+}
+
+// ============================================================================
+// Replay
+// ============================================================================
+
+/// One model decision and what became of it
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayDecision {
+    pub date: NaiveDate,
+    pub action: String,
+    pub symbol: String,
+    pub quantity_percent: f64,
+    pub confidence: f64,
+    pub reasoning: String,
+    /// Close the decision was priced at
+    pub price: f64,
+    /// Shares traded (0 for holds and rejected decisions)
+    pub shares: f64,
+    /// Why the decision was not executed
+    pub rejection: Option<String>,
+}
+
+/// Outcome of a replay
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayResult {
+    pub backtest: BacktestResult,
+    pub decisions: Vec<ReplayDecision>,
+    /// Decision days whose response failed or could not be parsed
+    pub failed_responses: usize,
+}
+
+/// An open position of the simulated portfolio
+struct SimPosition {
+    quantity: f64,
+    entry_price: f64,
+    entry_date: NaiveDate,
+    entry_reason: String,
+    tracker: StopTracker,
+}
+
+/// The replay's isolated portfolio, keyed by series index
+struct SimPortfolio {
+    cash: f64,
+    positions: BTreeMap<usize, SimPosition>,
+    trades: Vec<BacktestTrade>,
+    /// Every fill, oldest first
+    fills: Vec<RecentTrade>,
+}
+
+impl SimPortfolio {
+    fn new(cash: f64) -> Self {
+        Self {
+            cash,
+            positions: BTreeMap::new(),
+            trades: Vec::new(),
+            fills: Vec::new(),
+        }
+    }
+
+    fn fills_on(&self, date: NaiveDate) -> usize {
+        let date = date.to_string();
+        self.fills.iter().filter(|f| f.timestamp == date).count()
+    }
+
+    /// Buy shares, averaging into an existing position. As in the paper
+    /// account, adding restarts the stop tracker from the new average entry.
+    fn buy(
+        &mut self,
+        index: usize,
+        symbol: &str,
+        quantity: f64,
+        price: f64,
+        date: NaiveDate,
+        reason: &str,
+    ) {
+        self.cash -= quantity * price;
+        match self.positions.get_mut(&index) {
+            Some(pos) => {
+                let total = pos.quantity + quantity;
+                pos.entry_price = (pos.quantity * pos.entry_price + quantity * price) / total;
+                pos.quantity = total;
+                pos.tracker =
+                    StopTracker::new(TradeDirection::Long, pos.entry_price, pos.entry_date);
+            }
+            None => {
+                self.positions.insert(
+                    index,
+                    SimPosition {
+                        quantity,
+                        entry_price: price,
+                        entry_date: date,
+                        entry_reason: reason.to_string(),
+                        tracker: StopTracker::new(TradeDirection::Long, price, date),
+                    },
+                );
+            }
+        }
+        self.fills.push(RecentTrade {
+            symbol: symbol.to_string(),
+            action: "BUY".to_string(),
+            quantity,
+            price,
+            pnl: None,
+            timestamp: date.to_string(),
+        });
+    }
+
+    /// Sell up to `quantity` shares, closing a trade for the shares sold
+    fn sell(
+        &mut self,
+        index: usize,
+        symbol: &str,
+        quantity: f64,
+        price: f64,
+        date: NaiveDate,
+        reason: &str,
+    ) {
+        let Some(pos) = self.positions.get_mut(&index) else {
+            return;
+        };
+        let quantity = quantity.min(pos.quantity);
+        let pnl = (price - pos.entry_price) * quantity;
+        self.cash += quantity * price;
+        self.trades.push(BacktestTrade {
+            id: 0,
+            backtest_id: 0,
+            symbol: symbol.to_string(),
+            direction: TradeDirection::Long,
+            entry_date: pos.entry_date,
+            entry_price: pos.entry_price,
+            exit_date: Some(date),
+            exit_price: Some(price),
+            shares: quantity,
+            entry_reason: pos.entry_reason.clone(),
+            exit_reason: Some(reason.to_string()),
+            profit_loss: Some(pnl),
+            profit_loss_percent: Some((price / pos.entry_price - 1.0) * 100.0),
+        });
+        self.fills.push(RecentTrade {
+            symbol: symbol.to_string(),
+            action: "SELL".to_string(),
+            quantity,
+            price,
+            pnl: Some(pnl),
+            timestamp: date.to_string(),
+        });
+
+        pos.quantity -= quantity;
+        if pos.quantity <= 0.0001 {
+            self.positions.remove(&index);
+        }
+    }
+}
+
+/// What of a series is visible as of the replay clock
+#[derive(Default)]
+struct SeriesView {
+    /// Bars on or before the clock
+    bars: usize,
+    next_indicator: usize,
+    /// Latest value of each indicator
+    indicators: HashMap<String, f64>,
+}
+
+impl SeriesView {
+    fn advance(&mut self, series: &ReplaySeries, date: NaiveDate) {
+        self.bars = series.prices.partition_point(|p| p.date <= date);
+        while let Some(ind) = series
+            .indicators
+            .get(self.next_indicator)
+            .filter(|i| i.date <= date)
+        {
+            self.indicators
+                .insert(ind.indicator_name.clone(), ind.value);
+            self.next_indicator += 1;
+        }
+    }
+
+    fn close(&self, series: &ReplaySeries) -> Option<f64> {
+        self.bars.checked_sub(1).map(|i| series.prices[i].close)
+    }
+}
+
+/// Whether a signal had fired by `date` and was live within the lookback
+fn visible(signal: &Signal, date: NaiveDate, lookback_days: i64) -> bool {
+    let last_live = signal.end_date.unwrap_or(signal.timestamp);
+    signal.timestamp <= date && (date - last_live).num_days() < lookback_days
+}
+
+/// Whether a macro signal was visible by `date`, counting from when its
+/// series was published rather than the observation date
+fn macro_visible(signal: &Signal, date: NaiveDate, lookback_days: i64) -> bool {
+    let lag = chrono::Duration::days(publication_lag_days(&signal.triggered_by));
+    visible(signal, date - lag, lookback_days)
+}
+
+/// Latest regime on or before `date`
+fn regime_on(records: &[RegimeRecord], date: NaiveDate) -> Option<MarketRegime> {
+    let after = records.partition_point(|r| r.date <= date);
+    after.checked_sub(1).map(|i| records[i].regime)
+}
+
+impl AiTrader {
+    /// Replay the trader over `config`'s window with decisions from `model`
+    pub async fn replay<M: DecisionModel>(
+        &self,
+        data: &ReplayData,
+        config: &ReplayConfig,
+        model: &mut M,
+    ) -> Result<ReplayResult> {
+        let calendar: Vec<NaiveDate> = data
+            .series
+            .iter()
+            .flat_map(|s| s.prices.iter().map(|p| p.date))
+            .filter(|date| *date >= config.start && *date <= config.end)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        let (Some(&first_day), Some(&last_day)) = (calendar.first(), calendar.last()) else {
+            anyhow::bail!("No prices between {} and {}", config.start, config.end);
+        };
+
+        let initial = self.config.starting_capital;
+        let fixed = FixedStops {
+            stop_loss_percent: Some(self.config.stop_loss_percent).filter(|pct| *pct > 0.0),
+            take_profit_percent: Some(self.config.take_profit_percent).filter(|pct| *pct > 0.0),
+        };
+        let mut views: Vec<SeriesView> =
+            data.series.iter().map(|_| SeriesView::default()).collect();
+        let first_closes: Vec<Option<f64>> = data
+            .series
+            .iter()
+            .map(|s| {
+                let after = s.prices.partition_point(|p| p.date <= first_day);
+                after.checked_sub(1).map(|i| s.prices[i].close)
+            })
+            .collect();
+        let hold_share = initial / data.series.len().max(1) as f64;
+
+        let mut book = SimPortfolio::new(initial);
+        let mut decisions = Vec::new();
+        let mut failed_responses = 0;
+        let mut equity_curve: Vec<EquityPoint> = Vec::new();
+
+        for (day, &date) in calendar.iter().enumerate() {
+            for (view, series) in views.iter_mut().zip(&data.series) {
+                view.advance(series, date);
+            }
+
+            // Stops and exit rules on today's bar
+            let mut exits = Vec::new();
+            for (&index, pos) in book.positions.iter_mut() {
+                let (series, view) = (&data.series[index], &views[index]);
+                let bars = &series.prices[..view.bars];
+                if bars.last().is_none_or(|bar| bar.date != date) || date <= pos.entry_date {
+                    continue;
+                }
+                let atr = view.indicators.get("ATR_14").copied();
+                for exit in pos.tracker.check(&self.config.exit_rules, fixed, bars, atr) {
+                    exits.push((index, exit));
+                }
+            }
+            for (index, exit) in exits {
+                let Some(pos) = book.positions.get_mut(&index) else {
+                    continue;
+                };
+                let shares = if exit.closes_position() {
+                    pos.quantity
+                } else {
+                    (pos.quantity * exit.fraction).floor()
+                };
+                if shares > 0.0 {
+                    if exit.reason == "scale_out" {
+                        pos.tracker.record_scale_out();
+                    }
+                    book.sell(
+                        index,
+                        &data.series[index].symbol,
+                        shares,
+                        exit.price,
+                        date,
+                        &exit.reason,
+                    );
+                }
+            }
+
+            // Decide at the close
+            if day % config.step_days.max(1) == 0 {
+                let context = self.replay_context(data, &views, &book, date, config);
+                let prompt = self.format_context_prompt(&context);
+                match model
+                    .respond(date, &prompt, &context)
+                    .await
+                    .and_then(|response| self.parse_ai_response(&response))
+                {
+                    Ok(parsed) => {
+                        for decision in parsed.decisions {
+                            decisions.push(self.replay_decision(
+                                &mut book,
+                                data,
+                                &context,
+                                decision,
+                                date,
+                                config.enforce_guardrails,
+                            ));
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("[AI Replay] No decisions on {}: {}", date, e);
+                        failed_responses += 1;
+                    }
+                }
+            }
+
+            let values: Vec<f64> = book
+                .positions
+                .iter()
+                .map(|(&index, pos)| {
+                    pos.quantity
+                        * views[index]
+                            .close(&data.series[index])
+                            .unwrap_or(pos.entry_price)
+                })
+                .collect();
+            let equity = book.cash + values.iter().sum::<f64>();
+            equity_curve.push(EquityPoint {
+                date,
+                equity,
+                cash: book.cash,
+                exposure: values.iter().sum(),
+                open_positions: book.positions.len(),
+                drawdown: 0.0,
+                buy_and_hold: Some(
+                    views
+                        .iter()
+                        .zip(&data.series)
+                        .zip(&first_closes)
+                        .map(
+                            |((view, series), first)| match (view.close(series), first) {
+                                (Some(close), Some(first)) if *first > 0.0 => {
+                                    hold_share * close / first
+                                }
+                                _ => hold_share,
+                            },
+                        )
+                        .sum(),
+                ),
+            });
+
+            if equity < BANKRUPTCY_THRESHOLD {
+                println!(
+                    "[AI Replay] Portfolio bankrupt on {} (value: ${:.2})",
+                    date, equity
+                );
+                break;
+            }
+        }
+
+        // Close remaining positions at their last close
+        let end_date = equity_curve.last().map_or(last_day, |p| p.date);
+        for index in book.positions.keys().copied().collect::<Vec<_>>() {
+            let series = &data.series[index];
+            if let Some(price) = views[index].close(series) {
+                book.sell(
+                    index,
+                    &series.symbol,
+                    f64::MAX,
+                    price,
+                    end_date,
+                    "end_of_data",
+                );
+            }
+        }
+        metrics::mark_drawdowns(&mut equity_curve, initial);
+
+        let SimPortfolio { cash, trades, .. } = book;
+        let metrics = metrics::performance_metrics(
+            &trades,
+            &equity_curve,
+            initial,
+            ExecutionCosts::default(),
+            data.risk_free_rate_percent,
+            data.benchmark.as_ref(),
+        );
+
+        Ok(ReplayResult {
+            backtest: BacktestResult {
+                id: 0,
+                strategy_id: 0,
+                strategy_name: format!("AI replay ({})", model.name()),
+                symbol: config.symbols.join(","),
+                start_date: first_day,
+                end_date,
+                initial_capital: initial,
+                final_capital: cash,
+                metrics,
+                symbol_stats: symbol_trade_stats(&trades),
+                trades,
+                equity_curve,
+                created_at: String::new(),
+            },
+            decisions,
+            failed_responses,
+        })
+    }
+
+    /// Market context as of `date`'s close, from data visible by then
+    fn replay_context(
+        &self,
+        data: &ReplayData,
+        views: &[SeriesView],
+        book: &SimPortfolio,
+        date: NaiveDate,
+        config: &ReplayConfig,
+    ) -> MarketContext {
+        let lookback = config.signal_lookback_days;
+        let symbols_data: Vec<SymbolContext> = data
+            .series
+            .iter()
+            .zip(views)
+            .filter_map(|(series, view)| {
+                let signals = series
+                    .signals_as_of(date, data.lifecycle.as_ref())
+                    .iter()
+                    .rev()
+                    .filter(|s| visible(s, date, lookback))
+                    .take(5)
+                    .map(|s| SignalSummary {
+                        signal_type: format!("{:?}", s.signal_type),
+                        direction: format!("{:?}", s.direction),
+                        strength: s.strength,
+                        hit_rate: None,
+                        avg_return_5d: None,
+                        sample_size: 0,
+                    })
+                    .collect();
+                self.build_symbol_context(
+                    &self.signal_engine,
+                    &series.symbol,
+                    &series.prices[..view.bars],
+                    view.indicators.clone(),
+                    signals,
+                )
+                .ok()
+            })
+            .collect();
+
+        let positions: Vec<PositionInfo> = book
+            .positions
+            .iter()
+            .map(|(&index, pos)| {
+                let current_price = views[index]
+                    .close(&data.series[index])
+                    .unwrap_or(pos.entry_price);
+                let cost_basis = pos.quantity * pos.entry_price;
+                let unrealized_pnl = pos.quantity * current_price - cost_basis;
+                PositionInfo {
+                    symbol: data.series[index].symbol.clone(),
+                    quantity: pos.quantity,
+                    entry_price: pos.entry_price,
+                    current_price,
+                    unrealized_pnl,
+                    unrealized_pnl_percent: if cost_basis > 0.0 {
+                        unrealized_pnl / cost_basis * 100.0
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect();
+        let total_value = book.cash
+            + positions
+                .iter()
+                .map(|p| p.quantity * p.current_price)
+                .sum::<f64>();
+        let starting_capital = self.config.starting_capital;
+
+        let macro_signals = data
+            .macro_signals
+            .iter()
+            .rev()
+            .filter(|s| macro_visible(s, date, lookback))
+            .take(5)
+            .map(|s| MacroSignalSummary {
+                signal_type: s.signal_type.as_str().to_string(),
+                direction: s.direction.as_str().to_string(),
+                date: s.timestamp.to_string(),
+                source: s.triggered_by.clone(),
+                value: s.trigger_value,
+            })
+            .collect();
+
+        MarketContext {
+            timestamp: date.to_string(),
+            portfolio: PortfolioSnapshot {
+                cash: book.cash,
+                positions,
+                total_value,
+                total_pnl: total_value - starting_capital,
+                total_pnl_percent: (total_value - starting_capital) / starting_capital * 100.0,
+            },
+            symbols_data,
+            macro_signals,
+            recent_trades: book.fills.iter().rev().take(10).cloned().collect(),
+            prediction_accuracy: 0.0,
+            constraints: self.trading_constraints(),
+        }
+    }
+
+    /// Execute one decision against the simulated portfolio at the close
+    fn replay_decision(
+        &self,
+        book: &mut SimPortfolio,
+        data: &ReplayData,
+        context: &MarketContext,
+        decision: ParsedDecision,
+        date: NaiveDate,
+        enforce_guardrails: bool,
+    ) -> ReplayDecision {
+        let mut record = ReplayDecision {
+            date,
+            action: decision.action.to_uppercase(),
+            symbol: decision.symbol.clone(),
+            quantity_percent: decision.quantity_percent,
+            confidence: decision.confidence,
+            reasoning: decision.reasoning.clone(),
+            price: 0.0,
+            shares: 0.0,
+            rejection: None,
+        };
+        let found = data
+            .series
+            .iter()
+            .position(|s| s.symbol == decision.symbol)
+            .and_then(|index| {
+                let symbol = context
+                    .symbols_data
+                    .iter()
+                    .find(|s| s.symbol == decision.symbol)?;
+                Some((index, symbol))
+            });
+        let Some((index, symbol)) = found.filter(|(_, s)| s.current_price > 0.0) else {
+            record.rejection = Some(format!("No price for {}", decision.symbol));
+            return record;
+        };
+        // The last close may be stale; only fill on a day the symbol traded
+        if data.series[index].prices.binary_search_by_key(&date, |p| p.date).is_err() {
+            record.rejection = Some(format!("No {} bar on {}", decision.symbol, date));
+            return record;
+        }
+        let price = symbol.current_price;
+        record.price = price;
+        let reason = format!(
+            "AI: {}",
+            decision.reasoning.chars().take(200).collect::<String>()
+        );
+
+        match record.action.as_str() {
+            "BUY" => {
+                let total_value = context.portfolio.total_value;
+                let trade_returns: Vec<f64> = book
+                    .trades
+                    .iter()
+                    .filter_map(|t| t.profit_loss_percent)
+                    .collect();
+                let atr = symbol.indicators.get("ATR_14").copied();
+                let spend = self.sized_spend(
+                    &decision,
+                    book.cash,
+                    total_value,
+                    price,
+                    atr,
+                    &trade_returns,
+                );
+                let quantity = (spend.min(book.cash) / price).floor();
+                if quantity < 1.0 {
+                    record.rejection = Some("Sized below one share".to_string());
+                    return record;
+                }
+
+                if enforce_guardrails {
+                    let held = book
+                        .positions
+                        .get(&index)
+                        .map_or(0.0, |pos| pos.quantity * price);
+                    let proposed = ProposedTrade {
+                        action: record.action.clone(),
+                        symbol: decision.symbol.clone(),
+                        quantity,
+                        quantity_percent: (held + quantity * price) / total_value * 100.0,
+                        estimated_value: quantity * price,
+                        confidence: decision.confidence,
+                        reasoning: decision.reasoning.clone(),
+                    };
+                    let checks = GuardrailChecks {
+                        has_confluence: symbol
+                            .confluence
+                            .as_ref()
+                            .is_some_and(|c| c.direction == "Bullish"),
+                        volatility_regime: symbol
+                            .indicators
+                            .get(VOL_REGIME_INDICATOR)
+                            .copied()
+                            .and_then(VolatilityRegime::from_code),
+                        regimes: [
+                            (decision.symbol.as_str(), &data.series[index].regimes),
+                            (MARKET_REGIME_SYMBOL, &data.market_regimes),
+                        ]
+                        .into_iter()
+                        .filter_map(|(subject, records)| {
+                            Some((subject.to_string(), regime_on(records, date)?))
+                        })
+                        .collect(),
+                        trades_today: book.fills_on(date),
+                        hour: None,
+                    };
+                    if let Some(TradeResult::Rejected { reason, .. }) =
+                        self.guardrail_rejection(&proposed, &checks)
+                    {
+                        record.rejection = Some(reason);
+                        return record;
+                    }
+                }
+
+                book.buy(index, &decision.symbol, quantity, price, date, &reason);
+                record.shares = quantity;
+            }
+            "SELL" => {
+                let held = book.positions.get(&index).map_or(0.0, |pos| pos.quantity);
+                let quantity = (held * decision.quantity_percent / 100.0).floor();
+                if quantity < 1.0 {
+                    record.rejection = Some(format!("No {} shares to sell", decision.symbol));
+                    return record;
+                }
+                book.sell(index, &decision.symbol, quantity, price, date, &reason);
+                record.shares = quantity;
+            }
+            "HOLD" => {}
+            other => record.rejection = Some(format!("Unknown action: {}", other)),
+        }
+        record
+    }
+}
+
+/// Strategy row that replay backtests are stored under
+pub const REPLAY_STRATEGY_NAME: &str = "AI replay";
+
+/// Save a replay's backtest like any other run, filed under the
+/// `REPLAY_STRATEGY_NAME` strategy (created on first use). Sets its ids.
+pub fn save_replay_backtest(db: &Database, backtest: &mut BacktestResult) -> Result<i64> {
+    backtest.strategy_id = match db.get_strategy(REPLAY_STRATEGY_NAME)? {
+        Some(strategy) => strategy.id,
+        None => db.save_strategy(&Strategy {
+            id: 0,
+            name: REPLAY_STRATEGY_NAME.to_string(),
+            description: Some("AI trader replays; the model trades, not these rules".to_string()),
+            entry_condition: StrategyConditionType::ConfluenceBullish,
+            entry_threshold: 0.0,
+            exit_condition: StrategyConditionType::ConfluenceBearish,
+            exit_threshold: 0.0,
+            stop_loss_percent: None,
+            take_profit_percent: None,
+            position_size_percent: 100.0,
+            entry_formula: None,
+            exit_formula: None,
+            rs_benchmark: None,
+            allowed_regimes: vec![],
+            entry_rule: None,
+            exit_rule: None,
+            direction: StrategyDirection::LongOnly,
+            exit_rules: ExitRules::default(),
+            position_sizing: PositionSizing::default(),
+            created_at: String::new(),
+        })?,
+    };
+    backtest.id = db.save_backtest_result(backtest)?;
+    Ok(backtest.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai_trader::TradingMode;
+    use crate::models::{AiTraderConfig, SignalDirection, SignalStatus, SignalType};
+
+    fn series(closes: &[f64]) -> ReplayData {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let prices = closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| DailyPrice {
+                symbol: "TEST".to_string(),
+                date: start + chrono::Duration::days(i as i64),
+                open: close,
+                high: close + 0.5,
+                low: close - 0.5,
+                close,
+                volume: 1_000,
+                source: "test".to_string(),
+            })
+            .collect();
+        ReplayData {
+            series: vec![ReplaySeries {
+                symbol: "TEST".to_string(),
+                prices,
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn day(n: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, n).unwrap()
+    }
+
+    fn signal(date: NaiveDate, kind: SignalType, strength: f64, triggered_by: &str) -> Signal {
+        Signal {
+            id: 0,
+            symbol: "TEST".to_string(),
+            signal_type: kind,
+            direction: SignalDirection::Bullish,
+            strength,
+            price_at_signal: 100.0,
+            triggered_by: triggered_by.to_string(),
+            trigger_value: 0.0,
+            timestamp: date,
+            created_at: String::new(),
+            acknowledged: false,
+            status: SignalStatus::New,
+            end_date: None,
+        }
+    }
+
+    /// Context the model would see at `date`'s close with an empty book
+    fn context_on(trader: &AiTrader, data: &ReplayData, date: NaiveDate) -> MarketContext {
+        let config = ReplayConfig::new(day(1), date, vec!["TEST".to_string()]);
+        let views: Vec<SeriesView> = data
+            .series
+            .iter()
+            .map(|series| {
+                let mut view = SeriesView::default();
+                view.advance(series, date);
+                view
+            })
+            .collect();
+        trader.replay_context(data, &views, &SimPortfolio::new(1_000_000.0), date, &config)
+    }
+
+    fn decision(action: &str, percent: f64) -> String {
+        format!(
+            r#"{{"decisions":[{{"action":"{}","symbol":"TEST","quantity_percent":{},"confidence":0.8,"reasoning":"test"}}]}}"#,
+            action, percent
+        )
+    }
+
+    #[tokio::test]
+    async fn test_replay_executes_recorded_decisions() {
+        let trader = AiTrader::new(AiTraderConfig::default());
+        let data = series(&[100.0, 100.0, 102.0, 104.0, 106.0, 108.0]);
+        let mut config = ReplayConfig::new(day(1), day(6), vec!["TEST".to_string()]);
+        config.enforce_guardrails = false;
+        let mut model = RecordedModel::new("script")
+            .with_response(day(2), &decision("BUY", 5.0))
+            .with_response(day(3), "not json")
+            .with_response(day(5), &decision("SELL", 100.0));
+
+        let result = trader.replay(&data, &config, &mut model).await.unwrap();
+        let bt = &result.backtest;
+        assert_eq!(bt.strategy_name, "AI replay (script)");
+        assert_eq!(bt.equity_curve.len(), 6);
+        assert_eq!(result.failed_responses, 1);
+        assert_eq!(result.decisions.len(), 2);
+
+        // 5% of $1M at $100, sold at $106
+        assert_eq!(bt.trades.len(), 1);
+        let trade = &bt.trades[0];
+        assert_eq!(trade.shares, 500.0);
+        assert_eq!(trade.entry_date, day(2));
+        assert_eq!(trade.exit_date, Some(day(5)));
+        assert!((trade.profit_loss.unwrap() - 3_000.0).abs() < 1e-6);
+        assert!((bt.final_capital - 1_003_000.0).abs() < 1e-6);
+        assert_eq!(bt.equity_curve[5].open_positions, 0);
+
+        // Stored like any other backtest, reusing one strategy row
+        let db = Database::open_in_memory().unwrap();
+        db.init_schema().unwrap();
+        let mut bt = result.backtest.clone();
+        let id = save_replay_backtest(&db, &mut bt).unwrap();
+        let saved = db.get_backtest_detail(id).unwrap().unwrap();
+        assert_eq!(saved.strategy_name, bt.strategy_name);
+        assert_eq!(saved.trades.len(), 1);
+        let mut again = result.backtest.clone();
+        save_replay_backtest(&db, &mut again).unwrap();
+        assert_eq!(again.strategy_id, bt.strategy_id);
+        assert_ne!(again.id, bt.id);
+    }
+
+    #[tokio::test]
+    async fn test_replay_guardrails_and_stops() {
+        let trader = AiTrader::new(AiTraderConfig::default());
+        let data = series(&[100.0, 100.0, 99.0, 90.0, 91.0]);
+        let mut model = RecordedModel::new("script").with_response(day(2), &decision("BUY", 5.0));
+
+        // Normal mode wants confluence behind every buy
+        let config = ReplayConfig::new(day(1), day(5), vec!["TEST".to_string()]);
+        let result = trader.replay(&data, &config, &mut model).await.unwrap();
+        assert!(result.backtest.trades.is_empty());
+        assert_eq!(
+            result.decisions[0].rejection.as_deref(),
+            Some("Trade requires confluence signal support")
+        );
+
+        // Without guardrails the position is stopped out 5% down
+        let config = ReplayConfig {
+            enforce_guardrails: false,
+            ..config
+        };
+        let result = trader.replay(&data, &config, &mut model).await.unwrap();
+        let trade = &result.backtest.trades[0];
+        assert_eq!(trade.exit_reason.as_deref(), Some("stop_loss"));
+        assert_eq!(trade.exit_date, Some(day(4)));
+        assert!(trade.profit_loss.unwrap() < 0.0);
+    }
+
+    #[test]
+    fn test_macro_signals_wait_for_publication() {
+        let signal = signal(day(1), SignalType::SahmRuleTriggered, 0.6, "UNRATE");
+        let released = day(1) + chrono::Duration::days(37);
+        assert!(visible(&signal, day(15), 30));
+        assert!(!macro_visible(&signal, day(15), 30));
+        assert!(!macro_visible(&signal, released.pred_opt().unwrap(), 30));
+        assert!(macro_visible(&signal, released, 30));
+    }
+
+    #[test]
+    fn test_signal_episodes_use_only_bars_up_to_date() {
+        // One episode whose strength peaks on the bar after day 2
+        let mut data = series(&[100.0, 100.0, 100.0, 100.0, 100.0]);
+        data.series[0].signals = vec![
+            signal(day(2), SignalType::RsiOversold, 0.3, "RSI_14"),
+            signal(day(3), SignalType::RsiOversold, 0.9, "RSI_14"),
+        ];
+        data.lifecycle = Some(SignalLifecycleConfig::default());
+        let trader = AiTrader::new(AiTraderConfig::default());
+
+        let before = data.series[0].signals_as_of(day(2), data.lifecycle.as_ref());
+        assert_eq!(before.len(), 1);
+        assert_eq!(before[0].end_date, None);
+        let seen = &context_on(&trader, &data, day(2)).symbols_data[0].signals;
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].strength, 0.3);
+
+        // The next day the episode has grown
+        let seen = &context_on(&trader, &data, day(3)).symbols_data[0].signals;
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].strength, 0.9);
+        assert!(context_on(&trader, &data, day(1)).symbols_data[0].signals.is_empty());
+    }
+
+    #[test]
+    fn test_context_cuts_prices_and_indicators_at_date() {
+        let mut data = series(&[100.0, 101.0, 102.0, 103.0, 104.0]);
+        data.series[0].indicators = (1..=5)
+            .map(|n| TechnicalIndicator {
+                symbol: "TEST".to_string(),
+                date: day(n),
+                indicator_name: "RSI_14".to_string(),
+                value: n as f64,
+            })
+            .collect();
+        let trader = AiTrader::new(AiTraderConfig::default());
+
+        let context = context_on(&trader, &data, day(3));
+        assert_eq!(context.timestamp, day(3).to_string());
+        let test = &context.symbols_data[0];
+        assert_eq!(test.current_price, 102.0);
+        assert_eq!(test.indicators["RSI_14"], 3.0);
+
+        // Before the first bar the symbol is left out entirely
+        let mut late = data.clone();
+        late.series[0].prices.retain(|p| p.date >= day(4));
+        assert!(context_on(&trader, &late, day(3)).symbols_data.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_regimes_apply_from_their_date() {
+        let mut data = series(&[100.0, 100.0, 100.0, 100.0, 100.0]);
+        data.market_regimes = vec![RegimeRecord {
+            symbol: MARKET_REGIME_SYMBOL.to_string(),
+            date: day(4),
+            regime: MarketRegime::TrendDown,
+            adx: None,
+            vol_percentile: None,
+            vix: None,
+        }];
+        let mut trader = AiTrader::new(AiTraderConfig::default());
+        trader.guardrails = crate::ai_trader::TradeGuardrails::for_mode(TradingMode::Aggressive);
+        trader.guardrails.blocked_regimes = vec![MarketRegime::TrendDown];
+        let mut model = RecordedModel::new("script")
+            .with_response(day(3), &decision("BUY", 5.0))
+            .with_response(day(4), &decision("BUY", 5.0));
+
+        let config = ReplayConfig::new(day(1), day(5), vec!["TEST".to_string()]);
+        let result = trader.replay(&data, &config, &mut model).await.unwrap();
+        // The regime recorded on day 4 does not block the day 3 buy
+        assert_eq!(result.decisions[0].rejection, None);
+        assert!(result.decisions[1]
+            .rejection
+            .as_deref()
+            .is_some_and(|r| r.contains("trend_down")));
+    }
+
+    #[tokio::test]
+    async fn test_no_fill_without_a_bar_that_day() {
+        let mut data = series(&[100.0, 100.0, 100.0, 100.0, 100.0]);
+        let mut other = data.series[0].clone();
+        other.symbol = "OTHER".to_string();
+        other.prices.retain(|p| p.date != day(3));
+        data.series.push(other);
+        let trader = AiTrader::new(AiTraderConfig::default());
+        let buy = |symbol: &str| {
+            format!(
+                r#"{{"decisions":[{{"action":"BUY","symbol":"{}","quantity_percent":5,"confidence":0.8,"reasoning":"test"}}]}}"#,
+                symbol
+            )
+        };
+        let mut model = RecordedModel::new("script")
+            .with_response(day(3), &buy("OTHER"))
+            .with_response(day(4), &buy("OTHER"))
+            .with_response(day(5), &buy("MISSING"));
+
+        let mut config = ReplayConfig::new(
+            day(1),
+            day(5),
+            vec!["TEST".to_string(), "OTHER".to_string()],
+        );
+        config.enforce_guardrails = false;
+        let result = trader.replay(&data, &config, &mut model).await.unwrap();
+        let rejections: Vec<Option<&str>> =
+            result.decisions.iter().map(|d| d.rejection.as_deref()).collect();
+        assert_eq!(
+            rejections,
+            vec![Some("No OTHER bar on 2024-01-03"), None, Some("No price for MISSING")]
+        );
+        assert_eq!(result.backtest.trades[0].entry_date, day(4));
+    }
+}
@@ -8,22 +8,33 @@
 //! - Detailed decision logging with AI reasoning
 //! - Performance tracking vs SPY benchmark
 //! - Compounding forecast projections
+//! - Historical replay backtests with pluggable decision models (`ai_replay`)
 
 use anyhow::{Context, Result};
-use chrono::{Timelike, Utc};
+use chrono::{NaiveDate, Timelike, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::PathBuf;
 
+use crate::analytics::AnalyticsConfig;
 use crate::db::Database;
 use crate::models::{
     AiPerformanceSnapshot, AiTradeDecision, AiTraderConfig, AiTraderStatus, AiTradingSession,
-    BenchmarkComparison, CompoundingForecast,
+    BenchmarkComparison, CompoundingForecast, DailyPrice, EquityPoint, MarketRegime, PaperTrade,
+    PaperTradeAction, PositionSizing, RiskMetrics, TradeDirection,
 };
 use crate::ollama::OllamaClient;
-use crate::signals::SignalEngine;
+use crate::macro_signals::MARKET_SIGNAL_SYMBOL;
+use crate::metrics;
+use crate::regime::MARKET_REGIME_SYMBOL;
+use crate::scorecard;
+use crate::sizing::SizingContext;
+use crate::signal_lifecycle::refresh_signal_status;
+use crate::signals::{configured_signal_engine, SignalEngine};
+use crate::stops::{FixedStops, StopTracker};
+use crate::volatility::{VolatilityRegime, VOL_REGIME_INDICATOR};
 
 // ============================================================================
 // Constants
@@ -312,6 +323,8 @@ pub struct MarketContext {
     pub timestamp: String,
     pub portfolio: PortfolioSnapshot,
     pub symbols_data: Vec<SymbolContext>,
+    /// Live market-wide signals from macro series
+    pub macro_signals: Vec<MacroSignalSummary>,
     pub recent_trades: Vec<RecentTrade>,
     pub prediction_accuracy: f64,
     pub constraints: TradingConstraints,
@@ -355,6 +368,23 @@ pub struct SignalSummary {
     pub signal_type: String,
     pub direction: String,
     pub strength: f64,
+    /// Historical 5-day hit rate (%) of this signal type, from the scorecard
+    pub hit_rate: Option<f64>,
+    /// Historical mean 5-day forward return (%) of this signal type
+    pub avg_return_5d: Option<f64>,
+    /// Number of past signals behind the hit rate
+    pub sample_size: usize,
+}
+
+/// Market-wide macro signal for context
+#[derive(Debug, Clone, Serialize)]
+pub struct MacroSignalSummary {
+    pub signal_type: String,
+    pub direction: String,
+    pub date: String,
+    /// Series the signal came from (e.g. "DGS10-DGS2", "VIXCLS")
+    pub source: String,
+    pub value: f64,
 }
 
 /// Confluence summary for context
@@ -476,6 +506,10 @@ pub struct TradeGuardrails {
     pub require_confluence: bool,
     /// Hours to block trading (open/close volatility)
     pub blocked_hours: Vec<(u8, u8)>,
+    /// Multiplier applied to max position size when the symbol is in a high volatility regime
+    pub high_vol_position_scale: f64,
+    /// Symbol or market regimes in which new buys are rejected
+    pub blocked_regimes: Vec<MarketRegime>,
 }
 
 impl TradeGuardrails {
@@ -489,6 +523,8 @@ impl TradeGuardrails {
                 max_single_trade_value: 100_000.0,
                 require_confluence: false,
                 blocked_hours: vec![],  // No restrictions
+                high_vol_position_scale: 0.75,
+                blocked_regimes: vec![],
             },
             TradingMode::Normal => Self {
                 mode,
@@ -497,6 +533,8 @@ impl TradeGuardrails {
                 max_single_trade_value: 50_000.0,
                 require_confluence: true,
                 blocked_hours: vec![(9, 9), (15, 16)],  // 9:00-9:45, 15:45-16:00
+                high_vol_position_scale: 0.5,
+                blocked_regimes: vec![],
             },
             TradingMode::Conservative => Self {
                 mode,
@@ -505,6 +543,8 @@ impl TradeGuardrails {
                 max_single_trade_value: 25_000.0,
                 require_confluence: true,
                 blocked_hours: vec![(9, 10), (15, 16)],  // Extended blocked
+                high_vol_position_scale: 0.5,
+                blocked_regimes: vec![MarketRegime::TrendDown, MarketRegime::HighVolatility],
             },
             TradingMode::Paused => Self {
                 mode,
@@ -513,11 +553,23 @@ impl TradeGuardrails {
                 max_single_trade_value: 0.0,
                 require_confluence: true,
                 blocked_hours: vec![(0, 24)],  // All hours blocked
+                high_vol_position_scale: 0.0,
+                blocked_regimes: vec![],
             },
         }
     }
 }
 
+impl TradeGuardrails {
+    /// Max position size scaled for the symbol's volatility regime
+    pub fn regime_scaled_max_position(&self, base_pct: f64, regime: Option<VolatilityRegime>) -> f64 {
+        match regime {
+            Some(VolatilityRegime::High) => base_pct * self.high_vol_position_scale,
+            _ => base_pct,
+        }
+    }
+}
+
 impl Default for TradeGuardrails {
     fn default() -> Self {
         Self::for_mode(TradingMode::Normal)
@@ -666,6 +718,21 @@ pub struct ProposedTrade {
     pub reasoning: String,
 }
 
+/// Market state a proposed trade is checked against by the guardrails
+#[derive(Debug, Clone, Default)]
+pub struct GuardrailChecks {
+    /// Confluence supports the trade
+    pub has_confluence: bool,
+    /// Volatility regime of the symbol
+    pub volatility_regime: Option<VolatilityRegime>,
+    /// Latest regimes of the symbol and the broad market
+    pub regimes: Vec<(String, MarketRegime)>,
+    /// Trades already made today
+    pub trades_today: usize,
+    /// Hour of day (UTC), or None for daily-bar replays
+    pub hour: Option<u8>,
+}
+
 /// STRYK override - temporary elevated permissions
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Override {
@@ -744,7 +811,8 @@ pub struct TradeRejection {
 pub struct AiTrader {
     pub config: AiTraderConfig,
     ollama: OllamaClient,
-    signal_engine: SignalEngine,
+    /// Checks confluence for replays; live cycles use `configured_signal_engine`
+    pub(crate) signal_engine: SignalEngine,
     /// Current guardrails (derived from mode)
     pub guardrails: TradeGuardrails,
     /// Circuit breaker state
@@ -777,6 +845,12 @@ impl AiTrader {
         }
     }
 
+    /// Check confluence with `signal_engine` (e.g. `configured_signal_engine`)
+    pub fn with_signal_engine(mut self, signal_engine: SignalEngine) -> Self {
+        self.signal_engine = signal_engine;
+        self
+    }
+
     /// Create with default configuration
     pub fn with_defaults() -> Self {
         Self::new(AiTraderConfig::default())
@@ -886,15 +960,6 @@ impl AiTrader {
         proposed: &ProposedTrade,
         has_confluence: bool,
     ) -> Result<TradeResult> {
-        // Check if paused
-        if self.guardrails.mode == TradingMode::Paused {
-            return Ok(TradeResult::Rejected {
-                reason: "Trading is paused".to_string(),
-                rule_triggered: "mode_paused".to_string(),
-                proposed_trade: proposed.clone(),
-            });
-        }
-
         // Check circuit breaker pause
         if self.circuit_breaker.triggered && !self.circuit_breaker.can_resume() {
             return Ok(TradeResult::Rejected {
@@ -905,70 +970,120 @@ impl AiTrader {
             });
         }
 
-        // Check position size
-        let max_pct = self.get_effective_max_position();
-        if proposed.quantity_percent > max_pct {
-            return Ok(TradeResult::Rejected {
-                reason: format!("Position size {:.1}% exceeds max {:.1}%",
-                    proposed.quantity_percent, max_pct),
-                rule_triggered: "max_position_size".to_string(),
+        let mut regimes = Vec::new();
+        if proposed.action.eq_ignore_ascii_case("BUY") && !self.guardrails.blocked_regimes.is_empty() {
+            for subject in [proposed.symbol.as_str(), MARKET_REGIME_SYMBOL] {
+                if let Some(record) = db.get_latest_regime(subject)? {
+                    regimes.push((subject.to_string(), record.regime));
+                }
+            }
+        }
+        let checks = GuardrailChecks {
+            has_confluence,
+            volatility_regime: db
+                .get_latest_indicator_value(&proposed.symbol, VOL_REGIME_INDICATOR)?
+                .and_then(VolatilityRegime::from_code),
+            regimes,
+            trades_today: db.get_paper_trades_today()?.len(),
+            hour: Some(Utc::now().hour() as u8),
+        };
+        if let Some(rejected) = self.guardrail_rejection(proposed, &checks) {
+            return Ok(rejected);
+        }
+
+        // All validations passed - trade can proceed
+        // Note: Actual execution happens elsewhere, this just validates
+        Ok(TradeResult::Executed {
+            trade_id: String::new(), // Will be filled by execute
+            symbol: proposed.symbol.clone(),
+            action: proposed.action.clone(),
+            quantity: proposed.quantity,
+            price: 0.0, // Will be filled by execute
+            value: proposed.estimated_value,
+            timestamp: Utc::now().to_rfc3339(),
+        })
+    }
+
+    /// Check a proposed trade against the mode's guardrails, returning the
+    /// rejection if any rule fails
+    pub(crate) fn guardrail_rejection(
+        &self,
+        proposed: &ProposedTrade,
+        checks: &GuardrailChecks,
+    ) -> Option<TradeResult> {
+        let reject = |reason: String, rule: &str| {
+            Some(TradeResult::Rejected {
+                reason,
+                rule_triggered: rule.to_string(),
                 proposed_trade: proposed.clone(),
-            });
+            })
+        };
+
+        // Check if paused
+        if self.guardrails.mode == TradingMode::Paused {
+            return reject("Trading is paused".to_string(), "mode_paused");
+        }
+
+        // Check position size (scaled down in a high volatility regime)
+        let max_pct = self
+            .guardrails
+            .regime_scaled_max_position(self.get_effective_max_position(), checks.volatility_regime);
+        if proposed.quantity_percent > max_pct {
+            return reject(
+                format!("Position size {:.1}% exceeds max {:.1}%", proposed.quantity_percent, max_pct),
+                "max_position_size",
+            );
+        }
+
+        // Check symbol and market regime for new buys
+        if proposed.action.eq_ignore_ascii_case("BUY") {
+            for (subject, regime) in &checks.regimes {
+                if self.guardrails.blocked_regimes.contains(regime) {
+                    return reject(
+                        format!("{} regime is {} (blocked in {:?} mode)",
+                            subject, regime.as_str(), self.guardrails.mode),
+                        "blocked_regime",
+                    );
+                }
+            }
         }
 
         // Check single trade value
         if proposed.estimated_value > self.guardrails.max_single_trade_value {
-            return Ok(TradeResult::Rejected {
-                reason: format!("Trade value ${:.2} exceeds max ${:.2}",
+            return reject(
+                format!("Trade value ${:.2} exceeds max ${:.2}",
                     proposed.estimated_value, self.guardrails.max_single_trade_value),
-                rule_triggered: "max_trade_value".to_string(),
-                proposed_trade: proposed.clone(),
-            });
+                "max_trade_value",
+            );
         }
 
         // Check confluence requirement
-        if self.guardrails.require_confluence && !has_confluence {
-            return Ok(TradeResult::Rejected {
-                reason: "Trade requires confluence signal support".to_string(),
-                rule_triggered: "require_confluence".to_string(),
-                proposed_trade: proposed.clone(),
-            });
+        if self.guardrails.require_confluence && !checks.has_confluence {
+            return reject("Trade requires confluence signal support".to_string(), "require_confluence");
         }
 
         // Check daily trade limit
-        let today_trades = db.get_paper_trades_today()?;
-        if today_trades.len() as u32 >= self.guardrails.max_daily_trades {
-            return Ok(TradeResult::Rejected {
-                reason: format!("Daily trade limit reached ({}/{})",
-                    today_trades.len(), self.guardrails.max_daily_trades),
-                rule_triggered: "max_daily_trades".to_string(),
-                proposed_trade: proposed.clone(),
-            });
+        if checks.trades_today as u32 >= self.guardrails.max_daily_trades {
+            return reject(
+                format!("Daily trade limit reached ({}/{})",
+                    checks.trades_today, self.guardrails.max_daily_trades),
+                "max_daily_trades",
+            );
         }
 
         // Check blocked hours
-        let current_hour = Utc::now().hour() as u8;
-        for (start, end) in &self.guardrails.blocked_hours {
-            if current_hour >= *start && current_hour < *end {
-                return Ok(TradeResult::Rejected {
-                    reason: format!("Trading blocked during hours {}-{}", start, end),
-                    rule_triggered: "blocked_hours".to_string(),
-                    proposed_trade: proposed.clone(),
-                });
+        if let Some(hour) = checks.hour {
+            for (start, end) in &self.guardrails.blocked_hours {
+                if hour >= *start && hour < *end {
+                    return reject(
+                        format!("Trading blocked during hours {}-{}", start, end),
+                        "blocked_hours",
+                    );
+                }
             }
         }
 
-        // All validations passed - trade can proceed
-        // Note: Actual execution happens elsewhere, this just validates
-        Ok(TradeResult::Executed {
-            trade_id: String::new(), // Will be filled by execute
-            symbol: proposed.symbol.clone(),
-            action: proposed.action.clone(),
-            quantity: proposed.quantity,
-            price: 0.0, // Will be filled by execute
-            value: proposed.estimated_value,
-            timestamp: Utc::now().to_rfc3339(),
-        })
+        None
     }
 
     /// Log a trade rejection to the database
@@ -1042,6 +1157,12 @@ impl AiTrader {
             anyhow::bail!("Portfolio is bankrupt (value: ${:.2})", total_value);
         }
 
+        // Exit positions whose stops or targets were hit since the last cycle
+        let stop_trades = self.enforce_stops(db)?;
+        if !stop_trades.is_empty() {
+            println!("[AI Trader] {} stop/target exit(s) executed", stop_trades.len());
+        }
+
         // Get active session
         let session = db.get_active_ai_session()?;
         let session_id = session.map(|s| s.id);
@@ -1069,6 +1190,101 @@ impl AiTrader {
         Ok(recorded_decisions)
     }
 
+    /// Apply stop-loss, take-profit and the configured exit rules (trailing,
+    /// break-even, time stops, scale-outs) to open paper positions, selling
+    /// on any daily bars since the last check (the latest bar on a
+    /// position's first check)
+    pub fn enforce_stops(&self, db: &Database) -> Result<Vec<PaperTrade>> {
+        db.prune_paper_stop_state()?;
+        let fixed = FixedStops {
+            stop_loss_percent: Some(self.config.stop_loss_percent).filter(|pct| *pct > 0.0),
+            take_profit_percent: Some(self.config.take_profit_percent).filter(|pct| *pct > 0.0),
+        };
+
+        let mut trades = Vec::new();
+        for position in db.get_paper_positions()? {
+            let Some(entry_date) = position
+                .entry_date
+                .get(..10)
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            else {
+                continue;
+            };
+
+            let bars = db.get_prices(&position.symbol)?;
+
+            // Adding to a position changes its average entry: restart the
+            // tracker from the new entry but keep the bars already checked.
+            // Without saved state, tracking starts at the latest bar: replaying
+            // older bars would fill stale stops at today's timestamp.
+            let state = db.get_paper_stop_state(position.id)?;
+            let last_checked = match &state {
+                Some((_, date)) => *date,
+                None => bars
+                    .iter()
+                    .rev()
+                    .nth(1)
+                    .map_or(entry_date, |bar| bar.date)
+                    .max(entry_date),
+            };
+            let mut tracker = match state {
+                Some((tracker, _)) if (tracker.entry_price - position.entry_price).abs() < 1e-9 => tracker,
+                _ => StopTracker::new(TradeDirection::Long, position.entry_price, entry_date),
+            };
+
+            let atr: HashMap<NaiveDate, f64> = db
+                .get_indicator_history(&position.symbol, "ATR_14")?
+                .into_iter()
+                .map(|ind| (ind.date, ind.value))
+                .collect();
+
+            let mut quantity = position.quantity;
+            let mut last_bar = last_checked;
+            for (i, bar) in bars.iter().enumerate().filter(|(_, b)| b.date > last_checked) {
+                let exits = tracker.check(
+                    &self.config.exit_rules,
+                    fixed,
+                    &bars[..=i],
+                    atr.get(&bar.date).copied(),
+                );
+                for exit in exits {
+                    let shares = if exit.closes_position() {
+                        quantity
+                    } else {
+                        (quantity * exit.fraction).floor()
+                    };
+                    if shares <= 0.0 {
+                        continue;
+                    }
+                    if exit.reason == "scale_out" {
+                        tracker.record_scale_out();
+                    }
+                    let notes = format!("Stop: {} on {}", exit.reason, bar.date);
+                    trades.push(db.execute_paper_trade(
+                        &position.symbol,
+                        PaperTradeAction::Sell,
+                        shares,
+                        exit.price,
+                        position.linked_event_id,
+                        Some(&notes),
+                    )?);
+                    quantity -= shares;
+                }
+                last_bar = bar.date;
+                if quantity <= 0.0001 {
+                    break;
+                }
+            }
+
+            if quantity > 0.0001 {
+                db.save_paper_stop_state(position.id, &tracker, last_bar)?;
+            }
+        }
+        db.prune_paper_stop_state()?;
+
+        Ok(trades)
+    }
+
     /// Gather market context for AI decision making
     pub fn gather_market_context(&self, db: &Database) -> Result<MarketContext> {
         let (cash, positions_value, total_value) = db.get_paper_portfolio_value()?;
@@ -1130,14 +1346,30 @@ impl AiTrader {
             symbols = vec!["SPY".to_string(), "QQQ".to_string(), "AAPL".to_string(), "NVDA".to_string()];
         }
 
-        // Gather symbol data
+        // Gather symbol data, checking confluence with the saved detector settings
+        let signal_engine = configured_signal_engine(db)?;
         let mut symbols_data = Vec::new();
         for symbol in &symbols {
-            if let Ok(sym_context) = self.gather_symbol_context(db, symbol) {
+            if let Ok(sym_context) = self.gather_symbol_context(db, &signal_engine, symbol) {
                 symbols_data.push(sym_context);
             }
         }
 
+        // Live macro signals (most recent first)
+        let macro_signals = db
+            .get_signals(MARKET_SIGNAL_SYMBOL, false)?
+            .into_iter()
+            .filter(|s| s.status.is_live())
+            .take(5)
+            .map(|s| MacroSignalSummary {
+                signal_type: s.signal_type.as_str().to_string(),
+                direction: s.direction.as_str().to_string(),
+                date: s.timestamp.to_string(),
+                source: s.triggered_by,
+                value: s.trigger_value,
+            })
+            .collect();
+
         // Recent trades
         let recent = recent_trades
             .iter()
@@ -1155,35 +1387,31 @@ impl AiTrader {
             timestamp: Utc::now().to_rfc3339(),
             portfolio,
             symbols_data,
+            macro_signals,
             recent_trades: recent,
             prediction_accuracy: accuracy.accuracy_percent,
-            constraints: TradingConstraints {
-                max_position_size_percent: self.config.max_position_size_percent,
-                stop_loss_percent: self.config.stop_loss_percent,
-                take_profit_percent: self.config.take_profit_percent,
-                min_cash_reserve_percent: 20.0,
-            },
+            constraints: self.trading_constraints(),
         })
     }
 
-    /// Gather context for a single symbol
-    fn gather_symbol_context(&self, db: &Database, symbol: &str) -> Result<SymbolContext> {
-        let prices = db.get_prices(symbol)?;
-        if prices.is_empty() {
-            anyhow::bail!("No price data for {}", symbol);
+    /// Constraints stated to the model in every prompt
+    pub(crate) fn trading_constraints(&self) -> TradingConstraints {
+        TradingConstraints {
+            max_position_size_percent: self.config.max_position_size_percent,
+            stop_loss_percent: self.config.stop_loss_percent,
+            take_profit_percent: self.config.take_profit_percent,
+            min_cash_reserve_percent: 20.0,
         }
+    }
 
-        let current_price = prices.last().map(|p| p.close).unwrap_or(0.0);
-        let prev_price = if prices.len() >= 2 {
-            prices[prices.len() - 2].close
-        } else {
-            current_price
-        };
-        let price_change_percent = if prev_price > 0.0 {
-            ((current_price - prev_price) / prev_price) * 100.0
-        } else {
-            0.0
-        };
+    /// Gather context for a single symbol
+    fn gather_symbol_context(
+        &self,
+        db: &Database,
+        signal_engine: &SignalEngine,
+        symbol: &str,
+    ) -> Result<SymbolContext> {
+        let prices = db.get_prices(symbol)?;
 
         // Get indicators
         let indicators_list = db.get_all_indicators(symbol)?;
@@ -1192,21 +1420,63 @@ impl AiTrader {
             indicators.insert(ind.indicator_name.clone(), ind.value);
         }
 
-        // Get signals (all signals, not just unacknowledged)
+        // Get live signals (all, not just unacknowledged); expired and
+        // invalidated episodes are noise for the model. Statuses are re-aged
+        // first since they were assigned when the signals were generated.
+        if let Some(lifecycle) = &signal_engine.config().lifecycle {
+            refresh_signal_status(db, symbol, lifecycle)?;
+        }
         let signals = db.get_signals(symbol, false)?;
         let signal_summaries: Vec<SignalSummary> = signals
             .iter()
+            .filter(|s| s.status.is_live())
             .take(5)
-            .map(|s| SignalSummary {
-                signal_type: format!("{:?}", s.signal_type),
-                direction: format!("{:?}", s.direction),
-                strength: s.strength,
+            .map(|s| {
+                let record = scorecard::track_record(db, s.signal_type.as_str(), symbol)
+                    .ok()
+                    .flatten();
+                SignalSummary {
+                    signal_type: format!("{:?}", s.signal_type),
+                    direction: format!("{:?}", s.direction),
+                    strength: s.strength,
+                    hit_rate: record.as_ref().map(|r| r.hit_rate),
+                    avg_return_5d: record.as_ref().map(|r| r.mean_return_5d),
+                    sample_size: record.map_or(0, |r| r.hit_rate_samples),
+                }
             })
             .collect();
 
+        self.build_symbol_context(signal_engine, symbol, &prices, indicators, signal_summaries)
+    }
+
+    /// Symbol context from its prices and latest indicator values, as of the
+    /// last bar of `prices`
+    pub(crate) fn build_symbol_context(
+        &self,
+        signal_engine: &SignalEngine,
+        symbol: &str,
+        prices: &[DailyPrice],
+        indicators: HashMap<String, f64>,
+        signals: Vec<SignalSummary>,
+    ) -> Result<SymbolContext> {
+        let Some(latest_price) = prices.last() else {
+            anyhow::bail!("No price data for {}", symbol);
+        };
+
+        let current_price = latest_price.close;
+        let prev_price = if prices.len() >= 2 {
+            prices[prices.len() - 2].close
+        } else {
+            current_price
+        };
+        let price_change_percent = if prev_price > 0.0 {
+            ((current_price - prev_price) / prev_price) * 100.0
+        } else {
+            0.0
+        };
+
         // Check for confluence using signal engine
-        let latest_price = prices.last().unwrap();
-        let confluence = if let Some(c) = self.signal_engine.detect_confluence_signal(
+        let confluence = if let Some(c) = signal_engine.detect_confluence_signal(
             symbol,
             latest_price.date,
             current_price,
@@ -1226,7 +1496,7 @@ impl AiTrader {
             current_price,
             price_change_percent,
             indicators,
-            signals: signal_summaries,
+            signals,
             confluence,
         })
     }
@@ -1310,7 +1580,7 @@ impl AiTrader {
     }
 
     /// Format market context into a prompt
-    fn format_context_prompt(&self, context: &MarketContext) -> String {
+    pub(crate) fn format_context_prompt(&self, context: &MarketContext) -> String {
         let mut prompt = String::new();
 
         prompt.push_str("=== PORTFOLIO STATUS ===\n");
@@ -1336,6 +1606,22 @@ impl AiTrader {
             }
         }
 
+        // Relative analytics are stored against the configured benchmark
+        let analytics = AnalyticsConfig {
+            benchmark: self.config.benchmark_symbol.clone(),
+            ..Default::default()
+        };
+
+        if !context.macro_signals.is_empty() {
+            prompt.push_str("\n=== MACRO SIGNALS ===\n");
+            for m in &context.macro_signals {
+                prompt.push_str(&format!(
+                    "  {} ({}) on {} - {} at {:.2}\n",
+                    m.signal_type, m.direction, m.date, m.source, m.value
+                ));
+            }
+        }
+
         prompt.push_str("\n=== MARKET SIGNALS ===\n");
         for sym in &context.symbols_data {
             prompt.push_str(&format!(
@@ -1367,6 +1653,19 @@ impl AiTrader {
                 }
                 prompt.push('\n');
             }
+            if let Some(beta) = sym.indicators.get(&analytics.beta_name()) {
+                prompt.push_str(&format!("  Beta vs {}: {:.2}", analytics.benchmark, beta));
+                if let Some(corr) = sym.indicators.get(&analytics.correlation_name()) {
+                    prompt.push_str(&format!(" (correlation {:.2})", corr));
+                }
+                prompt.push('\n');
+            }
+            if let Some(rank) = sym.indicators.get(&analytics.rs_rank_name()) {
+                prompt.push_str(&format!(
+                    "  Relative strength rank vs {}: {:.0}/100\n",
+                    analytics.benchmark, rank
+                ));
+            }
 
             if !sym.signals.is_empty() {
                 prompt.push_str("  Recent signals: ");
@@ -1374,7 +1673,13 @@ impl AiTrader {
                     .signals
                     .iter()
                     .take(3)
-                    .map(|s| format!("{} ({})", s.signal_type, s.direction))
+                    .map(|s| match (s.hit_rate, s.avg_return_5d) {
+                        (Some(hit), Some(avg)) => format!(
+                            "{} ({}, historically {:.0}% hit rate, {:+.2}% avg 5d over {} signals)",
+                            s.signal_type, s.direction, hit, avg, s.sample_size
+                        ),
+                        _ => format!("{} ({})", s.signal_type, s.direction),
+                    })
                     .collect();
                 prompt.push_str(&sig_strs.join(", "));
                 prompt.push('\n');
@@ -1402,7 +1707,7 @@ impl AiTrader {
     }
 
     /// Parse AI response into decisions
-    fn parse_ai_response(&self, response: &str) -> Result<AiDecisionResponse> {
+    pub(crate) fn parse_ai_response(&self, response: &str) -> Result<AiDecisionResponse> {
         // Try to extract JSON from response (sometimes models add extra text)
         let json_str = if let Some(start) = response.find('{') {
             if let Some(end) = response.rfind('}') {
@@ -1417,6 +1722,71 @@ impl AiTrader {
         serde_json::from_str(json_str).context("Failed to parse AI response as JSON")
     }
 
+    /// Dollars to spend on a BUY decision. The default sizing model spends
+    /// the decision's `quantity_percent` of cash; the others size from
+    /// account equity and are capped at cash and the effective max position.
+    fn buy_allocation(
+        &self,
+        db: &Database,
+        decision: &ParsedDecision,
+        cash: f64,
+        total_value: f64,
+        price: f64,
+    ) -> Result<f64> {
+        if self.config.position_sizing == PositionSizing::PercentOfCapital {
+            return Ok(self.sized_spend(decision, cash, total_value, price, None, &[]));
+        }
+
+        let atr = db
+            .get_latest_indicators(&decision.symbol)?
+            .into_iter()
+            .find(|i| i.indicator_name == "ATR_14")
+            .map(|i| i.value);
+        let trade_returns: Vec<f64> = db
+            .get_paper_trades(None, i64::MAX as usize)?
+            .into_iter()
+            .filter_map(|t| Some(metrics::sale_pnl(t.quantity, t.price, t.pnl?).1))
+            .collect();
+        Ok(self.sized_spend(decision, cash, total_value, price, atr, &trade_returns))
+    }
+
+    /// Dollars the sizing model spends on a BUY decision given the account,
+    /// the symbol's ATR_14 and past closed-trade returns (%)
+    pub(crate) fn sized_spend(
+        &self,
+        decision: &ParsedDecision,
+        cash: f64,
+        total_value: f64,
+        price: f64,
+        atr: Option<f64>,
+        trade_returns: &[f64],
+    ) -> f64 {
+        let sizing = &self.config.position_sizing;
+        if *sizing == PositionSizing::PercentOfCapital {
+            return cash * (decision.quantity_percent / 100.0);
+        }
+
+        let max_position = self.get_effective_max_position();
+        let ctx = SizingContext {
+            equity: total_value,
+            capital: cash,
+            percent: decision.quantity_percent,
+            price,
+            atr,
+            stop_loss_percent: Some(self.config.stop_loss_percent),
+            // As many positions as fit at the max position size
+            slots: (100.0 / max_position.max(1.0)).ceil() as usize,
+            margin_percent: 100.0,
+            trade_returns,
+        };
+
+        let Some(allocation) = sizing.allocation(&ctx) else {
+            println!("[AI Trader] No ATR_14 for {}, skipping sized BUY", decision.symbol);
+            return 0.0;
+        };
+        allocation.min(cash).min(total_value * max_position / 100.0)
+    }
+
     /// Execute a single trading decision
     async fn execute_decision(
         &self,
@@ -1437,8 +1807,7 @@ impl AiTrader {
 
         match decision.action.to_uppercase().as_str() {
             "BUY" => {
-                // Calculate quantity based on percentage of available cash
-                let max_spend = cash * (decision.quantity_percent / 100.0);
+                let max_spend = self.buy_allocation(db, decision, cash, total_value, current_price)?;
                 let quantity = (max_spend / current_price).floor();
 
                 if quantity >= 1.0 {
@@ -1621,6 +1990,41 @@ impl AiTrader {
         })
     }
 
+    /// Risk and benchmark-relative statistics of the AI portfolio over the
+    /// last `days` days, from its last performance snapshot of each day
+    pub fn get_performance_metrics(&self, db: &Database, days: u32) -> Result<RiskMetrics> {
+        let mut curve: Vec<EquityPoint> = Vec::new();
+        for snapshot in db.get_ai_performance_snapshots(days)? {
+            let Some(date) = metrics::timestamp_date(&snapshot.timestamp) else {
+                continue;
+            };
+            if curve.last().is_some_and(|p| p.date == date) {
+                curve.pop();
+            }
+            curve.push(EquityPoint {
+                date,
+                equity: snapshot.portfolio_value,
+                cash: snapshot.cash,
+                exposure: snapshot.positions_value,
+                open_positions: usize::from(snapshot.positions_value > 0.0),
+                drawdown: 0.0,
+                buy_and_hold: None,
+            });
+        }
+        let Some(start) = curve.first().map(|p| p.date) else {
+            return Ok(RiskMetrics::default());
+        };
+
+        let closed: Vec<(f64, f64)> = db
+            .get_paper_trades(None, i64::MAX as usize)?
+            .into_iter()
+            .filter(|t| metrics::timestamp_date(&t.timestamp).is_some_and(|d| d >= start))
+            .filter_map(|t| Some(metrics::sale_pnl(t.quantity, t.price, t.pnl?)))
+            .collect();
+
+        Ok(metrics::account_metrics(db, &curve, &closed, &self.config.benchmark_symbol)?)
+    }
+
     /// Calculate compounding forecast
     pub fn get_compounding_forecast(&self, db: &Database) -> Result<CompoundingForecast> {
         let snapshots = db.get_ai_performance_snapshots(30)?;
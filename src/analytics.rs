@@ -0,0 +1,471 @@
+//! Relative analytics against a benchmark
+//!
+//! Rolling beta, rolling correlation, relative strength ratio and
+//! cross-sectional relative strength rank, all computed from `daily_prices`.
+//! Values are emitted as `TechnicalIndicator`s named with the benchmark
+//! (e.g. "BETA_60_SPY", "RS_RANK_SPY") so they can be stored in
+//! `technical_indicators` and read by strategies and the AI context.
+
+use crate::db::Database;
+use crate::error::Result;
+use crate::models::{DailyPrice, TechnicalIndicator};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Benchmark used when none is configured
+pub const DEFAULT_BENCHMARK: &str = "SPY";
+
+/// Configuration for relative analytics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyticsConfig {
+    /// Benchmark or sector ETF symbol
+    pub benchmark: String,
+    /// Rolling window (in return observations) for beta
+    pub beta_window: usize,
+    /// Rolling window (in return observations) for correlation
+    pub correlation_window: usize,
+    /// Lookback (in bars) for relative strength momentum used by the rank
+    pub rs_lookback: usize,
+}
+
+impl Default for AnalyticsConfig {
+    fn default() -> Self {
+        Self {
+            benchmark: DEFAULT_BENCHMARK.to_string(),
+            beta_window: 60,
+            correlation_window: 60,
+            rs_lookback: 63,
+        }
+    }
+}
+
+impl AnalyticsConfig {
+    pub fn beta_name(&self) -> String {
+        format!("BETA_{}_{}", self.beta_window, self.benchmark)
+    }
+
+    pub fn correlation_name(&self) -> String {
+        format!("CORR_{}_{}", self.correlation_window, self.benchmark)
+    }
+
+    pub fn rs_ratio_name(&self) -> String {
+        format!("RS_RATIO_{}", self.benchmark)
+    }
+
+    pub fn rs_momentum_name(&self) -> String {
+        format!("RS_MOM_{}_{}", self.rs_lookback, self.benchmark)
+    }
+
+    pub fn rs_rank_name(&self) -> String {
+        format!("RS_RANK_{}", self.benchmark)
+    }
+}
+
+/// Pairwise correlation matrix of daily returns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorrelationMatrix {
+    pub symbols: Vec<String>,
+    /// values[i][j] = correlation of symbols[i] with symbols[j]; NaN if not enough overlap
+    pub values: Vec<Vec<f64>>,
+    pub lookback: usize,
+}
+
+/// Closes of two series on their common dates, sorted by date
+fn aligned_closes(a: &[DailyPrice], b: &[DailyPrice]) -> Vec<(NaiveDate, f64, f64)> {
+    let b_map: HashMap<NaiveDate, f64> = b.iter().map(|p| (p.date, p.close)).collect();
+    let mut aligned: Vec<(NaiveDate, f64, f64)> = a
+        .iter()
+        .filter_map(|p| b_map.get(&p.date).map(|&bc| (p.date, p.close, bc)))
+        .collect();
+    aligned.sort_by_key(|(d, _, _)| *d);
+    aligned
+}
+
+/// Simple returns of two series on their common dates
+/// Returns (date, return_a, return_b) where the return ends on `date`
+pub fn aligned_returns(a: &[DailyPrice], b: &[DailyPrice]) -> Vec<(NaiveDate, f64, f64)> {
+    aligned_closes(a, b)
+        .windows(2)
+        .filter(|w| w[0].1 > 0.0 && w[0].2 > 0.0)
+        .map(|w| (w[1].0, w[1].1 / w[0].1 - 1.0, w[1].2 / w[0].2 - 1.0))
+        .collect()
+}
+
+/// Sample covariance, variance of x and variance of y
+fn covariance_stats(xs: &[f64], ys: &[f64]) -> (f64, f64, f64) {
+    let n = xs.len() as f64;
+    let mean_x = xs.iter().sum::<f64>() / n;
+    let mean_y = ys.iter().sum::<f64>() / n;
+    let mut cov = 0.0;
+    let mut var_x = 0.0;
+    let mut var_y = 0.0;
+    for (x, y) in xs.iter().zip(ys) {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    let denom = (n - 1.0).max(1.0);
+    (cov / denom, var_x / denom, var_y / denom)
+}
+
+/// Pearson correlation of two equally sized samples (None if degenerate)
+pub fn correlation(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    if xs.len() < 2 || xs.len() != ys.len() {
+        return None;
+    }
+    let (cov, var_x, var_y) = covariance_stats(xs, ys);
+    if var_x <= 0.0 || var_y <= 0.0 {
+        return None;
+    }
+    Some(cov / (var_x.sqrt() * var_y.sqrt()))
+}
+
+/// Beta of xs against ys (None if ys has no variance)
+pub fn beta(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    if xs.len() < 2 || xs.len() != ys.len() {
+        return None;
+    }
+    let (cov, _, var_y) = covariance_stats(xs, ys);
+    if var_y <= 0.0 {
+        return None;
+    }
+    Some(cov / var_y)
+}
+
+/// Calculate rolling beta of a symbol vs a benchmark
+pub fn calculate_rolling_beta(
+    prices: &[DailyPrice],
+    benchmark: &[DailyPrice],
+    window: usize,
+    indicator_name: &str,
+) -> Vec<TechnicalIndicator> {
+    rolling_pair_stat(prices, benchmark, window, indicator_name, beta)
+}
+
+/// Calculate rolling correlation of a symbol vs a benchmark
+pub fn calculate_rolling_correlation(
+    prices: &[DailyPrice],
+    benchmark: &[DailyPrice],
+    window: usize,
+    indicator_name: &str,
+) -> Vec<TechnicalIndicator> {
+    rolling_pair_stat(prices, benchmark, window, indicator_name, correlation)
+}
+
+fn rolling_pair_stat(
+    prices: &[DailyPrice],
+    benchmark: &[DailyPrice],
+    window: usize,
+    indicator_name: &str,
+    stat: fn(&[f64], &[f64]) -> Option<f64>,
+) -> Vec<TechnicalIndicator> {
+    let returns = aligned_returns(prices, benchmark);
+    if window < 2 || returns.len() < window || prices.is_empty() {
+        return vec![];
+    }
+
+    let xs: Vec<f64> = returns.iter().map(|r| r.1).collect();
+    let ys: Vec<f64> = returns.iter().map(|r| r.2).collect();
+
+    (window - 1..returns.len())
+        .filter_map(|i| {
+            let range = (i + 1 - window)..=i;
+            stat(&xs[range.clone()], &ys[range]).map(|value| TechnicalIndicator {
+                symbol: prices[0].symbol.clone(),
+                date: returns[i].0,
+                indicator_name: indicator_name.to_string(),
+                value,
+            })
+        })
+        .collect()
+}
+
+/// Calculate the relative strength ratio (symbol close / benchmark close),
+/// normalized to 1.0 on the first common date
+pub fn calculate_rs_ratio(
+    prices: &[DailyPrice],
+    benchmark: &[DailyPrice],
+    indicator_name: &str,
+) -> Vec<TechnicalIndicator> {
+    let aligned = aligned_closes(prices, benchmark);
+    let Some(&(_, first_a, first_b)) = aligned.first() else {
+        return vec![];
+    };
+    if first_a <= 0.0 || first_b <= 0.0 {
+        return vec![];
+    }
+    let base = first_a / first_b;
+
+    aligned
+        .iter()
+        .filter(|(_, _, b)| *b > 0.0)
+        .map(|(date, a, b)| TechnicalIndicator {
+            symbol: prices[0].symbol.clone(),
+            date: *date,
+            indicator_name: indicator_name.to_string(),
+            value: (a / b) / base,
+        })
+        .collect()
+}
+
+/// Relative strength momentum: percent change of the RS ratio over `lookback` bars
+pub fn calculate_rs_momentum(
+    prices: &[DailyPrice],
+    benchmark: &[DailyPrice],
+    lookback: usize,
+    indicator_name: &str,
+) -> Vec<TechnicalIndicator> {
+    let ratio = calculate_rs_ratio(prices, benchmark, indicator_name);
+    if lookback == 0 || ratio.len() <= lookback {
+        return vec![];
+    }
+
+    (lookback..ratio.len())
+        .filter(|&i| ratio[i - lookback].value > 0.0)
+        .map(|i| TechnicalIndicator {
+            value: (ratio[i].value / ratio[i - lookback].value - 1.0) * 100.0,
+            ..ratio[i].clone()
+        })
+        .collect()
+}
+
+/// Calculate beta, correlation, RS ratio and RS momentum for one symbol
+pub fn calculate_relative_analytics(
+    prices: &[DailyPrice],
+    benchmark: &[DailyPrice],
+    config: &AnalyticsConfig,
+) -> Vec<TechnicalIndicator> {
+    let mut all = Vec::new();
+    all.extend(calculate_rolling_beta(prices, benchmark, config.beta_window, &config.beta_name()));
+    all.extend(calculate_rolling_correlation(
+        prices,
+        benchmark,
+        config.correlation_window,
+        &config.correlation_name(),
+    ));
+    all.extend(calculate_rs_ratio(prices, benchmark, &config.rs_ratio_name()));
+    all.extend(calculate_rs_momentum(
+        prices,
+        benchmark,
+        config.rs_lookback,
+        &config.rs_momentum_name(),
+    ));
+    all
+}
+
+/// Rank each symbol's RS momentum against the rest of the universe per date
+///
+/// Rank is a percentile from 0 (weakest) to 100 (strongest). Dates with
+/// fewer than two symbols are skipped since a rank is meaningless there.
+pub fn calculate_rs_ranks(momentum: &[TechnicalIndicator], indicator_name: &str) -> Vec<TechnicalIndicator> {
+    let mut by_date: BTreeMap<NaiveDate, Vec<&TechnicalIndicator>> = BTreeMap::new();
+    for ind in momentum {
+        by_date.entry(ind.date).or_default().push(ind);
+    }
+
+    let mut ranks = Vec::new();
+    for (date, entries) in by_date {
+        if entries.len() < 2 {
+            continue;
+        }
+        let n = entries.len() as f64;
+        for entry in &entries {
+            let below = entries.iter().filter(|e| e.value < entry.value).count() as f64;
+            let ties = entries.iter().filter(|e| e.value == entry.value).count() as f64 - 1.0;
+            ranks.push(TechnicalIndicator {
+                symbol: entry.symbol.clone(),
+                date,
+                indicator_name: indicator_name.to_string(),
+                value: (below + ties / 2.0) / (n - 1.0) * 100.0,
+            });
+        }
+    }
+    ranks
+}
+
+/// Correlation matrix of daily returns over the last `lookback` common dates
+pub fn correlation_matrix(series: &[(String, Vec<DailyPrice>)], lookback: usize) -> CorrelationMatrix {
+    let n = series.len();
+    let mut values = vec![vec![f64::NAN; n]; n];
+
+    for i in 0..n {
+        values[i][i] = 1.0;
+        for j in (i + 1)..n {
+            let returns = aligned_returns(&series[i].1, &series[j].1);
+            let start = returns.len().saturating_sub(lookback);
+            let xs: Vec<f64> = returns[start..].iter().map(|r| r.1).collect();
+            let ys: Vec<f64> = returns[start..].iter().map(|r| r.2).collect();
+            let corr = correlation(&xs, &ys).unwrap_or(f64::NAN);
+            values[i][j] = corr;
+            values[j][i] = corr;
+        }
+    }
+
+    CorrelationMatrix {
+        symbols: series.iter().map(|(s, _)| s.clone()).collect(),
+        values,
+        lookback,
+    }
+}
+
+/// Compute and store relative analytics for a set of symbols vs the benchmark
+///
+/// RS ranks are always taken across every symbol with stored prices, so
+/// refreshing a few symbols doesn't rank them only against each other; the
+/// ranks of the whole universe are rewritten. Returns the number of indicator
+/// values written.
+pub fn update_relative_analytics(
+    db: &mut Database,
+    symbols: &[String],
+    config: &AnalyticsConfig,
+) -> Result<usize> {
+    let benchmark = db.get_prices(&config.benchmark)?;
+    if benchmark.is_empty() {
+        return Err(crate::error::PipelineError::NoData(config.benchmark.clone()));
+    }
+
+    let mut all = Vec::new();
+    for symbol in symbols {
+        let prices = db.get_prices(symbol)?;
+        if prices.is_empty() {
+            continue;
+        }
+        all.extend(calculate_relative_analytics(&prices, &benchmark, config));
+    }
+
+    let mom_name = config.rs_momentum_name();
+    let mut momentum = Vec::new();
+    for symbol in db.get_symbols_with_data()? {
+        if symbol == config.benchmark {
+            continue;
+        }
+        let prices = db.get_prices(&symbol)?;
+        momentum.extend(calculate_rs_momentum(&prices, &benchmark, config.rs_lookback, &mom_name));
+    }
+    all.extend(calculate_rs_ranks(&momentum, &config.rs_rank_name()));
+
+    db.upsert_indicators(&all)
+}
+
+/// Correlation matrix for all symbols in a watchlist
+pub fn watchlist_correlation_matrix(
+    db: &Database,
+    watchlist: &str,
+    lookback: usize,
+) -> Result<CorrelationMatrix> {
+    let mut series = Vec::new();
+    for symbol in db.get_watchlist(watchlist)? {
+        let prices = db.get_prices(&symbol)?;
+        series.push((symbol, prices));
+    }
+    Ok(correlation_matrix(&series, lookback))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(symbol: &str, closes: &[f64]) -> Vec<DailyPrice> {
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| DailyPrice {
+                symbol: symbol.to_string(),
+                date: start + chrono::Duration::days(i as i64),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 1000,
+                source: "test".to_string(),
+            })
+            .collect()
+    }
+
+    /// Benchmark with alternating returns and a symbol with exactly 2x its returns
+    fn leveraged_pair(len: usize) -> (Vec<DailyPrice>, Vec<DailyPrice>) {
+        let mut bench = vec![100.0];
+        let mut sym = vec![50.0];
+        for i in 1..len {
+            let r = if i % 2 == 0 { 0.01 } else { -0.005 } * (1.0 + (i % 5) as f64 / 10.0);
+            bench.push(bench[i - 1] * (1.0 + r));
+            sym.push(sym[i - 1] * (1.0 + 2.0 * r));
+        }
+        (series("LEV", &sym), series("SPY", &bench))
+    }
+
+    #[test]
+    fn test_rolling_beta_and_correlation() {
+        let (sym, bench) = leveraged_pair(40);
+        let betas = calculate_rolling_beta(&sym, &bench, 20, "BETA_20_SPY");
+        let corrs = calculate_rolling_correlation(&sym, &bench, 20, "CORR_20_SPY");
+
+        assert_eq!(betas.len(), 39 - 20 + 1);
+        assert!((betas.last().unwrap().value - 2.0).abs() < 1e-9);
+        assert!((corrs.last().unwrap().value - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rs_ratio_and_ranks() {
+        let bench = series("SPY", &[100.0, 100.0, 100.0]);
+        let strong = series("AAA", &[10.0, 11.0, 12.0]);
+        let weak = series("BBB", &[10.0, 9.0, 8.0]);
+
+        let ratio = calculate_rs_ratio(&strong, &bench, "RS_RATIO_SPY");
+        assert_eq!(ratio[0].value, 1.0);
+        assert!((ratio[2].value - 1.2).abs() < 1e-9);
+
+        let mut momentum = calculate_rs_momentum(&strong, &bench, 2, "RS_MOM_2_SPY");
+        momentum.extend(calculate_rs_momentum(&weak, &bench, 2, "RS_MOM_2_SPY"));
+        let ranks = calculate_rs_ranks(&momentum, "RS_RANK_SPY");
+
+        let rank_of = |s: &str| ranks.iter().find(|r| r.symbol == s).unwrap().value;
+        assert_eq!(rank_of("AAA"), 100.0);
+        assert_eq!(rank_of("BBB"), 0.0);
+    }
+
+    #[test]
+    fn test_refreshing_some_symbols_keeps_universe_ranks() {
+        let mut db = Database::open_in_memory().unwrap();
+        db.init_schema().unwrap();
+        db.upsert_daily_prices(&series("SPY", &[100.0, 100.0, 100.0])).unwrap();
+        db.upsert_daily_prices(&series("AAA", &[10.0, 11.0, 12.0])).unwrap();
+        db.upsert_daily_prices(&series("BBB", &[10.0, 10.0, 10.0])).unwrap();
+        db.upsert_daily_prices(&series("CCC", &[10.0, 9.0, 8.0])).unwrap();
+
+        let config = AnalyticsConfig {
+            rs_lookback: 2,
+            ..AnalyticsConfig::default()
+        };
+        let rank = |db: &Database| {
+            db.get_latest_indicator_value("BBB", &config.rs_rank_name())
+                .unwrap()
+                .unwrap()
+        };
+        let all: Vec<String> = ["AAA", "BBB", "CCC"].iter().map(|s| s.to_string()).collect();
+        update_relative_analytics(&mut db, &all, &config).unwrap();
+        assert_eq!(rank(&db), 50.0);
+
+        update_relative_analytics(&mut db, &["BBB".to_string()], &config).unwrap();
+        assert_eq!(rank(&db), 50.0);
+
+        // Against CCC alone BBB would rank 100
+        let pair = vec!["BBB".to_string(), "CCC".to_string()];
+        update_relative_analytics(&mut db, &pair, &config).unwrap();
+        assert_eq!(rank(&db), 50.0);
+    }
+
+    #[test]
+    fn test_correlation_matrix() {
+        let (sym, bench) = leveraged_pair(30);
+        let matrix = correlation_matrix(
+            &[("LEV".to_string(), sym), ("SPY".to_string(), bench)],
+            20,
+        );
+        assert_eq!(matrix.values[0][0], 1.0);
+        assert!((matrix.values[0][1] - 1.0).abs() < 1e-9);
+        assert_eq!(matrix.values[0][1], matrix.values[1][0]);
+    }
+}
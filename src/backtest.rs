@@ -2,41 +2,231 @@
 //!
 //! Simulates trading strategies against historical data
 
+use crate::error::Result;
+use crate::execution::{ExecutionModel, FillAt, OrderSide};
+use crate::metrics::{self, Benchmark};
 use crate::models::{
-    BacktestResult, BacktestTrade, DailyPrice, PerformanceMetrics, Strategy, StrategyConditionType,
+    BacktestResult, BacktestTrade, DailyPrice, EquityPoint, ExecutionCosts,
+    PerformanceMetrics, RuleNode, Strategy, StrategyConditionType, StrategyDirection, SymbolTradeStats,
     TechnicalIndicator, TradeDirection,
 };
+use crate::regime::{RegimeConfig, RegimeSeries};
+use crate::rules::{condition_met, RuleContext};
+use crate::signals::{is_signal_indicator, SignalEngine};
+use crate::sizing::SizingContext;
+use crate::stops::{FixedStops, StopExit, StopTracker};
 use chrono::NaiveDate;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+
+/// When orders generated by a bar's signals are filled
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FillTiming {
+    /// At the close of the signal bar (assumes the signal is known just
+    /// before the close)
+    #[default]
+    SameClose,
+    /// At the open of the following bar
+    NextOpen,
+    /// At the close of the following bar
+    NextClose,
+}
+
+impl FillTiming {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FillTiming::SameClose => "same_close",
+            FillTiming::NextOpen => "next_open",
+            FillTiming::NextClose => "next_close",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "same_close" => Some(FillTiming::SameClose),
+            "next_open" => Some(FillTiming::NextOpen),
+            "next_close" => Some(FillTiming::NextClose),
+            _ => None,
+        }
+    }
+}
+
+/// File format of an exported equity curve
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Render the daily equity, cash, exposure and drawdown series of a backtest
+pub fn export_equity_curve(curve: &[EquityPoint], format: ExportFormat) -> Result<String> {
+    match format {
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(curve)?),
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for point in curve {
+                writer.serialize(point)?;
+            }
+            let bytes = writer
+                .into_inner()
+                .map_err(|e| e.into_error())?;
+            Ok(String::from_utf8_lossy(&bytes).into_owned())
+        }
+    }
+}
+
+/// Whether a strategy's entry or exit reads signal or confluence indicators
+fn uses_signals(strategy: &Strategy) -> bool {
+    let side = |rule: &Option<RuleNode>, condition: StrategyConditionType| match rule {
+        Some(rule) => rule.uses_signals(),
+        None => condition.uses_signals(),
+    };
+    side(&strategy.entry_rule, strategy.entry_condition)
+        || side(&strategy.exit_rule, strategy.exit_condition)
+}
+
+/// `indicators` plus `engine`'s signals and confluence as pseudo-indicators,
+/// when the strategy trades them and they are not already there
+pub fn with_signal_indicators<'a>(
+    engine: &SignalEngine,
+    strategy: &Strategy,
+    symbol: &str,
+    prices: &[DailyPrice],
+    indicators: &'a [TechnicalIndicator],
+) -> Cow<'a, [TechnicalIndicator]> {
+    if !uses_signals(strategy) || indicators.iter().any(|i| is_signal_indicator(&i.indicator_name)) {
+        return Cow::Borrowed(indicators);
+    }
+    let mut all = indicators.to_vec();
+    all.extend(engine.signal_indicators(symbol, indicators, prices));
+    Cow::Owned(all)
+}
+
+/// Opening price of a bar, falling back to the close when the feed has no open
+pub(crate) fn bar_open(bar: &DailyPrice) -> f64 {
+    if bar.open > 0.0 {
+        bar.open
+    } else {
+        bar.close
+    }
+}
 
 /// Backtest configuration
 #[derive(Debug, Clone)]
 pub struct BacktestConfig {
     pub initial_capital: f64,
-    pub commission_per_trade: f64,
+    /// Commission, slippage, spread and liquidity model for fills
+    pub execution: ExecutionModel,
+    /// Equity set aside per dollar shorted (%). 100 = unlevered,
+    /// 50 = Reg T initial margin (shorts up to 2x the allocated cash)
+    pub short_margin_percent: f64,
+    /// Annual stock borrow fee on the short's entry value (%), charged per
+    /// calendar day held
+    pub borrow_rate_percent: f64,
+    /// When signal orders are filled; stops always trade intrabar
+    pub fill_timing: FillTiming,
+    /// Annual risk-free rate for Sharpe, Sortino and alpha (%)
+    pub risk_free_rate_percent: f64,
+    /// Prices to measure alpha, beta and yearly excess returns against
+    pub benchmark: Option<Benchmark>,
 }
 
 impl Default for BacktestConfig {
     fn default() -> Self {
         Self {
             initial_capital: 10000.0,
-            commission_per_trade: 0.0,
+            execution: ExecutionModel::default(),
+            short_margin_percent: 100.0,
+            borrow_rate_percent: 0.0,
+            fill_timing: FillTiming::default(),
+            risk_free_rate_percent: 0.0,
+            benchmark: None,
         }
     }
 }
 
 /// Open position during backtest
 #[derive(Debug, Clone)]
-struct OpenPosition {
-    entry_date: NaiveDate,
-    entry_price: f64,
-    shares: f64,
-    entry_reason: String,
+pub(crate) struct OpenPosition {
+    pub(crate) direction: TradeDirection,
+    pub(crate) entry_date: NaiveDate,
+    pub(crate) entry_price: f64,
+    pub(crate) shares: f64,
+    pub(crate) entry_reason: String,
+    pub(crate) entry_commission: f64,
+    pub(crate) stops: StopTracker,
+}
+
+impl OpenPosition {
+    /// Signed market value: positive for longs, negative for shorts
+    pub(crate) fn market_value(&self, price: f64) -> f64 {
+        match self.direction {
+            TradeDirection::Long => self.shares * price,
+            TradeDirection::Short => -self.shares * price,
+        }
+    }
+
+    /// Split `fraction` of the shares (and entry commission) into a new
+    /// position, e.g. for a scale-out
+    fn split_off(&mut self, fraction: f64) -> OpenPosition {
+        let part = OpenPosition {
+            shares: self.shares * fraction,
+            entry_commission: self.entry_commission * fraction,
+            ..self.clone()
+        };
+        self.shares -= part.shares;
+        self.entry_commission -= part.entry_commission;
+        part
+    }
+
+    /// Gross P/L per share at `price`
+    fn gain_per_share(&self, price: f64) -> f64 {
+        match self.direction {
+            TradeDirection::Long => price - self.entry_price,
+            TradeDirection::Short => self.entry_price - price,
+        }
+    }
+}
+
+/// Account state of a single-symbol backtest
+#[derive(Debug, Default)]
+struct Book {
+    cash: f64,
+    position: Option<OpenPosition>,
+    trades: Vec<BacktestTrade>,
+    costs: ExecutionCosts,
+    /// Exit reason of an order to fill on the next bar
+    pending_exit: Option<String>,
+    /// Side, reason and signal-bar ATR_14 of an entry to fill on the next bar
+    pending_entry: Option<(TradeDirection, String, Option<f64>)>,
 }
 
 /// Main backtesting engine
 pub struct BacktestEngine {
     config: BacktestConfig,
+    /// Generates the signal pseudo-indicators that signal conditions trade
+    signal_engine: SignalEngine,
 }
 
 impl Default for BacktestEngine {
@@ -47,11 +237,29 @@ impl Default for BacktestEngine {
 
 impl BacktestEngine {
     pub fn new(config: BacktestConfig) -> Self {
-        Self { config }
+        Self {
+            config,
+            signal_engine: SignalEngine::new(),
+        }
+    }
+
+    /// Trade signals from `signal_engine` (e.g. `configured_signal_engine`)
+    /// instead of the default detectors
+    pub fn with_signal_engine(mut self, signal_engine: SignalEngine) -> Self {
+        self.signal_engine = signal_engine;
+        self
+    }
+
+    pub fn config(&self) -> &BacktestConfig {
+        &self.config
+    }
+
+    pub fn signal_engine(&self) -> &SignalEngine {
+        &self.signal_engine
     }
 
     /// Build indicator map by date for O(1) lookups
-    fn build_indicator_map(
+    pub(crate) fn build_indicator_map(
         &self,
         indicators: &[TechnicalIndicator],
     ) -> HashMap<NaiveDate, HashMap<String, f64>> {
@@ -67,209 +275,302 @@ impl BacktestEngine {
     }
 
     /// Check if entry condition is met
-    fn check_entry_condition(
+    fn check_entry_condition(&self, strategy: &Strategy, ctx: &RuleContext) -> bool {
+        if let Some(rule) = &strategy.entry_rule {
+            return rule.evaluate(ctx);
+        }
+        let Some(today) = ctx.indicators_at(0) else {
+            return false;
+        };
+        condition_met(
+            strategy.entry_condition,
+            strategy.entry_threshold,
+            strategy.entry_formula.as_deref(),
+            strategy.rs_benchmark.as_deref(),
+            ctx.bars[ctx.index].close,
+            today,
+            ctx.indicators_at(1),
+        )
+    }
+
+    /// Check if exit rule/condition is met, returning the exit reason
+    fn check_exit_signal(&self, strategy: &Strategy, ctx: &RuleContext) -> Option<String> {
+        if let Some(rule) = &strategy.exit_rule {
+            return rule.evaluate(ctx).then(|| rule.describe());
+        }
+
+        let today = ctx.indicators_at(0)?;
+        condition_met(
+            strategy.exit_condition,
+            strategy.exit_threshold,
+            strategy.exit_formula.as_deref(),
+            strategy.rs_benchmark.as_deref(),
+            ctx.bars[ctx.index].close,
+            today,
+            ctx.indicators_at(1),
+        )
+        .then(|| strategy.exit_condition.as_str().to_string())
+    }
+
+    fn entry_reason(strategy: &Strategy) -> String {
+        match &strategy.entry_rule {
+            Some(rule) => rule.describe(),
+            None => strategy.entry_condition.as_str().to_string(),
+        }
+    }
+
+    /// Stop-loss and take-profit percents. Legacy `StopLoss`/`TakeProfit`
+    /// exit conditions use their threshold; the tighter level wins.
+    fn fixed_stops(strategy: &Strategy) -> FixedStops {
+        let from_exit = |condition: StrategyConditionType| {
+            (strategy.exit_rule.is_none() && strategy.exit_condition == condition)
+                .then_some(strategy.exit_threshold)
+        };
+        let tighter = |a: Option<f64>, b: Option<f64>| match (a, b) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        FixedStops {
+            stop_loss_percent: tighter(strategy.stop_loss_percent, from_exit(StrategyConditionType::StopLoss)),
+            take_profit_percent: tighter(strategy.take_profit_percent, from_exit(StrategyConditionType::TakeProfit)),
+        }
+    }
+
+    /// Stop, target, trailing, time and scale-out exits triggered on the last
+    /// of `bars`, in execution order. Advances the position's stop state.
+    pub(crate) fn check_stops(
         &self,
         strategy: &Strategy,
-        price: f64,
-        today: &HashMap<String, f64>,
-        prev: Option<&HashMap<String, f64>>,
-    ) -> bool {
-        match strategy.entry_condition {
-            StrategyConditionType::RsiOversold => {
-                today.get("RSI_14").map_or(false, |&rsi| rsi < strategy.entry_threshold)
-            }
-            StrategyConditionType::RsiOverbought => {
-                today.get("RSI_14").map_or(false, |&rsi| rsi > strategy.entry_threshold)
-            }
-            StrategyConditionType::MacdCrossUp => {
-                if let (Some(prev_ind), Some(macd), Some(signal)) = (
-                    prev,
-                    today.get("MACD_12_26"),
-                    today.get("MACD_SIGNAL_9"),
-                ) {
-                    if let (Some(&prev_macd), Some(&prev_signal)) = (
-                        prev_ind.get("MACD_12_26"),
-                        prev_ind.get("MACD_SIGNAL_9"),
-                    ) {
-                        prev_macd <= prev_signal && *macd > *signal
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            }
-            StrategyConditionType::MacdCrossDown => {
-                if let (Some(prev_ind), Some(macd), Some(signal)) = (
-                    prev,
-                    today.get("MACD_12_26"),
-                    today.get("MACD_SIGNAL_9"),
-                ) {
-                    if let (Some(&prev_macd), Some(&prev_signal)) = (
-                        prev_ind.get("MACD_12_26"),
-                        prev_ind.get("MACD_SIGNAL_9"),
-                    ) {
-                        prev_macd >= prev_signal && *macd < *signal
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            }
-            StrategyConditionType::PriceAboveSma => {
-                today.get("SMA_20").map_or(false, |&sma| price > sma)
-            }
-            StrategyConditionType::PriceBelowSma => {
-                today.get("SMA_20").map_or(false, |&sma| price < sma)
-            }
-            StrategyConditionType::SmaCrossUp => {
-                if let (Some(prev_ind), Some(&fast), Some(&slow)) =
-                    (prev, today.get("SMA_20"), today.get("SMA_50"))
-                {
-                    if let (Some(&prev_fast), Some(&prev_slow)) =
-                        (prev_ind.get("SMA_20"), prev_ind.get("SMA_50"))
-                    {
-                        prev_fast <= prev_slow && fast > slow
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            }
-            StrategyConditionType::SmaCrossDown => {
-                if let (Some(prev_ind), Some(&fast), Some(&slow)) =
-                    (prev, today.get("SMA_20"), today.get("SMA_50"))
-                {
-                    if let (Some(&prev_fast), Some(&prev_slow)) =
-                        (prev_ind.get("SMA_20"), prev_ind.get("SMA_50"))
-                    {
-                        prev_fast >= prev_slow && fast < slow
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            }
-            // StopLoss and TakeProfit are exit-only conditions
-            StrategyConditionType::StopLoss | StrategyConditionType::TakeProfit => false,
+        bars: &[DailyPrice],
+        atr: Option<f64>,
+        position: &mut OpenPosition,
+    ) -> Vec<StopExit> {
+        position
+            .stops
+            .check(&strategy.exit_rules, Self::fixed_stops(strategy), bars, atr)
+    }
+
+    /// Apply a stop exit at its price on the last of `bars`, returning the
+    /// cash delta, the trade and what is left of the position
+    pub(crate) fn stop_out(
+        &self,
+        symbol: &str,
+        mut position: OpenPosition,
+        bars: &[DailyPrice],
+        exit: StopExit,
+        costs: &mut ExecutionCosts,
+    ) -> (f64, BacktestTrade, Option<OpenPosition>) {
+        if exit.closes_position() {
+            let at = exit.fill_at();
+            let (cash_delta, trade) = self.close_position(symbol, position, bars, at, exit.reason, costs);
+            return (cash_delta, trade, None);
+        }
+        let part = position.split_off(exit.fraction);
+        if exit.reason == "scale_out" {
+            position.stops.record_scale_out();
         }
+        let at = exit.fill_at();
+        let (cash_delta, trade) = self.close_position(symbol, part, bars, at, exit.reason, costs);
+        (cash_delta, trade, Some(position))
     }
 
-    /// Check if exit condition is met
-    fn check_exit_condition(
+    /// Exit signal for an open position on this bar's close, if any
+    pub(crate) fn check_exit_condition(
         &self,
         strategy: &Strategy,
-        price: f64,
-        entry_price: f64,
-        today: &HashMap<String, f64>,
-        prev: Option<&HashMap<String, f64>>,
-    ) -> (bool, String) {
-        // Check stop loss
-        if let Some(stop_loss_pct) = strategy.stop_loss_percent {
-            let stop_price = entry_price * (1.0 - stop_loss_pct / 100.0);
-            if price <= stop_price {
-                return (true, "stop_loss".to_string());
-            }
+        ctx: &RuleContext,
+        position: &OpenPosition,
+    ) -> Option<String> {
+        // A long/short strategy covers shorts on its entry (go long) signal
+        if position.direction == TradeDirection::Short
+            && strategy.direction == StrategyDirection::LongShort
+        {
+            self.check_entry_condition(strategy, ctx)
+                .then(|| Self::entry_reason(strategy))
+        } else {
+            self.check_exit_signal(strategy, ctx)
         }
+    }
 
-        // Check take profit
-        if let Some(take_profit_pct) = strategy.take_profit_percent {
-            let target_price = entry_price * (1.0 + take_profit_pct / 100.0);
-            if price >= target_price {
-                return (true, "take_profit".to_string());
-            }
+    /// Side and reason of a new position to open on this bar, if any
+    pub(crate) fn check_open_signal(
+        &self,
+        strategy: &Strategy,
+        ctx: &RuleContext,
+    ) -> Option<(TradeDirection, String)> {
+        let entry = || {
+            self.check_entry_condition(strategy, ctx)
+                .then(|| Self::entry_reason(strategy))
+        };
+        match strategy.direction {
+            StrategyDirection::LongOnly => entry().map(|r| (TradeDirection::Long, r)),
+            StrategyDirection::ShortOnly => entry().map(|r| (TradeDirection::Short, r)),
+            StrategyDirection::LongShort => entry()
+                .map(|r| (TradeDirection::Long, r))
+                .or_else(|| {
+                    self.check_exit_signal(strategy, ctx)
+                        .map(|r| (TradeDirection::Short, r))
+                }),
         }
+    }
 
-        // Check strategy exit condition
-        let condition_met = match strategy.exit_condition {
-            StrategyConditionType::RsiOversold => {
-                today.get("RSI_14").map_or(false, |&rsi| rsi < strategy.exit_threshold)
-            }
-            StrategyConditionType::RsiOverbought => {
-                today.get("RSI_14").map_or(false, |&rsi| rsi > strategy.exit_threshold)
-            }
-            StrategyConditionType::MacdCrossUp => {
-                if let (Some(prev_ind), Some(macd), Some(signal)) = (
-                    prev,
-                    today.get("MACD_12_26"),
-                    today.get("MACD_SIGNAL_9"),
-                ) {
-                    if let (Some(&prev_macd), Some(&prev_signal)) = (
-                        prev_ind.get("MACD_12_26"),
-                        prev_ind.get("MACD_SIGNAL_9"),
-                    ) {
-                        prev_macd <= prev_signal && *macd > *signal
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            }
-            StrategyConditionType::MacdCrossDown => {
-                if let (Some(prev_ind), Some(macd), Some(signal)) = (
-                    prev,
-                    today.get("MACD_12_26"),
-                    today.get("MACD_SIGNAL_9"),
-                ) {
-                    if let (Some(&prev_macd), Some(&prev_signal)) = (
-                        prev_ind.get("MACD_12_26"),
-                        prev_ind.get("MACD_SIGNAL_9"),
-                    ) {
-                        prev_macd >= prev_signal && *macd < *signal
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            }
-            StrategyConditionType::PriceAboveSma => {
-                today.get("SMA_20").map_or(false, |&sma| price > sma)
-            }
-            StrategyConditionType::PriceBelowSma => {
-                today.get("SMA_20").map_or(false, |&sma| price < sma)
-            }
-            StrategyConditionType::SmaCrossUp => {
-                if let (Some(prev_ind), Some(&fast), Some(&slow)) =
-                    (prev, today.get("SMA_20"), today.get("SMA_50"))
-                {
-                    if let (Some(&prev_fast), Some(&prev_slow)) =
-                        (prev_ind.get("SMA_20"), prev_ind.get("SMA_50"))
-                    {
-                        prev_fast <= prev_slow && fast > slow
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
-            }
-            StrategyConditionType::SmaCrossDown => {
-                if let (Some(prev_ind), Some(&fast), Some(&slow)) =
-                    (prev, today.get("SMA_20"), today.get("SMA_50"))
-                {
-                    if let (Some(&prev_fast), Some(&prev_slow)) =
-                        (prev_ind.get("SMA_20"), prev_ind.get("SMA_50"))
-                    {
-                        prev_fast >= prev_slow && fast < slow
-                    } else {
-                        false
-                    }
-                } else {
-                    false
-                }
+    /// Open a position backed by `allocation` of equity filling `at` a price
+    /// on the last of `bars` (history up to the fill), returning the cash
+    /// delta and the position
+    pub(crate) fn open_position(
+        &self,
+        bars: &[DailyPrice],
+        at: FillAt,
+        direction: TradeDirection,
+        entry_reason: String,
+        allocation: f64,
+        costs: &mut ExecutionCosts,
+    ) -> Option<(f64, OpenPosition)> {
+        let index = bars.len() - 1;
+        let (side, position_value) = match direction {
+            TradeDirection::Long => (OrderSide::Buy, allocation),
+            TradeDirection::Short => (
+                OrderSide::Sell,
+                allocation / (self.config.short_margin_percent.max(1.0) / 100.0),
+            ),
+        };
+        let fill = self
+            .config
+            .execution
+            .fill_value(side, position_value, at, bars, index)?;
+        costs.record(&fill);
+
+        let pos = OpenPosition {
+            direction,
+            entry_date: bars[index].date,
+            entry_price: fill.price,
+            shares: fill.shares,
+            entry_reason,
+            entry_commission: fill.commission,
+            stops: StopTracker::new(direction, fill.price, bars[index].date),
+        };
+        // Longs pay for the shares; shorts receive the sale proceeds
+        let cash_delta = -pos.market_value(fill.price) - fill.commission;
+        Some((cash_delta, pos))
+    }
+
+    /// Close a position filling `at` a price on the last of `bars` (history
+    /// up to the fill), returning the cash delta and the trade
+    pub(crate) fn close_position(
+        &self,
+        symbol: &str,
+        position: OpenPosition,
+        bars: &[DailyPrice],
+        at: FillAt,
+        exit_reason: String,
+        costs: &mut ExecutionCosts,
+    ) -> (f64, BacktestTrade) {
+        let index = bars.len() - 1;
+        let date = bars[index].date;
+        let side = match position.direction {
+            TradeDirection::Long => OrderSide::Sell,
+            TradeDirection::Short => OrderSide::Buy,
+        };
+        let fill = self
+            .config
+            .execution
+            .fill(side, position.shares, at, bars, index);
+        costs.record(&fill);
+        let price = fill.price;
+
+        let borrow_cost = match position.direction {
+            TradeDirection::Long => 0.0,
+            TradeDirection::Short => {
+                let days = (date - position.entry_date).num_days().max(0) as f64;
+                position.entry_price * position.shares * self.config.borrow_rate_percent / 100.0
+                    * days
+                    / 365.0
             }
-            StrategyConditionType::StopLoss | StrategyConditionType::TakeProfit => false,
         };
 
-        if condition_met {
-            (true, strategy.exit_condition.as_str().to_string())
-        } else {
-            (false, String::new())
+        let profit_loss = position.gain_per_share(price) * position.shares
+            - position.entry_commission
+            - fill.commission
+            - borrow_cost;
+        let profit_loss_percent = position.gain_per_share(price) / position.entry_price * 100.0;
+        let cash_delta = position.market_value(price) - fill.commission - borrow_cost;
+
+        let trade = BacktestTrade {
+            id: 0,
+            backtest_id: 0,
+            symbol: symbol.to_string(),
+            direction: position.direction,
+            entry_date: position.entry_date,
+            entry_price: position.entry_price,
+            exit_date: Some(date),
+            exit_price: Some(price),
+            shares: position.shares,
+            entry_reason: position.entry_reason,
+            exit_reason: Some(exit_reason),
+            profit_loss: Some(profit_loss),
+            profit_loss_percent: Some(profit_loss_percent),
+        };
+
+        (cash_delta, trade)
+    }
+
+    fn exit(&self, book: &mut Book, symbol: &str, bars: &[DailyPrice], at: FillAt, reason: String) {
+        if let Some(pos) = book.position.take() {
+            let (cash_delta, trade) =
+                self.close_position(symbol, pos, bars, at, reason, &mut book.costs);
+            book.cash += cash_delta;
+            book.trades.push(trade);
+        }
+    }
+
+    fn enter(
+        &self,
+        book: &mut Book,
+        strategy: &Strategy,
+        bars: &[DailyPrice],
+        at: FillAt,
+        (direction, reason, atr): (TradeDirection, String, Option<f64>),
+    ) {
+        let trade_returns: Vec<f64> = book.trades.iter().filter_map(|t| t.profit_loss_percent).collect();
+        let sizing = SizingContext {
+            equity: book.cash,
+            capital: book.cash,
+            percent: strategy.position_size_percent,
+            price: at.price(),
+            atr,
+            stop_loss_percent: strategy.stop_loss_percent,
+            slots: 1,
+            margin_percent: match direction {
+                TradeDirection::Long => 100.0,
+                TradeDirection::Short => self.config.short_margin_percent.max(1.0),
+            },
+            trade_returns: &trade_returns,
+        };
+        let Some(allocation) = strategy
+            .position_sizing
+            .allocation(&sizing)
+            .map(|a| a.min(book.cash))
+            .filter(|a| *a > 0.0)
+        else {
+            return;
+        };
+        if let Some((cash_delta, pos)) =
+            self.open_position(bars, at, direction, reason, allocation, &mut book.costs)
+        {
+            book.cash += cash_delta;
+            book.position = Some(pos);
+        }
+    }
+
+    /// Fill orders queued by the previous bar's signals: exit first, then entry
+    fn fill_pending(&self, book: &mut Book, strategy: &Strategy, symbol: &str, bars: &[DailyPrice], at: FillAt) {
+        if let Some(reason) = book.pending_exit.take() {
+            self.exit(book, symbol, bars, at, reason);
+        }
+        if let Some(entry) = book.pending_entry.take() {
+            if book.position.is_none() {
+                self.enter(book, strategy, bars, at, entry);
+            }
         }
     }
 
@@ -281,125 +582,122 @@ impl BacktestEngine {
         prices: &[DailyPrice],
         indicators: &[TechnicalIndicator],
     ) -> BacktestResult {
-        let indicator_map = self.build_indicator_map(indicators);
+        let indicators =
+            with_signal_indicators(&self.signal_engine, strategy, symbol, prices, indicators);
+        let indicator_map = self.build_indicator_map(&indicators);
+        let regimes = if strategy.allowed_regimes.is_empty() {
+            RegimeSeries::default()
+        } else {
+            RegimeSeries::from_prices(prices, &RegimeConfig::default())
+        };
+        let timing = self.config.fill_timing;
 
-        let mut cash = self.config.initial_capital;
-        let mut position: Option<OpenPosition> = None;
-        let mut trades: Vec<BacktestTrade> = Vec::new();
-        let mut equity_history: Vec<f64> = Vec::new();
+        let mut book = Book {
+            cash: self.config.initial_capital,
+            ..Book::default()
+        };
+        let mut equity_curve: Vec<EquityPoint> = Vec::new();
 
         // Sort prices by date
         let mut sorted_prices = prices.to_vec();
         sorted_prices.sort_by_key(|p| p.date);
+        let first_close = sorted_prices.first().map_or(0.0, |p| p.close);
 
         // Walk through each day
-        for (i, price_data) in sorted_prices.iter().enumerate() {
-            let date = price_data.date;
-            let price = price_data.close;
-
-            let today_indicators = indicator_map.get(&date);
-            let prev_indicators = if i > 0 {
-                indicator_map.get(&sorted_prices[i - 1].date)
-            } else {
-                None
+        for (i, bar) in sorted_prices.iter().enumerate() {
+            let history = &sorted_prices[..=i];
+            let ctx = RuleContext {
+                bars: &sorted_prices,
+                index: i,
+                indicators: &indicator_map,
             };
+            let atr_at = |bars_ago| ctx.indicators_at(bars_ago).and_then(|day| day.get("ATR_14").copied());
 
-            // Calculate current equity
-            let current_equity = if let Some(ref pos) = position {
-                cash + pos.shares * price
-            } else {
-                cash
-            };
-            equity_history.push(current_equity);
-
-            // Skip if no indicators for today
-            let Some(today) = today_indicators else {
-                continue;
-            };
+            if timing == FillTiming::NextOpen {
+                self.fill_pending(&mut book, strategy, symbol, history, FillAt::Open(bar_open(bar)));
+            }
 
-            // If we have a position, check exit conditions
-            if let Some(ref pos) = position {
-                let (should_exit, exit_reason) =
-                    self.check_exit_condition(strategy, price, pos.entry_price, today, prev_indicators);
-
-                if should_exit {
-                    // Close position
-                    let profit_loss = (price - pos.entry_price) * pos.shares - self.config.commission_per_trade;
-                    let profit_loss_percent = (price - pos.entry_price) / pos.entry_price * 100.0;
-
-                    cash += pos.shares * price - self.config.commission_per_trade;
-
-                    trades.push(BacktestTrade {
-                        id: 0,
-                        backtest_id: 0,
-                        symbol: symbol.to_string(),
-                        direction: TradeDirection::Long,
-                        entry_date: pos.entry_date,
-                        entry_price: pos.entry_price,
-                        exit_date: Some(date),
-                        exit_price: Some(price),
-                        shares: pos.shares,
-                        entry_reason: pos.entry_reason.clone(),
-                        exit_reason: Some(exit_reason),
-                        profit_loss: Some(profit_loss),
-                        profit_loss_percent: Some(profit_loss_percent),
-                    });
-
-                    position = None;
+            // Resting stop and target orders trade intrabar
+            if let Some(mut pos) = book.position.take() {
+                let atr = atr_at(0);
+                let exits = self.check_stops(strategy, history, atr, &mut pos);
+                let mut remaining = Some(pos);
+                for exit in exits {
+                    let Some(open) = remaining.take() else {
+                        break;
+                    };
+                    let (cash_delta, trade, rest) = self.stop_out(symbol, open, history, exit, &mut book.costs);
+                    book.cash += cash_delta;
+                    book.trades.push(trade);
+                    remaining = rest;
+                }
+                if remaining.is_none() {
+                    book.pending_exit = None;
                 }
+                book.position = remaining;
             }
 
-            // If no position, check entry conditions
-            if position.is_none() {
-                if self.check_entry_condition(strategy, price, today, prev_indicators) {
-                    // Open position
-                    let position_value = cash * (strategy.position_size_percent / 100.0);
-                    let shares = (position_value - self.config.commission_per_trade) / price;
-
-                    if shares > 0.0 {
-                        cash -= shares * price + self.config.commission_per_trade;
-
-                        position = Some(OpenPosition {
-                            entry_date: date,
-                            entry_price: price,
-                            shares,
-                            entry_reason: strategy.entry_condition.as_str().to_string(),
-                        });
+            if timing == FillTiming::NextClose {
+                self.fill_pending(&mut book, strategy, symbol, history, FillAt::Close(bar.close));
+            }
+
+            // Signals need today's indicators
+            if ctx.indicators_at(0).is_some() {
+                let exit = match (&book.position, &book.pending_exit) {
+                    (Some(pos), None) => self.check_exit_condition(strategy, &ctx, pos),
+                    _ => None,
+                };
+                if let Some(reason) = exit {
+                    if timing == FillTiming::SameClose {
+                        self.exit(&mut book, symbol, history, FillAt::Close(bar.close), reason);
+                    } else {
+                        book.pending_exit = Some(reason);
+                    }
+                }
+
+                // Enter when flat or about to be (only in allowed regimes)
+                let flat = book.position.is_none() || book.pending_exit.is_some();
+                if flat
+                    && book.pending_entry.is_none()
+                    && regimes.allows(bar.date, &strategy.allowed_regimes)
+                {
+                    if let Some((direction, reason)) = self.check_open_signal(strategy, &ctx) {
+                        if timing == FillTiming::SameClose {
+                            let entry = (direction, reason, atr_at(0));
+                            self.enter(&mut book, strategy, history, FillAt::Close(bar.close), entry);
+                        } else {
+                            book.pending_entry = Some((direction, reason, atr_at(0)));
+                        }
                     }
                 }
             }
+
+            // End-of-day equity (short proceeds are held in cash)
+            let position_value = book.position.as_ref().map_or(0.0, |pos| pos.market_value(bar.close));
+            equity_curve.push(EquityPoint {
+                date: bar.date,
+                equity: book.cash + position_value,
+                cash: book.cash,
+                exposure: position_value.abs(),
+                open_positions: usize::from(book.position.is_some()),
+                drawdown: 0.0,
+                buy_and_hold: (first_close > 0.0)
+                    .then(|| self.config.initial_capital * bar.close / first_close),
+            });
         }
 
-        // Close any remaining position at end
-        if let Some(pos) = position {
-            if let Some(last_price) = sorted_prices.last() {
-                let profit_loss =
-                    (last_price.close - pos.entry_price) * pos.shares - self.config.commission_per_trade;
-                let profit_loss_percent =
-                    (last_price.close - pos.entry_price) / pos.entry_price * 100.0;
-
-                cash += pos.shares * last_price.close;
-
-                trades.push(BacktestTrade {
-                    id: 0,
-                    backtest_id: 0,
-                    symbol: symbol.to_string(),
-                    direction: TradeDirection::Long,
-                    entry_date: pos.entry_date,
-                    entry_price: pos.entry_price,
-                    exit_date: Some(last_price.date),
-                    exit_price: Some(last_price.close),
-                    shares: pos.shares,
-                    entry_reason: pos.entry_reason,
-                    exit_reason: Some("end_of_data".to_string()),
-                    profit_loss: Some(profit_loss),
-                    profit_loss_percent: Some(profit_loss_percent),
-                });
-            }
+        // Close any remaining position at end; unfilled orders lapse
+        if let Some(last) = sorted_prices.last() {
+            let at = FillAt::Close(last.close);
+            self.exit(&mut book, symbol, &sorted_prices, at, "end_of_data".to_string());
         }
+        let Book {
+            cash, trades, costs, ..
+        } = book;
+        metrics::mark_drawdowns(&mut equity_curve, self.config.initial_capital);
 
         // Calculate metrics
-        let metrics = self.calculate_metrics(&trades, &equity_history);
+        let metrics = self.calculate_metrics(&trades, &equity_curve, costs);
 
         let start_date = sorted_prices.first().map(|p| p.date).unwrap_or_else(|| {
             NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()
@@ -418,146 +716,389 @@ impl BacktestEngine {
             initial_capital: self.config.initial_capital,
             final_capital: cash,
             metrics,
+            symbol_stats: symbol_trade_stats(&trades),
             trades,
+            equity_curve,
             created_at: String::new(),
         }
     }
 
     /// Calculate performance metrics
-    fn calculate_metrics(&self, trades: &[BacktestTrade], equity_history: &[f64]) -> PerformanceMetrics {
-        let initial = self.config.initial_capital;
-        let final_equity = *equity_history.last().unwrap_or(&initial);
-
-        let total_return_dollars = final_equity - initial;
-        let total_return = (total_return_dollars / initial) * 100.0;
-
-        // Max drawdown
-        let mut max_drawdown = 0.0;
-        let mut peak = initial;
-        for &equity in equity_history {
-            if equity > peak {
-                peak = equity;
-            }
-            let drawdown = (peak - equity) / peak * 100.0;
-            if drawdown > max_drawdown {
-                max_drawdown = drawdown;
+    pub(crate) fn calculate_metrics(
+        &self,
+        trades: &[BacktestTrade],
+        equity_curve: &[EquityPoint],
+        costs: ExecutionCosts,
+    ) -> PerformanceMetrics {
+        metrics::performance_metrics(
+            trades,
+            equity_curve,
+            self.config.initial_capital,
+            costs,
+            self.config.risk_free_rate_percent,
+            self.config.benchmark.as_ref(),
+        )
+    }
+}
+
+/// Trade statistics per symbol, sorted by symbol
+pub fn symbol_trade_stats(trades: &[BacktestTrade]) -> Vec<SymbolTradeStats> {
+    let mut by_symbol: BTreeMap<&str, Vec<&BacktestTrade>> = BTreeMap::new();
+    for trade in trades {
+        by_symbol.entry(&trade.symbol).or_default().push(trade);
+    }
+
+    by_symbol
+        .into_iter()
+        .map(|(symbol, trades)| {
+            let pnl: Vec<f64> = trades.iter().map(|t| t.profit_loss.unwrap_or(0.0)).collect();
+            let winning_trades = pnl.iter().filter(|p| **p > 0.0).count();
+            let n = trades.len() as f64;
+            SymbolTradeStats {
+                symbol: symbol.to_string(),
+                total_trades: trades.len(),
+                winning_trades,
+                losing_trades: pnl.iter().filter(|p| **p < 0.0).count(),
+                win_rate: winning_trades as f64 / n * 100.0,
+                total_profit: pnl.iter().sum(),
+                avg_profit_percent: trades.iter().map(|t| t.profit_loss_percent.unwrap_or(0.0)).sum::<f64>() / n,
             }
-        }
+        })
+        .collect()
+}
 
-        // Trade statistics
-        let winning_trades: Vec<_> = trades
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        CompareOp, ExitRules, PositionSizing, PriceField, RuleNode, RuleOperand, ScaleOut, StrategyConditionType, TrailingStop,
+    };
+
+    fn bars(closes: &[f64]) -> (Vec<DailyPrice>, Vec<TechnicalIndicator>) {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let prices: Vec<DailyPrice> = closes
             .iter()
-            .filter(|t| t.profit_loss.unwrap_or(0.0) > 0.0)
+            .enumerate()
+            .map(|(i, &close)| DailyPrice {
+                symbol: "TEST".to_string(),
+                date: start + chrono::Duration::days(i as i64),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 1000,
+                source: "test".to_string(),
+            })
             .collect();
-        let losing_trades: Vec<_> = trades
+        // Rules only read prices, but bars without indicators are skipped
+        let indicators = prices
             .iter()
-            .filter(|t| t.profit_loss.unwrap_or(0.0) < 0.0)
+            .map(|p| TechnicalIndicator {
+                symbol: "TEST".to_string(),
+                date: p.date,
+                indicator_name: "RSI_14".to_string(),
+                value: 50.0,
+            })
             .collect();
+        (prices, indicators)
+    }
 
-        let total_trades = trades.len();
-        let num_winners = winning_trades.len();
-        let num_losers = losing_trades.len();
-
-        let win_rate = if total_trades > 0 {
-            (num_winners as f64 / total_trades as f64) * 100.0
-        } else {
-            0.0
-        };
+    /// Entry when the close is above `level`, exit when below it
+    fn strategy(direction: StrategyDirection, level: f64) -> Strategy {
+        let close = || RuleOperand::price(PriceField::Close);
+        Strategy {
+            id: 1,
+            name: "test".to_string(),
+            description: None,
+            entry_condition: StrategyConditionType::RsiOversold,
+            entry_threshold: 30.0,
+            exit_condition: StrategyConditionType::RsiOverbought,
+            exit_threshold: 70.0,
+            stop_loss_percent: None,
+            take_profit_percent: None,
+            position_size_percent: 100.0,
+            entry_formula: None,
+            exit_formula: None,
+            rs_benchmark: None,
+            allowed_regimes: vec![],
+            entry_rule: Some(RuleNode::compare(close(), CompareOp::Gt, RuleOperand::constant(level))),
+            exit_rule: Some(RuleNode::compare(close(), CompareOp::Lt, RuleOperand::constant(level))),
+            direction,
+            exit_rules: ExitRules::default(),
+            position_sizing: PositionSizing::default(),
+            created_at: String::new(),
+        }
+    }
 
-        let avg_win = if !winning_trades.is_empty() {
-            winning_trades
-                .iter()
-                .map(|t| t.profit_loss_percent.unwrap_or(0.0))
-                .sum::<f64>()
-                / winning_trades.len() as f64
-        } else {
-            0.0
-        };
+    #[test]
+    fn test_short_profits_from_decline_minus_borrow() {
+        let (prices, indicators) = bars(&[100.0, 110.0, 100.0, 80.0, 80.0]);
+        let engine = BacktestEngine::new(BacktestConfig {
+            borrow_rate_percent: 36.5, // 0.1% per day
+            ..BacktestConfig::default()
+        });
+        // Short on close > 105, cover on close < 105
+        let result = engine.run(&strategy(StrategyDirection::ShortOnly, 105.0), "TEST", &prices, &indicators);
 
-        let avg_loss = if !losing_trades.is_empty() {
-            losing_trades
-                .iter()
-                .map(|t| t.profit_loss_percent.unwrap_or(0.0).abs())
-                .sum::<f64>()
-                / losing_trades.len() as f64
-        } else {
-            0.0
-        };
+        assert_eq!(result.trades.len(), 1);
+        let trade = &result.trades[0];
+        assert_eq!(trade.direction, TradeDirection::Short);
+        assert_eq!(trade.exit_price, Some(100.0));
+        // 10000 / 110 shares, +10 each, one day of borrow on the 10000 entry value
+        let expected = 10000.0 / 110.0 * 10.0 - 10.0;
+        assert!((trade.profit_loss.unwrap() - expected).abs() < 1e-6);
+        assert!((result.final_capital - (10000.0 + expected)).abs() < 1e-6);
+        assert_eq!(result.metrics.short.total_trades, 1);
+        assert_eq!(result.metrics.long.total_trades, 0);
+    }
 
-        let gross_profit: f64 = winning_trades
-            .iter()
-            .map(|t| t.profit_loss.unwrap_or(0.0))
-            .sum();
-        let gross_loss: f64 = losing_trades
+    #[test]
+    fn test_confluence_conditions_trade_signal_engine_output() {
+        let (prices, _) = bars(&[100.0, 101.0, 102.0, 103.0, 104.0]);
+        // Oversold RSI, stochastic and CCI agree on day 1; overbought on day 3
+        let readings = [(50.0, 50.0, 0.0), (25.0, 15.0, -150.0), (50.0, 50.0, 0.0), (80.0, 90.0, 150.0), (50.0, 50.0, 0.0)];
+        let indicators: Vec<TechnicalIndicator> = prices
             .iter()
-            .map(|t| t.profit_loss.unwrap_or(0.0).abs())
-            .sum();
+            .zip(readings)
+            .flat_map(|(p, (rsi, stoch, cci))| {
+                [("RSI_14", rsi), ("STOCH_K_14", stoch), ("CCI_20", cci)].map(|(name, value)| TechnicalIndicator {
+                    symbol: "TEST".to_string(),
+                    date: p.date,
+                    indicator_name: name.to_string(),
+                    value,
+                })
+            })
+            .collect();
 
-        let profit_factor = if gross_loss > 0.0 {
-            gross_profit / gross_loss
-        } else if gross_profit > 0.0 {
-            f64::INFINITY
-        } else {
-            0.0
-        };
+        let mut strat = strategy(StrategyDirection::LongOnly, 0.0);
+        strat.entry_rule = None;
+        strat.exit_rule = None;
+        strat.entry_condition = StrategyConditionType::ConfluenceBullish;
+        strat.entry_threshold = 0.1;
+        strat.exit_condition = StrategyConditionType::ConfluenceBearish;
+        strat.exit_threshold = 0.1;
 
-        // Average trade duration
-        let avg_duration = if !trades.is_empty() {
-            trades
-                .iter()
-                .filter_map(|t| {
-                    t.exit_date.map(|exit| {
-                        (exit - t.entry_date).num_days() as f64
-                    })
-                })
-                .sum::<f64>()
-                / trades.len() as f64
-        } else {
-            0.0
+        let result = BacktestEngine::default().run(&strat, "TEST", &prices, &indicators);
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.trades[0].entry_price, 101.0);
+        assert_eq!(result.trades[0].exit_price, Some(103.0));
+
+        // Too strong a requirement never enters
+        strat.entry_threshold = 1.0;
+        assert!(BacktestEngine::default().run(&strat, "TEST", &prices, &indicators).trades.is_empty());
+    }
+
+    #[test]
+    fn test_position_sizing_models() {
+        let (prices, mut indicators) = bars(&[100.0, 110.0, 120.0, 100.0]);
+        let engine = BacktestEngine::default();
+        let mut strat = strategy(StrategyDirection::LongOnly, 105.0);
+
+        strat.position_sizing = PositionSizing::FixedShares { shares: 10.0 };
+        let result = engine.run(&strat, "TEST", &prices, &indicators);
+        assert_eq!(result.trades[0].shares, 10.0);
+
+        // Volatility targeting skips entries without an ATR
+        strat.position_sizing = PositionSizing::VolatilityTarget {
+            risk_percent: 1.0,
+            atr_multiple: 1.0,
         };
+        assert!(engine.run(&strat, "TEST", &prices, &indicators).trades.is_empty());
 
-        // Simple Sharpe ratio approximation (assuming 252 trading days)
-        let daily_returns: Vec<f64> = equity_history
-            .windows(2)
-            .map(|w| (w[1] - w[0]) / w[0])
-            .collect();
+        // 1% of 10000 at risk over an ATR of 2: 50 shares
+        indicators.extend(prices.iter().map(|p| TechnicalIndicator {
+            symbol: "TEST".to_string(),
+            date: p.date,
+            indicator_name: "ATR_14".to_string(),
+            value: 2.0,
+        }));
+        let result = engine.run(&strat, "TEST", &prices, &indicators);
+        assert!((result.trades[0].shares - 50.0).abs() < 1e-9);
+    }
 
-        let avg_return = if !daily_returns.is_empty() {
-            daily_returns.iter().sum::<f64>() / daily_returns.len() as f64
-        } else {
-            0.0
+    #[test]
+    fn test_equity_curve_drawdown_and_export() {
+        let (prices, indicators) = bars(&[100.0, 110.0, 121.0, 99.0, 110.0]);
+        let result = BacktestEngine::default().run(
+            &strategy(StrategyDirection::LongOnly, 105.0),
+            "TEST",
+            &prices,
+            &indicators,
+        );
+
+        // Long 110 -> 121 -> out at 99, back in at 110
+        let curve = &result.equity_curve;
+        assert_eq!(curve.len(), 5);
+        assert!((curve[2].equity - 11000.0).abs() < 1e-6);
+        assert!((curve[2].exposure - 11000.0).abs() < 1e-6);
+        assert_eq!(curve[3].open_positions, 0);
+        assert!((curve[3].drawdown - 2000.0 / 11000.0 * 100.0).abs() < 1e-9);
+        assert_eq!(curve[4].open_positions, 1);
+        assert!((curve[4].buy_and_hold.unwrap() - 11000.0).abs() < 1e-6);
+
+        let csv = export_equity_curve(curve, ExportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[0], "date,equity,cash,exposure,open_positions,drawdown,buy_and_hold");
+        assert!(lines[1].starts_with("2024-01-01,10000"));
+
+        let json = export_equity_curve(curve, ExportFormat::Json).unwrap();
+        let parsed: Vec<EquityPoint> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.len(), 5);
+    }
+
+    #[test]
+    fn test_long_short_flips_sides() {
+        let (prices, indicators) = bars(&[100.0, 110.0, 100.0, 90.0, 110.0]);
+        let result = BacktestEngine::default().run(
+            &strategy(StrategyDirection::LongShort, 105.0),
+            "TEST",
+            &prices,
+            &indicators,
+        );
+
+        let sides: Vec<TradeDirection> = result.trades.iter().map(|t| t.direction).collect();
+        // Short on day 0, long on day 1, short on day 2, long on day 4 to the end
+        assert_eq!(
+            sides,
+            vec![TradeDirection::Short, TradeDirection::Long, TradeDirection::Short, TradeDirection::Long]
+        );
+        // Short 100 -> 110 and long 110 -> 100 both lose
+        assert!(result.trades[0].profit_loss.unwrap() < 0.0);
+        assert!(result.trades[1].profit_loss.unwrap() < 0.0);
+        assert_eq!(result.metrics.long.total_trades + result.metrics.short.total_trades, 4);
+        assert_eq!(result.metrics.short.losing_trades, 2);
+    }
+
+    #[test]
+    fn test_execution_costs_reduce_returns_and_are_reported() {
+        let (prices, indicators) = bars(&[100.0, 110.0, 120.0, 100.0]);
+        let engine = BacktestEngine::new(BacktestConfig {
+            execution: ExecutionModel {
+                spread: crate::execution::SpreadModel::Fixed { bps: 20.0 },
+                ..ExecutionModel::per_trade(1.0)
+            },
+            ..BacktestConfig::default()
+        });
+        let result = engine.run(&strategy(StrategyDirection::LongOnly, 105.0), "TEST", &prices, &indicators);
+
+        assert_eq!(result.trades.len(), 1);
+        let trade = &result.trades[0];
+        // Buy at the ask, sell at the bid (10bps half spread each side)
+        assert!((trade.entry_price - 110.11).abs() < 1e-9);
+        assert_eq!(trade.exit_price, Some(99.9));
+        let shares = 9999.0 / 110.11;
+        assert!((trade.shares - shares).abs() < 1e-9);
+
+        let costs = &result.metrics.costs;
+        assert_eq!(costs.commission, 2.0);
+        assert!((costs.spread - shares * (0.11 + 0.1)).abs() < 1e-9);
+        assert!((costs.total - (costs.commission + costs.spread)).abs() < 1e-9);
+        assert!((costs.cost_drag_percent - costs.total / 100.0).abs() < 1e-9);
+        // Round-trip commissions are part of the trade's P/L
+        assert!((result.final_capital - (10000.0 + trade.profit_loss.unwrap())).abs() < 1e-6);
+    }
+
+    fn bar(open: f64, high: f64, low: f64, close: f64) -> DailyPrice {
+        DailyPrice {
+            symbol: "TEST".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            open,
+            high,
+            low,
+            close,
+            volume: 1000,
+            source: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_next_open_fills_on_following_bar() {
+        let (mut prices, indicators) = bars(&[100.0, 110.0, 120.0, 130.0]);
+        for p in prices.iter_mut() {
+            p.open = p.close - 1.0;
+        }
+        let engine = BacktestEngine::new(BacktestConfig {
+            fill_timing: FillTiming::NextOpen,
+            ..BacktestConfig::default()
+        });
+        let result = engine.run(&strategy(StrategyDirection::LongOnly, 105.0), "TEST", &prices, &indicators);
+
+        // Signal on day 1's close, filled at day 2's open
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.trades[0].entry_date, prices[2].date);
+        assert_eq!(result.trades[0].entry_price, 119.0);
+    }
+
+    #[test]
+    fn test_intrabar_stops_gaps_and_ordering() {
+        let engine = BacktestEngine::default();
+        let mut strat = strategy(StrategyDirection::LongOnly, 105.0);
+        strat.stop_loss_percent = Some(5.0);
+        strat.take_profit_percent = Some(10.0);
+        let long = OpenPosition {
+            direction: TradeDirection::Long,
+            entry_date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            entry_price: 100.0,
+            shares: 1.0,
+            entry_reason: String::new(),
+            entry_commission: 0.0,
+            stops: StopTracker::new(TradeDirection::Long, 100.0, NaiveDate::from_ymd_opt(2024, 1, 1).unwrap()),
+        };
+        let stops = |strat: &Strategy, b: DailyPrice, pos: &OpenPosition| {
+            let mut pos = pos.clone();
+            let exits = engine.check_stops(strat, &[b], None, &mut pos);
+            exits.first().map(|exit| (exit.price, exit.reason.clone()))
         };
 
-        let std_dev = if daily_returns.len() > 1 {
-            let variance = daily_returns
-                .iter()
-                .map(|r| (r - avg_return).powi(2))
-                .sum::<f64>()
-                / daily_returns.len() as f64;
-            variance.sqrt()
-        } else {
-            0.0
+        // Low touches the stop: filled at the stop level, not the close
+        assert_eq!(stops(&strat, bar(99.0, 101.0, 94.0, 98.0), &long), Some((95.0, "stop_loss".to_string())));
+        // Gap down through the stop: filled at the open
+        assert_eq!(stops(&strat, bar(90.0, 92.0, 88.0, 91.0), &long), Some((90.0, "stop_loss".to_string())));
+        // Gap up through the target: filled at the open
+        assert_eq!(stops(&strat, bar(112.0, 115.0, 111.0, 113.0), &long), Some((112.0, "take_profit".to_string())));
+        // Both levels inside the range: assume the stop traded first
+        assert_eq!(stops(&strat, bar(100.0, 111.0, 94.0, 105.0), &long), Some((95.0, "stop_loss".to_string())));
+        assert_eq!(stops(&strat, bar(100.0, 104.0, 96.0, 101.0), &long), None);
+
+        let short = OpenPosition {
+            direction: TradeDirection::Short,
+            stops: StopTracker::new(TradeDirection::Short, 100.0, long.entry_date),
+            ..long.clone()
         };
+        assert_eq!(stops(&strat, bar(101.0, 106.0, 99.0, 100.0), &short), Some((105.0, "stop_loss".to_string())));
+        assert_eq!(stops(&strat, bar(95.0, 96.0, 89.0, 92.0), &short), Some((90.0, "take_profit".to_string())));
 
-        let sharpe_ratio = if std_dev > 0.0 {
-            (avg_return / std_dev) * (252.0_f64).sqrt()
-        } else {
-            0.0
+        // Legacy StopLoss exit condition supplies the stop level
+        strat.stop_loss_percent = None;
+        strat.exit_rule = None;
+        strat.exit_condition = StrategyConditionType::StopLoss;
+        strat.exit_threshold = 2.0;
+        assert_eq!(stops(&strat, bar(99.0, 99.5, 97.5, 98.0), &long), Some((98.0, "stop_loss".to_string())));
+    }
+
+    #[test]
+    fn test_scale_out_then_trailing_stop() {
+        let (prices, indicators) = bars(&[100.0, 110.0, 121.0, 140.0, 120.0, 120.0]);
+        let mut strat = strategy(StrategyDirection::LongOnly, 105.0);
+        strat.exit_rules = ExitRules {
+            trailing_stop: Some(TrailingStop::Percent { percent: 10.0 }),
+            scale_outs: vec![ScaleOut {
+                gain_percent: 10.0,
+                size_percent: 50.0,
+            }],
+            ..ExitRules::default()
         };
+        let result = BacktestEngine::default().run(&strat, "TEST", &prices, &indicators);
 
-        PerformanceMetrics {
-            total_return,
-            total_return_dollars,
-            max_drawdown,
-            sharpe_ratio,
-            win_rate,
-            total_trades,
-            winning_trades: num_winners,
-            losing_trades: num_losers,
-            avg_win_percent: avg_win,
-            avg_loss_percent: avg_loss,
-            profit_factor,
-            avg_trade_duration_days: avg_duration,
-        }
+        // Half taken at +10%, the rest trailed 10% below the 140 high (the
+        // entry rule still holds, so a new position opens on that close)
+        assert_eq!(result.trades.len(), 3);
+        assert_eq!(result.trades[0].exit_reason.as_deref(), Some("scale_out"));
+        assert_eq!(result.trades[0].exit_price, Some(121.0));
+        assert!((result.trades[0].shares - result.trades[1].shares).abs() < 1e-9);
+        assert_eq!(result.trades[1].exit_reason.as_deref(), Some("trailing_stop"));
+        assert_eq!(result.trades[1].exit_price, Some(120.0));
+        assert_eq!(result.trades[1].exit_date, Some(prices[4].date));
     }
 }
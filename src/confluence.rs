@@ -0,0 +1,229 @@
+//! Learned confluence weights
+//!
+//! Fits per-vote weights for `detect_confluence_signal` from history: each
+//! bar's confluence votes become a feature vector (+strength bullish,
+//! -strength bearish) and a logistic regression predicts whether the forward
+//! return over the horizon is positive. Votes whose coefficient is positive
+//! get proportional weight; votes that don't help get zero weight.
+//! Weights are normalized to a mean of 1.0 so `min_agreeing_indicators`
+//! keeps its meaning, and stored as named confluence profiles.
+
+use crate::db::Database;
+use crate::error::{PipelineError, Result};
+use crate::models::{ConfluenceProfile, DailyPrice, SignalDirection, TechnicalIndicator};
+use crate::signals::SignalEngine;
+use chrono::NaiveDate;
+use std::collections::{BTreeSet, HashMap};
+
+/// Minimum training samples needed to fit a profile
+pub const MIN_SAMPLES: usize = 30;
+
+/// One bar's votes and what followed
+#[derive(Debug, Clone)]
+pub struct VoteSample {
+    /// Vote name -> signed strength
+    pub features: HashMap<String, f64>,
+    pub forward_return: f64,
+}
+
+/// Build training samples from a symbol's history. Bars without any vote are skipped.
+pub fn collect_vote_samples(
+    engine: &SignalEngine,
+    prices: &[DailyPrice],
+    indicators: &[TechnicalIndicator],
+    horizon: usize,
+) -> Vec<VoteSample> {
+    let mut sorted = prices.to_vec();
+    sorted.sort_by_key(|p| p.date);
+
+    let mut by_date: HashMap<NaiveDate, HashMap<String, f64>> = HashMap::new();
+    for ind in indicators {
+        by_date
+            .entry(ind.date)
+            .or_default()
+            .insert(ind.indicator_name.clone(), ind.value);
+    }
+
+    let mut samples = Vec::new();
+    for i in 0..sorted.len().saturating_sub(horizon) {
+        let Some(day) = by_date.get(&sorted[i].date) else {
+            continue;
+        };
+        let votes = engine.collect_confluence_votes(sorted[i].close, day);
+        if votes.is_empty() || sorted[i].close <= 0.0 {
+            continue;
+        }
+
+        let features = votes
+            .into_iter()
+            .map(|v| {
+                let sign = if v.direction == SignalDirection::Bearish { -1.0 } else { 1.0 };
+                (v.indicator_name, sign * v.strength)
+            })
+            .collect();
+
+        samples.push(VoteSample {
+            features,
+            forward_return: sorted[i + horizon].close / sorted[i].close - 1.0,
+        });
+    }
+
+    samples
+}
+
+fn sigmoid(z: f64) -> f64 {
+    1.0 / (1.0 + (-z).exp())
+}
+
+/// L2-regularized logistic regression by batch gradient descent.
+/// Returns (intercept, coefficients in `names` order).
+pub fn fit_logistic(samples: &[VoteSample], names: &[String], epochs: usize, learning_rate: f64, l2: f64) -> (f64, Vec<f64>) {
+    let rows: Vec<(Vec<f64>, f64)> = samples
+        .iter()
+        .map(|s| {
+            let x = names.iter().map(|n| s.features.get(n).copied().unwrap_or(0.0)).collect();
+            (x, if s.forward_return > 0.0 { 1.0 } else { 0.0 })
+        })
+        .collect();
+
+    let n = rows.len().max(1) as f64;
+    let mut intercept = 0.0;
+    let mut coefs = vec![0.0; names.len()];
+
+    for _ in 0..epochs {
+        let mut grad_b = 0.0;
+        let mut grad_w = vec![0.0; names.len()];
+        for (x, y) in &rows {
+            let z = intercept + x.iter().zip(&coefs).map(|(a, b)| a * b).sum::<f64>();
+            let err = sigmoid(z) - y;
+            grad_b += err;
+            for (g, xi) in grad_w.iter_mut().zip(x) {
+                *g += err * xi;
+            }
+        }
+        intercept -= learning_rate * grad_b / n;
+        for (w, g) in coefs.iter_mut().zip(&grad_w) {
+            *w -= learning_rate * (g / n + l2 * *w);
+        }
+    }
+
+    (intercept, coefs)
+}
+
+/// Fit a named profile from training samples
+pub fn fit_confluence_profile(name: &str, samples: &[VoteSample], horizon: usize) -> Result<ConfluenceProfile> {
+    if samples.len() < MIN_SAMPLES {
+        return Err(PipelineError::NoData(format!(
+            "Need at least {} bars with confluence votes to fit '{}', found {}",
+            MIN_SAMPLES,
+            name,
+            samples.len()
+        )));
+    }
+
+    let names: Vec<String> = samples
+        .iter()
+        .flat_map(|s| s.features.keys().cloned())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    let (intercept, coefs) = fit_logistic(samples, &names, 500, 0.5, 0.01);
+
+    // Only votes whose direction agrees with subsequent returns keep weight
+    let positive: Vec<f64> = coefs.iter().map(|c| c.max(0.0)).collect();
+    let mean = positive.iter().sum::<f64>() / positive.len() as f64;
+    if mean <= 0.0 {
+        return Err(PipelineError::Config(format!(
+            "No confluence vote predicted forward returns when fitting '{}'",
+            name
+        )));
+    }
+    let weights: HashMap<String, f64> = names
+        .iter()
+        .cloned()
+        .zip(positive.iter().map(|w| w / mean))
+        .collect();
+
+    let correct = samples
+        .iter()
+        .filter(|s| {
+            let z = intercept
+                + names
+                    .iter()
+                    .zip(&coefs)
+                    .map(|(n, c)| c * s.features.get(n).copied().unwrap_or(0.0))
+                    .sum::<f64>();
+            (sigmoid(z) > 0.5) == (s.forward_return > 0.0)
+        })
+        .count();
+
+    Ok(ConfluenceProfile {
+        id: 0,
+        name: name.to_string(),
+        weights,
+        intercept,
+        horizon_days: horizon,
+        sample_size: samples.len(),
+        accuracy: correct as f64 / samples.len() as f64 * 100.0,
+        symbols: Vec::new(),
+        active: false,
+        created_at: String::new(),
+    })
+}
+
+/// Fit a profile over the stored history of `symbols` and save it
+pub fn fit_and_save_profile(
+    db: &Database,
+    engine: &SignalEngine,
+    name: &str,
+    symbols: &[String],
+    horizon: usize,
+) -> Result<ConfluenceProfile> {
+    let mut samples = Vec::new();
+    for symbol in symbols {
+        let prices = db.get_prices(symbol)?;
+        let indicators = db.get_all_indicators(symbol)?;
+        samples.extend(collect_vote_samples(engine, &prices, &indicators, horizon));
+    }
+
+    let mut profile = fit_confluence_profile(name, &samples, horizon)?;
+    profile.symbols = symbols.to_vec();
+    profile.id = db.save_confluence_profile(&profile)?;
+    Ok(profile)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RSI votes predict the forward return; CCI votes are noise
+    fn samples() -> Vec<VoteSample> {
+        (0..200)
+            .map(|i| {
+                let bullish = i % 2 == 0;
+                let rsi = if bullish { 0.8 } else { -0.8 };
+                let cci = if i % 3 == 0 { 0.8 } else { -0.8 };
+                VoteSample {
+                    features: HashMap::from([("RSI_14".to_string(), rsi), ("CCI_20".to_string(), cci)]),
+                    forward_return: if bullish { 0.02 } else { -0.02 },
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fit_weights_predictive_votes() {
+        let profile = fit_confluence_profile("test", &samples(), 5).unwrap();
+        let rsi = profile.weights["RSI_14"];
+        let cci = profile.weights["CCI_20"];
+        assert!(rsi > cci, "predictive vote should outweigh noise ({} vs {})", rsi, cci);
+        assert!((rsi + cci - 2.0).abs() < 1e-9, "weights normalized to mean 1.0");
+        assert!(profile.accuracy > 90.0);
+    }
+
+    #[test]
+    fn test_fit_requires_samples() {
+        assert!(fit_confluence_profile("empty", &samples()[..10], 5).is_err());
+    }
+}
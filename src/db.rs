@@ -5,10 +5,13 @@ use rusqlite::{params, Connection, OptionalExtension, Result as SqliteResult};
 use std::path::Path;
 
 use crate::error::Result;
+use crate::formula::formula_indicator_name;
 use crate::models::{
-    AlertCondition, BacktestResult, BacktestTrade, DailyPrice, IndicatorAlert,
-    IndicatorAlertCondition, IndicatorAlertType, MacroData, PerformanceMetrics, Position,
-    PositionType, PriceAlert, Signal, SignalDirection, SignalType, Strategy,
+    AlertCondition, BacktestResult, BacktestTrade, ConfluenceProfile, DailyPrice, DetectorSetting, DirectionMetrics, EquityPoint, Formula,
+    IndicatorAlert,
+    SignalOutcome, SignalScorecardEntry,
+    IndicatorAlertCondition, IndicatorAlertType, MacroData, MarketRegime, PerformanceMetrics,
+    Position, PositionType, PriceAlert, RegimeRecord, Signal, SignalDirection, SignalStatus, SignalType, Strategy, StrategyDirection, SymbolTradeStats,
     StrategyConditionType, Symbol, TechnicalIndicator, TradeDirection,
     PaperWallet, PaperPosition, PaperTrade, PaperTradeAction,
     // AI Trading types
@@ -18,6 +21,12 @@ use crate::models::{
     // Trade queue types
     QueuedTrade, QueueLogEntry,
 };
+use crate::monte_carlo::MonteCarloResult;
+use crate::optimizer::{
+    OptimizationConfig, OptimizationJob, OptimizationResult, OptimizationRun, ParamSet,
+};
+use crate::stops::StopTracker;
+use crate::timeframe::{resample, Timeframe};
 use crate::trends::TrendData;
 
 /// Extension trait for pipe-style method chaining
@@ -135,6 +144,167 @@ impl Database {
             println!("[MIGRATION] Added guardrail columns to ai_trader_config");
         }
 
+        if !ai_config_columns.contains(&"exit_rules".to_string()) {
+            self.conn.execute(
+                "ALTER TABLE ai_trader_config ADD COLUMN exit_rules TEXT",
+                [],
+            )?;
+            println!("[MIGRATION] Added exit_rules column to ai_trader_config");
+        }
+
+        if !ai_config_columns.contains(&"position_sizing".to_string()) {
+            self.conn.execute(
+                "ALTER TABLE ai_trader_config ADD COLUMN position_sizing TEXT",
+                [],
+            )?;
+            println!("[MIGRATION] Added position_sizing column to ai_trader_config");
+        }
+
+        // Add formula columns to strategies
+        let strategy_columns: Vec<String> = self
+            .conn
+            .prepare("PRAGMA table_info(strategies)")?
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        if !strategy_columns.contains(&"entry_formula".to_string()) {
+            self.conn.execute_batch(r#"
+                ALTER TABLE strategies ADD COLUMN entry_formula TEXT;
+                ALTER TABLE strategies ADD COLUMN exit_formula TEXT;
+            "#)?;
+            println!("[MIGRATION] Added formula columns to strategies");
+        }
+
+        if !strategy_columns.contains(&"allowed_regimes".to_string()) {
+            self.conn.execute(
+                "ALTER TABLE strategies ADD COLUMN allowed_regimes TEXT NOT NULL DEFAULT ''",
+                [],
+            )?;
+            println!("[MIGRATION] Added allowed_regimes column to strategies");
+        }
+
+        if !strategy_columns.contains(&"entry_rule".to_string()) {
+            self.conn.execute_batch(r#"
+                ALTER TABLE strategies ADD COLUMN entry_rule TEXT;
+                ALTER TABLE strategies ADD COLUMN exit_rule TEXT;
+            "#)?;
+            println!("[MIGRATION] Added rule columns to strategies");
+        }
+
+        if !strategy_columns.contains(&"direction".to_string()) {
+            self.conn.execute(
+                "ALTER TABLE strategies ADD COLUMN direction TEXT NOT NULL DEFAULT 'long'",
+                [],
+            )?;
+            println!("[MIGRATION] Added direction column to strategies");
+        }
+
+        if !strategy_columns.contains(&"exit_rules".to_string()) {
+            self.conn.execute("ALTER TABLE strategies ADD COLUMN exit_rules TEXT", [])?;
+            println!("[MIGRATION] Added exit_rules column to strategies");
+        }
+
+        if !strategy_columns.contains(&"position_sizing".to_string()) {
+            self.conn.execute("ALTER TABLE strategies ADD COLUMN position_sizing TEXT", [])?;
+            println!("[MIGRATION] Added position_sizing column to strategies");
+        }
+
+        if !strategy_columns.contains(&"rs_benchmark".to_string()) {
+            self.conn.execute("ALTER TABLE strategies ADD COLUMN rs_benchmark TEXT", [])?;
+            println!("[MIGRATION] Added rs_benchmark column to strategies");
+        }
+
+        // Add per-direction metrics to backtest runs
+        let backtest_columns: Vec<String> = self
+            .conn
+            .prepare("PRAGMA table_info(backtest_runs)")?
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        if !backtest_columns.contains(&"long_metrics".to_string()) {
+            self.conn.execute_batch(r#"
+                ALTER TABLE backtest_runs ADD COLUMN long_metrics TEXT;
+                ALTER TABLE backtest_runs ADD COLUMN short_metrics TEXT;
+            "#)?;
+            println!("[MIGRATION] Added direction metrics columns to backtest_runs");
+        }
+        if !backtest_columns.contains(&"cost_metrics".to_string()) {
+            self.conn.execute(
+                "ALTER TABLE backtest_runs ADD COLUMN cost_metrics TEXT",
+                [],
+            )?;
+            println!("[MIGRATION] Added cost_metrics column to backtest_runs");
+        }
+        if !backtest_columns.contains(&"optimization_job_id".to_string()) {
+            self.conn.execute_batch(r#"
+                ALTER TABLE backtest_runs ADD COLUMN optimization_job_id INTEGER;
+                ALTER TABLE backtest_runs ADD COLUMN optimization_role TEXT;
+                ALTER TABLE backtest_runs ADD COLUMN optimization_window INTEGER;
+                ALTER TABLE backtest_runs ADD COLUMN parameters TEXT;
+            "#)?;
+            println!("[MIGRATION] Added optimization columns to backtest_runs");
+        }
+        // Not in SCHEMA_SQL: older databases only gain the column above, and
+        // fresh ones skip that migration, so create it for both here
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_backtest_runs_optimization ON backtest_runs(optimization_job_id)",
+            [],
+        )?;
+        if !backtest_columns.contains(&"risk_metrics".to_string()) {
+            self.conn.execute(
+                "ALTER TABLE backtest_runs ADD COLUMN risk_metrics TEXT",
+                [],
+            )?;
+            println!("[MIGRATION] Added risk_metrics column to backtest_runs");
+        }
+
+        // Add lifecycle columns to signals
+        let signal_columns: Vec<String> = self
+            .conn
+            .prepare("PRAGMA table_info(signals)")?
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        if !signal_columns.contains(&"status".to_string()) {
+            self.conn.execute_batch(r#"
+                ALTER TABLE signals ADD COLUMN status TEXT NOT NULL DEFAULT 'new';
+                ALTER TABLE signals ADD COLUMN end_date DATE;
+            "#)?;
+            println!("[MIGRATION] Added lifecycle columns to signals");
+        }
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_signals_status ON signals(status)",
+            [],
+        )?;
+
+        let scorecard_columns: Vec<String> = self
+            .conn
+            .prepare("PRAGMA table_info(signal_scorecard)")?
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        if !scorecard_columns.contains(&"hit_rate_samples".to_string()) {
+            self.conn.execute(
+                "ALTER TABLE signal_scorecard ADD COLUMN hit_rate_samples INTEGER NOT NULL DEFAULT 0",
+                [],
+            )?;
+            println!("[MIGRATION] Added hit_rate_samples column to signal_scorecard");
+        }
+
+        let profile_columns: Vec<String> = self
+            .conn
+            .prepare("PRAGMA table_info(confluence_profiles)")?
+            .query_map([], |row| row.get::<_, String>(1))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        if !profile_columns.contains(&"active".to_string()) {
+            self.conn.execute(
+                "ALTER TABLE confluence_profiles ADD COLUMN active INTEGER NOT NULL DEFAULT 0",
+                [],
+            )?;
+            println!("[MIGRATION] Added active column to confluence_profiles");
+        }
+
         Ok(())
     }
 
@@ -280,6 +450,53 @@ impl Database {
         Ok(data)
     }
 
+    /// Get the full history of a macro indicator, oldest first
+    pub fn get_macro_history(&self, indicator: &str) -> Result<Vec<MacroData>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT indicator, date, value, source
+            FROM macro_data
+            WHERE indicator = ?1
+            ORDER BY date ASC
+            "#,
+        )?;
+
+        let data = stmt
+            .query_map(params![indicator], |row| {
+                let date_str: String = row.get(1)?;
+                Ok(MacroData {
+                    indicator: row.get(0)?,
+                    date: NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")
+                        .unwrap_or_else(|_| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()),
+                    value: row.get(2)?,
+                    source: row.get(3)?,
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(data)
+    }
+
+    /// Average value of a macro indicator between two dates (inclusive),
+    /// or None when it has no data in the range
+    pub fn get_macro_average(
+        &self,
+        indicator: &str,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Option<f64>> {
+        let average = self.conn.query_row(
+            r#"
+            SELECT AVG(value)
+            FROM macro_data
+            WHERE indicator = ?1 AND date >= ?2 AND date <= ?3
+            "#,
+            params![indicator, start.to_string(), end.to_string()],
+            |row| row.get(0),
+        )?;
+        Ok(average)
+    }
+
     /// Get all unique macro indicators
     pub fn get_macro_indicators(&self) -> Result<Vec<String>> {
         let mut stmt = self.conn.prepare(
@@ -389,6 +606,12 @@ impl Database {
         Ok(prices)
     }
 
+    /// Get prices for a symbol resampled to a weekly, monthly or N-day timeframe
+    pub fn get_prices_resampled(&self, symbol: &str, timeframe: Timeframe) -> Result<Vec<DailyPrice>> {
+        let prices = self.get_prices(symbol)?;
+        Ok(resample(&prices, timeframe))
+    }
+
     /// Get all symbols with price data
     pub fn get_symbols_with_data(&self) -> Result<Vec<String>> {
         let mut stmt = self
@@ -1024,10 +1247,18 @@ impl Database {
     pub fn upsert_signal(&self, signal: &Signal) -> Result<i64> {
         self.conn.execute(
             r#"
-            INSERT OR REPLACE INTO signals
+            INSERT INTO signals
             (symbol, signal_type, direction, strength, price_at_signal,
-             triggered_by, trigger_value, timestamp, acknowledged)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             triggered_by, trigger_value, timestamp, acknowledged, status, end_date)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+            ON CONFLICT(symbol, signal_type, timestamp) DO UPDATE SET
+                direction = excluded.direction,
+                strength = excluded.strength,
+                price_at_signal = excluded.price_at_signal,
+                triggered_by = excluded.triggered_by,
+                trigger_value = excluded.trigger_value,
+                status = excluded.status,
+                end_date = excluded.end_date
             "#,
             params![
                 signal.symbol,
@@ -1039,6 +1270,8 @@ impl Database {
                 signal.trigger_value,
                 signal.timestamp.to_string(),
                 signal.acknowledged,
+                signal.status.as_str(),
+                signal.end_date.map(|d| d.to_string()),
             ],
         )?;
 
@@ -1053,10 +1286,18 @@ impl Database {
         {
             let mut stmt = tx.prepare(
                 r#"
-                INSERT OR REPLACE INTO signals
+                INSERT INTO signals
                 (symbol, signal_type, direction, strength, price_at_signal,
-                 triggered_by, trigger_value, timestamp, acknowledged)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+                 triggered_by, trigger_value, timestamp, acknowledged, status, end_date)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                ON CONFLICT(symbol, signal_type, timestamp) DO UPDATE SET
+                    direction = excluded.direction,
+                    strength = excluded.strength,
+                    price_at_signal = excluded.price_at_signal,
+                    triggered_by = excluded.triggered_by,
+                    trigger_value = excluded.trigger_value,
+                    status = excluded.status,
+                    end_date = excluded.end_date
                 "#,
             )?;
 
@@ -1071,6 +1312,8 @@ impl Database {
                     signal.trigger_value,
                     signal.timestamp.to_string(),
                     signal.acknowledged,
+                    signal.status.as_str(),
+                    signal.end_date.map(|d| d.to_string()),
                 ])?;
                 count += 1;
             }
@@ -1080,12 +1323,22 @@ impl Database {
         Ok(count)
     }
 
+    /// Update the lifecycle status of stored signals (matched by id)
+    pub fn update_signal_statuses(&self, signals: &[Signal]) -> Result<usize> {
+        let mut stmt = self.conn.prepare("UPDATE signals SET status = ?1 WHERE id = ?2")?;
+        for signal in signals {
+            stmt.execute(params![signal.status.as_str(), signal.id])?;
+        }
+        Ok(signals.len())
+    }
+
     /// Get signals for a symbol
     pub fn get_signals(&self, symbol: &str, only_unacknowledged: bool) -> Result<Vec<Signal>> {
         let sql = if only_unacknowledged {
             r#"
             SELECT id, symbol, signal_type, direction, strength, price_at_signal,
-                   triggered_by, trigger_value, timestamp, created_at, acknowledged
+                   triggered_by, trigger_value, timestamp, created_at, acknowledged,
+                   status, end_date
             FROM signals
             WHERE symbol = ?1 AND acknowledged = 0
             ORDER BY timestamp DESC
@@ -1093,7 +1346,8 @@ impl Database {
         } else {
             r#"
             SELECT id, symbol, signal_type, direction, strength, price_at_signal,
-                   triggered_by, trigger_value, timestamp, created_at, acknowledged
+                   triggered_by, trigger_value, timestamp, created_at, acknowledged,
+                   status, end_date
             FROM signals
             WHERE symbol = ?1
             ORDER BY timestamp DESC
@@ -1111,8 +1365,7 @@ impl Database {
                 Ok(Signal {
                     id: row.get(0)?,
                     symbol: row.get(1)?,
-                    signal_type: SignalType::from_str(&signal_type_str)
-                        .unwrap_or(SignalType::RsiOversold),
+                    signal_type: SignalType::from_id(&signal_type_str),
                     direction: SignalDirection::from_str(&direction_str),
                     strength: row.get(4)?,
                     price_at_signal: row.get(5)?,
@@ -1122,6 +1375,12 @@ impl Database {
                         .unwrap_or_else(|_| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()),
                     created_at: row.get(9)?,
                     acknowledged: row.get(10)?,
+                    status: SignalStatus::from_str(
+                        &row.get::<_, Option<String>>(11)?.unwrap_or_default(),
+                    ),
+                    end_date: row
+                        .get::<_, Option<String>>(12)?
+                        .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
                 })
             })?
             .collect::<SqliteResult<Vec<_>>>()?;
@@ -1134,7 +1393,8 @@ impl Database {
         let mut stmt = self.conn.prepare(
             r#"
             SELECT id, symbol, signal_type, direction, strength, price_at_signal,
-                   triggered_by, trigger_value, timestamp, created_at, acknowledged
+                   triggered_by, trigger_value, timestamp, created_at, acknowledged,
+                   status, end_date
             FROM signals
             ORDER BY timestamp DESC, strength DESC
             LIMIT ?1
@@ -1150,8 +1410,7 @@ impl Database {
                 Ok(Signal {
                     id: row.get(0)?,
                     symbol: row.get(1)?,
-                    signal_type: SignalType::from_str(&signal_type_str)
-                        .unwrap_or(SignalType::RsiOversold),
+                    signal_type: SignalType::from_id(&signal_type_str),
                     direction: SignalDirection::from_str(&direction_str),
                     strength: row.get(4)?,
                     price_at_signal: row.get(5)?,
@@ -1161,6 +1420,12 @@ impl Database {
                         .unwrap_or_else(|_| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()),
                     created_at: row.get(9)?,
                     acknowledged: row.get(10)?,
+                    status: SignalStatus::from_str(
+                        &row.get::<_, Option<String>>(11)?.unwrap_or_default(),
+                    ),
+                    end_date: row
+                        .get::<_, Option<String>>(12)?
+                        .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
                 })
             })?
             .collect::<SqliteResult<Vec<_>>>()?;
@@ -1195,6 +1460,487 @@ impl Database {
         Ok(deleted)
     }
 
+    /// Get every stored signal, oldest first (for outcome evaluation)
+    pub fn get_all_signal_history(&self) -> Result<Vec<Signal>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, symbol, signal_type, direction, strength, price_at_signal,
+                   triggered_by, trigger_value, timestamp, created_at, acknowledged,
+                   status, end_date
+            FROM signals
+            ORDER BY symbol ASC, timestamp ASC
+            "#,
+        )?;
+
+        let signals = stmt
+            .query_map([], |row| {
+                let signal_type_str: String = row.get(2)?;
+                let direction_str: String = row.get(3)?;
+                let date_str: String = row.get(8)?;
+
+                Ok(Signal {
+                    id: row.get(0)?,
+                    symbol: row.get(1)?,
+                    signal_type: SignalType::from_id(&signal_type_str),
+                    direction: SignalDirection::from_str(&direction_str),
+                    strength: row.get(4)?,
+                    price_at_signal: row.get(5)?,
+                    triggered_by: row.get(6)?,
+                    trigger_value: row.get(7)?,
+                    timestamp: NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")
+                        .unwrap_or_else(|_| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()),
+                    created_at: row.get(9)?,
+                    acknowledged: row.get(10)?,
+                    status: SignalStatus::from_str(
+                        &row.get::<_, Option<String>>(11)?.unwrap_or_default(),
+                    ),
+                    end_date: row
+                        .get::<_, Option<String>>(12)?
+                        .and_then(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d").ok()),
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(signals)
+    }
+
+    // ========================================================================
+    // Signal Scorecard Methods
+    // ========================================================================
+
+    /// Insert or update signal outcomes (kept after the signals themselves are cleaned up)
+    pub fn upsert_signal_outcomes(&mut self, outcomes: &[SignalOutcome]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT OR REPLACE INTO signal_outcomes
+                (symbol, signal_type, direction, strength, signal_date, entry_price,
+                 return_1d, return_5d, return_20d, mae, mfe)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                "#,
+            )?;
+
+            for outcome in outcomes {
+                stmt.execute(params![
+                    outcome.symbol,
+                    outcome.signal_type.as_str(),
+                    outcome.direction.as_str(),
+                    outcome.strength,
+                    outcome.signal_date.to_string(),
+                    outcome.entry_price,
+                    outcome.return_1d,
+                    outcome.return_5d,
+                    outcome.return_20d,
+                    outcome.mae,
+                    outcome.mfe,
+                ])?;
+                count += 1;
+            }
+        }
+
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Get all recorded signal outcomes
+    pub fn get_signal_outcomes(&self) -> Result<Vec<SignalOutcome>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT symbol, signal_type, direction, strength, signal_date, entry_price,
+                   return_1d, return_5d, return_20d, mae, mfe
+            FROM signal_outcomes
+            ORDER BY symbol ASC, signal_date ASC
+            "#,
+        )?;
+
+        let outcomes = stmt
+            .query_map([], |row| {
+                let signal_type_str: String = row.get(1)?;
+                let direction_str: String = row.get(2)?;
+                let date_str: String = row.get(4)?;
+
+                Ok(SignalOutcome {
+                    symbol: row.get(0)?,
+                    signal_type: SignalType::from_id(&signal_type_str),
+                    direction: SignalDirection::from_str(&direction_str),
+                    strength: row.get(3)?,
+                    signal_date: NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")
+                        .unwrap_or_else(|_| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()),
+                    entry_price: row.get(5)?,
+                    return_1d: row.get(6)?,
+                    return_5d: row.get(7)?,
+                    return_20d: row.get(8)?,
+                    mae: row.get(9)?,
+                    mfe: row.get(10)?,
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(outcomes)
+    }
+
+    /// Replace the signal scorecard with freshly aggregated entries
+    pub fn save_signal_scorecard(&mut self, entries: &[SignalScorecardEntry]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM signal_scorecard", [])?;
+
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT INTO signal_scorecard
+                (signal_type, symbol, strength_bucket, sample_size, hit_rate,
+                 mean_return_1d, median_return_1d, mean_return_5d, median_return_5d,
+                 mean_return_20d, median_return_20d, mean_mae, mean_mfe, hit_rate_samples)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+                "#,
+            )?;
+
+            for e in entries {
+                stmt.execute(params![
+                    e.signal_type,
+                    e.symbol,
+                    e.strength_bucket,
+                    e.sample_size as i64,
+                    e.hit_rate,
+                    e.mean_return_1d,
+                    e.median_return_1d,
+                    e.mean_return_5d,
+                    e.median_return_5d,
+                    e.mean_return_20d,
+                    e.median_return_20d,
+                    e.mean_mae,
+                    e.mean_mfe,
+                    e.hit_rate_samples as i64,
+                ])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(entries.len())
+    }
+
+    /// Get scorecard rows, optionally filtered by signal type and/or symbol
+    /// (pooled rows use "ALL" for symbol and strength bucket)
+    pub fn get_signal_scorecard(
+        &self,
+        signal_type: Option<&str>,
+        symbol: Option<&str>,
+    ) -> Result<Vec<SignalScorecardEntry>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT signal_type, symbol, strength_bucket, sample_size, hit_rate,
+                   mean_return_1d, median_return_1d, mean_return_5d, median_return_5d,
+                   mean_return_20d, median_return_20d, mean_mae, mean_mfe, updated_at,
+                   hit_rate_samples
+            FROM signal_scorecard
+            WHERE (?1 IS NULL OR signal_type = ?1) AND (?2 IS NULL OR symbol = ?2)
+            ORDER BY signal_type ASC, symbol ASC, strength_bucket ASC
+            "#,
+        )?;
+
+        let entries = stmt
+            .query_map(params![signal_type, symbol], |row| {
+                Ok(SignalScorecardEntry {
+                    signal_type: row.get(0)?,
+                    symbol: row.get(1)?,
+                    strength_bucket: row.get(2)?,
+                    sample_size: row.get::<_, i64>(3)? as usize,
+                    hit_rate: row.get(4)?,
+                    hit_rate_samples: row.get::<_, i64>(14)? as usize,
+                    mean_return_1d: row.get(5)?,
+                    median_return_1d: row.get(6)?,
+                    mean_return_5d: row.get(7)?,
+                    median_return_5d: row.get(8)?,
+                    mean_return_20d: row.get(9)?,
+                    median_return_20d: row.get(10)?,
+                    mean_mae: row.get(11)?,
+                    mean_mfe: row.get(12)?,
+                    updated_at: row.get(13)?,
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(entries)
+    }
+
+    // ========================================================================
+    // Detector Settings Methods
+    // ========================================================================
+
+    /// Get the saved settings of all signal detectors
+    pub fn get_detector_settings(&self) -> Result<Vec<DetectorSetting>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT detector_id, enabled, params FROM detector_settings ORDER BY detector_id ASC",
+        )?;
+
+        let settings = stmt
+            .query_map([], |row| {
+                let params_json: String = row.get(2)?;
+                Ok(DetectorSetting {
+                    detector_id: row.get(0)?,
+                    enabled: row.get(1)?,
+                    params: serde_json::from_str(&params_json).unwrap_or_default(),
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(settings)
+    }
+
+    /// Get the saved settings of one detector
+    pub fn get_detector_setting(&self, detector_id: &str) -> Result<Option<DetectorSetting>> {
+        Ok(self
+            .get_detector_settings()?
+            .into_iter()
+            .find(|s| s.detector_id == detector_id))
+    }
+
+    /// Save (insert or replace) a detector's settings
+    pub fn save_detector_setting(&self, setting: &DetectorSetting) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT OR REPLACE INTO detector_settings (detector_id, enabled, params, updated_at)
+            VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)
+            "#,
+            params![
+                setting.detector_id,
+                setting.enabled,
+                serde_json::to_string(&setting.params)?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    // ========================================================================
+    // Confluence Profile Methods
+    // ========================================================================
+
+    /// Save (insert or replace) a named confluence profile
+    pub fn save_confluence_profile(&self, profile: &ConfluenceProfile) -> Result<i64> {
+        self.conn.execute(
+            r#"
+            INSERT INTO confluence_profiles
+            (name, weights, intercept, horizon_days, sample_size, accuracy, symbols)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ON CONFLICT(name) DO UPDATE SET
+                weights = excluded.weights,
+                intercept = excluded.intercept,
+                horizon_days = excluded.horizon_days,
+                sample_size = excluded.sample_size,
+                accuracy = excluded.accuracy,
+                symbols = excluded.symbols
+            "#,
+            params![
+                profile.name,
+                serde_json::to_string(&profile.weights)?,
+                profile.intercept,
+                profile.horizon_days as i64,
+                profile.sample_size as i64,
+                profile.accuracy,
+                profile.symbols.join(","),
+            ],
+        )?;
+
+        // Refitting keeps the row (and its active flag), so look the id up
+        let id = self.conn.query_row(
+            "SELECT id FROM confluence_profiles WHERE name = ?1",
+            params![profile.name],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    /// Get all confluence profiles
+    pub fn get_confluence_profiles(&self) -> Result<Vec<ConfluenceProfile>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, name, weights, intercept, horizon_days, sample_size, accuracy,
+                   symbols, created_at, active
+            FROM confluence_profiles
+            ORDER BY name ASC
+            "#,
+        )?;
+
+        let profiles = stmt
+            .query_map([], Self::map_confluence_profile_row)?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(profiles)
+    }
+
+    /// Get a confluence profile by name
+    pub fn get_confluence_profile(&self, name: &str) -> Result<Option<ConfluenceProfile>> {
+        let profile = self
+            .conn
+            .query_row(
+                r#"
+                SELECT id, name, weights, intercept, horizon_days, sample_size, accuracy,
+                       symbols, created_at, active
+                FROM confluence_profiles
+                WHERE name = ?1
+                "#,
+                params![name],
+                Self::map_confluence_profile_row,
+            )
+            .optional()?;
+
+        Ok(profile)
+    }
+
+    /// Get the confluence profile signal engines should use, if any
+    pub fn get_active_confluence_profile(&self) -> Result<Option<ConfluenceProfile>> {
+        let profile = self
+            .conn
+            .query_row(
+                r#"
+                SELECT id, name, weights, intercept, horizon_days, sample_size, accuracy,
+                       symbols, created_at, active
+                FROM confluence_profiles
+                WHERE active = 1
+                ORDER BY id ASC
+                LIMIT 1
+                "#,
+                [],
+                Self::map_confluence_profile_row,
+            )
+            .optional()?;
+
+        Ok(profile)
+    }
+
+    /// Make `name` the active confluence profile (None = plain vote counting)
+    pub fn set_active_confluence_profile(&mut self, name: Option<&str>) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("UPDATE confluence_profiles SET active = 0", [])?;
+        if let Some(name) = name {
+            let updated = tx.execute(
+                "UPDATE confluence_profiles SET active = 1 WHERE name = ?1",
+                params![name],
+            )?;
+            if updated == 0 {
+                return Err(crate::error::PipelineError::Config(format!(
+                    "Unknown confluence profile '{}'",
+                    name
+                )));
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Delete a confluence profile
+    pub fn delete_confluence_profile(&self, name: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM confluence_profiles WHERE name = ?1", params![name])?;
+        Ok(())
+    }
+
+    fn map_confluence_profile_row(row: &rusqlite::Row) -> SqliteResult<ConfluenceProfile> {
+        let weights_json: String = row.get(2)?;
+        let symbols: String = row.get(7)?;
+
+        Ok(ConfluenceProfile {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            weights: serde_json::from_str(&weights_json).unwrap_or_default(),
+            intercept: row.get(3)?,
+            horizon_days: row.get::<_, i64>(4)? as usize,
+            sample_size: row.get::<_, i64>(5)? as usize,
+            accuracy: row.get(6)?,
+            symbols: symbols
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect(),
+            created_at: row.get(8)?,
+            active: row.get::<_, i64>(9)? != 0,
+        })
+    }
+
+    /// Insert or update regime classifications
+    pub fn upsert_regime_history(&mut self, records: &[RegimeRecord]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let mut count = 0;
+
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT OR REPLACE INTO regime_history
+                (symbol, date, regime, adx, vol_percentile, vix)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                "#,
+            )?;
+
+            for record in records {
+                stmt.execute(params![
+                    record.symbol,
+                    record.date.to_string(),
+                    record.regime.as_str(),
+                    record.adx,
+                    record.vol_percentile,
+                    record.vix,
+                ])?;
+                count += 1;
+            }
+        }
+
+        tx.commit()?;
+        Ok(count)
+    }
+
+    /// Get the regime history for a symbol (or the market), oldest first
+    pub fn get_regime_history(&self, symbol: &str) -> Result<Vec<RegimeRecord>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT symbol, date, regime, adx, vol_percentile, vix
+            FROM regime_history
+            WHERE symbol = ?1
+            ORDER BY date ASC
+            "#,
+        )?;
+
+        let records = stmt
+            .query_map(params![symbol], Self::map_regime_row)?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(records)
+    }
+
+    /// Get the most recent regime for a symbol (or the market)
+    pub fn get_latest_regime(&self, symbol: &str) -> Result<Option<RegimeRecord>> {
+        Ok(self
+            .conn
+            .query_row(
+                r#"
+                SELECT symbol, date, regime, adx, vol_percentile, vix
+                FROM regime_history
+                WHERE symbol = ?1
+                ORDER BY date DESC
+                LIMIT 1
+                "#,
+                params![symbol],
+                Self::map_regime_row,
+            )
+            .optional()?)
+    }
+
+    fn map_regime_row(row: &rusqlite::Row) -> SqliteResult<RegimeRecord> {
+        let date_str: String = row.get(1)?;
+        let regime_str: String = row.get(2)?;
+
+        Ok(RegimeRecord {
+            symbol: row.get(0)?,
+            date: NaiveDate::parse_from_str(&date_str, "%Y-%m-%d").unwrap_or_default(),
+            regime: MarketRegime::from_str(&regime_str).unwrap_or(MarketRegime::Range),
+            adx: row.get(3)?,
+            vol_percentile: row.get(4)?,
+            vix: row.get(5)?,
+        })
+    }
+
     /// Get all indicators for a symbol (for signal generation)
     pub fn get_all_indicators(&self, symbol: &str) -> Result<Vec<TechnicalIndicator>> {
         let mut stmt = self.conn.prepare(
@@ -1439,6 +2185,68 @@ impl Database {
         Ok(triggered_alerts)
     }
 
+    // ========================================================================
+    // Formula Methods
+    // ========================================================================
+
+    /// Save (insert or replace) a named formula
+    pub fn save_formula(&self, name: &str, expression: &str, description: Option<&str>) -> Result<i64> {
+        self.conn.execute(
+            r#"
+            INSERT INTO formulas (name, expression, description)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT(name) DO UPDATE SET
+                expression = excluded.expression,
+                description = excluded.description
+            "#,
+            params![name, expression, description],
+        )?;
+
+        // last_insert_rowid is stale when the upsert updated an existing row
+        let id = self.conn.query_row(
+            "SELECT id FROM formulas WHERE name = ?1",
+            params![name],
+            |row| row.get(0),
+        )?;
+        Ok(id)
+    }
+
+    /// Get all formulas in creation order
+    pub fn get_formulas(&self) -> Result<Vec<Formula>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, name, expression, description, created_at
+            FROM formulas
+            ORDER BY id ASC
+            "#,
+        )?;
+
+        let formulas = stmt
+            .query_map([], |row| {
+                Ok(Formula {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    expression: row.get(2)?,
+                    description: row.get(3)?,
+                    created_at: row.get(4)?,
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(formulas)
+    }
+
+    /// Delete a formula and its stored values
+    pub fn delete_formula(&self, name: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM formulas WHERE name = ?1", params![name])?;
+        self.conn.execute(
+            "DELETE FROM technical_indicators WHERE indicator_name = ?1",
+            params![formula_indicator_name(name)],
+        )?;
+        Ok(())
+    }
+
     // ========================================================================
     // Backtest Methods
     // ========================================================================
@@ -1450,8 +2258,10 @@ impl Database {
             INSERT OR REPLACE INTO strategies
             (name, description, entry_condition, entry_threshold,
              exit_condition, exit_threshold,
-             stop_loss_percent, take_profit_percent, position_size_percent)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             stop_loss_percent, take_profit_percent, position_size_percent,
+             entry_formula, exit_formula, allowed_regimes, entry_rule, exit_rule, direction,
+             exit_rules, position_sizing, rs_benchmark)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
             "#,
             params![
                 strategy.name,
@@ -1463,6 +2273,15 @@ impl Database {
                 strategy.stop_loss_percent,
                 strategy.take_profit_percent,
                 strategy.position_size_percent,
+                strategy.entry_formula,
+                strategy.exit_formula,
+                MarketRegime::join(&strategy.allowed_regimes),
+                strategy.entry_rule.as_ref().map(|r| r.to_json()).transpose()?,
+                strategy.exit_rule.as_ref().map(|r| r.to_json()).transpose()?,
+                strategy.direction.as_str(),
+                serde_json::to_string(&strategy.exit_rules)?,
+                serde_json::to_string(&strategy.position_sizing)?,
+                strategy.rs_benchmark,
             ],
         )?;
 
@@ -1475,33 +2294,16 @@ impl Database {
             r#"
             SELECT id, name, description, entry_condition, entry_threshold,
                    exit_condition, exit_threshold,
-                   stop_loss_percent, take_profit_percent, position_size_percent, created_at
+                   stop_loss_percent, take_profit_percent, position_size_percent, created_at,
+                   entry_formula, exit_formula, allowed_regimes, entry_rule, exit_rule,
+                   direction, exit_rules, position_sizing, rs_benchmark
             FROM strategies
             ORDER BY name ASC
             "#,
         )?;
 
         let strategies = stmt
-            .query_map([], |row| {
-                let entry_cond_str: String = row.get(3)?;
-                let exit_cond_str: String = row.get(5)?;
-
-                Ok(Strategy {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    description: row.get(2)?,
-                    entry_condition: StrategyConditionType::from_str(&entry_cond_str)
-                        .unwrap_or(StrategyConditionType::RsiOversold),
-                    entry_threshold: row.get(4)?,
-                    exit_condition: StrategyConditionType::from_str(&exit_cond_str)
-                        .unwrap_or(StrategyConditionType::RsiOverbought),
-                    exit_threshold: row.get(6)?,
-                    stop_loss_percent: row.get(7)?,
-                    take_profit_percent: row.get(8)?,
-                    position_size_percent: row.get(9)?,
-                    created_at: row.get(10)?,
-                })
-            })?
+            .query_map([], Self::map_strategy_row)?
             .collect::<SqliteResult<Vec<_>>>()?;
 
         Ok(strategies)
@@ -1513,32 +2315,15 @@ impl Database {
             r#"
             SELECT id, name, description, entry_condition, entry_threshold,
                    exit_condition, exit_threshold,
-                   stop_loss_percent, take_profit_percent, position_size_percent, created_at
+                   stop_loss_percent, take_profit_percent, position_size_percent, created_at,
+                   entry_formula, exit_formula, allowed_regimes, entry_rule, exit_rule,
+                   direction, exit_rules, position_sizing, rs_benchmark
             FROM strategies
             WHERE name = ?1
             "#,
         )?;
 
-        let result = stmt.query_row(params![name], |row| {
-            let entry_cond_str: String = row.get(3)?;
-            let exit_cond_str: String = row.get(5)?;
-
-            Ok(Strategy {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                entry_condition: StrategyConditionType::from_str(&entry_cond_str)
-                    .unwrap_or(StrategyConditionType::RsiOversold),
-                entry_threshold: row.get(4)?,
-                exit_condition: StrategyConditionType::from_str(&exit_cond_str)
-                    .unwrap_or(StrategyConditionType::RsiOverbought),
-                exit_threshold: row.get(6)?,
-                stop_loss_percent: row.get(7)?,
-                take_profit_percent: row.get(8)?,
-                position_size_percent: row.get(9)?,
-                created_at: row.get(10)?,
-            })
-        });
+        let result = stmt.query_row(params![name], Self::map_strategy_row);
 
         match result {
             Ok(strategy) => Ok(Some(strategy)),
@@ -1547,6 +2332,55 @@ impl Database {
         }
     }
 
+    /// Parse an optional JSON column, failing the row on invalid JSON rather
+    /// than silently dropping the value
+    fn json_column<T: serde::de::DeserializeOwned>(
+        row: &rusqlite::Row,
+        idx: usize,
+    ) -> SqliteResult<Option<T>> {
+        row.get::<_, Option<String>>(idx)?
+            .map(|json| {
+                serde_json::from_str(&json).map_err(|e| {
+                    rusqlite::Error::FromSqlConversionFailure(
+                        idx,
+                        rusqlite::types::Type::Text,
+                        Box::new(e),
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    fn map_strategy_row(row: &rusqlite::Row) -> SqliteResult<Strategy> {
+        let entry_cond_str: String = row.get(3)?;
+        let exit_cond_str: String = row.get(5)?;
+
+        Ok(Strategy {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            entry_condition: StrategyConditionType::from_str(&entry_cond_str)
+                .unwrap_or(StrategyConditionType::RsiOversold),
+            entry_threshold: row.get(4)?,
+            exit_condition: StrategyConditionType::from_str(&exit_cond_str)
+                .unwrap_or(StrategyConditionType::RsiOverbought),
+            exit_threshold: row.get(6)?,
+            stop_loss_percent: row.get(7)?,
+            take_profit_percent: row.get(8)?,
+            position_size_percent: row.get(9)?,
+            entry_formula: row.get(11)?,
+            exit_formula: row.get(12)?,
+            rs_benchmark: row.get(19)?,
+            allowed_regimes: MarketRegime::parse_list(&row.get::<_, String>(13)?),
+            entry_rule: Self::json_column(row, 14)?,
+            exit_rule: Self::json_column(row, 15)?,
+            direction: StrategyDirection::from_str(&row.get::<_, String>(16)?).unwrap_or_default(),
+            exit_rules: Self::json_column(row, 17)?.unwrap_or_default(),
+            position_sizing: Self::json_column(row, 18)?.unwrap_or_default(),
+            created_at: row.get(10)?,
+        })
+    }
+
     /// Delete a strategy
     pub fn delete_strategy(&self, name: &str) -> Result<()> {
         self.conn
@@ -1557,7 +2391,13 @@ impl Database {
     /// Save a backtest result
     pub fn save_backtest_result(&self, result: &BacktestResult) -> Result<i64> {
         let tx = self.conn.unchecked_transaction()?;
+        let backtest_id = Self::insert_backtest_result(&tx, result)?;
+        tx.commit()?;
+        Ok(backtest_id)
+    }
 
+    /// Insert a backtest run with its trades and per-symbol breakdown
+    fn insert_backtest_result(tx: &Connection, result: &BacktestResult) -> Result<i64> {
         // Insert the backtest run
         tx.execute(
             r#"
@@ -1566,8 +2406,9 @@ impl Database {
              initial_capital, final_capital, total_return, total_return_dollars,
              max_drawdown, sharpe_ratio, win_rate, total_trades, winning_trades,
              losing_trades, avg_win_percent, avg_loss_percent, profit_factor,
-             avg_trade_duration_days)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)
+             avg_trade_duration_days, long_metrics, short_metrics, cost_metrics, risk_metrics)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19,
+                    ?20, ?21, ?22, ?23)
             "#,
             params![
                 result.strategy_id,
@@ -1589,6 +2430,10 @@ impl Database {
                 result.metrics.avg_loss_percent,
                 result.metrics.profit_factor,
                 result.metrics.avg_trade_duration_days,
+                serde_json::to_string(&result.metrics.long)?,
+                serde_json::to_string(&result.metrics.short)?,
+                serde_json::to_string(&result.metrics.costs)?,
+                serde_json::to_string(&result.metrics.risk)?,
             ],
         )?;
 
@@ -1623,7 +2468,55 @@ impl Database {
             }
         }
 
-        tx.commit()?;
+        // Insert equity curve
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT INTO backtest_equity
+                (backtest_id, date, equity, cash, exposure, open_positions, drawdown, buy_and_hold)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                "#,
+            )?;
+
+            for point in &result.equity_curve {
+                stmt.execute(params![
+                    backtest_id,
+                    point.date.to_string(),
+                    point.equity,
+                    point.cash,
+                    point.exposure,
+                    point.open_positions as i64,
+                    point.drawdown,
+                    point.buy_and_hold,
+                ])?;
+            }
+        }
+
+        // Insert per-symbol breakdown
+        {
+            let mut stmt = tx.prepare(
+                r#"
+                INSERT INTO backtest_symbol_stats
+                (backtest_id, symbol, total_trades, winning_trades, losing_trades,
+                 win_rate, total_profit, avg_profit_percent)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+                "#,
+            )?;
+
+            for stats in &result.symbol_stats {
+                stmt.execute(params![
+                    backtest_id,
+                    stats.symbol,
+                    stats.total_trades as i64,
+                    stats.winning_trades as i64,
+                    stats.losing_trades as i64,
+                    stats.win_rate,
+                    stats.total_profit,
+                    stats.avg_profit_percent,
+                ])?;
+            }
+        }
+
         Ok(backtest_id)
     }
 
@@ -1640,9 +2533,10 @@ impl Database {
                    initial_capital, final_capital, total_return, total_return_dollars,
                    max_drawdown, sharpe_ratio, win_rate, total_trades, winning_trades,
                    losing_trades, avg_win_percent, avg_loss_percent, profit_factor,
-                   avg_trade_duration_days, created_at
+                   avg_trade_duration_days, created_at, long_metrics, short_metrics, cost_metrics,
+                   risk_metrics
             FROM backtest_runs
-            WHERE 1=1
+            WHERE optimization_job_id IS NULL
             "#,
         );
 
@@ -1689,6 +2583,12 @@ impl Database {
         let total_trades_i64: i64 = row.get(13)?;
         let winning_trades_i64: i64 = row.get(14)?;
         let losing_trades_i64: i64 = row.get(15)?;
+        let direction_metrics = |idx: usize| -> SqliteResult<DirectionMetrics> {
+            Ok(row
+                .get::<_, Option<String>>(idx)?
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or_default())
+        };
 
         Ok(BacktestResult {
             id: row.get(0)?,
@@ -1714,8 +2614,20 @@ impl Database {
                 avg_loss_percent: row.get(17)?,
                 profit_factor: row.get(18)?,
                 avg_trade_duration_days: row.get(19)?,
+                long: direction_metrics(21)?,
+                short: direction_metrics(22)?,
+                costs: row
+                    .get::<_, Option<String>>(23)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
+                risk: row
+                    .get::<_, Option<String>>(24)?
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                    .unwrap_or_default(),
             },
             trades: Vec::new(), // Trades loaded separately if needed
+            symbol_stats: Vec::new(),
+            equity_curve: Vec::new(),
             created_at: row.get(20)?,
         })
     }
@@ -1728,7 +2640,8 @@ impl Database {
                    initial_capital, final_capital, total_return, total_return_dollars,
                    max_drawdown, sharpe_ratio, win_rate, total_trades, winning_trades,
                    losing_trades, avg_win_percent, avg_loss_percent, profit_factor,
-                   avg_trade_duration_days, created_at
+                   avg_trade_duration_days, created_at, long_metrics, short_metrics, cost_metrics,
+                   risk_metrics
             FROM backtest_runs
             WHERE id = ?1
             "#,
@@ -1779,10 +2692,283 @@ impl Database {
             .collect::<SqliteResult<Vec<_>>>()?;
 
         backtest.trades = trades;
+        backtest.symbol_stats = self.get_backtest_symbol_stats(backtest_id)?;
+        backtest.equity_curve = self.get_backtest_equity(backtest_id)?;
 
         Ok(Some(backtest))
     }
 
+    /// Get the daily equity curve of a backtest, oldest first
+    pub fn get_backtest_equity(&self, backtest_id: i64) -> Result<Vec<EquityPoint>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT date, equity, cash, exposure, open_positions, drawdown, buy_and_hold
+            FROM backtest_equity
+            WHERE backtest_id = ?1
+            ORDER BY date ASC
+            "#,
+        )?;
+
+        let curve = stmt
+            .query_map(params![backtest_id], |row| {
+                let date_str: String = row.get(0)?;
+                Ok(EquityPoint {
+                    date: NaiveDate::parse_from_str(&date_str, "%Y-%m-%d")
+                        .unwrap_or_else(|_| NaiveDate::from_ymd_opt(1970, 1, 1).unwrap()),
+                    equity: row.get(1)?,
+                    cash: row.get(2)?,
+                    exposure: row.get(3)?,
+                    open_positions: row.get::<_, i64>(4)? as usize,
+                    drawdown: row.get(5)?,
+                    buy_and_hold: row.get(6)?,
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(curve)
+    }
+
+    /// Get the per-symbol trade breakdown of a backtest
+    pub fn get_backtest_symbol_stats(&self, backtest_id: i64) -> Result<Vec<SymbolTradeStats>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT symbol, total_trades, winning_trades, losing_trades, win_rate,
+                   total_profit, avg_profit_percent
+            FROM backtest_symbol_stats
+            WHERE backtest_id = ?1
+            ORDER BY symbol ASC
+            "#,
+        )?;
+
+        let stats = stmt
+            .query_map(params![backtest_id], |row| {
+                Ok(SymbolTradeStats {
+                    symbol: row.get(0)?,
+                    total_trades: row.get::<_, i64>(1)? as usize,
+                    winning_trades: row.get::<_, i64>(2)? as usize,
+                    losing_trades: row.get::<_, i64>(3)? as usize,
+                    win_rate: row.get(4)?,
+                    total_profit: row.get(5)?,
+                    avg_profit_percent: row.get(6)?,
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(stats)
+    }
+
+    /// Save an optimization job and every backtest it ran. Sets `job_id`.
+    pub fn save_optimization(
+        &self,
+        result: &mut OptimizationResult,
+        config: &OptimizationConfig,
+    ) -> Result<i64> {
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            r#"
+            INSERT INTO optimization_jobs
+            (strategy_id, strategy_name, symbol, config, best_params, in_sample_score,
+             out_of_sample_score, degradation_percent, overfit, trial_count)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            "#,
+            params![
+                result.strategy_id,
+                result.strategy_name,
+                result.symbol,
+                serde_json::to_string(config)?,
+                serde_json::to_string(&result.best_params)?,
+                result.in_sample_score,
+                result.out_of_sample_score,
+                result.degradation_percent,
+                result.overfit as i32,
+                result.trials.len() as i64,
+            ],
+        )?;
+        let job_id = tx.last_insert_rowid();
+
+        let link = |backtest_id: i64, role: &str, window: usize, params: &ParamSet| -> Result<()> {
+            tx.execute(
+                r#"
+                UPDATE backtest_runs
+                SET optimization_job_id = ?1, optimization_role = ?2,
+                    optimization_window = ?3, parameters = ?4
+                WHERE id = ?5
+                "#,
+                params![job_id, role, window as i64, serde_json::to_string(params)?, backtest_id],
+            )?;
+            Ok(())
+        };
+        for trial in &result.trials {
+            let backtest_id = Self::insert_backtest_result(&tx, &trial.result)?;
+            link(backtest_id, "in_sample", trial.window, &trial.params)?;
+        }
+        for window in &result.windows {
+            let backtest_id = Self::insert_backtest_result(&tx, &window.out_of_sample)?;
+            link(backtest_id, "out_of_sample", window.index, &window.best_params)?;
+        }
+        tx.commit()?;
+
+        result.job_id = job_id;
+        Ok(job_id)
+    }
+
+    /// Get optimization jobs, newest first
+    pub fn get_optimization_jobs(
+        &self,
+        strategy_name: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<OptimizationJob>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, strategy_id, strategy_name, symbol, config, best_params, in_sample_score,
+                   out_of_sample_score, degradation_percent, overfit, trial_count, created_at
+            FROM optimization_jobs
+            WHERE ?1 IS NULL OR strategy_name = ?1
+            ORDER BY created_at DESC, id DESC
+            LIMIT ?2
+            "#,
+        )?;
+
+        let jobs = stmt
+            .query_map(params![strategy_name, limit as i64], |row| {
+                Ok(OptimizationJob {
+                    id: row.get(0)?,
+                    strategy_id: row.get(1)?,
+                    strategy_name: row.get(2)?,
+                    symbol: row.get(3)?,
+                    config: serde_json::from_str(&row.get::<_, String>(4)?).unwrap_or_default(),
+                    best_params: serde_json::from_str(&row.get::<_, String>(5)?).unwrap_or_default(),
+                    in_sample_score: row.get(6)?,
+                    out_of_sample_score: row.get(7)?,
+                    degradation_percent: row.get(8)?,
+                    overfit: row.get::<_, i32>(9)? != 0,
+                    trial_count: row.get::<_, i64>(10)? as usize,
+                    created_at: row.get(11)?,
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(jobs)
+    }
+
+    /// Get the backtest runs of an optimization job, by window then role
+    pub fn get_optimization_runs(&self, job_id: i64) -> Result<Vec<OptimizationRun>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, strategy_id, strategy_name, symbol, start_date, end_date,
+                   initial_capital, final_capital, total_return, total_return_dollars,
+                   max_drawdown, sharpe_ratio, win_rate, total_trades, winning_trades,
+                   losing_trades, avg_win_percent, avg_loss_percent, profit_factor,
+                   avg_trade_duration_days, created_at, long_metrics, short_metrics, cost_metrics,
+                   risk_metrics, optimization_role, optimization_window, parameters
+            FROM backtest_runs
+            WHERE optimization_job_id = ?1
+            ORDER BY optimization_window ASC, optimization_role ASC, id ASC
+            "#,
+        )?;
+
+        let runs = stmt
+            .query_map(params![job_id], |row| {
+                Ok(OptimizationRun {
+                    result: self.map_backtest_row(row)?,
+                    role: row.get(25)?,
+                    window: row.get::<_, i64>(26)? as usize,
+                    params: row
+                        .get::<_, Option<String>>(27)?
+                        .and_then(|json| serde_json::from_str(&json).ok())
+                        .unwrap_or_default(),
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(runs)
+    }
+
+    /// Delete an optimization job and its backtest runs
+    pub fn delete_optimization_job(&self, job_id: i64) -> Result<()> {
+        let backtest_ids: Vec<i64> = self
+            .conn
+            .prepare("SELECT id FROM backtest_runs WHERE optimization_job_id = ?1")?
+            .query_map(params![job_id], |row| row.get(0))?
+            .collect::<SqliteResult<Vec<_>>>()?;
+        for backtest_id in backtest_ids {
+            self.delete_backtest(backtest_id)?;
+        }
+        self.conn.execute(
+            "DELETE FROM optimization_jobs WHERE id = ?1",
+            params![job_id],
+        )?;
+        Ok(())
+    }
+
+    /// Save a Monte Carlo analysis. Sets `id`.
+    pub fn save_monte_carlo(&self, result: &mut MonteCarloResult) -> Result<i64> {
+        self.conn.execute(
+            r#"
+            INSERT INTO monte_carlo_runs
+            (backtest_id, config, original_return, original_max_drawdown, final_return,
+             max_drawdown, probability_of_loss_percent, risk_of_ruin_percent, equity_bands)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            "#,
+            params![
+                result.backtest_id,
+                serde_json::to_string(&result.config)?,
+                result.original_return,
+                result.original_max_drawdown,
+                serde_json::to_string(&result.final_return)?,
+                serde_json::to_string(&result.max_drawdown)?,
+                result.probability_of_loss_percent,
+                result.risk_of_ruin_percent,
+                serde_json::to_string(&result.equity_bands)?,
+            ],
+        )?;
+        result.id = self.conn.last_insert_rowid();
+        Ok(result.id)
+    }
+
+    /// Get the Monte Carlo analyses of a backtest, newest first
+    pub fn get_monte_carlo_runs(&self, backtest_id: i64) -> Result<Vec<MonteCarloResult>> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT id, backtest_id, config, original_return, original_max_drawdown, final_return,
+                   max_drawdown, probability_of_loss_percent, risk_of_ruin_percent, equity_bands,
+                   created_at
+            FROM monte_carlo_runs
+            WHERE backtest_id = ?1
+            ORDER BY created_at DESC, id DESC
+            "#,
+        )?;
+
+        let json = |row: &rusqlite::Row, idx: usize| -> SqliteResult<Option<String>> { row.get(idx) };
+        let runs = stmt
+            .query_map(params![backtest_id], |row| {
+                Ok(MonteCarloResult {
+                    id: row.get(0)?,
+                    backtest_id: row.get(1)?,
+                    config: json(row, 2)?
+                        .and_then(|j| serde_json::from_str(&j).ok())
+                        .unwrap_or_default(),
+                    original_return: row.get(3)?,
+                    original_max_drawdown: row.get(4)?,
+                    final_return: json(row, 5)?
+                        .and_then(|j| serde_json::from_str(&j).ok())
+                        .unwrap_or_default(),
+                    max_drawdown: json(row, 6)?
+                        .and_then(|j| serde_json::from_str(&j).ok())
+                        .unwrap_or_default(),
+                    probability_of_loss_percent: row.get(7)?,
+                    risk_of_ruin_percent: row.get(8)?,
+                    equity_bands: json(row, 9)?
+                        .and_then(|j| serde_json::from_str(&j).ok())
+                        .unwrap_or_default(),
+                    created_at: row.get(10)?,
+                })
+            })?
+            .collect::<SqliteResult<Vec<_>>>()?;
+
+        Ok(runs)
+    }
+
     /// Delete a backtest result and its trades
     pub fn delete_backtest(&self, backtest_id: i64) -> Result<()> {
         let tx = self.conn.unchecked_transaction()?;
@@ -1790,6 +2976,18 @@ impl Database {
             "DELETE FROM backtest_trades WHERE backtest_id = ?1",
             params![backtest_id],
         )?;
+        tx.execute(
+            "DELETE FROM backtest_symbol_stats WHERE backtest_id = ?1",
+            params![backtest_id],
+        )?;
+        tx.execute(
+            "DELETE FROM backtest_equity WHERE backtest_id = ?1",
+            params![backtest_id],
+        )?;
+        tx.execute(
+            "DELETE FROM monte_carlo_runs WHERE backtest_id = ?1",
+            params![backtest_id],
+        )?;
         tx.execute(
             "DELETE FROM backtest_runs WHERE id = ?1",
             params![backtest_id],
@@ -2078,10 +3276,56 @@ impl Database {
         Ok(trades)
     }
 
+    /// Get the stored stop state of a paper position and the date of the
+    /// last bar it was advanced past
+    pub fn get_paper_stop_state(&self, position_id: i64) -> Result<Option<(StopTracker, NaiveDate)>> {
+        let row: Option<(String, String)> = self
+            .conn
+            .query_row(
+                "SELECT state, last_bar_date FROM paper_stop_state WHERE position_id = ?1",
+                params![position_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        Ok(row.and_then(|(state, date)| {
+            let tracker = serde_json::from_str(&state).ok()?;
+            let date = NaiveDate::parse_from_str(&date, "%Y-%m-%d").ok()?;
+            Some((tracker, date))
+        }))
+    }
+
+    /// Save the stop state of a paper position
+    pub fn save_paper_stop_state(
+        &self,
+        position_id: i64,
+        tracker: &StopTracker,
+        last_bar_date: NaiveDate,
+    ) -> Result<()> {
+        self.conn.execute(
+            r#"
+            INSERT OR REPLACE INTO paper_stop_state (position_id, state, last_bar_date, updated_at)
+            VALUES (?1, ?2, ?3, CURRENT_TIMESTAMP)
+            "#,
+            params![position_id, serde_json::to_string(tracker)?, last_bar_date.to_string()],
+        )?;
+        Ok(())
+    }
+
+    /// Remove stop state of positions that have been closed
+    pub fn prune_paper_stop_state(&self) -> Result<usize> {
+        let count = self.conn.execute(
+            "DELETE FROM paper_stop_state WHERE position_id NOT IN (SELECT id FROM paper_positions)",
+            [],
+        )?;
+        Ok(count)
+    }
+
     /// Reset paper trading account (clear all positions, trades, reset cash)
     pub fn reset_paper_account(&self, starting_cash: f64) -> Result<()> {
         let tx = self.conn.unchecked_transaction()?;
         tx.execute("DELETE FROM paper_positions", [])?;
+        tx.execute("DELETE FROM paper_stop_state", [])?;
         tx.execute("DELETE FROM paper_trades", [])?;
         tx.execute(
             "UPDATE paper_wallet SET cash = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = 1",
@@ -2950,7 +4194,7 @@ impl Database {
                     take_profit_percent, session_duration_minutes, benchmark_symbol, model_priority,
                     trading_mode, daily_loss_threshold, consecutive_loss_limit,
                     auto_conservative_on_trigger, max_daily_trades, max_single_trade_value,
-                    require_confluence, blocked_hours
+                    require_confluence, blocked_hours, exit_rules, position_sizing
              FROM ai_trader_config WHERE id = 1"#,
             [],
             |row| {
@@ -2973,6 +4217,14 @@ impl Database {
                     max_single_trade_value: row.get(12)?,
                     require_confluence: row.get::<_, i32>(13)? != 0,
                     blocked_hours: row.get(14)?,
+                    exit_rules: row
+                        .get::<_, Option<String>>(15)?
+                        .and_then(|json| serde_json::from_str(&json).ok())
+                        .unwrap_or_default(),
+                    position_sizing: row
+                        .get::<_, Option<String>>(16)?
+                        .and_then(|json| serde_json::from_str(&json).ok())
+                        .unwrap_or_default(),
                 })
             },
         )?;
@@ -2989,7 +4241,8 @@ impl Database {
                 model_priority = ?7, trading_mode = ?8, daily_loss_threshold = ?9,
                 consecutive_loss_limit = ?10, auto_conservative_on_trigger = ?11,
                 max_daily_trades = ?12, max_single_trade_value = ?13,
-                require_confluence = ?14, blocked_hours = ?15,
+                require_confluence = ?14, blocked_hours = ?15, exit_rules = ?16,
+                position_sizing = ?17,
                 updated_at = CURRENT_TIMESTAMP
              WHERE id = 1"#,
             params![
@@ -3008,6 +4261,8 @@ impl Database {
                 config.max_single_trade_value,
                 config.require_confluence as i32,
                 config.blocked_hours,
+                serde_json::to_string(&config.exit_rules)?,
+                serde_json::to_string(&config.position_sizing)?,
             ],
         )?;
         Ok(())
@@ -3384,6 +4639,7 @@ impl Database {
         tx.execute("DELETE FROM ai_trade_decisions", [])?;
         tx.execute("DELETE FROM ai_trading_sessions", [])?;
         tx.execute("DELETE FROM paper_positions", [])?;
+        tx.execute("DELETE FROM paper_stop_state", [])?;
         tx.execute("DELETE FROM paper_trades", [])?;
         tx.execute(
             "UPDATE paper_wallet SET cash = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = 1",
@@ -4001,6 +5257,8 @@ CREATE TABLE IF NOT EXISTS signals (
     timestamp DATE NOT NULL,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     acknowledged BOOLEAN DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'new',
+    end_date DATE,
     UNIQUE(symbol, signal_type, timestamp)
 );
 
@@ -4010,6 +5268,75 @@ CREATE INDEX IF NOT EXISTS idx_signals_timestamp ON signals(timestamp);
 CREATE INDEX IF NOT EXISTS idx_signals_direction ON signals(direction);
 CREATE INDEX IF NOT EXISTS idx_signals_acknowledged ON signals(acknowledged);
 
+-- Forward returns after each signal (outlives cleanup of the signals table)
+CREATE TABLE IF NOT EXISTS signal_outcomes (
+    symbol TEXT NOT NULL,
+    signal_type TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    strength REAL NOT NULL,
+    signal_date DATE NOT NULL,
+    entry_price REAL NOT NULL,
+    return_1d REAL,
+    return_5d REAL,
+    return_20d REAL,
+    mae REAL,
+    mfe REAL,
+    PRIMARY KEY (symbol, signal_type, signal_date)
+);
+
+-- Aggregated signal performance per type / symbol / strength bucket
+CREATE TABLE IF NOT EXISTS signal_scorecard (
+    signal_type TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    strength_bucket TEXT NOT NULL,
+    sample_size INTEGER NOT NULL,
+    hit_rate REAL NOT NULL,
+    hit_rate_samples INTEGER NOT NULL DEFAULT 0,
+    mean_return_1d REAL NOT NULL,
+    median_return_1d REAL NOT NULL,
+    mean_return_5d REAL NOT NULL,
+    median_return_5d REAL NOT NULL,
+    mean_return_20d REAL NOT NULL,
+    median_return_20d REAL NOT NULL,
+    mean_mae REAL NOT NULL,
+    mean_mfe REAL NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (signal_type, symbol, strength_bucket)
+);
+
+-- Market regime classifications (symbol 'MARKET' = broad market)
+CREATE TABLE IF NOT EXISTS regime_history (
+    symbol TEXT NOT NULL,
+    date DATE NOT NULL,
+    regime TEXT NOT NULL,
+    adx REAL,
+    vol_percentile REAL,
+    vix REAL,
+    PRIMARY KEY (symbol, date)
+);
+
+-- Learned confluence vote weights
+CREATE TABLE IF NOT EXISTS confluence_profiles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT UNIQUE NOT NULL,
+    weights TEXT NOT NULL,
+    intercept REAL NOT NULL,
+    horizon_days INTEGER NOT NULL,
+    sample_size INTEGER NOT NULL,
+    accuracy REAL NOT NULL,
+    symbols TEXT NOT NULL DEFAULT '',
+    active INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Runtime signal detector settings
+CREATE TABLE IF NOT EXISTS detector_settings (
+    detector_id TEXT PRIMARY KEY,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    params TEXT NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
 -- Indicator-based alerts
 CREATE TABLE IF NOT EXISTS indicator_alerts (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -4042,11 +5369,29 @@ CREATE TABLE IF NOT EXISTS strategies (
     stop_loss_percent REAL,
     take_profit_percent REAL,
     position_size_percent REAL NOT NULL DEFAULT 100.0,
+    entry_formula TEXT,
+    exit_formula TEXT,
+    allowed_regimes TEXT NOT NULL DEFAULT '',
+    entry_rule TEXT,
+    exit_rule TEXT,
+    direction TEXT NOT NULL DEFAULT 'long',
+    exit_rules TEXT,
+    position_sizing TEXT,
+    rs_benchmark TEXT,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
 );
 
 CREATE INDEX IF NOT EXISTS idx_strategies_name ON strategies(name);
 
+-- User-defined indicator formulas
+CREATE TABLE IF NOT EXISTS formulas (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT UNIQUE NOT NULL,
+    expression TEXT NOT NULL,
+    description TEXT,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
 -- Backtest runs
 CREATE TABLE IF NOT EXISTS backtest_runs (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -4069,6 +5414,15 @@ CREATE TABLE IF NOT EXISTS backtest_runs (
     avg_loss_percent REAL NOT NULL,
     profit_factor REAL NOT NULL,
     avg_trade_duration_days REAL NOT NULL,
+    long_metrics TEXT,
+    short_metrics TEXT,
+    cost_metrics TEXT,
+    risk_metrics TEXT,
+    -- Set on runs made by an optimization job
+    optimization_job_id INTEGER,
+    optimization_role TEXT,          -- 'in_sample' or 'out_of_sample'
+    optimization_window INTEGER,
+    parameters TEXT,                 -- JSON of the parameter values tried
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     FOREIGN KEY (strategy_id) REFERENCES strategies(id)
 );
@@ -4077,6 +5431,43 @@ CREATE INDEX IF NOT EXISTS idx_backtest_runs_strategy ON backtest_runs(strategy_
 CREATE INDEX IF NOT EXISTS idx_backtest_runs_symbol ON backtest_runs(symbol);
 CREATE INDEX IF NOT EXISTS idx_backtest_runs_date ON backtest_runs(created_at);
 
+-- Parameter optimization jobs
+CREATE TABLE IF NOT EXISTS optimization_jobs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    strategy_id INTEGER NOT NULL,
+    strategy_name TEXT NOT NULL,
+    symbol TEXT NOT NULL,
+    config TEXT NOT NULL,            -- JSON OptimizationConfig
+    best_params TEXT NOT NULL,       -- JSON of the most recent window's winner
+    in_sample_score REAL NOT NULL,
+    out_of_sample_score REAL NOT NULL,
+    degradation_percent REAL NOT NULL,
+    overfit INTEGER NOT NULL DEFAULT 0,
+    trial_count INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (strategy_id) REFERENCES strategies(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_optimization_jobs_strategy ON optimization_jobs(strategy_name);
+
+-- Monte Carlo analyses of backtest runs
+CREATE TABLE IF NOT EXISTS monte_carlo_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    backtest_id INTEGER NOT NULL,
+    config TEXT NOT NULL,            -- JSON MonteCarloConfig
+    original_return REAL NOT NULL,
+    original_max_drawdown REAL NOT NULL,
+    final_return TEXT NOT NULL,      -- JSON percentiles
+    max_drawdown TEXT NOT NULL,      -- JSON percentiles
+    probability_of_loss_percent REAL NOT NULL,
+    risk_of_ruin_percent REAL NOT NULL,
+    equity_bands TEXT NOT NULL,      -- JSON percentiles per step
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (backtest_id) REFERENCES backtest_runs(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_monte_carlo_runs_backtest ON monte_carlo_runs(backtest_id);
+
 -- Backtest trades
 CREATE TABLE IF NOT EXISTS backtest_trades (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -4098,6 +5489,34 @@ CREATE TABLE IF NOT EXISTS backtest_trades (
 CREATE INDEX IF NOT EXISTS idx_backtest_trades_run ON backtest_trades(backtest_id);
 CREATE INDEX IF NOT EXISTS idx_backtest_trades_symbol ON backtest_trades(symbol);
 
+-- Per-symbol trade breakdown of backtests
+CREATE TABLE IF NOT EXISTS backtest_symbol_stats (
+    backtest_id INTEGER NOT NULL,
+    symbol TEXT NOT NULL,
+    total_trades INTEGER NOT NULL,
+    winning_trades INTEGER NOT NULL,
+    losing_trades INTEGER NOT NULL,
+    win_rate REAL NOT NULL,
+    total_profit REAL NOT NULL,
+    avg_profit_percent REAL NOT NULL,
+    PRIMARY KEY (backtest_id, symbol),
+    FOREIGN KEY (backtest_id) REFERENCES backtest_runs(id)
+);
+
+-- Daily equity curve of a backtest run
+CREATE TABLE IF NOT EXISTS backtest_equity (
+    backtest_id INTEGER NOT NULL,
+    date DATE NOT NULL,
+    equity REAL NOT NULL,
+    cash REAL NOT NULL,
+    exposure REAL NOT NULL,
+    open_positions INTEGER NOT NULL,
+    drawdown REAL NOT NULL,          -- % below the running peak
+    buy_and_hold REAL,
+    PRIMARY KEY (backtest_id, date),
+    FOREIGN KEY (backtest_id) REFERENCES backtest_runs(id)
+);
+
 -- Paper trading wallet (singleton - one paper account)
 CREATE TABLE IF NOT EXISTS paper_wallet (
     id INTEGER PRIMARY KEY CHECK (id = 1),
@@ -4122,6 +5541,14 @@ CREATE TABLE IF NOT EXISTS paper_positions (
 
 CREATE INDEX IF NOT EXISTS idx_paper_positions_symbol ON paper_positions(symbol);
 
+-- Stop state (best price, trailing level, scale-outs taken) of open paper positions
+CREATE TABLE IF NOT EXISTS paper_stop_state (
+    position_id INTEGER PRIMARY KEY,
+    state TEXT NOT NULL,
+    last_bar_date TEXT NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
 -- Paper trading history (all trades)
 CREATE TABLE IF NOT EXISTS paper_trades (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -4231,6 +5658,10 @@ CREATE TABLE IF NOT EXISTS ai_trader_config (
     max_single_trade_value REAL NOT NULL DEFAULT 50000.0,
     require_confluence INTEGER NOT NULL DEFAULT 1,
     blocked_hours TEXT DEFAULT '09:30-09:45,15:45-16:00',
+    -- Trailing/break-even/time/scale-out exits for paper positions (JSON)
+    exit_rules TEXT,
+    -- Position sizing model for BUY decisions (JSON)
+    position_sizing TEXT,
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
 );
@@ -0,0 +1,638 @@
+//! Pluggable signal detectors
+//!
+//! Every signal source implements `SignalDetector` and lives in a
+//! `DetectorRegistry`, where it can be enabled, disabled and tuned at runtime
+//! by id. The built-in detectors read and tune the registry's `SignalConfig`;
+//! detectors defined outside this crate are registered the same way and emit
+//! `SignalType::custom("<id>")` signals, which are stored and read back like
+//! any other signal type.
+
+use crate::error::{PipelineError, Result};
+use crate::models::{DailyPrice, DetectorSetting, Signal, TechnicalIndicator};
+use crate::price_action::{detect_gap_signals, detect_range_signals, detect_volume_signals};
+use crate::signals::SignalConfig;
+use chrono::NaiveDate;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Everything a detector sees for one symbol
+pub struct DetectorContext<'a> {
+    pub symbol: &'a str,
+    /// Price bars sorted by date
+    pub prices: &'a [DailyPrice],
+    pub indicators: &'a [TechnicalIndicator],
+    /// Indicator values by date and name
+    pub indicator_map: &'a HashMap<NaiveDate, HashMap<String, f64>>,
+    /// Dates with indicator values, sorted
+    pub dates: &'a [NaiveDate],
+}
+
+impl<'a> DetectorContext<'a> {
+    /// Closing price on `date` (0.0 if there is no bar)
+    pub fn close_on(&self, date: NaiveDate) -> f64 {
+        self.prices
+            .binary_search_by_key(&date, |p| p.date)
+            .map(|i| self.prices[i].close)
+            .unwrap_or(0.0)
+    }
+
+    /// Call `detect` for every indicator date with today's and the previous
+    /// date's values
+    pub fn for_each_bar<F>(&self, mut detect: F) -> Vec<Signal>
+    where
+        F: FnMut(
+            NaiveDate,
+            f64,
+            &HashMap<String, f64>,
+            Option<&HashMap<String, f64>>,
+        ) -> Option<Signal>,
+    {
+        let mut signals = Vec::new();
+        for (i, date) in self.dates.iter().enumerate() {
+            let Some(today) = self.indicator_map.get(date) else {
+                continue;
+            };
+            let prev = if i > 0 {
+                self.indicator_map.get(&self.dates[i - 1])
+            } else {
+                None
+            };
+            signals.extend(detect(*date, self.close_on(*date), today, prev));
+        }
+        signals
+    }
+}
+
+/// A tunable numeric parameter of a detector
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectorParam {
+    pub name: String,
+    pub value: f64,
+}
+
+/// A source of trading signals
+pub trait SignalDetector: Send + Sync {
+    /// Stable id used to enable, disable and configure the detector
+    fn id(&self) -> &str;
+
+    fn description(&self) -> &str {
+        ""
+    }
+
+    /// Current parameter values
+    fn params(&self) -> Vec<DetectorParam> {
+        Vec::new()
+    }
+
+    /// Set a parameter by name
+    fn set_param(&mut self, name: &str, _value: f64) -> Result<()> {
+        Err(PipelineError::Config(format!(
+            "Detector '{}' has no parameter '{}'",
+            self.id(),
+            name
+        )))
+    }
+
+    fn detect(&self, ctx: &DetectorContext) -> Vec<Signal>;
+}
+
+/// Detector state for display
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectorInfo {
+    pub id: String,
+    pub description: String,
+    pub enabled: bool,
+    pub params: Vec<DetectorParam>,
+}
+
+enum Source {
+    Builtin(&'static BuiltinSpec),
+    Plugin(Box<dyn SignalDetector>),
+}
+
+struct Entry {
+    source: Source,
+    enabled: bool,
+}
+
+impl Entry {
+    fn id(&self) -> &str {
+        match &self.source {
+            Source::Builtin(spec) => spec.id,
+            Source::Plugin(detector) => detector.id(),
+        }
+    }
+}
+
+/// Ordered set of detectors keyed by id
+#[derive(Default)]
+pub struct DetectorRegistry {
+    entries: Vec<Entry>,
+    /// Thresholds the built-in detectors run with and tune
+    config: SignalConfig,
+}
+
+impl DetectorRegistry {
+    /// An empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in detectors, configured from `config`
+    pub fn builtin(config: &SignalConfig) -> Self {
+        let mut registry = Self {
+            entries: Vec::new(),
+            config: config.clone(),
+        };
+        for spec in BUILTIN_DETECTORS {
+            registry.insert(Source::Builtin(spec));
+        }
+        registry
+    }
+
+    /// Thresholds used by the built-in detectors, including tuned parameters
+    pub fn config(&self) -> &SignalConfig {
+        &self.config
+    }
+
+    /// Add a detector, replacing any detector with the same id.
+    /// New detectors start enabled.
+    pub fn register(&mut self, detector: Box<dyn SignalDetector>) {
+        self.insert(Source::Plugin(detector));
+    }
+
+    fn insert(&mut self, source: Source) {
+        let entry = Entry {
+            source,
+            enabled: true,
+        };
+        match self.position(entry.id()) {
+            Some(i) => self.entries[i] = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// Remove a detector; returns whether it existed
+    pub fn unregister(&mut self, id: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|e| e.id() != id);
+        self.entries.len() != before
+    }
+
+    fn position(&self, id: &str) -> Option<usize> {
+        self.entries.iter().position(|e| e.id() == id)
+    }
+
+    fn index(&self, id: &str) -> Result<usize> {
+        self.position(id)
+            .ok_or_else(|| PipelineError::Config(format!("Unknown signal detector '{}'", id)))
+    }
+
+    pub fn ids(&self) -> Vec<&str> {
+        self.entries.iter().map(|e| e.id()).collect()
+    }
+
+    pub fn is_enabled(&self, id: &str) -> bool {
+        self.position(id).is_some_and(|i| self.entries[i].enabled)
+    }
+
+    pub fn set_enabled(&mut self, id: &str, enabled: bool) -> Result<()> {
+        let i = self.index(id)?;
+        self.entries[i].enabled = enabled;
+        Ok(())
+    }
+
+    pub fn set_param(&mut self, id: &str, name: &str, value: f64) -> Result<()> {
+        let i = self.index(id)?;
+        match &mut self.entries[i].source {
+            Source::Builtin(spec) => spec.set_param(&mut self.config, name, value),
+            Source::Plugin(detector) => detector.set_param(name, value),
+        }
+    }
+
+    /// Apply persisted settings. Settings for detectors that are not
+    /// registered are skipped, so a removed plugin doesn't break loading.
+    pub fn apply_settings(&mut self, settings: &[DetectorSetting]) -> Result<()> {
+        for setting in settings {
+            if self.position(&setting.detector_id).is_none() {
+                continue;
+            }
+            self.set_enabled(&setting.detector_id, setting.enabled)?;
+            for (name, value) in &setting.params {
+                self.set_param(&setting.detector_id, name, *value)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn info(&self) -> Vec<DetectorInfo> {
+        self.entries
+            .iter()
+            .map(|e| {
+                let (description, params) = match &e.source {
+                    Source::Builtin(spec) => (spec.description, spec.params(&self.config)),
+                    Source::Plugin(detector) => (detector.description(), detector.params()),
+                };
+                DetectorInfo {
+                    id: e.id().to_string(),
+                    description: description.to_string(),
+                    enabled: e.enabled,
+                    params,
+                }
+            })
+            .collect()
+    }
+
+    /// Run every enabled detector
+    pub fn detect(&self, ctx: &DetectorContext) -> Vec<Signal> {
+        self.entries
+            .iter()
+            .filter(|e| e.enabled)
+            .flat_map(|e| match &e.source {
+                Source::Builtin(spec) => (spec.run)(&self.config, ctx),
+                Source::Plugin(detector) => detector.detect(ctx),
+            })
+            .collect()
+    }
+}
+
+// ============================================================================
+// Built-in Detectors
+// ============================================================================
+
+/// A numeric `SignalConfig` field exposed as a detector parameter
+enum Field {
+    F64(fn(&mut SignalConfig) -> &mut f64),
+    Usize(fn(&mut SignalConfig) -> &mut usize),
+}
+
+struct BuiltinSpec {
+    id: &'static str,
+    description: &'static str,
+    params: &'static [(&'static str, Field)],
+    run: fn(&SignalConfig, &DetectorContext) -> Vec<Signal>,
+}
+
+impl BuiltinSpec {
+    fn params(&self, config: &SignalConfig) -> Vec<DetectorParam> {
+        let mut config = config.clone();
+        self.params
+            .iter()
+            .map(|(name, field)| DetectorParam {
+                name: name.to_string(),
+                value: match field {
+                    Field::F64(get) => *get(&mut config),
+                    Field::Usize(get) => *get(&mut config) as f64,
+                },
+            })
+            .collect()
+    }
+
+    fn set_param(&self, config: &mut SignalConfig, name: &str, value: f64) -> Result<()> {
+        let (_, field) = self
+            .params
+            .iter()
+            .find(|(n, _)| *n == name)
+            .ok_or_else(|| {
+                PipelineError::Config(format!(
+                    "Detector '{}' has no parameter '{}'",
+                    self.id, name
+                ))
+            })?;
+        match field {
+            Field::F64(get) => *get(config) = value,
+            Field::Usize(get) => {
+                if !value.is_finite() || value < 0.0 || value.fract() != 0.0 {
+                    return Err(PipelineError::Config(format!(
+                        "'{}' must be a non-negative whole number, got {}",
+                        name, value
+                    )));
+                }
+                *get(config) = value as usize;
+            }
+        }
+        Ok(())
+    }
+}
+
+static BUILTIN_DETECTORS: &[BuiltinSpec] = &[
+    BuiltinSpec {
+        id: "rsi",
+        description: "RSI crossing into overbought/oversold",
+        params: &[
+            ("overbought", Field::F64(|c| &mut c.rsi_overbought)),
+            ("oversold", Field::F64(|c| &mut c.rsi_oversold)),
+        ],
+        run: |c, ctx| ctx.for_each_bar(|d, p, t, y| c.detect_rsi_signal(ctx.symbol, d, p, t, y)),
+    },
+    BuiltinSpec {
+        id: "macd",
+        description: "MACD line crossing its signal line",
+        params: &[],
+        run: |c, ctx| ctx.for_each_bar(|d, p, t, y| c.detect_macd_signal(ctx.symbol, d, p, t, y)),
+    },
+    BuiltinSpec {
+        id: "bollinger",
+        description: "Close outside the Bollinger Bands",
+        params: &[],
+        run: |c, ctx| ctx.for_each_bar(|d, p, t, _| c.detect_bollinger_signal(ctx.symbol, d, p, t)),
+    },
+    BuiltinSpec {
+        id: "ma_crossover",
+        description: "SMA 20/50 crossover",
+        params: &[],
+        run: |c, ctx| {
+            ctx.for_each_bar(|d, p, t, y| c.detect_ma_crossover_signal(ctx.symbol, d, p, t, y))
+        },
+    },
+    BuiltinSpec {
+        id: "adx",
+        description: "ADX trend strengthening/weakening",
+        params: &[
+            ("strong_trend", Field::F64(|c| &mut c.adx_strong_trend)),
+            ("weak_trend", Field::F64(|c| &mut c.adx_weak_trend)),
+        ],
+        run: |c, ctx| ctx.for_each_bar(|d, p, t, y| c.detect_adx_signal(ctx.symbol, d, p, t, y)),
+    },
+    BuiltinSpec {
+        id: "stochastic",
+        description: "Stochastic %K/%D crosses in extreme zones",
+        params: &[
+            ("overbought", Field::F64(|c| &mut c.stoch_overbought)),
+            ("oversold", Field::F64(|c| &mut c.stoch_oversold)),
+        ],
+        run: |c, ctx| {
+            ctx.for_each_bar(|d, p, t, y| c.detect_stochastic_signal(ctx.symbol, d, p, t, y))
+        },
+    },
+    BuiltinSpec {
+        id: "williams_r",
+        description: "Williams %R crossing into overbought/oversold",
+        params: &[
+            ("overbought", Field::F64(|c| &mut c.willr_overbought)),
+            ("oversold", Field::F64(|c| &mut c.willr_oversold)),
+        ],
+        run: |c, ctx| ctx.for_each_bar(|d, p, t, y| c.detect_willr_signal(ctx.symbol, d, p, t, y)),
+    },
+    BuiltinSpec {
+        id: "cci",
+        description: "CCI crossing into overbought/oversold",
+        params: &[
+            ("overbought", Field::F64(|c| &mut c.cci_overbought)),
+            ("oversold", Field::F64(|c| &mut c.cci_oversold)),
+        ],
+        run: |c, ctx| ctx.for_each_bar(|d, p, t, y| c.detect_cci_signal(ctx.symbol, d, p, t, y)),
+    },
+    BuiltinSpec {
+        id: "mfi",
+        description: "MFI crossing into overbought/oversold",
+        params: &[
+            ("overbought", Field::F64(|c| &mut c.mfi_overbought)),
+            ("oversold", Field::F64(|c| &mut c.mfi_oversold)),
+        ],
+        run: |c, ctx| ctx.for_each_bar(|d, p, t, y| c.detect_mfi_signal(ctx.symbol, d, p, t, y)),
+    },
+    BuiltinSpec {
+        id: "divergence",
+        description: "Regular and hidden price/indicator divergences",
+        params: &[
+            ("lookback", Field::Usize(|c| &mut c.divergence_lookback)),
+            (
+                "swing_window",
+                Field::Usize(|c| &mut c.divergence_swing_window),
+            ),
+        ],
+        run: |c, ctx| c.detect_divergence_signals(ctx.symbol, ctx.indicators, ctx.prices),
+    },
+    BuiltinSpec {
+        id: "volume",
+        description: "Volume spikes and breakouts on volume",
+        params: &[
+            (
+                "avg_period",
+                Field::Usize(|c| &mut c.price_action.volume_avg_period),
+            ),
+            (
+                "spike_ratio",
+                Field::F64(|c| &mut c.price_action.volume_spike_ratio),
+            ),
+            (
+                "breakout_lookback",
+                Field::Usize(|c| &mut c.price_action.breakout_lookback),
+            ),
+            (
+                "breakout_volume_ratio",
+                Field::F64(|c| &mut c.price_action.breakout_volume_ratio),
+            ),
+        ],
+        run: |c, ctx| detect_volume_signals(ctx.symbol, ctx.prices, &c.price_action),
+    },
+    BuiltinSpec {
+        id: "gap",
+        description: "Opening gaps and gap fills",
+        params: &[
+            (
+                "min_gap_percent",
+                Field::F64(|c| &mut c.price_action.min_gap_percent),
+            ),
+            (
+                "fill_max_bars",
+                Field::Usize(|c| &mut c.price_action.gap_fill_max_bars),
+            ),
+        ],
+        run: |c, ctx| detect_gap_signals(ctx.symbol, ctx.prices, &c.price_action),
+    },
+    BuiltinSpec {
+        id: "range",
+        description: "NR7/WR7 range contraction and expansion",
+        params: &[(
+            "lookback",
+            Field::Usize(|c| &mut c.price_action.range_lookback),
+        )],
+        run: |c, ctx| detect_range_signals(ctx.symbol, ctx.prices, &c.price_action),
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SignalDirection, SignalStatus, SignalType};
+
+    /// Fires on every bar whose close is a round number
+    struct RoundNumberDetector;
+
+    impl SignalDetector for RoundNumberDetector {
+        fn id(&self) -> &str {
+            "round_number"
+        }
+
+        fn detect(&self, ctx: &DetectorContext) -> Vec<Signal> {
+            ctx.prices
+                .iter()
+                .filter(|p| p.close.fract() == 0.0)
+                .map(|p| Signal {
+                    id: 0,
+                    symbol: ctx.symbol.to_string(),
+                    signal_type: SignalType::custom("ROUND_NUMBER"),
+                    direction: SignalDirection::Neutral,
+                    strength: 0.5,
+                    price_at_signal: p.close,
+                    triggered_by: "CLOSE".to_string(),
+                    trigger_value: p.close,
+                    timestamp: p.date,
+                    created_at: String::new(),
+                    acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
+                })
+                .collect()
+        }
+    }
+
+    fn context_parts() -> (Vec<DailyPrice>, Vec<TechnicalIndicator>) {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let prices: Vec<DailyPrice> = [100.0, 100.5, 101.0]
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| DailyPrice {
+                symbol: "TEST".to_string(),
+                date: start + chrono::Duration::days(i as i64),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 1000,
+                source: "test".to_string(),
+            })
+            .collect();
+        let indicators = prices
+            .iter()
+            .zip([50.0, 25.0, 20.0])
+            .map(|(p, rsi)| TechnicalIndicator {
+                symbol: "TEST".to_string(),
+                date: p.date,
+                indicator_name: "RSI_14".to_string(),
+                value: rsi,
+            })
+            .collect();
+        (prices, indicators)
+    }
+
+    fn run(registry: &DetectorRegistry) -> Vec<Signal> {
+        let (prices, indicators) = context_parts();
+        let mut map: HashMap<NaiveDate, HashMap<String, f64>> = HashMap::new();
+        for ind in &indicators {
+            map.entry(ind.date)
+                .or_default()
+                .insert(ind.indicator_name.clone(), ind.value);
+        }
+        let dates: Vec<NaiveDate> = prices.iter().map(|p| p.date).collect();
+        registry.detect(&DetectorContext {
+            symbol: "TEST",
+            prices: &prices,
+            indicators: &indicators,
+            indicator_map: &map,
+            dates: &dates,
+        })
+    }
+
+    #[test]
+    fn test_custom_detector_and_toggle() {
+        let mut registry = DetectorRegistry::builtin(&SignalConfig::default());
+        registry.register(Box::new(RoundNumberDetector));
+
+        let signals = run(&registry);
+        assert_eq!(
+            signals
+                .iter()
+                .filter(|s| s.signal_type.as_str() == "ROUND_NUMBER")
+                .count(),
+            2
+        );
+        assert!(signals
+            .iter()
+            .any(|s| s.signal_type == SignalType::RsiOversold));
+
+        registry.set_enabled("rsi", false).unwrap();
+        assert!(!run(&registry)
+            .iter()
+            .any(|s| s.signal_type == SignalType::RsiOversold));
+        assert!(registry.set_enabled("missing", true).is_err());
+    }
+
+    #[test]
+    fn test_builtin_params() {
+        let mut registry = DetectorRegistry::builtin(&SignalConfig::default());
+        // RSI 25 is oversold at the default 30, not at 22
+        registry.set_param("rsi", "oversold", 22.0).unwrap();
+        let signals = run(&registry);
+        let oversold: Vec<&Signal> = signals
+            .iter()
+            .filter(|s| s.signal_type == SignalType::RsiOversold)
+            .collect();
+        assert_eq!(oversold.len(), 1);
+        assert_eq!(oversold[0].trigger_value, 20.0);
+
+        let info = registry.info();
+        let rsi = info.iter().find(|d| d.id == "rsi").unwrap();
+        assert!(rsi
+            .params
+            .iter()
+            .any(|p| p.name == "oversold" && p.value == 22.0));
+        assert!(registry.set_param("rsi", "nope", 1.0).is_err());
+    }
+
+    #[test]
+    fn test_engine_config_reflects_tuned_params() {
+        let mut engine = crate::signals::SignalEngine::new();
+        engine.registry_mut().set_param("rsi", "oversold", 22.0).unwrap();
+        assert_eq!(engine.config().rsi_oversold, 22.0);
+        assert_eq!(engine.registry().config().rsi_oversold, 22.0);
+    }
+
+    #[test]
+    fn test_count_params_must_be_whole_numbers() {
+        let mut registry = DetectorRegistry::builtin(&SignalConfig::default());
+        for bad in [f64::NAN, f64::INFINITY, -1.0, 2.5] {
+            assert!(registry.set_param("divergence", "lookback", bad).is_err());
+        }
+        registry.set_param("divergence", "lookback", 40.0).unwrap();
+        assert_eq!(registry.config().divergence_lookback, 40);
+    }
+
+    #[test]
+    fn test_custom_signals_and_settings_persist() {
+        let mut db = crate::db::Database::open_in_memory().unwrap();
+        db.init_schema().unwrap();
+
+        let mut registry = DetectorRegistry::new();
+        registry.register(Box::new(RoundNumberDetector));
+        let signals = run(&registry);
+        db.upsert_signals(&signals).unwrap();
+
+        let stored = db.get_signals("TEST", false).unwrap();
+        assert_eq!(stored.len(), 2);
+        assert!(stored
+            .iter()
+            .all(|s| s.signal_type == SignalType::custom("ROUND_NUMBER")));
+        assert!(stored[0].signal_type.is_custom());
+
+        // Settings for detectors that aren't registered are ignored
+        let mut params = HashMap::new();
+        params.insert("oversold".to_string(), 22.0);
+        for (id, enabled) in [("rsi", false), ("gone", true)] {
+            db.save_detector_setting(&DetectorSetting {
+                detector_id: id.to_string(),
+                enabled,
+                params: params.clone(),
+            })
+            .unwrap();
+        }
+        let mut builtin = DetectorRegistry::builtin(&SignalConfig::default());
+        builtin
+            .apply_settings(&db.get_detector_settings().unwrap())
+            .unwrap();
+        assert!(!builtin.is_enabled("rsi"));
+        assert!(builtin.is_enabled("macd"));
+    }
+}
@@ -0,0 +1,210 @@
+//! Price/indicator divergence detection
+//!
+//! Finds swing highs and lows in price and compares them with the indicator
+//! at the same bars:
+//! - Regular bullish: price lower low, indicator higher low
+//! - Hidden bullish: price higher low, indicator lower low
+//! - Regular bearish: price higher high, indicator lower high
+//! - Hidden bearish: price lower high, indicator higher high
+//!
+//! A swing needs `swing_window` bars on each side to be confirmed, so every
+//! divergence is dated on the bar where its second swing became known.
+
+use crate::models::{DailyPrice, SignalDirection};
+use chrono::NaiveDate;
+
+/// Indicators checked for divergence against price
+pub const DIVERGENCE_INDICATORS: [&str; 4] = ["RSI_14", "MACD_HIST", "OBV", "MFI_14"];
+
+/// Prefix of the per-date divergence votes fed into confluence
+/// (e.g. "DIV_RSI_14" = +strength for bullish, -strength for bearish)
+pub const DIVERGENCE_VOTE_PREFIX: &str = "DIV_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivergenceKind {
+    RegularBullish,
+    HiddenBullish,
+    RegularBearish,
+    HiddenBearish,
+}
+
+impl DivergenceKind {
+    pub fn direction(&self) -> SignalDirection {
+        match self {
+            DivergenceKind::RegularBullish | DivergenceKind::HiddenBullish => SignalDirection::Bullish,
+            DivergenceKind::RegularBearish | DivergenceKind::HiddenBearish => SignalDirection::Bearish,
+        }
+    }
+}
+
+/// A detected divergence between two price swings
+#[derive(Debug, Clone)]
+pub struct Divergence {
+    pub kind: DivergenceKind,
+    /// Date of the earlier swing
+    pub start_date: NaiveDate,
+    /// Date of the later swing
+    pub end_date: NaiveDate,
+    /// Date the later swing was confirmed (the signal date)
+    pub confirmed_date: NaiveDate,
+    /// Price at the later swing (low for bullish, high for bearish)
+    pub price: f64,
+    /// Indicator value at the later swing
+    pub indicator_value: f64,
+    /// 0.0 - 1.0
+    pub strength: f64,
+}
+
+/// Bars with their indicator value, sorted by date
+fn aligned(prices: &[DailyPrice], values: &[(NaiveDate, f64)]) -> Vec<(NaiveDate, f64, f64, f64)> {
+    let lookup: std::collections::HashMap<NaiveDate, f64> = values.iter().copied().collect();
+    let mut bars: Vec<_> = prices
+        .iter()
+        .filter_map(|p| lookup.get(&p.date).map(|&v| (p.date, p.high, p.low, v)))
+        .collect();
+    bars.sort_by_key(|b| b.0);
+    bars
+}
+
+fn is_swing(values: &[f64], i: usize, window: usize, high: bool) -> bool {
+    let lo = i.saturating_sub(window);
+    let hi = (i + window).min(values.len() - 1);
+    (lo..=hi).filter(|&j| j != i).all(|j| {
+        if high {
+            values[i] > values[j]
+        } else {
+            values[i] < values[j]
+        }
+    })
+}
+
+fn strength(p1: f64, p2: f64, v1: f64, v2: f64) -> f64 {
+    let price_move = ((p2 - p1).abs() / p1.abs().max(f64::EPSILON) * 100.0 / 5.0).min(1.0);
+    let indicator_move = ((v2 - v1).abs() / ((v1.abs() + v2.abs()) / 2.0).max(f64::EPSILON)).min(1.0);
+    (price_move + indicator_move) / 2.0
+}
+
+/// Find divergences between price swings and an indicator series
+///
+/// `lookback` bounds how many bars apart the two swings may be.
+pub fn find_divergences(
+    prices: &[DailyPrice],
+    values: &[(NaiveDate, f64)],
+    swing_window: usize,
+    lookback: usize,
+) -> Vec<Divergence> {
+    let bars = aligned(prices, values);
+    if swing_window == 0 || bars.len() < 2 * swing_window + 1 {
+        return vec![];
+    }
+
+    let highs: Vec<f64> = bars.iter().map(|b| b.1).collect();
+    let lows: Vec<f64> = bars.iter().map(|b| b.2).collect();
+
+    let mut divergences = Vec::new();
+    let mut last_high: Option<usize> = None;
+    let mut last_low: Option<usize> = None;
+
+    for i in swing_window..bars.len() - swing_window {
+        let confirmed_date = bars[i + swing_window].0;
+
+        if is_swing(&lows, i, swing_window, false) {
+            if let Some(prev) = last_low.filter(|&j| i - j <= lookback) {
+                let (p1, p2) = (lows[prev], lows[i]);
+                let (v1, v2) = (bars[prev].3, bars[i].3);
+                let kind = if p2 < p1 && v2 > v1 {
+                    Some(DivergenceKind::RegularBullish)
+                } else if p2 > p1 && v2 < v1 {
+                    Some(DivergenceKind::HiddenBullish)
+                } else {
+                    None
+                };
+                if let Some(kind) = kind {
+                    divergences.push(Divergence {
+                        kind,
+                        start_date: bars[prev].0,
+                        end_date: bars[i].0,
+                        confirmed_date,
+                        price: p2,
+                        indicator_value: v2,
+                        strength: strength(p1, p2, v1, v2),
+                    });
+                }
+            }
+            last_low = Some(i);
+        }
+
+        if is_swing(&highs, i, swing_window, true) {
+            if let Some(prev) = last_high.filter(|&j| i - j <= lookback) {
+                let (p1, p2) = (highs[prev], highs[i]);
+                let (v1, v2) = (bars[prev].3, bars[i].3);
+                let kind = if p2 > p1 && v2 < v1 {
+                    Some(DivergenceKind::RegularBearish)
+                } else if p2 < p1 && v2 > v1 {
+                    Some(DivergenceKind::HiddenBearish)
+                } else {
+                    None
+                };
+                if let Some(kind) = kind {
+                    divergences.push(Divergence {
+                        kind,
+                        start_date: bars[prev].0,
+                        end_date: bars[i].0,
+                        confirmed_date,
+                        price: p2,
+                        indicator_value: v2,
+                        strength: strength(p1, p2, v1, v2),
+                    });
+                }
+            }
+            last_high = Some(i);
+        }
+    }
+
+    divergences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(i: usize, low: f64, high: f64) -> DailyPrice {
+        DailyPrice {
+            symbol: "TEST".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + chrono::Duration::days(i as i64),
+            open: (low + high) / 2.0,
+            high,
+            low,
+            close: (low + high) / 2.0,
+            volume: 1000,
+            source: "test".to_string(),
+        }
+    }
+
+    /// Two dips: the second lower in price, with `second_ind` as the indicator at the dip
+    fn double_dip(second_low: f64, second_ind: f64) -> (Vec<DailyPrice>, Vec<(NaiveDate, f64)>) {
+        let lows = [100.0, 98.0, 96.0, 94.0, 96.0, 98.0, 100.0, 98.0, 96.0, second_low, 96.0, 98.0, 100.0];
+        let inds = [50.0, 45.0, 40.0, 30.0, 40.0, 45.0, 50.0, 45.0, 40.0, second_ind, 40.0, 45.0, 50.0];
+        let prices: Vec<DailyPrice> = lows.iter().enumerate().map(|(i, &l)| bar(i, l, l + 2.0)).collect();
+        let values = prices.iter().zip(inds).map(|(p, v)| (p.date, v)).collect();
+        (prices, values)
+    }
+
+    #[test]
+    fn test_regular_and_hidden_bullish() {
+        let (prices, values) = double_dip(90.0, 35.0);
+        let found = find_divergences(&prices, &values, 2, 20);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, DivergenceKind::RegularBullish);
+        // Confirmed two bars after the second swing low
+        assert_eq!(found[0].confirmed_date, prices[11].date);
+        assert!(found[0].strength > 0.0 && found[0].strength <= 1.0);
+
+        let (prices, values) = double_dip(95.0, 25.0);
+        let found = find_divergences(&prices, &values, 2, 20);
+        assert_eq!(found[0].kind, DivergenceKind::HiddenBullish);
+
+        // Swings too far apart for the lookback
+        assert!(find_divergences(&prices, &values, 2, 4).is_empty());
+    }
+}
@@ -30,6 +30,9 @@ pub enum PipelineError {
 
     #[error("API error: {0}")]
     ApiError(String),
+
+    #[error("Formula error: {0}")]
+    Formula(String),
 }
 
 pub type Result<T> = std::result::Result<T, PipelineError>;
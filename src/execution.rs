@@ -0,0 +1,389 @@
+//! Execution Cost Models
+//!
+//! Commissions, slippage, bid/ask spread and liquidity limits applied to
+//! simulated fills. The default model is frictionless (fills at the close).
+
+use crate::models::{DailyPrice, ExecutionCosts};
+use serde::{Deserialize, Serialize};
+
+/// Side of an order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+/// Broker commission schedule
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CommissionModel {
+    /// Flat fee per order
+    PerTrade { amount: f64 },
+    /// Percentage of the traded value, clamped to `[min, max]`
+    Percent {
+        rate_percent: f64,
+        #[serde(default)]
+        min: f64,
+        #[serde(default)]
+        max: Option<f64>,
+    },
+    /// Fee per share, clamped to `[min, max]`
+    PerShare {
+        rate: f64,
+        #[serde(default)]
+        min: f64,
+        #[serde(default)]
+        max: Option<f64>,
+    },
+}
+
+impl Default for CommissionModel {
+    fn default() -> Self {
+        CommissionModel::PerTrade { amount: 0.0 }
+    }
+}
+
+impl CommissionModel {
+    /// Commission for an order of `shares` at `price`
+    pub fn commission(&self, shares: f64, price: f64) -> f64 {
+        let clamp = |fee: f64, min: f64, max: Option<f64>| {
+            let fee = fee.max(min);
+            max.map_or(fee, |max| fee.min(max))
+        };
+        match *self {
+            CommissionModel::PerTrade { amount } => amount,
+            CommissionModel::Percent {
+                rate_percent,
+                min,
+                max,
+            } => clamp(shares * price * rate_percent / 100.0, min, max),
+            CommissionModel::PerShare { rate, min, max } => clamp(shares * rate, min, max),
+        }
+    }
+}
+
+/// Adverse price movement between the signal and the fill
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlippageModel {
+    #[default]
+    None,
+    /// Fixed slippage in basis points of the price
+    Fixed { bps: f64 },
+    /// `multiplier` x the average daily range (high - low) / close over the
+    /// last `lookback` bars known at the fill
+    Volatility { multiplier: f64, lookback: usize },
+}
+
+impl SlippageModel {
+    /// Slippage as a fraction of the price, given the bars whose range is
+    /// known at the fill (see `known_bars`)
+    pub fn fraction(&self, known: &[DailyPrice]) -> f64 {
+        match *self {
+            SlippageModel::None => 0.0,
+            SlippageModel::Fixed { bps } => bps / 10_000.0,
+            SlippageModel::Volatility {
+                multiplier,
+                lookback,
+            } => {
+                let start = known.len().saturating_sub(lookback.max(1));
+                let ranges: Vec<f64> = known[start..]
+                    .iter()
+                    .filter(|b| b.close > 0.0)
+                    .map(|b| (b.high - b.low).max(0.0) / b.close)
+                    .collect();
+                if ranges.is_empty() {
+                    return 0.0;
+                }
+                multiplier * ranges.iter().sum::<f64>() / ranges.len() as f64
+            }
+        }
+    }
+}
+
+/// Bid/ask spread estimate; half the spread is paid on every fill
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpreadModel {
+    #[default]
+    None,
+    /// Fixed quoted spread in basis points
+    Fixed { bps: f64 },
+    /// Spread as a fraction of the high-low range of the last bar known at the fill
+    RangeFraction { fraction: f64 },
+}
+
+impl SpreadModel {
+    /// Half spread as a fraction of the price, given the bars whose range is
+    /// known at the fill (see `known_bars`)
+    pub fn half_spread(&self, known: &[DailyPrice]) -> f64 {
+        match (self, known.last()) {
+            (SpreadModel::None, _) => 0.0,
+            (SpreadModel::Fixed { bps }, _) => bps / 10_000.0 / 2.0,
+            (SpreadModel::RangeFraction { fraction }, Some(bar)) if bar.close > 0.0 => {
+                fraction * (bar.high - bar.low).max(0.0) / bar.close / 2.0
+            }
+            (SpreadModel::RangeFraction { .. }, _) => 0.0,
+        }
+    }
+}
+
+/// Price an order fills at, and where in the fill bar
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FillAt {
+    /// At the bar's close: its range and volume are known
+    Close(f64),
+    /// At the bar's open
+    Open(f64),
+    /// Intrabar at a stop or target level (or the open it gapped through)
+    Stop(f64),
+}
+
+impl FillAt {
+    pub fn price(self) -> f64 {
+        match self {
+            FillAt::Close(price) | FillAt::Open(price) | FillAt::Stop(price) => price,
+        }
+    }
+}
+
+/// Bars whose range and volume are known when an order fills on
+/// `bars[index]`. The fill bar itself is only known at its close; fills at
+/// the open or at a stop level see the bars up to the signal bar.
+pub fn known_bars(bars: &[DailyPrice], index: usize, at: FillAt) -> &[DailyPrice] {
+    match at {
+        FillAt::Close(_) => &bars[..=index],
+        FillAt::Open(_) | FillAt::Stop(_) => &bars[..index],
+    }
+}
+
+/// Complete execution model used by the backtesters
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ExecutionModel {
+    #[serde(default)]
+    pub commission: CommissionModel,
+    #[serde(default)]
+    pub slippage: SlippageModel,
+    #[serde(default)]
+    pub spread: SpreadModel,
+    /// Largest order as a % of the bar's volume; larger entries are partially
+    /// filled. Exits always fill completely.
+    #[serde(default)]
+    pub max_participation_percent: Option<f64>,
+}
+
+/// A simulated fill
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fill {
+    pub shares: f64,
+    /// Execution price including spread and slippage
+    pub price: f64,
+    pub commission: f64,
+    pub slippage_cost: f64,
+    pub spread_cost: f64,
+}
+
+impl Fill {
+    /// Total cost of the fill versus a frictionless fill at the close
+    pub fn cost(&self) -> f64 {
+        self.commission + self.slippage_cost + self.spread_cost
+    }
+}
+
+impl ExecutionModel {
+    /// Model with only a flat per-order commission
+    pub fn per_trade(amount: f64) -> Self {
+        Self {
+            commission: CommissionModel::PerTrade { amount },
+            ..Self::default()
+        }
+    }
+
+    /// Execution price for an order filling `at` on `bars[index]`
+    pub fn fill_price(&self, side: OrderSide, at: FillAt, bars: &[DailyPrice], index: usize) -> f64 {
+        let known = known_bars(bars, index, at);
+        let adverse = self.slippage.fraction(known) + self.spread.half_spread(known);
+        match side {
+            OrderSide::Buy => at.price() * (1.0 + adverse),
+            OrderSide::Sell => at.price() * (1.0 - adverse),
+        }
+    }
+
+    /// Most shares that can trade, from the volume of the last bar known at
+    /// the fill, if a participation cap applies
+    pub fn max_shares(&self, known: &[DailyPrice]) -> Option<f64> {
+        let pct = self.max_participation_percent?;
+        // No volume data (indices, some imports): leave the order uncapped
+        let volume = known.last().map_or(0, |bar| bar.volume);
+        (volume > 0).then(|| volume as f64 * pct / 100.0)
+    }
+
+    /// Fill `shares` `at` the close, open or a stop level of `bars[index]`
+    pub fn fill(
+        &self,
+        side: OrderSide,
+        shares: f64,
+        at: FillAt,
+        bars: &[DailyPrice],
+        index: usize,
+    ) -> Fill {
+        let known = known_bars(bars, index, at);
+        let slippage = self.slippage.fraction(known);
+        let half_spread = self.spread.half_spread(known);
+        let price = at.price();
+        Fill {
+            shares,
+            price: self.fill_price(side, at, bars, index),
+            commission: self.commission.commission(shares, price),
+            slippage_cost: shares * price * slippage,
+            spread_cost: shares * price * half_spread,
+        }
+    }
+
+    /// Fill an opening order worth `value` (including commission) `at` a
+    /// price, capped by the participation limit. None if nothing can be bought.
+    pub fn fill_value(
+        &self,
+        side: OrderSide,
+        value: f64,
+        at: FillAt,
+        bars: &[DailyPrice],
+        index: usize,
+    ) -> Option<Fill> {
+        let fill_price = self.fill_price(side, at, bars, index);
+        if fill_price <= 0.0 {
+            return None;
+        }
+        let commission = self.commission.commission(value / fill_price, at.price());
+        let mut shares = (value - commission) / fill_price;
+        if let Some(cap) = self.max_shares(known_bars(bars, index, at)) {
+            shares = shares.min(cap);
+        }
+        (shares > 0.0).then(|| self.fill(side, shares, at, bars, index))
+    }
+}
+
+impl ExecutionCosts {
+    /// Add a fill's costs to the running totals
+    pub fn record(&mut self, fill: &Fill) {
+        self.commission += fill.commission;
+        self.slippage += fill.slippage_cost;
+        self.spread += fill.spread_cost;
+        self.total = self.commission + self.slippage + self.spread;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn bar(high: f64, low: f64, close: f64, volume: i64) -> DailyPrice {
+        DailyPrice {
+            symbol: "TEST".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            open: close,
+            high,
+            low,
+            close,
+            volume,
+            source: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_commission_schedules_clamp() {
+        let pct = CommissionModel::Percent {
+            rate_percent: 0.1,
+            min: 1.0,
+            max: Some(5.0),
+        };
+        assert_eq!(pct.commission(10.0, 50.0), 1.0); // 0.50 -> min
+        assert!((pct.commission(100.0, 20.0) - 2.0).abs() < 1e-9);
+        assert_eq!(pct.commission(1000.0, 100.0), 5.0); // 100 -> max
+
+        let per_share = CommissionModel::PerShare {
+            rate: 0.005,
+            min: 1.0,
+            max: None,
+        };
+        assert_eq!(per_share.commission(100.0, 10.0), 1.0);
+        assert!((per_share.commission(1000.0, 10.0) - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_fill_applies_spread_slippage_and_volume_cap() {
+        let bars = vec![bar(102.0, 98.0, 100.0, 1_000)];
+        let model = ExecutionModel {
+            commission: CommissionModel::PerTrade { amount: 1.0 },
+            slippage: SlippageModel::Volatility {
+                multiplier: 0.5,
+                lookback: 10,
+            },
+            spread: SpreadModel::Fixed { bps: 20.0 },
+            max_participation_percent: Some(10.0),
+        };
+
+        // 0.5 * 4% range = 2% slippage, plus 10bps half spread
+        let close = FillAt::Close(100.0);
+        assert!((model.fill_price(OrderSide::Buy, close, &bars, 0) - 102.1).abs() < 1e-9);
+        assert!((model.fill_price(OrderSide::Sell, close, &bars, 0) - 97.9).abs() < 1e-9);
+
+        // $1M order is capped at 10% of 1,000 shares
+        let fill = model
+            .fill_value(OrderSide::Buy, 1_000_000.0, close, &bars, 0)
+            .unwrap();
+        assert_eq!(fill.shares, 100.0);
+        assert!((fill.slippage_cost - 200.0).abs() < 1e-9);
+        assert!((fill.spread_cost - 10.0).abs() < 1e-9);
+        assert!((fill.cost() - 211.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_open_fills_use_signal_bar_range() {
+        // Signal bar ranges 4%, the fill bar gaps into a 20% range
+        let bars = vec![bar(102.0, 98.0, 100.0, 1_000), bar(120.0, 100.0, 100.0, 1_000)];
+        let model = ExecutionModel {
+            slippage: SlippageModel::Volatility {
+                multiplier: 0.5,
+                lookback: 1,
+            },
+            spread: SpreadModel::RangeFraction { fraction: 0.5 },
+            ..ExecutionModel::default()
+        };
+
+        // At the open: 2% slippage + 1% half spread from the signal bar
+        let open = FillAt::Open(101.0);
+        assert!((model.fill_price(OrderSide::Buy, open, &bars, 1) - 101.0 * 1.03).abs() < 1e-9);
+        // At the close the fill bar's own range is known
+        let close = FillAt::Close(100.0);
+        assert!((model.fill_price(OrderSide::Buy, close, &bars, 1) - 115.0).abs() < 1e-9);
+        // No earlier bar: range-based costs are unknown
+        assert_eq!(model.fill_price(OrderSide::Buy, FillAt::Stop(99.0), &bars, 0), 99.0);
+    }
+
+    #[test]
+    fn test_open_fill_equal_to_close_does_not_see_fill_bar() {
+        // The fill bar opens at its own close, with a wide range and thin volume
+        let mut bars = vec![bar(102.0, 98.0, 100.0, 10_000), bar(130.0, 90.0, 110.0, 100)];
+        bars[1].open = 110.0;
+        let model = ExecutionModel {
+            slippage: SlippageModel::Volatility {
+                multiplier: 0.5,
+                lookback: 1,
+            },
+            max_participation_percent: Some(10.0),
+            ..ExecutionModel::default()
+        };
+
+        // Slippage and the volume cap come from the signal bar only
+        let open = FillAt::Open(110.0);
+        assert!((model.fill_price(OrderSide::Buy, open, &bars, 1) - 110.0 * 1.02).abs() < 1e-9);
+        let fill = model
+            .fill_value(OrderSide::Buy, 1_000_000.0, open, &bars, 1)
+            .unwrap();
+        assert_eq!(fill.shares, 1_000.0);
+        assert_eq!(known_bars(&bars, 1, open).len(), 1);
+        assert_eq!(known_bars(&bars, 1, FillAt::Close(110.0)).len(), 2);
+    }
+}
@@ -0,0 +1,714 @@
+//! User-defined indicator formulas
+//!
+//! A small expression language evaluated over a symbol's price history, e.g.
+//! `(close - SMA(50)) / ATR(14)` or `RSI(14) - RSI(14)[5]`.
+//!
+//! - Arithmetic: `+ - * /`, unary minus, parentheses
+//! - Price fields: `open`, `high`, `low`, `close`, `volume`
+//! - Indicators: bare names (`OBV`, `MACD_HIST`, another formula's name) or
+//!   calls with integer arguments (`RSI(14)` -> `RSI_14`, `MACD(12, 26)` -> `MACD_12_26`)
+//! - Lag: `x[n]` (n bars ago), `LAG(x, n)`. `LEAD` is rejected because it
+//!   reads future bars, which would leak into backtests and alerts.
+//! - Rolling: `SMA(x, n)`, `EMA(x, n)`, `STD(x, n)`, `MIN(x, n)`, `MAX(x, n)`, `SUM(x, n)`
+//! - Math: `ABS(x)`, `SQRT(x)`, `LN(x)`
+//!
+//! Values that cannot be computed (warm-up, division by zero, missing
+//! indicator dates) are skipped rather than emitted.
+//!
+//! Formula values are stored as `F_<NAME>` so a formula can never overwrite
+//! (or be deleted together with) a built-in indicator or signal pseudo-indicator.
+
+use crate::db::Database;
+use crate::error::{PipelineError, Result};
+use crate::indicators::{
+    calculate_adx, calculate_atr, calculate_cci, calculate_ema, calculate_mfi, calculate_roc,
+    calculate_rsi, calculate_sma, calculate_williams_r,
+};
+use crate::models::{DailyPrice, TechnicalIndicator};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+/// Prefix of formula values in `technical_indicators`
+pub const FORMULA_INDICATOR_PREFIX: &str = "F_";
+
+/// Value of a formula at each bar (None where undefined)
+pub type Series = Vec<Option<f64>>;
+
+/// Indicator name a formula's values are stored under, e.g. "TREND" -> "F_TREND"
+pub fn formula_indicator_name(name: &str) -> String {
+    format!("{}{}", FORMULA_INDICATOR_PREFIX, name.trim().to_uppercase())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceField {
+    Open,
+    High,
+    Low,
+    Close,
+    Volume,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollingFn {
+    Sma,
+    Ema,
+    Std,
+    Min,
+    Max,
+    Sum,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathFn {
+    Abs,
+    Sqrt,
+    Ln,
+}
+
+/// Parsed formula expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Field(PriceField),
+    /// Reference to a stored indicator, with the builtin call it came from
+    /// (if any) so it can be computed on the fly when not stored
+    Indicator {
+        name: String,
+        call: Option<(String, Vec<usize>)>,
+    },
+    /// Shift back by n bars (lag); the parser never builds a look-ahead shift
+    Shift(Box<Expr>, i64),
+    Rolling(RollingFn, Box<Expr>, usize),
+    Apply(MathFn, Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = src.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text
+                .parse()
+                .map_err(|_| PipelineError::Formula(format!("Invalid number '{}'", text)))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else if "+-*/()[],".contains(c) {
+            tokens.push(Token::Op(c));
+            i += 1;
+        } else {
+            return Err(PipelineError::Formula(format!("Unexpected character '{}'", c)));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: char) -> Result<()> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(PipelineError::Formula(format!("Expected '{}'", op)))
+        }
+    }
+
+    fn expression(&mut self) -> Result<Expr> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = if self.eat('+') {
+                BinOp::Add
+            } else if self.eat('-') {
+                BinOp::Sub
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.term()?));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = if self.eat('*') {
+                BinOp::Mul
+            } else if self.eat('/') {
+                BinOp::Div
+            } else {
+                return Ok(lhs);
+            };
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat('-') {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+
+        let mut expr = self.primary()?;
+        while self.eat('[') {
+            if self.eat('-') {
+                return Err(PipelineError::Formula(
+                    "Negative offsets in [ ] read future bars and cannot be used".to_string(),
+                ));
+            }
+            let n = match self.next() {
+                Some(Token::Number(n)) if n.fract() == 0.0 => n as i64,
+                _ => return Err(PipelineError::Formula("Expected integer offset in [ ]".to_string())),
+            };
+            self.expect(']')?;
+            expr = Expr::Shift(Box::new(expr), n);
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Op('(')) => {
+                let expr = self.expression()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) => {
+                if self.eat('(') {
+                    let mut args = Vec::new();
+                    if !self.eat(')') {
+                        loop {
+                            args.push(self.expression()?);
+                            if self.eat(')') {
+                                break;
+                            }
+                            self.expect(',')?;
+                        }
+                    }
+                    build_call(&name, args)
+                } else {
+                    Ok(identifier(&name))
+                }
+            }
+            Some(Token::Op(c)) => Err(PipelineError::Formula(format!("Unexpected '{}'", c))),
+            None => Err(PipelineError::Formula("Unexpected end of formula".to_string())),
+        }
+    }
+}
+
+fn identifier(name: &str) -> Expr {
+    match name.to_lowercase().as_str() {
+        "open" => Expr::Field(PriceField::Open),
+        "high" => Expr::Field(PriceField::High),
+        "low" => Expr::Field(PriceField::Low),
+        "close" => Expr::Field(PriceField::Close),
+        "volume" => Expr::Field(PriceField::Volume),
+        _ => Expr::Indicator {
+            name: name.to_uppercase(),
+            call: None,
+        },
+    }
+}
+
+fn integer_arg(expr: &Expr) -> Option<usize> {
+    match expr {
+        Expr::Number(n) if *n >= 0.0 && n.fract() == 0.0 => Some(*n as usize),
+        _ => None,
+    }
+}
+
+fn build_call(name: &str, mut args: Vec<Expr>) -> Result<Expr> {
+    let upper = name.to_uppercase();
+    let arity_error = || PipelineError::Formula(format!("Wrong arguments for {}()", upper));
+
+    // Indicator reference: every argument is an integer literal, e.g. RSI(14)
+    let integers: Option<Vec<usize>> = args.iter().map(integer_arg).collect();
+    if let Some(params) = integers.filter(|p| !p.is_empty()) {
+        if !matches!(upper.as_str(), "ABS" | "SQRT" | "LN" | "LAG" | "LEAD") {
+            let suffix: Vec<String> = params.iter().map(|p| p.to_string()).collect();
+            return Ok(Expr::Indicator {
+                name: format!("{}_{}", upper, suffix.join("_")),
+                call: Some((upper, params)),
+            });
+        }
+    }
+
+    let math = match upper.as_str() {
+        "ABS" => Some(MathFn::Abs),
+        "SQRT" => Some(MathFn::Sqrt),
+        "LN" => Some(MathFn::Ln),
+        _ => None,
+    };
+    if let Some(f) = math {
+        if args.len() != 1 {
+            return Err(arity_error());
+        }
+        return Ok(Expr::Apply(f, Box::new(args.remove(0))));
+    }
+
+    if args.len() != 2 {
+        return Err(arity_error());
+    }
+    let n = integer_arg(&args[1]).ok_or_else(arity_error)?;
+    let inner = Box::new(args.remove(0));
+
+    let rolling = match upper.as_str() {
+        "LAG" => return Ok(Expr::Shift(inner, n as i64)),
+        "LEAD" => {
+            return Err(PipelineError::Formula(
+                "LEAD() reads future bars and cannot be used in a formula".to_string(),
+            ))
+        }
+        "SMA" | "MEAN" | "AVG" => RollingFn::Sma,
+        "EMA" => RollingFn::Ema,
+        "STD" | "STDEV" => RollingFn::Std,
+        "MIN" => RollingFn::Min,
+        "MAX" => RollingFn::Max,
+        "SUM" => RollingFn::Sum,
+        _ => return Err(PipelineError::Formula(format!("Unknown function {}()", upper))),
+    };
+    if n == 0 {
+        return Err(arity_error());
+    }
+
+    Ok(Expr::Rolling(rolling, inner, n))
+}
+
+/// Parse a formula expression
+pub fn parse(src: &str) -> Result<Expr> {
+    let mut parser = Parser {
+        tokens: tokenize(src)?,
+        pos: 0,
+    };
+    let expr = parser.expression()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(PipelineError::Formula(format!(
+            "Unexpected trailing input in '{}'",
+            src
+        )));
+    }
+    Ok(expr)
+}
+
+/// Validate a formula name; returns the normalized (uppercase) name.
+/// Values are stored under `formula_indicator_name` of this name.
+pub fn normalize_formula_name(name: &str) -> Result<String> {
+    let name = name.trim().to_uppercase();
+    let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(PipelineError::Formula(format!(
+            "Invalid formula name '{}': use letters, digits and underscores",
+            name
+        )));
+    }
+    if matches!(identifier(&name), Expr::Field(_)) {
+        return Err(PipelineError::Formula(format!("'{}' is a reserved price field", name)));
+    }
+    Ok(name)
+}
+
+// ============================================================================
+// Evaluation
+// ============================================================================
+
+/// Price history and indicator values a formula is evaluated against
+pub struct FormulaContext {
+    prices: Vec<DailyPrice>,
+    indicators: HashMap<String, HashMap<NaiveDate, f64>>,
+}
+
+impl FormulaContext {
+    pub fn new(prices: &[DailyPrice], indicators: &[TechnicalIndicator]) -> Self {
+        let mut prices = prices.to_vec();
+        prices.sort_by_key(|p| p.date);
+
+        let mut ctx = Self {
+            prices,
+            indicators: HashMap::new(),
+        };
+        ctx.add_indicators(indicators);
+        ctx
+    }
+
+    /// Make more indicator values available (e.g. previously evaluated formulas)
+    pub fn add_indicators(&mut self, indicators: &[TechnicalIndicator]) {
+        for ind in indicators {
+            self.indicators
+                .entry(ind.indicator_name.clone())
+                .or_default()
+                .insert(ind.date, ind.value);
+        }
+    }
+
+    pub fn dates(&self) -> Vec<NaiveDate> {
+        self.prices.iter().map(|p| p.date).collect()
+    }
+
+    fn indicator_series(&self, name: &str, call: &Option<(String, Vec<usize>)>) -> Result<Series> {
+        // A bare name that isn't a stored indicator may refer to another formula
+        let stored = self
+            .indicators
+            .get(name)
+            .or_else(|| self.indicators.get(&formula_indicator_name(name)));
+        if let Some(values) = stored {
+            return Ok(self.prices.iter().map(|p| values.get(&p.date).copied()).collect());
+        }
+
+        let computed = match call.as_ref().map(|(f, p)| (f.as_str(), p.as_slice())) {
+            Some(("SMA", [n])) => calculate_sma(&self.prices, *n),
+            Some(("EMA", [n])) => calculate_ema(&self.prices, *n),
+            Some(("RSI", [n])) => calculate_rsi(&self.prices, *n),
+            Some(("ATR", [n])) => calculate_atr(&self.prices, *n),
+            Some(("ROC", [n])) => calculate_roc(&self.prices, *n),
+            Some(("CCI", [n])) => calculate_cci(&self.prices, *n),
+            Some(("MFI", [n])) => calculate_mfi(&self.prices, *n),
+            Some(("WILLR", [n])) => calculate_williams_r(&self.prices, *n),
+            Some(("ADX", [n])) => calculate_adx(&self.prices, *n)
+                .into_iter()
+                .filter(|i| i.indicator_name == name)
+                .collect(),
+            _ => return Err(PipelineError::Formula(format!("Unknown indicator {}", name))),
+        };
+
+        let values: HashMap<NaiveDate, f64> = computed.into_iter().map(|i| (i.date, i.value)).collect();
+        Ok(self.prices.iter().map(|p| values.get(&p.date).copied()).collect())
+    }
+}
+
+fn rolling(f: RollingFn, input: &[Option<f64>], n: usize) -> Series {
+    let mut out = vec![None; input.len()];
+
+    if f == RollingFn::Ema {
+        let k = 2.0 / (n as f64 + 1.0);
+        let mut ema: Option<f64> = None;
+        for (i, value) in input.iter().enumerate() {
+            ema = match (*value, ema) {
+                (Some(v), Some(prev)) => Some(v * k + prev * (1.0 - k)),
+                // Seed with the SMA of the first full window
+                (Some(_), None) if i + 1 >= n => {
+                    let window: Option<Vec<f64>> = input[i + 1 - n..=i].iter().copied().collect();
+                    window.map(|w| w.iter().sum::<f64>() / n as f64)
+                }
+                _ => None,
+            };
+            out[i] = ema;
+        }
+        return out;
+    }
+
+    for i in (n.saturating_sub(1))..input.len() {
+        let Some(window) = input[i + 1 - n..=i].iter().copied().collect::<Option<Vec<f64>>>() else {
+            continue;
+        };
+        let sum: f64 = window.iter().sum();
+        out[i] = match f {
+            RollingFn::Sma => Some(sum / n as f64),
+            RollingFn::Sum => Some(sum),
+            RollingFn::Min => window.iter().copied().reduce(f64::min),
+            RollingFn::Max => window.iter().copied().reduce(f64::max),
+            RollingFn::Std if n > 1 => {
+                let mean = sum / n as f64;
+                Some((window.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt())
+            }
+            RollingFn::Std => None,
+            RollingFn::Ema => unreachable!(),
+        };
+    }
+
+    out
+}
+
+impl Expr {
+    /// Evaluate over every bar of the context
+    pub fn evaluate(&self, ctx: &FormulaContext) -> Result<Series> {
+        let len = ctx.prices.len();
+        let series = match self {
+            Expr::Number(n) => vec![Some(*n); len],
+            Expr::Field(field) => ctx
+                .prices
+                .iter()
+                .map(|p| {
+                    Some(match field {
+                        PriceField::Open => p.open,
+                        PriceField::High => p.high,
+                        PriceField::Low => p.low,
+                        PriceField::Close => p.close,
+                        PriceField::Volume => p.volume as f64,
+                    })
+                })
+                .collect(),
+            Expr::Indicator { name, call } => ctx.indicator_series(name, call)?,
+            Expr::Shift(inner, n) => {
+                let values = inner.evaluate(ctx)?;
+                (0..len as i64)
+                    .map(|i| {
+                        let j = i - n;
+                        if j >= 0 && j < len as i64 {
+                            values[j as usize]
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            }
+            Expr::Rolling(f, inner, n) => rolling(*f, &inner.evaluate(ctx)?, *n),
+            Expr::Apply(f, inner) => inner
+                .evaluate(ctx)?
+                .into_iter()
+                .map(|v| {
+                    v.map(|x| match f {
+                        MathFn::Abs => x.abs(),
+                        MathFn::Sqrt => x.sqrt(),
+                        MathFn::Ln => x.ln(),
+                    })
+                })
+                .collect(),
+            Expr::Neg(inner) => inner.evaluate(ctx)?.into_iter().map(|v| v.map(|x| -x)).collect(),
+            Expr::Binary(op, lhs, rhs) => lhs
+                .evaluate(ctx)?
+                .into_iter()
+                .zip(rhs.evaluate(ctx)?)
+                .map(|(a, b)| {
+                    let (a, b) = (a?, b?);
+                    match op {
+                        BinOp::Add => Some(a + b),
+                        BinOp::Sub => Some(a - b),
+                        BinOp::Mul => Some(a * b),
+                        BinOp::Div if b != 0.0 => Some(a / b),
+                        BinOp::Div => None,
+                    }
+                })
+                .collect(),
+        };
+
+        Ok(series)
+    }
+}
+
+/// Evaluate a named formula into indicator values (NaN/inf values are dropped)
+pub fn evaluate_formula(name: &str, expression: &str, ctx: &FormulaContext) -> Result<Vec<TechnicalIndicator>> {
+    let name = formula_indicator_name(&normalize_formula_name(name)?);
+    let values = parse(expression)?.evaluate(ctx)?;
+
+    Ok(ctx
+        .prices
+        .iter()
+        .zip(values)
+        .filter_map(|(p, v)| {
+            v.filter(|x| x.is_finite()).map(|value| TechnicalIndicator {
+                symbol: p.symbol.clone(),
+                date: p.date,
+                indicator_name: name.clone(),
+                value,
+            })
+        })
+        .collect())
+}
+
+/// Result of evaluating the saved formulas for one symbol
+#[derive(Debug, Clone, Default)]
+pub struct FormulaUpdate {
+    /// Number of formula values stored
+    pub stored: usize,
+    /// Formulas that failed to evaluate, with the error
+    pub failed: Vec<(String, String)>,
+}
+
+/// Evaluate all saved formulas for a symbol and store them in `technical_indicators`
+///
+/// Formulas are evaluated in creation order, so a formula may reference any
+/// formula saved before it. A formula that fails to evaluate is logged and
+/// skipped so it doesn't block the others.
+pub fn update_formula_indicators(db: &mut Database, symbol: &str) -> Result<FormulaUpdate> {
+    let formulas = db.get_formulas()?;
+    if formulas.is_empty() {
+        return Ok(FormulaUpdate::default());
+    }
+
+    let prices = db.get_prices(symbol)?;
+    if prices.is_empty() {
+        return Err(PipelineError::NoData(symbol.to_string()));
+    }
+
+    let mut ctx = FormulaContext::new(&prices, &db.get_all_indicators(symbol)?);
+    let mut all = Vec::new();
+    let mut failed = Vec::new();
+
+    for formula in &formulas {
+        match evaluate_formula(&formula.name, &formula.expression, &ctx) {
+            Ok(values) => {
+                ctx.add_indicators(&values);
+                all.extend(values);
+            }
+            Err(e) => {
+                eprintln!("[Formula] {} failed for {}: {}", formula.name, symbol, e);
+                failed.push((formula.name.clone(), e.to_string()));
+            }
+        }
+    }
+
+    Ok(FormulaUpdate {
+        stored: db.upsert_indicators(&all)?,
+        failed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices(closes: &[f64]) -> Vec<DailyPrice> {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| DailyPrice {
+                symbol: "TEST".to_string(),
+                date: start + chrono::Duration::days(i as i64),
+                open: close - 0.5,
+                high: close + 1.0,
+                low: close - 1.0,
+                close,
+                volume: 1000,
+                source: "test".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_precedence_and_calls() {
+        let expr = parse("(close - SMA(50)) / ATR(14)").unwrap();
+        let Expr::Binary(BinOp::Div, lhs, rhs) = expr else {
+            panic!("expected division");
+        };
+        assert!(matches!(*lhs, Expr::Binary(BinOp::Sub, _, _)));
+        assert_eq!(
+            *rhs,
+            Expr::Indicator {
+                name: "ATR_14".to_string(),
+                call: Some(("ATR".to_string(), vec![14]))
+            }
+        );
+
+        assert!(matches!(parse("RSI(14) - RSI(14)[5]").unwrap(), Expr::Binary(BinOp::Sub, _, _)));
+        assert!(matches!(parse("SMA(close, 3)").unwrap(), Expr::Rolling(RollingFn::Sma, _, 3)));
+        assert!(parse("close +").is_err());
+        assert!(parse("FOO(close, 2)").is_err());
+        assert!(parse("close $ 2").is_err());
+    }
+
+    #[test]
+    fn test_evaluate_lag_rolling_and_stored_indicators() {
+        let data = prices(&[10.0, 11.0, 12.0, 13.0, 14.0]);
+        let stored: Vec<TechnicalIndicator> = data
+            .iter()
+            .map(|p| TechnicalIndicator {
+                symbol: "TEST".to_string(),
+                date: p.date,
+                indicator_name: "OBV".to_string(),
+                value: 2.0,
+            })
+            .collect();
+        let ctx = FormulaContext::new(&data, &stored);
+
+        let momentum = parse("close - close[2]").unwrap().evaluate(&ctx).unwrap();
+        assert_eq!(momentum, vec![None, None, Some(2.0), Some(2.0), Some(2.0)]);
+
+        let lag = parse("LAG(close, 1) / OBV").unwrap().evaluate(&ctx).unwrap();
+        assert_eq!(lag[0], None);
+        assert_eq!(lag[4], Some(6.5));
+        assert!(parse("LEAD(close, 1)").is_err(), "LEAD would look ahead");
+        assert!(parse("close[-1]").is_err(), "negative offsets would look ahead");
+        assert!(parse("LAG(close, -1)").is_err());
+
+        let sma = parse("SMA(close, 3)").unwrap().evaluate(&ctx).unwrap();
+        assert_eq!(sma[2], Some(11.0));
+        assert_eq!(sma[1], None);
+
+        // SMA(3) is not stored, so it is computed from prices
+        let builtin = parse("SMA(3)").unwrap().evaluate(&ctx).unwrap();
+        assert_eq!(builtin, sma);
+
+        assert!(parse("UNKNOWN_IND").unwrap().evaluate(&ctx).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_formula_names_and_drops_undefined() {
+        let data = prices(&[10.0, 10.0, 12.0]);
+        let ctx = FormulaContext::new(&data, &[]);
+
+        let values = evaluate_formula("zscore", "(close - close[1]) / (close[1] - 10)", &ctx).unwrap();
+        assert!(values.is_empty(), "division by zero should be skipped");
+
+        let values = evaluate_formula("chg", "close - close[1]", &ctx).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].indicator_name, "F_CHG");
+
+        // Later formulas can reference earlier ones by their bare name
+        let mut ctx = ctx;
+        ctx.add_indicators(&values);
+        let doubled = parse("CHG * 2").unwrap().evaluate(&ctx).unwrap();
+        assert_eq!(doubled, vec![None, Some(0.0), Some(4.0)]);
+
+        assert!(normalize_formula_name("close").is_err());
+        assert!(normalize_formula_name("1bad").is_err());
+    }
+}
@@ -756,5 +756,8 @@ pub fn calculate_all(prices: &[DailyPrice]) -> Vec<TechnicalIndicator> {
     // ROC 12
     all.extend(calculate_roc(prices, 12));
 
+    // Volatility estimators, forecasts and regime
+    all.extend(crate::volatility::calculate_volatility_suite(prices));
+
     all
 }
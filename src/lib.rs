@@ -27,24 +27,53 @@
 //! println!("AAPL: ${:.2}", price.unwrap_or(0.0));
 //! ```
 
+pub mod analytics;
+pub mod confluence;
 pub mod db;
+pub mod detectors;
+pub mod divergence;
 pub mod error;
+pub mod execution;
+pub mod formula;
 pub mod fred;
 pub mod indicators;
+pub mod macro_signals;
+pub mod metrics;
 pub mod models;
+pub mod monte_carlo;
+pub mod price_action;
 pub mod backtest;
+pub mod regime;
+pub mod rules;
+pub mod scorecard;
+pub mod signal_lifecycle;
 pub mod signals;
+pub mod sizing;
+pub mod stops;
+pub mod timeframe;
 pub mod trends;
 pub mod vectors;
+pub mod volatility;
 pub mod yahoo;
 pub mod claude;
 pub mod finnhub;
 pub mod ollama;
+pub mod optimizer;
+pub mod portfolio_backtest;
 pub mod ai_trader;
+pub mod ai_replay;
 
 // Re-exports for convenience
+pub use analytics::{AnalyticsConfig, CorrelationMatrix};
 pub use db::Database;
+pub use detectors::{DetectorContext, DetectorInfo, DetectorParam, DetectorRegistry, SignalDetector};
 pub use error::{PipelineError, Result};
+pub use execution::{
+    CommissionModel, ExecutionModel, Fill, FillAt, OrderSide, SlippageModel, SpreadModel,
+};
+pub use formula::{
+    evaluate_formula, formula_indicator_name, update_formula_indicators, FormulaContext, FormulaUpdate,
+};
 pub use fred::Fred;
 pub use indicators::{
     calculate_adx, calculate_all, calculate_atr, calculate_bollinger_bands, calculate_cci,
@@ -52,20 +81,51 @@ pub use indicators::{
     calculate_sma, calculate_stochastic, calculate_williams_r,
 };
 pub use models::{
-    AlertCondition, BacktestResult, BacktestTrade, DailyPrice, IndicatorAlert,
-    IndicatorAlertCondition, IndicatorAlertType, MacroData, PerformanceMetrics, Position,
-    PositionType, PriceAlert, Signal, SignalDirection, SignalType, Strategy,
+    AlertCondition, BacktestResult, BacktestTrade, BenchmarkStats, DailyPrice, DetectorSetting, DirectionMetrics, EquityPoint, ExecutionCosts, ExitRules, Formula, IndicatorAlert,
+    IndicatorAlertCondition, IndicatorAlertType, MacroData, MarketRegime, PerformanceMetrics, Position, RiskMetrics, YearlyReturn,
+    PositionType, PriceAlert, RegimeRecord, RuleNode, RuleOperand, CompareOp, PriceField, Signal, SignalDirection, SignalOutcome, SignalScorecardEntry,
+    SignalStatus, SignalType, Strategy, StrategyDirection, SymbolTradeStats,
     StrategyConditionType, Symbol, TechnicalIndicator, TradeDirection, Watchlist,
-    PaperWallet, PaperPosition, PaperTrade, PaperTradeAction,
+    PaperWallet, PaperPosition, PaperTrade, PaperTradeAction, PositionSizing, ScaleOut, TrailingStop,
+};
+pub use backtest::{
+    export_equity_curve, with_signal_indicators, BacktestConfig, BacktestEngine, ExportFormat,
+    FillTiming,
+};
+pub use metrics::{Benchmark, DEFAULT_BENCHMARK_SYMBOL, TRADING_DAYS_PER_YEAR};
+pub use monte_carlo::{MonteCarloConfig, MonteCarloMethod, MonteCarloResult, Percentiles};
+pub use optimizer::{
+    optimize, Objective, OptimizationConfig, OptimizationJob, OptimizationResult, OptimizationRun,
+    ParamRange, SearchMethod, StrategyParam, Validation,
 };
-pub use backtest::{BacktestConfig, BacktestEngine};
-pub use signals::{SignalConfig, SignalEngine};
+pub use portfolio_backtest::{
+    run_portfolio_backtest, PortfolioConfig, PortfolioRanking, SymbolData,
+};
+pub use macro_signals::{update_macro_signals, MacroSignalConfig, MARKET_SIGNAL_SYMBOL};
+pub use regime::{RegimeConfig, RegimeSeries, MARKET_REGIME_SYMBOL};
+pub use rules::{condition_met, RuleContext};
+pub use signal_lifecycle::{apply_lifecycle, refresh_signal_status, SignalLifecycleConfig};
+pub use signals::{
+    configured_signal_engine, is_signal_indicator, signal_indicator_name, SignalConfig,
+    SignalEngine, CONFLUENCE_INDICATOR, SIGNAL_INDICATOR_PREFIX,
+};
+pub use sizing::{kelly_fraction, SizingContext};
+pub use stops::{FixedStops, StopExit, StopTracker};
+pub use timeframe::{calculate_all_for_timeframe, resample, HigherTimeframeTrend, Timeframe};
 pub use trends::{GoogleTrends, TrendData};
+pub use volatility::{
+    calculate_volatility_suite, classify_volatility_regime, GarchModel, VolatilityRegime,
+    VolatilityRegimeConfig,
+};
 pub use vectors::{VectorStore, MarketEvent, PricePattern, SearchResult as VectorSearchResult, ChatMessage, ChatResponse};
 pub use yahoo::YahooFinance;
 pub use claude::{ClaudeClient, ClaudeMessage, ChatResult, FinancialContext, PriceContext};
 pub use finnhub::{FinnhubClient, NewsItem, SimpleNewsItem, Quote, Candles, PriceReaction};
 pub use ai_trader::AiTrader;
+pub use ai_replay::{
+    DecisionModel, OllamaDecisionModel, RecordedModel, ReplayConfig, ReplayData, ReplayDecision,
+    ReplayResult, ReplaySeries, RuleBasedModel, save_replay_backtest, REPLAY_STRATEGY_NAME,
+};
 pub use models::{
     AiTraderConfig, AiTradingSession, AiTradeDecision, AiPerformanceSnapshot,
     AiPredictionAccuracy, AiTraderStatus, BenchmarkComparison, CompoundingForecast,
@@ -0,0 +1,390 @@
+//! Market-wide signals from FRED macro series
+//!
+//! - Yield curve: DGS10 - DGS2 crossing below zero (inversion) or back above
+//!   it (un-inversion). Un-inversion has historically come shortly before
+//!   recessions, so it is not treated as bullish.
+//! - VIX: a close at/above `vix_spike_ratio` times its trailing mean (spike),
+//!   and the first close back below the mean after a spike (mean reversion)
+//! - Fed funds (DFF): a move of at least `fed_funds_step` from one
+//!   observation to the next (hike or cut)
+//! - Unemployment (UNRATE), Sahm-rule style: the 3-month average minus the
+//!   lowest 3-month average of the prior 12 months crossing `sahm_threshold`
+//!   (triggered) or falling back below it (cleared)
+//!
+//! Signals are stored under `MARKET_SIGNAL_SYMBOL` next to symbol signals.
+
+use crate::db::Database;
+use crate::error::Result;
+use crate::fred::indicators::{
+    CPI, FED_FUNDS_RATE, GDP, INDUSTRIAL_PROD, SAVINGS_RATE, TREASURY_10Y, TREASURY_2Y,
+    UNEMPLOYMENT, VIX,
+};
+use crate::models::{MacroData, Signal, SignalDirection, SignalStatus, SignalType};
+use crate::regime::MARKET_REGIME_SYMBOL;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+/// Pseudo-symbol for market-wide signals (shared with the market regime)
+pub const MARKET_SIGNAL_SYMBOL: &str = MARKET_REGIME_SYMBOL;
+
+/// Macro signal thresholds
+#[derive(Debug, Clone)]
+pub struct MacroSignalConfig {
+    /// Trailing observations for the VIX mean
+    pub vix_mean_period: usize,
+    /// VIX / trailing mean ratio that counts as a spike
+    pub vix_spike_ratio: f64,
+    /// Minimum fed funds change (percentage points) between observations
+    pub fed_funds_step: f64,
+    /// Sahm-rule threshold (percentage points)
+    pub sahm_threshold: f64,
+    /// Days after which a macro signal is no longer active
+    pub active_days: i64,
+}
+
+impl Default for MacroSignalConfig {
+    fn default() -> Self {
+        Self {
+            vix_mean_period: 20,
+            vix_spike_ratio: 1.3,
+            fed_funds_step: 0.2,
+            sahm_threshold: 0.5,
+            active_days: 30,
+        }
+    }
+}
+
+fn macro_signal(
+    date: NaiveDate,
+    signal_type: SignalType,
+    direction: SignalDirection,
+    strength: f64,
+    level: f64,
+    triggered_by: &str,
+    trigger_value: f64,
+) -> Signal {
+    Signal {
+        id: 0,
+        symbol: MARKET_SIGNAL_SYMBOL.to_string(),
+        signal_type,
+        direction,
+        strength: strength.clamp(0.0, 1.0),
+        price_at_signal: level,
+        triggered_by: triggered_by.to_string(),
+        trigger_value,
+        timestamp: date,
+        created_at: String::new(),
+        acknowledged: false,
+        status: SignalStatus::New,
+        end_date: None,
+    }
+}
+
+fn sorted(series: &[MacroData]) -> Vec<(NaiveDate, f64)> {
+    let mut points: Vec<(NaiveDate, f64)> = series.iter().map(|m| (m.date, m.value)).collect();
+    points.sort_by_key(|p| p.0);
+    points
+}
+
+/// Yield curve inversion / un-inversion from the 10y and 2y Treasury yields
+pub fn detect_yield_curve_signals(dgs10: &[MacroData], dgs2: &[MacroData]) -> Vec<Signal> {
+    let short: HashMap<NaiveDate, f64> = dgs2.iter().map(|m| (m.date, m.value)).collect();
+    let spreads: Vec<(NaiveDate, f64)> = sorted(dgs10)
+        .into_iter()
+        .filter_map(|(date, long)| short.get(&date).map(|s| (date, long - s)))
+        .collect();
+
+    spreads
+        .windows(2)
+        .filter_map(|w| {
+            let ((_, prev), (date, spread)) = (w[0], w[1]);
+            let strength = spread.abs();
+            if prev >= 0.0 && spread < 0.0 {
+                Some(macro_signal(
+                    date,
+                    SignalType::YieldCurveInversion,
+                    SignalDirection::Bearish,
+                    strength,
+                    spread,
+                    "DGS10-DGS2",
+                    spread,
+                ))
+            } else if prev < 0.0 && spread >= 0.0 {
+                Some(macro_signal(
+                    date,
+                    SignalType::YieldCurveUninversion,
+                    SignalDirection::Bearish,
+                    strength,
+                    spread,
+                    "DGS10-DGS2",
+                    spread,
+                ))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// VIX spikes above its trailing mean and the reversion that follows
+pub fn detect_vix_signals(vix: &[MacroData], config: &MacroSignalConfig) -> Vec<Signal> {
+    let points = sorted(vix);
+    let period = config.vix_mean_period.max(1);
+    let mut signals = Vec::new();
+    let mut in_spike = false;
+
+    for i in period..points.len() {
+        let (date, level) = points[i];
+        let mean = points[i - period..i].iter().map(|p| p.1).sum::<f64>() / period as f64;
+        if mean <= 0.0 {
+            continue;
+        }
+        let ratio = level / mean;
+
+        if !in_spike && ratio >= config.vix_spike_ratio {
+            in_spike = true;
+            let strength = (ratio - 1.0) / (config.vix_spike_ratio - 1.0).max(f64::EPSILON) / 2.0;
+            signals.push(macro_signal(
+                date,
+                SignalType::VixSpike,
+                SignalDirection::Bearish,
+                strength,
+                level,
+                VIX,
+                ratio,
+            ));
+        } else if in_spike && level < mean {
+            in_spike = false;
+            signals.push(macro_signal(
+                date,
+                SignalType::VixMeanReversion,
+                SignalDirection::Bullish,
+                1.0 - ratio,
+                level,
+                VIX,
+                ratio,
+            ));
+        }
+    }
+
+    signals
+}
+
+/// Fed funds rate steps between consecutive observations
+pub fn detect_fed_funds_signals(dff: &[MacroData], config: &MacroSignalConfig) -> Vec<Signal> {
+    sorted(dff)
+        .windows(2)
+        .filter_map(|w| {
+            let ((_, prev), (date, rate)) = (w[0], w[1]);
+            let change = rate - prev;
+            // A 0.25pt move is a full-strength step
+            let strength = change.abs() / 0.25;
+            if change >= config.fed_funds_step {
+                Some(macro_signal(
+                    date,
+                    SignalType::FedFundsHike,
+                    SignalDirection::Bearish,
+                    strength,
+                    rate,
+                    FED_FUNDS_RATE,
+                    change,
+                ))
+            } else if change <= -config.fed_funds_step {
+                Some(macro_signal(
+                    date,
+                    SignalType::FedFundsCut,
+                    SignalDirection::Bullish,
+                    strength,
+                    rate,
+                    FED_FUNDS_RATE,
+                    change,
+                ))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Sahm-rule indicator per month: 3-month average unemployment minus the
+/// lowest 3-month average of the prior 12 months
+pub fn sahm_indicator(unrate: &[MacroData]) -> Vec<(NaiveDate, f64)> {
+    let points = sorted(unrate);
+    let avg3: Vec<(NaiveDate, f64)> = points
+        .windows(3)
+        .map(|w| (w[2].0, w.iter().map(|p| p.1).sum::<f64>() / 3.0))
+        .collect();
+
+    (12..avg3.len())
+        .map(|i| {
+            let low = avg3[i - 12..i].iter().map(|p| p.1).fold(f64::MAX, f64::min);
+            (avg3[i].0, avg3[i].1 - low)
+        })
+        .collect()
+}
+
+/// Unemployment trend turns: Sahm rule crossing its threshold
+pub fn detect_unemployment_signals(
+    unrate: &[MacroData],
+    config: &MacroSignalConfig,
+) -> Vec<Signal> {
+    sahm_indicator(unrate)
+        .windows(2)
+        .filter_map(|w| {
+            let ((_, prev), (date, value)) = (w[0], w[1]);
+            let strength = value / config.sahm_threshold / 2.0;
+            if prev < config.sahm_threshold && value >= config.sahm_threshold {
+                Some(macro_signal(
+                    date,
+                    SignalType::SahmRuleTriggered,
+                    SignalDirection::Bearish,
+                    strength,
+                    value,
+                    UNEMPLOYMENT,
+                    value,
+                ))
+            } else if prev >= config.sahm_threshold && value < config.sahm_threshold {
+                Some(macro_signal(
+                    date,
+                    SignalType::SahmRuleCleared,
+                    SignalDirection::Bullish,
+                    1.0 - strength,
+                    value,
+                    UNEMPLOYMENT,
+                    value,
+                ))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Mark signals older than `active_days` before `as_of` as expired; signals
+/// on `as_of` stay new and the rest are active
+pub fn assign_macro_status(signals: &mut [Signal], as_of: NaiveDate, config: &MacroSignalConfig) {
+    for signal in signals {
+        let age = (as_of - signal.timestamp).num_days();
+        signal.status = if age <= 0 {
+            SignalStatus::New
+        } else if age <= config.active_days {
+            SignalStatus::Active
+        } else {
+            SignalStatus::Expired
+        };
+    }
+}
+
+/// Days from a series' observation date until FRED publishes the value.
+/// Monthly and quarterly series are dated at the start of the period they
+/// cover, so a signal on them is only known weeks after its timestamp.
+pub fn publication_lag_days(series: &str) -> i64 {
+    match series {
+        // Employment report, first Friday of the following month
+        UNEMPLOYMENT => 37,
+        CPI => 45,
+        INDUSTRIAL_PROD => 47,
+        SAVINGS_RATE => 60,
+        // Advance estimate, about a month after the quarter ends
+        GDP => 120,
+        // Daily series and spreads of them: the next business day
+        _ => 1,
+    }
+}
+
+/// Detect macro signals from the stored FRED series and save them.
+/// Returns the number of signals written.
+pub fn update_macro_signals(db: &mut Database, config: &MacroSignalConfig) -> Result<usize> {
+    let dgs10 = db.get_macro_history(TREASURY_10Y)?;
+    let dgs2 = db.get_macro_history(TREASURY_2Y)?;
+    let vix = db.get_macro_history(VIX)?;
+    let dff = db.get_macro_history(FED_FUNDS_RATE)?;
+    let unrate = db.get_macro_history(UNEMPLOYMENT)?;
+
+    let mut signals = detect_yield_curve_signals(&dgs10, &dgs2);
+    signals.extend(detect_vix_signals(&vix, config));
+    signals.extend(detect_fed_funds_signals(&dff, config));
+    signals.extend(detect_unemployment_signals(&unrate, config));
+
+    let as_of = [&dgs10, &dgs2, &vix, &dff, &unrate]
+        .iter()
+        .filter_map(|series| series.last().map(|m| m.date))
+        .max();
+    let Some(as_of) = as_of else {
+        return Ok(0);
+    };
+    assign_macro_status(&mut signals, as_of, config);
+
+    db.upsert_signals(&signals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(indicator: &str, values: &[f64], step_days: i64) -> Vec<MacroData> {
+        let start = NaiveDate::from_ymd_opt(2020, 1, 1).unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, &value)| MacroData {
+                indicator: indicator.to_string(),
+                date: start + chrono::Duration::days(i as i64 * step_days),
+                value,
+                source: "FRED".to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_yield_curve_inversion_and_uninversion() {
+        let dgs10 = series(TREASURY_10Y, &[4.0, 4.0, 4.0, 4.0, 4.0], 1);
+        let dgs2 = series(TREASURY_2Y, &[3.8, 4.1, 4.3, 3.9, 3.7], 1);
+        let signals = detect_yield_curve_signals(&dgs10, &dgs2);
+        let types: Vec<SignalType> = signals.iter().map(|s| s.signal_type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![
+                SignalType::YieldCurveInversion,
+                SignalType::YieldCurveUninversion
+            ]
+        );
+        assert_eq!(signals[0].symbol, MARKET_SIGNAL_SYMBOL);
+        assert!((signals[0].trigger_value + 0.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_vix_spike_then_reversion_and_fed_steps() {
+        let mut levels = vec![15.0; 20];
+        levels.extend([16.0, 25.0, 26.0, 20.0, 14.0]);
+        let signals = detect_vix_signals(&series(VIX, &levels, 1), &MacroSignalConfig::default());
+        let types: Vec<SignalType> = signals.iter().map(|s| s.signal_type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![SignalType::VixSpike, SignalType::VixMeanReversion]
+        );
+
+        let dff = series(FED_FUNDS_RATE, &[5.33, 5.33, 5.08, 5.08, 5.09, 5.34], 1);
+        let signals = detect_fed_funds_signals(&dff, &MacroSignalConfig::default());
+        let types: Vec<SignalType> = signals.iter().map(|s| s.signal_type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![SignalType::FedFundsCut, SignalType::FedFundsHike]
+        );
+    }
+
+    #[test]
+    fn test_sahm_rule_trigger() {
+        let mut rates = vec![3.5; 15];
+        rates.extend([3.8, 4.1, 4.3, 4.4]);
+        let signals = detect_unemployment_signals(
+            &series(UNEMPLOYMENT, &rates, 30),
+            &MacroSignalConfig::default(),
+        );
+        assert_eq!(signals.len(), 1);
+        assert_eq!(signals[0].signal_type, SignalType::SahmRuleTriggered);
+        assert!(signals[0].trigger_value >= 0.5);
+        assert_eq!(publication_lag_days(&signals[0].triggered_by), 37);
+        assert_eq!(publication_lag_days("DGS10-DGS2"), 1);
+    }
+}
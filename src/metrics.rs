@@ -0,0 +1,683 @@
+//! Performance Metrics
+//!
+//! Return, risk and benchmark-relative statistics shared by the backtesters,
+//! the paper/DC portfolios and the AI trader, so every equity curve is
+//! measured the same way:
+//! - Ratios annualize daily returns over 252 trading days and use the sample
+//!   (n - 1) standard deviation
+//! - Sharpe, Sortino and alpha are measured in excess of the risk-free rate,
+//!   the average Fed funds rate (FRED DFF) over the period when it has been
+//!   fetched
+//! - Benchmark statistics pair each day's return with the benchmark's close
+//!   on or before the same date
+
+use crate::db::Database;
+use crate::error::Result;
+use crate::fred::indicators::FED_FUNDS_RATE;
+use crate::models::{
+    BacktestTrade, BenchmarkStats, DailyPrice, DirectionMetrics, EquityPoint, ExecutionCosts,
+    PerformanceMetrics, RiskMetrics, TradeDirection, YearlyReturn,
+};
+use chrono::{Datelike, NaiveDate};
+
+/// Trading days used to annualize daily statistics
+pub const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+
+/// Benchmark used when none is chosen
+pub const DEFAULT_BENCHMARK_SYMBOL: &str = "SPY";
+
+/// Price history of the symbol an equity curve is compared against
+#[derive(Debug, Clone)]
+pub struct Benchmark {
+    pub symbol: String,
+    /// Sorted by date
+    pub prices: Vec<DailyPrice>,
+}
+
+impl Benchmark {
+    pub fn new(symbol: &str, mut prices: Vec<DailyPrice>) -> Self {
+        prices.sort_by_key(|p| p.date);
+        Self {
+            symbol: symbol.to_string(),
+            prices,
+        }
+    }
+
+    /// Latest close on or before `date`
+    pub fn close_on(&self, date: NaiveDate) -> Option<f64> {
+        let after = self.prices.partition_point(|p| p.date <= date);
+        after.checked_sub(1).map(|i| self.prices[i].close)
+    }
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+/// Sample standard deviation (n - 1)
+fn sample_std(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let avg = mean(values);
+    let variance =
+        values.iter().map(|v| (v - avg).powi(2)).sum::<f64>() / (values.len() - 1) as f64;
+    variance.sqrt()
+}
+
+/// Daily rate compounding to an annual rate (%)
+fn daily_rate(annual_percent: f64) -> f64 {
+    (1.0 + annual_percent / 100.0).powf(1.0 / TRADING_DAYS_PER_YEAR) - 1.0
+}
+
+/// Simple returns between consecutive equity values
+pub fn daily_returns(equity: &[f64]) -> Vec<f64> {
+    equity
+        .windows(2)
+        .filter(|w| w[0] > 0.0)
+        .map(|w| w[1] / w[0] - 1.0)
+        .collect()
+}
+
+/// Annualized Sharpe ratio of daily returns over an annual risk-free rate (%)
+pub fn sharpe_ratio(returns: &[f64], risk_free_rate_percent: f64) -> f64 {
+    let std_dev = sample_std(returns);
+    if std_dev > 0.0 {
+        (mean(returns) - daily_rate(risk_free_rate_percent)) / std_dev
+            * TRADING_DAYS_PER_YEAR.sqrt()
+    } else {
+        0.0
+    }
+}
+
+/// Annualized Sortino ratio: excess return over the downside deviation
+pub fn sortino_ratio(returns: &[f64], risk_free_rate_percent: f64) -> f64 {
+    if returns.is_empty() {
+        return 0.0;
+    }
+    let rf = daily_rate(risk_free_rate_percent);
+    let downside = (returns
+        .iter()
+        .map(|r| (r - rf).min(0.0).powi(2))
+        .sum::<f64>()
+        / returns.len() as f64)
+        .sqrt();
+    if downside > 0.0 {
+        (mean(returns) - rf) / downside * TRADING_DAYS_PER_YEAR.sqrt()
+    } else {
+        0.0
+    }
+}
+
+/// Compound annual growth rate (%) over `days` calendar days
+pub fn cagr_percent(initial: f64, final_equity: f64, days: i64) -> f64 {
+    let years = days as f64 / 365.25;
+    if years <= 0.0 || initial <= 0.0 {
+        return 0.0;
+    }
+    let growth = final_equity / initial;
+    if growth <= 0.0 {
+        return -100.0;
+    }
+    (growth.powf(1.0 / years) - 1.0) * 100.0
+}
+
+/// Largest fall from a running peak (%), starting from `initial`
+pub fn max_drawdown_percent(initial: f64, equity: &[f64]) -> f64 {
+    let mut peak = initial;
+    let mut worst: f64 = 0.0;
+    for &value in equity {
+        peak = peak.max(value);
+        if peak > 0.0 {
+            worst = worst.max((peak - value) / peak * 100.0);
+        }
+    }
+    worst
+}
+
+/// Set each point's drawdown from the running peak, starting from `initial`
+pub fn mark_drawdowns(curve: &mut [EquityPoint], initial: f64) {
+    let mut peak = initial;
+    for point in curve {
+        peak = peak.max(point.equity);
+        point.drawdown = if peak > 0.0 {
+            (peak - point.equity) / peak * 100.0
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Longest drawdown (calendar days) and ulcer index of an equity curve
+fn drawdown_profile(curve: &[EquityPoint], initial: f64) -> (i64, f64) {
+    let Some(first) = curve.first() else {
+        return (0, 0.0);
+    };
+    let mut peak = initial;
+    let mut peak_date = first.date;
+    let mut longest = 0;
+    let mut squares = 0.0;
+    let mut under_water = false;
+    for point in curve {
+        // A drawdown lasts until the day equity is back at the peak
+        let recovered = point.equity >= peak;
+        if under_water || !recovered {
+            longest = longest.max((point.date - peak_date).num_days());
+        }
+        under_water = !recovered;
+        if recovered {
+            peak = point.equity;
+            peak_date = point.date;
+        }
+        if peak > 0.0 {
+            squares += ((peak - point.equity) / peak * 100.0).powi(2);
+        }
+    }
+    (longest, (squares / curve.len() as f64).sqrt())
+}
+
+/// Returns per calendar year, each measured from the previous year's last
+/// equity (or `initial` for the first year)
+fn yearly_returns(
+    curve: &[EquityPoint],
+    initial: f64,
+    benchmark: Option<&Benchmark>,
+) -> Vec<YearlyReturn> {
+    let mut years = Vec::new();
+    let Some(first) = curve.first() else {
+        return years;
+    };
+    let mut start_equity = initial;
+    let mut start_date = first.date;
+    for year in curve.chunk_by(|a, b| a.date.year() == b.date.year()) {
+        let last = &year[year.len() - 1];
+        let benchmark_return_percent = benchmark.and_then(|b| {
+            let start = b.close_on(start_date)?;
+            let end = b.close_on(last.date)?;
+            (start > 0.0).then(|| (end / start - 1.0) * 100.0)
+        });
+        years.push(YearlyReturn {
+            year: last.date.year(),
+            return_percent: if start_equity > 0.0 {
+                (last.equity / start_equity - 1.0) * 100.0
+            } else {
+                0.0
+            },
+            benchmark_return_percent,
+        });
+        start_equity = last.equity;
+        start_date = last.date;
+    }
+    years
+}
+
+/// Alpha, beta, correlation and information ratio against `benchmark`.
+/// None when fewer than two days overlap with its price history.
+pub fn benchmark_stats(
+    curve: &[EquityPoint],
+    risk_free_rate_percent: f64,
+    benchmark: &Benchmark,
+) -> Option<BenchmarkStats> {
+    let closes: Vec<Option<f64>> = curve.iter().map(|p| benchmark.close_on(p.date)).collect();
+    let (portfolio, market): (Vec<f64>, Vec<f64>) = curve
+        .windows(2)
+        .zip(closes.windows(2))
+        .filter_map(|(points, closes)| {
+            let (Some(before), Some(after)) = (closes[0], closes[1]) else {
+                return None;
+            };
+            (points[0].equity > 0.0 && before > 0.0).then(|| {
+                (
+                    points[1].equity / points[0].equity - 1.0,
+                    after / before - 1.0,
+                )
+            })
+        })
+        .unzip();
+    if portfolio.len() < 2 {
+        return None;
+    }
+
+    let (mean_p, mean_m) = (mean(&portfolio), mean(&market));
+    let covariance = portfolio
+        .iter()
+        .zip(&market)
+        .map(|(p, m)| (p - mean_p) * (m - mean_m))
+        .sum::<f64>()
+        / (portfolio.len() - 1) as f64;
+    let (std_p, std_m) = (sample_std(&portfolio), sample_std(&market));
+    let beta = if std_m > 0.0 {
+        covariance / std_m.powi(2)
+    } else {
+        0.0
+    };
+    let correlation = if std_p > 0.0 && std_m > 0.0 {
+        covariance / (std_p * std_m)
+    } else {
+        0.0
+    };
+    let rf = daily_rate(risk_free_rate_percent);
+    let alpha = ((mean_p - rf) - beta * (mean_m - rf)) * TRADING_DAYS_PER_YEAR * 100.0;
+
+    let active: Vec<f64> = portfolio.iter().zip(&market).map(|(p, m)| p - m).collect();
+    let tracking_error = sample_std(&active);
+    let information_ratio = if tracking_error > 0.0 {
+        mean(&active) / tracking_error * TRADING_DAYS_PER_YEAR.sqrt()
+    } else {
+        0.0
+    };
+
+    let first = closes.iter().flatten().next()?;
+    let last = closes.iter().rev().flatten().next()?;
+
+    Some(BenchmarkStats {
+        symbol: benchmark.symbol.clone(),
+        return_percent: (last / first - 1.0) * 100.0,
+        alpha,
+        beta,
+        correlation,
+        information_ratio,
+    })
+}
+
+/// Risk statistics of an equity curve starting from `initial`. Trade
+/// expectancy is left at zero for the caller to fill in.
+pub fn risk_metrics(
+    curve: &[EquityPoint],
+    initial: f64,
+    risk_free_rate_percent: f64,
+    benchmark: Option<&Benchmark>,
+) -> RiskMetrics {
+    let (Some(first), Some(last)) = (curve.first(), curve.last()) else {
+        return RiskMetrics {
+            risk_free_rate_percent,
+            ..RiskMetrics::default()
+        };
+    };
+    let equity: Vec<f64> = curve.iter().map(|p| p.equity).collect();
+    let returns = daily_returns(&equity);
+
+    let cagr = cagr_percent(initial, last.equity, (last.date - first.date).num_days());
+    let max_drawdown = max_drawdown_percent(initial, &equity);
+    let (max_drawdown_duration_days, ulcer_index) = drawdown_profile(curve, initial);
+    let invested = curve.iter().filter(|p| p.exposure > 0.0).count();
+
+    RiskMetrics {
+        cagr,
+        annualized_volatility: sample_std(&returns) * TRADING_DAYS_PER_YEAR.sqrt() * 100.0,
+        sortino_ratio: sortino_ratio(&returns, risk_free_rate_percent),
+        calmar_ratio: if max_drawdown > 0.0 {
+            cagr / max_drawdown
+        } else {
+            0.0
+        },
+        max_drawdown_duration_days,
+        ulcer_index,
+        exposure_percent: invested as f64 / curve.len() as f64 * 100.0,
+        expectancy: 0.0,
+        expectancy_percent: 0.0,
+        risk_free_rate_percent,
+        benchmark: benchmark.and_then(|b| benchmark_stats(curve, risk_free_rate_percent, b)),
+        yearly_returns: yearly_returns(curve, initial, benchmark),
+    }
+}
+
+/// Full metrics of a backtest from its trades and daily equity curve
+pub fn performance_metrics(
+    trades: &[BacktestTrade],
+    curve: &[EquityPoint],
+    initial: f64,
+    costs: ExecutionCosts,
+    risk_free_rate_percent: f64,
+    benchmark: Option<&Benchmark>,
+) -> PerformanceMetrics {
+    let equity: Vec<f64> = curve.iter().map(|p| p.equity).collect();
+    let final_equity = *equity.last().unwrap_or(&initial);
+
+    let total_return_dollars = final_equity - initial;
+    let total_return = (total_return_dollars / initial) * 100.0;
+
+    // Trade statistics
+    let winning_trades: Vec<_> = trades
+        .iter()
+        .filter(|t| t.profit_loss.unwrap_or(0.0) > 0.0)
+        .collect();
+    let losing_trades: Vec<_> = trades
+        .iter()
+        .filter(|t| t.profit_loss.unwrap_or(0.0) < 0.0)
+        .collect();
+
+    let total_trades = trades.len();
+    let num_winners = winning_trades.len();
+    let num_losers = losing_trades.len();
+
+    let win_rate = if total_trades > 0 {
+        (num_winners as f64 / total_trades as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    let avg_win = if !winning_trades.is_empty() {
+        winning_trades
+            .iter()
+            .map(|t| t.profit_loss_percent.unwrap_or(0.0))
+            .sum::<f64>()
+            / winning_trades.len() as f64
+    } else {
+        0.0
+    };
+
+    let avg_loss = if !losing_trades.is_empty() {
+        losing_trades
+            .iter()
+            .map(|t| t.profit_loss_percent.unwrap_or(0.0).abs())
+            .sum::<f64>()
+            / losing_trades.len() as f64
+    } else {
+        0.0
+    };
+
+    let gross_profit: f64 = winning_trades
+        .iter()
+        .map(|t| t.profit_loss.unwrap_or(0.0))
+        .sum();
+    let gross_loss: f64 = losing_trades
+        .iter()
+        .map(|t| t.profit_loss.unwrap_or(0.0).abs())
+        .sum();
+
+    let profit_factor = if gross_loss > 0.0 {
+        gross_profit / gross_loss
+    } else if gross_profit > 0.0 {
+        f64::INFINITY
+    } else {
+        0.0
+    };
+
+    // Average trade duration
+    let avg_duration = if !trades.is_empty() {
+        trades
+            .iter()
+            .filter_map(|t| {
+                t.exit_date
+                    .map(|exit| (exit - t.entry_date).num_days() as f64)
+            })
+            .sum::<f64>()
+            / trades.len() as f64
+    } else {
+        0.0
+    };
+
+    let closed: Vec<&BacktestTrade> = trades.iter().filter(|t| t.profit_loss.is_some()).collect();
+    let pnl: Vec<f64> = closed.iter().filter_map(|t| t.profit_loss).collect();
+    let pnl_percent: Vec<f64> = closed
+        .iter()
+        .map(|t| t.profit_loss_percent.unwrap_or(0.0))
+        .collect();
+
+    PerformanceMetrics {
+        total_return,
+        total_return_dollars,
+        max_drawdown: max_drawdown_percent(initial, &equity),
+        sharpe_ratio: sharpe_ratio(&daily_returns(&equity), risk_free_rate_percent),
+        win_rate,
+        total_trades,
+        winning_trades: num_winners,
+        losing_trades: num_losers,
+        avg_win_percent: avg_win,
+        avg_loss_percent: avg_loss,
+        profit_factor,
+        avg_trade_duration_days: avg_duration,
+        long: direction_metrics(trades, TradeDirection::Long),
+        short: direction_metrics(trades, TradeDirection::Short),
+        costs: ExecutionCosts {
+            cost_drag_percent: costs.total / initial * 100.0,
+            ..costs
+        },
+        risk: RiskMetrics {
+            expectancy: mean(&pnl),
+            expectancy_percent: mean(&pnl_percent),
+            ..risk_metrics(curve, initial, risk_free_rate_percent, benchmark)
+        },
+    }
+}
+
+/// Trade statistics for one side
+fn direction_metrics(trades: &[BacktestTrade], direction: TradeDirection) -> DirectionMetrics {
+    let side: Vec<&BacktestTrade> = trades.iter().filter(|t| t.direction == direction).collect();
+    let pnl = |t: &&BacktestTrade| t.profit_loss.unwrap_or(0.0);
+
+    let winning_trades = side.iter().filter(|t| pnl(t) > 0.0).count();
+    let losing_trades = side.iter().filter(|t| pnl(t) < 0.0).count();
+    let gross_profit: f64 = side.iter().map(pnl).filter(|p| *p > 0.0).sum();
+    let gross_loss: f64 = side
+        .iter()
+        .map(pnl)
+        .filter(|p| *p < 0.0)
+        .map(f64::abs)
+        .sum();
+
+    let (win_rate, avg_profit_percent) = if side.is_empty() {
+        (0.0, 0.0)
+    } else {
+        (
+            winning_trades as f64 / side.len() as f64 * 100.0,
+            side.iter()
+                .map(|t| t.profit_loss_percent.unwrap_or(0.0))
+                .sum::<f64>()
+                / side.len() as f64,
+        )
+    };
+
+    let profit_factor = if gross_loss > 0.0 {
+        gross_profit / gross_loss
+    } else if gross_profit > 0.0 {
+        f64::INFINITY
+    } else {
+        0.0
+    };
+
+    DirectionMetrics {
+        total_trades: side.len(),
+        winning_trades,
+        losing_trades,
+        win_rate,
+        total_profit: gross_profit - gross_loss,
+        avg_profit_percent,
+        profit_factor,
+    }
+}
+
+/// Average Fed funds rate (DFF) between two dates (%), or 0 when it has not
+/// been fetched
+pub fn risk_free_rate_percent(db: &Database, start: NaiveDate, end: NaiveDate) -> Result<f64> {
+    Ok(db
+        .get_macro_average(FED_FUNDS_RATE, start, end)?
+        .unwrap_or(0.0))
+}
+
+/// Benchmark prices from the database, or None when there are none
+pub fn load_benchmark(db: &Database, symbol: &str) -> Result<Option<Benchmark>> {
+    let prices = db.get_prices(symbol)?;
+    Ok((!prices.is_empty()).then(|| Benchmark::new(symbol, prices)))
+}
+
+/// Risk statistics of a live or paper account from its daily equity curve
+/// and the (dollar, percent) P/L of its closed trades
+pub fn account_metrics(
+    db: &Database,
+    curve: &[EquityPoint],
+    closed_trades: &[(f64, f64)],
+    benchmark_symbol: &str,
+) -> Result<RiskMetrics> {
+    let (Some(first), Some(last)) = (curve.first(), curve.last()) else {
+        return Ok(RiskMetrics::default());
+    };
+    let risk_free = risk_free_rate_percent(db, first.date, last.date)?;
+    let benchmark = load_benchmark(db, benchmark_symbol)?;
+    let pnl: Vec<f64> = closed_trades.iter().map(|t| t.0).collect();
+    let pnl_percent: Vec<f64> = closed_trades.iter().map(|t| t.1).collect();
+
+    Ok(RiskMetrics {
+        expectancy: mean(&pnl),
+        expectancy_percent: mean(&pnl_percent),
+        ..risk_metrics(curve, first.equity, risk_free, benchmark.as_ref())
+    })
+}
+
+/// P/L of a closing sale as (dollars, % of its cost basis)
+pub fn sale_pnl(quantity: f64, price: f64, pnl: f64) -> (f64, f64) {
+    let cost = quantity * price - pnl;
+    (pnl, if cost > 0.0 { pnl / cost * 100.0 } else { 0.0 })
+}
+
+/// Date part of a `YYYY-MM-DD[ HH:MM:SS]` timestamp
+pub(crate) fn timestamp_date(timestamp: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(timestamp.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// Risk statistics of a team's portfolio ("KALIC" paper or "DC") over its
+/// daily snapshots of the last `days` days
+pub fn portfolio_metrics(
+    db: &Database,
+    team: &str,
+    days: i32,
+    benchmark_symbol: &str,
+) -> Result<RiskMetrics> {
+    let curve: Vec<EquityPoint> = db
+        .get_portfolio_snapshots(Some(team), days)?
+        .into_iter()
+        .filter_map(|s| {
+            Some(EquityPoint {
+                date: timestamp_date(&s.date)?,
+                equity: s.total_value,
+                cash: s.cash,
+                exposure: s.positions_value,
+                open_positions: usize::from(s.positions_value > 0.0),
+                drawdown: 0.0,
+                buy_and_hold: None,
+            })
+        })
+        .collect();
+    let Some(start) = curve.first().map(|p| p.date) else {
+        return Ok(RiskMetrics::default());
+    };
+
+    let in_window = |timestamp: &str| timestamp_date(timestamp).is_some_and(|d| d >= start);
+    let closed: Vec<(f64, f64)> = match team {
+        "DC" => db
+            .get_dc_trades(i64::MAX as usize)?
+            .into_iter()
+            .filter(|t| in_window(&t.timestamp))
+            .filter_map(|t| Some(sale_pnl(t.quantity, t.price, t.pnl?)))
+            .collect(),
+        _ => db
+            .get_paper_trades(None, i64::MAX as usize)?
+            .into_iter()
+            .filter(|t| in_window(&t.timestamp))
+            .filter_map(|t| Some(sale_pnl(t.quantity, t.price, t.pnl?)))
+            .collect(),
+    };
+
+    account_metrics(db, &curve, &closed, benchmark_symbol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(n: i64) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 1, 2).unwrap() + chrono::Duration::days(n)
+    }
+
+    fn curve(equity: &[f64]) -> Vec<EquityPoint> {
+        equity
+            .iter()
+            .enumerate()
+            .map(|(i, &e)| EquityPoint {
+                date: day(i as i64),
+                equity: e,
+                cash: 0.0,
+                exposure: if i % 2 == 0 { e } else { 0.0 },
+                open_positions: 1,
+                drawdown: 0.0,
+                buy_and_hold: None,
+            })
+            .collect()
+    }
+
+    fn benchmark(closes: &[f64]) -> Benchmark {
+        let prices = closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| DailyPrice {
+                symbol: "SPY".to_string(),
+                date: day(i as i64),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 0,
+                source: "test".to_string(),
+            })
+            .collect();
+        Benchmark::new("SPY", prices)
+    }
+
+    #[test]
+    fn test_ratios_use_sample_variance_and_risk_free_rate() {
+        let returns = [0.01, -0.02, 0.03, 0.0];
+        // mean 0.005, sample variance 0.0013 / 3
+        let expected = 0.005 / (0.0013f64 / 3.0).sqrt() * TRADING_DAYS_PER_YEAR.sqrt();
+        assert!((sharpe_ratio(&returns, 0.0) - expected).abs() < 1e-9);
+        assert!(sharpe_ratio(&returns, 5.0) < sharpe_ratio(&returns, 0.0));
+
+        // Only the -2% day counts as downside: sqrt(0.0004 / 4) = 0.01
+        let sortino = sortino_ratio(&returns, 0.0);
+        assert!((sortino - 0.005 / 0.01 * TRADING_DAYS_PER_YEAR.sqrt()).abs() < 1e-9);
+        assert_eq!(sortino_ratio(&[0.01, 0.02], 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_drawdown_duration_exposure_and_years() {
+        let points = curve(&[100.0, 120.0, 90.0, 110.0, 130.0, 125.0]);
+        let risk = risk_metrics(&points, 100.0, 0.0, None);
+
+        // Peak of 120 on day 1 recovered on day 4
+        assert_eq!(risk.max_drawdown_duration_days, 3);
+        assert!((max_drawdown_percent(100.0, &[100.0, 120.0, 90.0]) - 25.0).abs() < 1e-9);
+        assert!((risk.exposure_percent - 50.0).abs() < 1e-9);
+        assert!(risk.ulcer_index > 0.0);
+        assert_eq!(risk.yearly_returns.len(), 1);
+        assert!((risk.yearly_returns[0].return_percent - 25.0).abs() < 1e-9);
+        assert!(risk.calmar_ratio > 0.0);
+        assert!(risk.benchmark.is_none());
+    }
+
+    #[test]
+    fn test_benchmark_beta_and_alpha() {
+        let closes = [100.0, 101.0, 99.0, 102.0, 103.0, 101.0];
+        let market = benchmark(&closes);
+
+        // Twice the benchmark's daily moves: beta 2, perfectly correlated
+        let mut equity = vec![1000.0];
+        for w in closes.windows(2) {
+            let last = *equity.last().unwrap();
+            equity.push(last * (1.0 + 2.0 * (w[1] / w[0] - 1.0)));
+        }
+        let stats = benchmark_stats(&curve(&equity), 0.0, &market).unwrap();
+        assert!((stats.beta - 2.0).abs() < 1e-9);
+        assert!((stats.correlation - 1.0).abs() < 1e-9);
+        assert!(stats.alpha.abs() < 1e-6);
+        assert!((stats.return_percent - 1.0).abs() < 1e-9);
+
+        // Tracking the benchmark exactly has no active return
+        let stats = benchmark_stats(&curve(&closes), 0.0, &market).unwrap();
+        assert!((stats.beta - 1.0).abs() < 1e-9);
+        assert_eq!(stats.information_ratio, 0.0);
+    }
+}
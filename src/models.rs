@@ -2,6 +2,8 @@
 
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Stock symbol metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -109,7 +111,10 @@ pub struct Position {
 // ============================================================================
 
 /// Type of trading signal
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Serialized as its `as_str` id. Ids that don't match a built-in type
+/// (signals from plugin detectors) become `Custom`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SignalType {
     // RSI signals
     RsiOverbought,
@@ -138,10 +143,35 @@ pub enum SignalType {
     // MFI signals
     MfiOverbought,
     MfiOversold,
+    // Price/indicator divergence signals
+    BullishDivergence,
+    BearishDivergence,
+    HiddenBullishDivergence,
+    HiddenBearishDivergence,
+    // Volume, gap and range signals
+    VolumeSpike,
+    VolumeBreakout,
+    VolumeBreakdown,
+    GapUp,
+    GapDown,
+    GapFill,
+    NarrowRange7,
+    WideRange7,
+    // Market-wide macro signals
+    YieldCurveInversion,
+    YieldCurveUninversion,
+    VixSpike,
+    VixMeanReversion,
+    FedFundsHike,
+    FedFundsCut,
+    SahmRuleTriggered,
+    SahmRuleCleared,
+    // Signals from detectors registered outside this crate
+    Custom(Arc<str>),
 }
 
 impl SignalType {
-    pub fn as_str(&self) -> &'static str {
+    pub fn as_str(&self) -> &str {
         match self {
             SignalType::RsiOverbought => "RSI_OVERBOUGHT",
             SignalType::RsiOversold => "RSI_OVERSOLD",
@@ -161,9 +191,84 @@ impl SignalType {
             SignalType::CciOversold => "CCI_OVERSOLD",
             SignalType::MfiOverbought => "MFI_OVERBOUGHT",
             SignalType::MfiOversold => "MFI_OVERSOLD",
+            SignalType::BullishDivergence => "BULLISH_DIVERGENCE",
+            SignalType::BearishDivergence => "BEARISH_DIVERGENCE",
+            SignalType::HiddenBullishDivergence => "HIDDEN_BULLISH_DIVERGENCE",
+            SignalType::HiddenBearishDivergence => "HIDDEN_BEARISH_DIVERGENCE",
+            SignalType::VolumeSpike => "VOLUME_SPIKE",
+            SignalType::VolumeBreakout => "VOLUME_BREAKOUT",
+            SignalType::VolumeBreakdown => "VOLUME_BREAKDOWN",
+            SignalType::GapUp => "GAP_UP",
+            SignalType::GapDown => "GAP_DOWN",
+            SignalType::GapFill => "GAP_FILL",
+            SignalType::NarrowRange7 => "NR7",
+            SignalType::WideRange7 => "WR7",
+            SignalType::YieldCurveInversion => "YIELD_CURVE_INVERSION",
+            SignalType::YieldCurveUninversion => "YIELD_CURVE_UNINVERSION",
+            SignalType::VixSpike => "VIX_SPIKE",
+            SignalType::VixMeanReversion => "VIX_MEAN_REVERSION",
+            SignalType::FedFundsHike => "FED_FUNDS_HIKE",
+            SignalType::FedFundsCut => "FED_FUNDS_CUT",
+            SignalType::SahmRuleTriggered => "SAHM_RULE_TRIGGERED",
+            SignalType::SahmRuleCleared => "SAHM_RULE_CLEARED",
+            SignalType::Custom(id) => id,
         }
     }
 
+    /// Signal type for a custom detector id
+    pub fn custom(id: &str) -> Self {
+        SignalType::Custom(Arc::from(id))
+    }
+
+    /// Built-in type for `id`, or `Custom` for any other id
+    pub fn from_id(id: &str) -> Self {
+        Self::from_str(id).unwrap_or_else(|| Self::custom(id))
+    }
+
+    pub fn is_custom(&self) -> bool {
+        matches!(self, SignalType::Custom(_))
+    }
+
+    pub fn is_divergence(&self) -> bool {
+        matches!(
+            self,
+            SignalType::BullishDivergence
+                | SignalType::BearishDivergence
+                | SignalType::HiddenBullishDivergence
+                | SignalType::HiddenBearishDivergence
+        )
+    }
+
+    /// Volume, gap and range signals detected from raw bars
+    pub fn is_price_action(&self) -> bool {
+        matches!(
+            self,
+            SignalType::VolumeSpike
+                | SignalType::VolumeBreakout
+                | SignalType::VolumeBreakdown
+                | SignalType::GapUp
+                | SignalType::GapDown
+                | SignalType::GapFill
+                | SignalType::NarrowRange7
+                | SignalType::WideRange7
+        )
+    }
+
+    /// Market-wide signals derived from FRED series
+    pub fn is_macro(&self) -> bool {
+        matches!(
+            self,
+            SignalType::YieldCurveInversion
+                | SignalType::YieldCurveUninversion
+                | SignalType::VixSpike
+                | SignalType::VixMeanReversion
+                | SignalType::FedFundsHike
+                | SignalType::FedFundsCut
+                | SignalType::SahmRuleTriggered
+                | SignalType::SahmRuleCleared
+        )
+    }
+
     pub fn from_str(s: &str) -> Option<Self> {
         match s {
             "RSI_OVERBOUGHT" => Some(SignalType::RsiOverbought),
@@ -184,11 +289,44 @@ impl SignalType {
             "CCI_OVERSOLD" => Some(SignalType::CciOversold),
             "MFI_OVERBOUGHT" => Some(SignalType::MfiOverbought),
             "MFI_OVERSOLD" => Some(SignalType::MfiOversold),
+            "BULLISH_DIVERGENCE" => Some(SignalType::BullishDivergence),
+            "BEARISH_DIVERGENCE" => Some(SignalType::BearishDivergence),
+            "HIDDEN_BULLISH_DIVERGENCE" => Some(SignalType::HiddenBullishDivergence),
+            "HIDDEN_BEARISH_DIVERGENCE" => Some(SignalType::HiddenBearishDivergence),
+            "VOLUME_SPIKE" => Some(SignalType::VolumeSpike),
+            "VOLUME_BREAKOUT" => Some(SignalType::VolumeBreakout),
+            "VOLUME_BREAKDOWN" => Some(SignalType::VolumeBreakdown),
+            "GAP_UP" => Some(SignalType::GapUp),
+            "GAP_DOWN" => Some(SignalType::GapDown),
+            "GAP_FILL" => Some(SignalType::GapFill),
+            "NR7" => Some(SignalType::NarrowRange7),
+            "WR7" => Some(SignalType::WideRange7),
+            "YIELD_CURVE_INVERSION" => Some(SignalType::YieldCurveInversion),
+            "YIELD_CURVE_UNINVERSION" => Some(SignalType::YieldCurveUninversion),
+            "VIX_SPIKE" => Some(SignalType::VixSpike),
+            "VIX_MEAN_REVERSION" => Some(SignalType::VixMeanReversion),
+            "FED_FUNDS_HIKE" => Some(SignalType::FedFundsHike),
+            "FED_FUNDS_CUT" => Some(SignalType::FedFundsCut),
+            "SAHM_RULE_TRIGGERED" => Some(SignalType::SahmRuleTriggered),
+            "SAHM_RULE_CLEARED" => Some(SignalType::SahmRuleCleared),
             _ => None,
         }
     }
 }
 
+impl Serialize for SignalType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for SignalType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let id = String::deserialize(deserializer)?;
+        Ok(SignalType::from_id(&id))
+    }
+}
+
 /// Direction of the signal
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum SignalDirection {
@@ -215,7 +353,48 @@ impl SignalDirection {
     }
 }
 
+/// Lifecycle status of a signal episode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignalStatus {
+    /// Fired on the latest bar
+    New,
+    /// Still within its expiry window
+    Active,
+    /// No longer relevant (expiry window passed)
+    Expired,
+    /// Contradicted by an opposite signal from the same indicator
+    Invalidated,
+}
+
+impl SignalStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignalStatus::New => "new",
+            SignalStatus::Active => "active",
+            SignalStatus::Expired => "expired",
+            SignalStatus::Invalidated => "invalidated",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "active" => SignalStatus::Active,
+            "expired" => SignalStatus::Expired,
+            "invalidated" => SignalStatus::Invalidated,
+            _ => SignalStatus::New,
+        }
+    }
+
+    /// New or active signals are the ones worth acting on
+    pub fn is_live(&self) -> bool {
+        matches!(self, SignalStatus::New | SignalStatus::Active)
+    }
+}
+
 /// A generated trading signal
+///
+/// Consecutive occurrences of the same signal are merged into one episode
+/// running from `timestamp` (start) to `end_date`.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Signal {
     pub id: i64,
@@ -229,6 +408,51 @@ pub struct Signal {
     pub timestamp: NaiveDate,
     pub created_at: String,
     pub acknowledged: bool,
+    pub status: SignalStatus,
+    /// Last bar of the episode (None for a single-bar signal)
+    pub end_date: Option<NaiveDate>,
+}
+
+/// What happened after a signal: forward returns and excursions, in percent,
+/// measured in the signal's direction (bearish signals profit from declines)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalOutcome {
+    pub symbol: String,
+    pub signal_type: SignalType,
+    pub direction: SignalDirection,
+    pub strength: f64,
+    pub signal_date: NaiveDate,
+    pub entry_price: f64,
+    pub return_1d: Option<f64>,
+    pub return_5d: Option<f64>,
+    pub return_20d: Option<f64>,
+    /// Maximum adverse excursion over the 20-day horizon (<= 0)
+    pub mae: Option<f64>,
+    /// Maximum favorable excursion over the 20-day horizon (>= 0)
+    pub mfe: Option<f64>,
+}
+
+/// Aggregated forward-return statistics for a signal type
+/// (`symbol` / `strength_bucket` are "ALL" for the pooled rows)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalScorecardEntry {
+    pub signal_type: String,
+    pub symbol: String,
+    pub strength_bucket: String,
+    pub sample_size: usize,
+    /// Percent of signals with a positive 5-day forward return
+    pub hit_rate: f64,
+    /// Signals with a known 5-day return (the sample behind `hit_rate`)
+    pub hit_rate_samples: usize,
+    pub mean_return_1d: f64,
+    pub median_return_1d: f64,
+    pub mean_return_5d: f64,
+    pub median_return_5d: f64,
+    pub mean_return_20d: f64,
+    pub median_return_20d: f64,
+    pub mean_mae: f64,
+    pub mean_mfe: f64,
+    pub updated_at: String,
 }
 
 // ============================================================================
@@ -308,6 +532,17 @@ pub struct IndicatorAlert {
     pub message: Option<String>,
 }
 
+/// A named user-defined indicator formula (see `crate::formula`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Formula {
+    pub id: i64,
+    /// Indicator name the formula is stored under (uppercase)
+    pub name: String,
+    pub expression: String,
+    pub description: Option<String>,
+    pub created_at: String,
+}
+
 // ============================================================================
 // Backtesting Types
 // ============================================================================
@@ -325,6 +560,12 @@ pub enum StrategyConditionType {
     SmaCrossDown,     // Fast SMA crosses below slow SMA
     StopLoss,         // Price falls below entry - threshold%
     TakeProfit,       // Price rises above entry + threshold%
+    RsRankAbove,      // Relative strength rank vs benchmark > threshold (0-100)
+    RsRankBelow,      // Relative strength rank vs benchmark < threshold (0-100)
+    FormulaAbove,     // User formula value > threshold
+    FormulaBelow,     // User formula value < threshold
+    ConfluenceBullish, // Bullish confluence signal with strength >= threshold
+    ConfluenceBearish, // Bearish confluence signal with strength >= threshold
 }
 
 impl StrategyConditionType {
@@ -340,6 +581,12 @@ impl StrategyConditionType {
             StrategyConditionType::SmaCrossDown => "sma_cross_down",
             StrategyConditionType::StopLoss => "stop_loss",
             StrategyConditionType::TakeProfit => "take_profit",
+            StrategyConditionType::RsRankAbove => "rs_rank_above",
+            StrategyConditionType::RsRankBelow => "rs_rank_below",
+            StrategyConditionType::FormulaAbove => "formula_above",
+            StrategyConditionType::FormulaBelow => "formula_below",
+            StrategyConditionType::ConfluenceBullish => "confluence_bullish",
+            StrategyConditionType::ConfluenceBearish => "confluence_bearish",
         }
     }
 
@@ -355,9 +602,23 @@ impl StrategyConditionType {
             "sma_cross_down" => Some(StrategyConditionType::SmaCrossDown),
             "stop_loss" => Some(StrategyConditionType::StopLoss),
             "take_profit" => Some(StrategyConditionType::TakeProfit),
+            "rs_rank_above" => Some(StrategyConditionType::RsRankAbove),
+            "rs_rank_below" => Some(StrategyConditionType::RsRankBelow),
+            "formula_above" => Some(StrategyConditionType::FormulaAbove),
+            "formula_below" => Some(StrategyConditionType::FormulaBelow),
+            "confluence_bullish" => Some(StrategyConditionType::ConfluenceBullish),
+            "confluence_bearish" => Some(StrategyConditionType::ConfluenceBearish),
             _ => None,
         }
     }
+
+    /// Whether the condition reads the signal engine's output
+    pub fn uses_signals(&self) -> bool {
+        matches!(
+            self,
+            StrategyConditionType::ConfluenceBullish | StrategyConditionType::ConfluenceBearish
+        )
+    }
 }
 
 /// A trading strategy definition
@@ -373,9 +634,296 @@ pub struct Strategy {
     pub stop_loss_percent: Option<f64>,
     pub take_profit_percent: Option<f64>,
     pub position_size_percent: f64, // % of capital per trade
+    /// Formula name used by formula entry conditions
+    pub entry_formula: Option<String>,
+    /// Formula name used by formula exit conditions
+    pub exit_formula: Option<String>,
+    /// Benchmark of relative strength rank conditions (default SPY)
+    #[serde(default)]
+    pub rs_benchmark: Option<String>,
+    /// Regimes in which new entries are allowed (empty = any regime)
+    pub allowed_regimes: Vec<MarketRegime>,
+    /// Entry rule tree; replaces `entry_condition` when set
+    pub entry_rule: Option<RuleNode>,
+    /// Exit rule tree; replaces `exit_condition` when set
+    pub exit_rule: Option<RuleNode>,
+    /// Which side(s) the strategy trades
+    pub direction: StrategyDirection,
+    /// Trailing, break-even, time and scale-out exits
+    #[serde(default)]
+    pub exit_rules: ExitRules,
+    /// How entries are sized; the default uses `position_size_percent`
+    #[serde(default)]
+    pub position_sizing: PositionSizing,
     pub created_at: String,
 }
 
+/// Trailing stop methods. Levels ratchet in the position's favour only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TrailingStop {
+    /// `percent` below the best price since entry (above it for shorts)
+    Percent { percent: f64 },
+    /// `multiple` x ATR_14 below the best price since entry
+    Atr { multiple: f64 },
+    /// Highest high of the last `period` bars minus `multiple` x ATR_14
+    /// (lowest low plus the offset for shorts)
+    Chandelier { period: usize, multiple: f64 },
+}
+
+/// Partial profit-taking at a gain target
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScaleOut {
+    /// Gain from entry (%) at which to sell
+    pub gain_percent: f64,
+    /// Share of the original position to sell (%)
+    pub size_percent: f64,
+}
+
+/// Position management exits on top of a strategy's exit signal and fixed
+/// stop-loss / take-profit
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExitRules {
+    #[serde(default)]
+    pub trailing_stop: Option<TrailingStop>,
+    /// Move the stop to the entry price once the gain reaches this %
+    #[serde(default)]
+    pub break_even_after_percent: Option<f64>,
+    /// Close positions held this many calendar days
+    #[serde(default)]
+    pub max_holding_days: Option<u32>,
+    /// Partial exits, taken once each in order of gain
+    #[serde(default)]
+    pub scale_outs: Vec<ScaleOut>,
+}
+
+/// Position sizing models for new entries
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PositionSizing {
+    /// A percent of available capital (the strategy's `position_size_percent`,
+    /// or the AI decision's `quantity_percent`)
+    #[default]
+    PercentOfCapital,
+    /// A fixed dollar amount per position
+    FixedDollar { amount: f64 },
+    /// A fixed number of shares per position
+    FixedShares { shares: f64 },
+    /// Risk `risk_percent` of equity per trade, taking `atr_multiple` x ATR_14
+    /// as the loss per share
+    VolatilityTarget { risk_percent: f64, atr_multiple: f64 },
+    /// `fraction` of the Kelly bet implied by the win rate and win/loss ratio
+    /// of past closed trades, capped at `max_percent` of equity. Percent
+    /// sizing is used until `min_trades` trades have closed.
+    Kelly {
+        fraction: f64,
+        max_percent: f64,
+        min_trades: usize,
+    },
+    /// Split a risk budget of `portfolio_risk_percent` of equity equally
+    /// across position slots; each position loses its share at the stop loss
+    /// (or at 2 x ATR_14 without one)
+    EqualRisk { portfolio_risk_percent: f64 },
+}
+
+/// Sides a strategy trades
+///
+/// - `LongOnly`: entry opens a long, exit closes it
+/// - `ShortOnly`: entry opens a short, exit covers it
+/// - `LongShort`: always-in-market reversal; entry goes long (covering any
+///   short), exit goes short (closing any long)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum StrategyDirection {
+    #[default]
+    LongOnly,
+    ShortOnly,
+    LongShort,
+}
+
+impl StrategyDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StrategyDirection::LongOnly => "long",
+            StrategyDirection::ShortOnly => "short",
+            StrategyDirection::LongShort => "long_short",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "long" | "long_only" => Some(StrategyDirection::LongOnly),
+            "short" | "short_only" => Some(StrategyDirection::ShortOnly),
+            "long_short" => Some(StrategyDirection::LongShort),
+            _ => None,
+        }
+    }
+}
+
+/// Price field referenced by a rule operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceField {
+    Open,
+    High,
+    Low,
+    Close,
+    Volume,
+}
+
+impl PriceField {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PriceField::Open => "open",
+            PriceField::High => "high",
+            PriceField::Low => "low",
+            PriceField::Close => "close",
+            PriceField::Volume => "volume",
+        }
+    }
+}
+
+/// A value a rule compares: an indicator, a price field or a constant.
+/// `bars_ago` looks back that many bars (0 = current bar).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RuleOperand {
+    Indicator {
+        name: String,
+        #[serde(default)]
+        bars_ago: usize,
+    },
+    Price {
+        field: PriceField,
+        #[serde(default)]
+        bars_ago: usize,
+    },
+    Constant {
+        value: f64,
+    },
+}
+
+/// Comparison operator in a rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompareOp {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+}
+
+impl CompareOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompareOp::Gt => ">",
+            CompareOp::Gte => ">=",
+            CompareOp::Lt => "<",
+            CompareOp::Lte => "<=",
+        }
+    }
+}
+
+/// Strategy rule tree, stored as JSON.
+///
+/// Example: `{"type":"all","rules":[{"type":"compare","left":{"kind":"indicator","name":"RSI_14"},
+/// "op":"lt","right":{"kind":"constant","value":30}}, ...]}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuleNode {
+    /// True when every child is true (an empty list is true)
+    All { rules: Vec<RuleNode> },
+    /// True when any child is true (an empty list is false)
+    Any { rules: Vec<RuleNode> },
+    Not { rule: Box<RuleNode> },
+    Compare {
+        left: RuleOperand,
+        op: CompareOp,
+        right: RuleOperand,
+    },
+    /// `left` moved from at/below `right` on the previous bar to above it
+    CrossAbove { left: RuleOperand, right: RuleOperand },
+    /// `left` moved from at/above `right` on the previous bar to below it
+    CrossBelow { left: RuleOperand, right: RuleOperand },
+    /// A single-condition strategy condition
+    Condition {
+        condition: StrategyConditionType,
+        threshold: f64,
+        #[serde(default)]
+        formula: Option<String>,
+        /// Benchmark of relative strength rank conditions (default SPY)
+        #[serde(default)]
+        benchmark: Option<String>,
+    },
+    /// A confluence signal in `direction` at least `min_strength` strong
+    /// fired on the bar
+    Confluence {
+        direction: SignalDirection,
+        #[serde(default)]
+        min_strength: f64,
+    },
+    /// A `signal_type` signal (in `direction`, or either when None) at least
+    /// `min_strength` strong fired on the bar
+    Signal {
+        signal_type: SignalType,
+        #[serde(default)]
+        direction: Option<SignalDirection>,
+        #[serde(default)]
+        min_strength: f64,
+    },
+}
+
+/// Market regime of a symbol or the broad market
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum MarketRegime {
+    TrendUp,
+    TrendDown,
+    Range,
+    HighVolatility,
+}
+
+impl MarketRegime {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MarketRegime::TrendUp => "trend_up",
+            MarketRegime::TrendDown => "trend_down",
+            MarketRegime::Range => "range",
+            MarketRegime::HighVolatility => "high_volatility",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "trend_up" => Some(MarketRegime::TrendUp),
+            "trend_down" => Some(MarketRegime::TrendDown),
+            "range" => Some(MarketRegime::Range),
+            "high_volatility" => Some(MarketRegime::HighVolatility),
+            _ => None,
+        }
+    }
+
+    /// Parse a comma-separated list, skipping unknown names
+    pub fn parse_list(s: &str) -> Vec<Self> {
+        s.split(',').filter_map(MarketRegime::from_str).collect()
+    }
+
+    /// Comma-separated list as stored in the database
+    pub fn join(regimes: &[Self]) -> String {
+        regimes.iter().map(|r| r.as_str()).collect::<Vec<_>>().join(",")
+    }
+}
+
+/// Regime classification for one symbol (or the market) on one date
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegimeRecord {
+    pub symbol: String,
+    pub date: NaiveDate,
+    pub regime: MarketRegime,
+    pub adx: Option<f64>,
+    pub vol_percentile: Option<f64>,
+    /// VIX close (market regime only)
+    pub vix: Option<f64>,
+}
+
 /// Trade direction
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TradeDirection {
@@ -418,7 +966,7 @@ pub struct BacktestTrade {
 }
 
 /// Performance metrics from backtesting
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct PerformanceMetrics {
     pub total_return: f64,
     pub total_return_dollars: f64,
@@ -432,6 +980,91 @@ pub struct PerformanceMetrics {
     pub avg_loss_percent: f64,
     pub profit_factor: f64,
     pub avg_trade_duration_days: f64,
+    /// Statistics of long trades only
+    #[serde(default)]
+    pub long: DirectionMetrics,
+    /// Statistics of short trades only
+    #[serde(default)]
+    pub short: DirectionMetrics,
+    /// Trading costs paid over the backtest
+    #[serde(default)]
+    pub costs: ExecutionCosts,
+    /// Risk-adjusted and benchmark-relative statistics
+    #[serde(default)]
+    pub risk: RiskMetrics,
+}
+
+/// Risk-adjusted statistics of an equity curve
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RiskMetrics {
+    /// Compound annual growth rate (%)
+    pub cagr: f64,
+    /// Annualized standard deviation of daily returns (%)
+    pub annualized_volatility: f64,
+    pub sortino_ratio: f64,
+    /// CAGR over max drawdown
+    pub calmar_ratio: f64,
+    /// Longest time from an equity peak back to a new high (calendar days)
+    pub max_drawdown_duration_days: i64,
+    /// Root mean square of the daily drawdowns (%)
+    pub ulcer_index: f64,
+    /// Share of days with capital in the market (%)
+    pub exposure_percent: f64,
+    /// Average net P/L per closed trade ($)
+    pub expectancy: f64,
+    /// Average net P/L per closed trade (%)
+    pub expectancy_percent: f64,
+    /// Annual risk-free rate used for Sharpe, Sortino and alpha (%)
+    pub risk_free_rate_percent: f64,
+    /// Statistics relative to the benchmark, when its prices were available
+    pub benchmark: Option<BenchmarkStats>,
+    pub yearly_returns: Vec<YearlyReturn>,
+}
+
+/// Equity curve statistics relative to a benchmark symbol
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BenchmarkStats {
+    pub symbol: String,
+    /// Buy-and-hold return of the benchmark over the same period (%)
+    pub return_percent: f64,
+    /// Annualized Jensen's alpha (%)
+    pub alpha: f64,
+    pub beta: f64,
+    pub correlation: f64,
+    /// Annualized active return over tracking error
+    pub information_ratio: f64,
+}
+
+/// Return of one calendar year
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YearlyReturn {
+    pub year: i32,
+    pub return_percent: f64,
+    pub benchmark_return_percent: Option<f64>,
+}
+
+/// Trading costs paid over a backtest, in dollars
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionCosts {
+    pub commission: f64,
+    pub slippage: f64,
+    pub spread: f64,
+    pub total: f64,
+    /// Total costs as a % of initial capital
+    pub cost_drag_percent: f64,
+}
+
+/// Trade statistics for one side of a backtest
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirectionMetrics {
+    pub total_trades: usize,
+    pub winning_trades: usize,
+    pub losing_trades: usize,
+    pub win_rate: f64,
+    /// Net P/L in dollars
+    pub total_profit: f64,
+    pub avg_profit_percent: f64,
+    pub profit_factor: f64,
 }
 
 /// Complete backtest result
@@ -447,9 +1080,45 @@ pub struct BacktestResult {
     pub final_capital: f64,
     pub metrics: PerformanceMetrics,
     pub trades: Vec<BacktestTrade>,
+    /// Trade statistics per traded symbol
+    #[serde(default)]
+    pub symbol_stats: Vec<SymbolTradeStats>,
+    /// End-of-day account values, one point per bar
+    #[serde(default)]
+    pub equity_curve: Vec<EquityPoint>,
     pub created_at: String,
 }
 
+/// Trade statistics for one symbol of a backtest
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SymbolTradeStats {
+    pub symbol: String,
+    pub total_trades: usize,
+    pub winning_trades: usize,
+    pub losing_trades: usize,
+    pub win_rate: f64,
+    /// Net P/L in dollars
+    pub total_profit: f64,
+    pub avg_profit_percent: f64,
+}
+
+/// One day of an equity curve
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub date: NaiveDate,
+    pub equity: f64,
+    pub cash: f64,
+    /// Gross market value of open positions
+    pub exposure: f64,
+    pub open_positions: usize,
+    /// Fall from the running equity peak (%)
+    #[serde(default)]
+    pub drawdown: f64,
+    /// Equity from holding the traded symbol(s) since the first bar
+    #[serde(default)]
+    pub buy_and_hold: Option<f64>,
+}
+
 // ============================================================================
 // Paper Trading Types
 // ============================================================================
@@ -654,7 +1323,20 @@ pub struct ConfluenceConfig {
     pub stoch_overbought: f64,
     pub cci_oversold: f64,
     pub cci_overbought: f64,
+    pub willr_oversold: f64,
+    pub willr_overbought: f64,
     pub adx_strong_trend: f64,
+    /// Weight per vote name (e.g. "RSI_14", "MACD"); votes not listed weigh 1.0.
+    /// Confluence fires when the summed weight of agreeing votes reaches
+    /// `min_agreeing_indicators`, so all-1.0 weights mean plain counting.
+    pub weights: HashMap<String, f64>,
+    /// Votes that measure nearly the same thing; only the strongest vote of
+    /// each group is counted
+    pub correlated_groups: Vec<Vec<String>>,
+    /// Count Williams %R (`WILLR_14`) as a vote; off unless asked for or
+    /// weighted by the active profile
+    #[serde(default)]
+    pub willr_vote: bool,
 }
 
 impl Default for ConfluenceConfig {
@@ -667,11 +1349,68 @@ impl Default for ConfluenceConfig {
             stoch_overbought: 80.0,
             cci_oversold: -100.0,
             cci_overbought: 100.0,
+            willr_oversold: -80.0,
+            willr_overbought: -20.0,
             adx_strong_trend: 25.0,
+            weights: HashMap::new(),
+            correlated_groups: vec![
+                vec!["STOCH_K".to_string(), "WILLR_14".to_string()],
+                // Breakouts require a volume surge, so they mostly fire together
+                vec![
+                    "PA_VOLUME_SPIKE".to_string(),
+                    "PA_VOLUME_BREAKOUT".to_string(),
+                    "PA_VOLUME_BREAKDOWN".to_string(),
+                ],
+            ],
+            willr_vote: false,
         }
     }
 }
 
+impl ConfluenceConfig {
+    /// Weight of a vote by name (1.0 if not configured)
+    pub fn vote_weight(&self, name: &str) -> f64 {
+        self.weights.get(name).copied().unwrap_or(1.0)
+    }
+
+    /// Use the weights learned in a confluence profile; a profile that weights
+    /// `WILLR_14` also turns the Williams %R vote on
+    pub fn with_profile(mut self, profile: &ConfluenceProfile) -> Self {
+        self.weights = profile.weights.clone();
+        self.willr_vote |= self.weights.get("WILLR_14").is_some_and(|&w| w > 0.0);
+        self
+    }
+}
+
+/// Named set of confluence vote weights fitted from historical forward returns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfluenceProfile {
+    pub id: i64,
+    pub name: String,
+    pub weights: HashMap<String, f64>,
+    /// Logistic regression intercept
+    pub intercept: f64,
+    /// Forward return horizon the profile was fitted on (trading days)
+    pub horizon_days: usize,
+    pub sample_size: usize,
+    /// In-sample classification accuracy (0-100)
+    pub accuracy: f64,
+    pub symbols: Vec<String>,
+    /// Whether signal engines built by `configured_signal_engine` use these weights
+    #[serde(default)]
+    pub active: bool,
+    pub created_at: String,
+}
+
+/// Persisted runtime settings of a signal detector
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectorSetting {
+    pub detector_id: String,
+    pub enabled: bool,
+    /// Parameter overrides by name
+    pub params: HashMap<String, f64>,
+}
+
 /// A confluence signal that fires when 3+ indicators agree on direction
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfluenceSignal {
@@ -713,6 +1452,13 @@ pub struct AiTraderConfig {
     pub max_single_trade_value: f64,
     pub require_confluence: bool,
     pub blocked_hours: String,
+    /// Trailing, break-even, time and scale-out exits for paper positions
+    #[serde(default)]
+    pub exit_rules: ExitRules,
+    /// How BUY decisions are sized; the default spends the decision's
+    /// `quantity_percent` of cash
+    #[serde(default)]
+    pub position_sizing: PositionSizing,
 }
 
 impl Default for AiTraderConfig {
@@ -737,6 +1483,8 @@ impl Default for AiTraderConfig {
             max_single_trade_value: 50_000.0,
             require_confluence: true,
             blocked_hours: "09:30-09:45,15:45-16:00".to_string(),
+            exit_rules: ExitRules::default(),
+            position_sizing: PositionSizing::default(),
         }
     }
 }
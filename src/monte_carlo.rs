@@ -0,0 +1,497 @@
+//! Monte Carlo Robustness Analysis
+//!
+//! Re-orders or resamples the outcome of a backtest many times to show how
+//! much of its result was down to the particular sequence of trades:
+//! - `TradeShuffle`: the same trades in random order (final P/L unchanged,
+//!   drawdowns vary)
+//! - `TradeBootstrap`: trades drawn with replacement
+//! - `ReturnBootstrap`: daily returns of the equity curve drawn with
+//!   replacement, in blocks to keep short-term autocorrelation
+//!
+//! Reports percentile bands of final return and max drawdown, the equity
+//! path, and the risk of ruin.
+
+use crate::db::Database;
+use crate::error::{PipelineError, Result};
+use crate::metrics::max_drawdown_percent;
+use crate::models::{BacktestResult, DailyPrice, TradeDirection};
+use crate::optimizer::SplitMix64;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Most simulations in one analysis
+pub const MAX_SIMULATIONS: usize = 20_000;
+
+/// Equity bands come from at most this many simulations, so memory stays
+/// bounded by it rather than by `simulations` times the path length
+pub const MAX_BAND_PATHS: usize = 1_000;
+
+/// How simulated paths are generated
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MonteCarloMethod {
+    #[default]
+    TradeShuffle,
+    TradeBootstrap,
+    /// Blocks of `block_size` consecutive daily returns
+    ReturnBootstrap {
+        block_size: usize,
+    },
+}
+
+fn default_simulations() -> usize {
+    1000
+}
+
+fn default_seed() -> u64 {
+    42
+}
+
+fn default_ruin_threshold() -> f64 {
+    50.0
+}
+
+/// Settings of a Monte Carlo analysis
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonteCarloConfig {
+    #[serde(default)]
+    pub method: MonteCarloMethod,
+    #[serde(default = "default_simulations")]
+    pub simulations: usize,
+    /// Same seed, same paths
+    #[serde(default = "default_seed")]
+    pub seed: u64,
+    /// A path is ruined once equity falls this far below initial capital (%)
+    #[serde(default = "default_ruin_threshold")]
+    pub ruin_threshold_percent: f64,
+}
+
+impl Default for MonteCarloConfig {
+    fn default() -> Self {
+        Self {
+            method: MonteCarloMethod::default(),
+            simulations: default_simulations(),
+            seed: default_seed(),
+            ruin_threshold_percent: default_ruin_threshold(),
+        }
+    }
+}
+
+/// Percentile summary of a simulated distribution
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Percentiles {
+    pub p5: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p95: f64,
+    pub mean: f64,
+}
+
+impl Percentiles {
+    pub fn from_values(values: &[f64]) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        Self {
+            p5: percentile(&sorted, 5.0),
+            p25: percentile(&sorted, 25.0),
+            p50: percentile(&sorted, 50.0),
+            p75: percentile(&sorted, 75.0),
+            p95: percentile(&sorted, 95.0),
+            mean: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        }
+    }
+}
+
+/// Linear-interpolated percentile of sorted values
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    let rank = pct / 100.0 * (sorted.len() - 1) as f64;
+    let (lo, hi) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64)
+}
+
+/// Outcome of a Monte Carlo analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonteCarloResult {
+    /// Database id once saved
+    pub id: i64,
+    pub backtest_id: i64,
+    pub config: MonteCarloConfig,
+    /// Return (%) and max drawdown (%) of the backtest itself
+    pub original_return: f64,
+    pub original_max_drawdown: f64,
+    /// Final return across simulations (%)
+    pub final_return: Percentiles,
+    /// Max drawdown across simulations (%)
+    pub max_drawdown: Percentiles,
+    /// Share of simulations ending below initial capital (%)
+    pub probability_of_loss_percent: f64,
+    /// Share of simulations that hit the ruin threshold (%)
+    pub risk_of_ruin_percent: f64,
+    /// Equity percentiles after each trade (trade methods) or day, over the
+    /// first `MAX_BAND_PATHS` simulations
+    pub equity_bands: Vec<Percentiles>,
+    pub created_at: String,
+}
+
+/// Daily equity of a backtest rebuilt from its trades: realized P/L of
+/// closed trades plus open trades marked to each day's close
+pub fn equity_from_trades(
+    result: &BacktestResult,
+    prices: &HashMap<String, Vec<DailyPrice>>,
+) -> Vec<f64> {
+    let closes: HashMap<&str, BTreeMap<NaiveDate, f64>> = prices
+        .iter()
+        .map(|(symbol, bars)| {
+            (
+                symbol.as_str(),
+                bars.iter().map(|b| (b.date, b.close)).collect(),
+            )
+        })
+        .collect();
+    let dates: BTreeSet<NaiveDate> = closes
+        .values()
+        .flat_map(|c| c.keys().copied())
+        .filter(|d| *d >= result.start_date && *d <= result.end_date)
+        .collect();
+
+    dates
+        .into_iter()
+        .map(|date| {
+            let pnl: f64 = result
+                .trades
+                .iter()
+                .map(|t| match t.exit_date {
+                    Some(exit) if exit <= date => t.profit_loss.unwrap_or(0.0),
+                    _ if t.entry_date <= date => closes
+                        .get(t.symbol.as_str())
+                        .and_then(|c| c.range(..=date).next_back())
+                        .map_or(0.0, |(_, &close)| {
+                            let sign = match t.direction {
+                                TradeDirection::Long => 1.0,
+                                TradeDirection::Short => -1.0,
+                            };
+                            sign * t.shares * (close - t.entry_price)
+                        }),
+                    _ => 0.0,
+                })
+                .sum();
+            result.initial_capital + pnl
+        })
+        .collect()
+}
+
+/// Draws one simulated equity path
+type PathSampler = Box<dyn Fn(&mut SplitMix64) -> Vec<f64>>;
+
+/// Run the analysis on `result`. `daily_equity` is needed only by
+/// `ReturnBootstrap`.
+pub fn analyze(
+    result: &BacktestResult,
+    daily_equity: &[f64],
+    config: &MonteCarloConfig,
+) -> Result<MonteCarloResult> {
+    if config.simulations == 0 || config.simulations > MAX_SIMULATIONS {
+        return Err(PipelineError::Config(format!(
+            "Simulations must be between 1 and {}",
+            MAX_SIMULATIONS
+        )));
+    }
+    let initial = result.initial_capital;
+    let ruin_level = initial * (1.0 - config.ruin_threshold_percent / 100.0);
+    let mut rng = SplitMix64(config.seed);
+
+    // Each simulation is a path of equity values, starting after step one
+    let simulate: PathSampler = match config.method {
+        MonteCarloMethod::TradeShuffle | MonteCarloMethod::TradeBootstrap => {
+            let pnl: Vec<f64> = result.trades.iter().filter_map(|t| t.profit_loss).collect();
+            if pnl.is_empty() {
+                return Err(PipelineError::NoData(
+                    "Backtest has no closed trades to resample".to_string(),
+                ));
+            }
+            let shuffle = config.method == MonteCarloMethod::TradeShuffle;
+            Box::new(move |rng: &mut SplitMix64| {
+                let mut order = pnl.clone();
+                if shuffle {
+                    // Fisher-Yates
+                    for i in (1..order.len()).rev() {
+                        order.swap(i, rng.below(i + 1));
+                    }
+                } else {
+                    for value in order.iter_mut() {
+                        *value = pnl[rng.below(pnl.len())];
+                    }
+                }
+                order
+                    .iter()
+                    .scan(initial, |equity, p| {
+                        *equity += p;
+                        Some(*equity)
+                    })
+                    .collect()
+            })
+        }
+        MonteCarloMethod::ReturnBootstrap { block_size } => {
+            let returns: Vec<f64> = daily_equity
+                .windows(2)
+                .filter(|w| w[0] > 0.0)
+                .map(|w| w[1] / w[0] - 1.0)
+                .collect();
+            if returns.is_empty() {
+                return Err(PipelineError::NoData(
+                    "Backtest has no daily equity to resample".to_string(),
+                ));
+            }
+            let block = block_size.clamp(1, returns.len());
+            Box::new(move |rng: &mut SplitMix64| {
+                let mut path = Vec::with_capacity(returns.len());
+                let mut equity = initial;
+                while path.len() < returns.len() {
+                    let start = rng.below(returns.len() - block + 1);
+                    for r in returns[start..start + block]
+                        .iter()
+                        .take(returns.len() - path.len())
+                    {
+                        equity *= 1.0 + r;
+                        path.push(equity);
+                    }
+                }
+                path
+            })
+        }
+    };
+
+    // Summarize each path as it is drawn; only the band sample is kept
+    let mut finals = Vec::with_capacity(config.simulations);
+    let mut drawdowns = Vec::with_capacity(config.simulations);
+    let mut ruined = 0;
+    let mut paths: Vec<Vec<f64>> = Vec::with_capacity(config.simulations.min(MAX_BAND_PATHS));
+    for _ in 0..config.simulations {
+        let path = simulate(&mut rng);
+        finals.push((path.last().copied().unwrap_or(initial) / initial - 1.0) * 100.0);
+        drawdowns.push(max_drawdown_percent(initial, &path));
+        if path.iter().any(|&equity| equity <= ruin_level) {
+            ruined += 1;
+        }
+        if paths.len() < MAX_BAND_PATHS {
+            paths.push(path);
+        }
+    }
+    let share = |count: usize| count as f64 / config.simulations as f64 * 100.0;
+    let losses = finals.iter().filter(|r| **r < 0.0).count();
+
+    let steps = paths.iter().map(Vec::len).max().unwrap_or(0);
+    let equity_bands = (0..steps)
+        .map(|step| {
+            let values: Vec<f64> = paths.iter().filter_map(|p| p.get(step).copied()).collect();
+            Percentiles::from_values(&values)
+        })
+        .collect();
+
+    Ok(MonteCarloResult {
+        id: 0,
+        backtest_id: result.id,
+        config: config.clone(),
+        original_return: result.metrics.total_return,
+        original_max_drawdown: result.metrics.max_drawdown,
+        final_return: Percentiles::from_values(&finals),
+        max_drawdown: Percentiles::from_values(&drawdowns),
+        probability_of_loss_percent: share(losses),
+        risk_of_ruin_percent: share(ruined),
+        equity_bands,
+        created_at: String::new(),
+    })
+}
+
+/// Load a saved backtest and the daily equity `method` resamples, if any.
+/// Split from `analyze` so callers need the database only around it.
+pub fn load_backtest(
+    db: &Database,
+    backtest_id: i64,
+    method: &MonteCarloMethod,
+) -> Result<(BacktestResult, Vec<f64>)> {
+    let result = db
+        .get_backtest_detail(backtest_id)?
+        .ok_or_else(|| PipelineError::NoData(format!("Backtest {}", backtest_id)))?;
+
+    let daily_equity = if !matches!(method, MonteCarloMethod::ReturnBootstrap { .. }) {
+        Vec::new()
+    } else if !result.equity_curve.is_empty() {
+        result.equity_curve.iter().map(|p| p.equity).collect()
+    } else {
+        // Runs saved before equity curves were stored
+        let symbols: BTreeSet<&str> = result.trades.iter().map(|t| t.symbol.as_str()).collect();
+        let mut prices = HashMap::new();
+        for symbol in symbols {
+            prices.insert(symbol.to_string(), db.get_prices(symbol)?);
+        }
+        equity_from_trades(&result, &prices)
+    };
+    Ok((result, daily_equity))
+}
+
+/// Load a saved backtest with the prices it traded, analyze it and save the
+/// analysis
+pub fn run_for_backtest(
+    db: &Database,
+    backtest_id: i64,
+    config: &MonteCarloConfig,
+) -> Result<MonteCarloResult> {
+    let (result, daily_equity) = load_backtest(db, backtest_id, &config.method)?;
+    let mut analysis = analyze(&result, &daily_equity, config)?;
+    db.save_monte_carlo(&mut analysis)?;
+    Ok(analysis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BacktestTrade, PerformanceMetrics};
+
+    fn backtest(pnl: &[f64]) -> BacktestResult {
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        BacktestResult {
+            id: 9,
+            strategy_id: 1,
+            strategy_name: "test".to_string(),
+            symbol: "TEST".to_string(),
+            start_date: date,
+            end_date: date + chrono::Duration::days(pnl.len() as i64),
+            initial_capital: 1000.0,
+            final_capital: 1000.0 + pnl.iter().sum::<f64>(),
+            metrics: PerformanceMetrics::default(),
+            trades: pnl
+                .iter()
+                .enumerate()
+                .map(|(i, &p)| BacktestTrade {
+                    id: i as i64,
+                    backtest_id: 9,
+                    symbol: "TEST".to_string(),
+                    direction: TradeDirection::Long,
+                    entry_date: date + chrono::Duration::days(i as i64),
+                    entry_price: 10.0,
+                    exit_date: Some(date + chrono::Duration::days(i as i64 + 1)),
+                    exit_price: Some(10.0 + p / 10.0),
+                    shares: 10.0,
+                    entry_reason: String::new(),
+                    exit_reason: None,
+                    profit_loss: Some(p),
+                    profit_loss_percent: None,
+                })
+                .collect(),
+            symbol_stats: Vec::new(),
+            equity_curve: Vec::new(),
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_percentiles_interpolate() {
+        let p = Percentiles::from_values(&[4.0, 1.0, 3.0, 2.0, 5.0]);
+        assert_eq!(p.p50, 3.0);
+        assert_eq!(p.p25, 2.0);
+        assert!((p.p5 - 1.2).abs() < 1e-9);
+        assert_eq!(p.mean, 3.0);
+    }
+
+    #[test]
+    fn test_trade_shuffle_keeps_final_and_varies_drawdown() {
+        let result = backtest(&[100.0, -300.0, 50.0, 200.0, -250.0, 400.0]);
+        let config = MonteCarloConfig {
+            simulations: 500,
+            ruin_threshold_percent: 40.0,
+            ..MonteCarloConfig::default()
+        };
+        let mc = analyze(&result, &[], &config).unwrap();
+
+        // Order never changes the total
+        assert!((mc.final_return.p5 - 20.0).abs() < 1e-9);
+        assert!((mc.final_return.p95 - 20.0).abs() < 1e-9);
+        assert!(mc.max_drawdown.p95 > mc.max_drawdown.p5);
+        assert_eq!(mc.equity_bands.len(), 6);
+        assert_eq!(mc.probability_of_loss_percent, 0.0);
+        // Only orders with the two big losses close together reach 600
+        assert!(mc.risk_of_ruin_percent > 0.0 && mc.risk_of_ruin_percent < 50.0);
+
+        let again = analyze(&result, &[], &config).unwrap();
+        assert_eq!(again.max_drawdown, mc.max_drawdown);
+
+        // Every simulation is counted, bands use a capped sample of paths
+        let many = MonteCarloConfig {
+            simulations: MAX_BAND_PATHS * 3,
+            ..config.clone()
+        };
+        let mc = analyze(&result, &[], &many).unwrap();
+        assert_eq!(mc.equity_bands.len(), 6);
+        assert!(mc.risk_of_ruin_percent > 0.0 && mc.risk_of_ruin_percent < 50.0);
+        let too_many = MonteCarloConfig {
+            simulations: MAX_SIMULATIONS + 1,
+            ..config
+        };
+        assert!(analyze(&result, &[], &too_many).is_err());
+    }
+
+    #[test]
+    fn test_bootstraps_vary_final_return() {
+        let result = backtest(&[100.0, -50.0, 80.0, -120.0]);
+        let trades = analyze(
+            &result,
+            &[],
+            &MonteCarloConfig {
+                method: MonteCarloMethod::TradeBootstrap,
+                ..MonteCarloConfig::default()
+            },
+        )
+        .unwrap();
+        assert!(trades.final_return.p95 > trades.final_return.p5);
+
+        let equity = [1000.0, 1010.0, 990.0, 1020.0, 1000.0, 1050.0];
+        let daily = analyze(
+            &result,
+            &equity,
+            &MonteCarloConfig {
+                method: MonteCarloMethod::ReturnBootstrap { block_size: 2 },
+                ..MonteCarloConfig::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(daily.equity_bands.len(), 5);
+        assert!(daily.final_return.p95 > daily.final_return.p5);
+
+        // Daily resampling needs an equity curve
+        let config = MonteCarloConfig {
+            method: MonteCarloMethod::ReturnBootstrap { block_size: 1 },
+            ..MonteCarloConfig::default()
+        };
+        assert!(analyze(&result, &[], &config).is_err());
+    }
+
+    #[test]
+    fn test_equity_from_trades_marks_open_positions() {
+        let result = backtest(&[50.0]);
+        let date = result.start_date;
+        let bars: Vec<DailyPrice> = [10.0, 12.0, 15.0]
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| DailyPrice {
+                symbol: "TEST".to_string(),
+                date: date + chrono::Duration::days(i as i64),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 0,
+                source: "test".to_string(),
+            })
+            .collect();
+        let prices = HashMap::from([("TEST".to_string(), bars)]);
+
+        // Entered day 0 at 10, closed day 1 for +50
+        assert_eq!(equity_from_trades(&result, &prices), vec![1000.0, 1050.0]);
+    }
+}
@@ -0,0 +1,735 @@
+//! Strategy Optimization
+//!
+//! Grid or random search over strategy parameters, validated on data the
+//! search never saw:
+//! - `Split`: fit on the first part of the history, test on the rest
+//! - `WalkForward`: fit on a rolling in-sample window, test on the bars that
+//!   follow it, then roll forward by the test length
+//!
+//! Each window keeps the in-sample winner by the selected objective and
+//! re-runs it out of sample. The drop from in-sample to out-of-sample score
+//! (degradation) flags parameter sets that were fitted to noise.
+
+use crate::backtest::{with_signal_indicators, BacktestEngine};
+use crate::error::{PipelineError, Result};
+use crate::models::{BacktestResult, DailyPrice, Strategy, TechnicalIndicator, TrailingStop};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Range;
+
+/// Most parameter combinations evaluated per window
+pub const MAX_TRIALS: usize = 5_000;
+
+/// Fewest bars in any in-sample or out-of-sample segment
+const MIN_SEGMENT_BARS: usize = 20;
+
+/// Profit factor scored for runs without a losing trade
+const MAX_PROFIT_FACTOR: f64 = 100.0;
+
+/// Parameter values keyed by `StrategyParam::as_str`
+pub type ParamSet = BTreeMap<String, f64>;
+
+/// Strategy setting that can be searched
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyParam {
+    EntryThreshold,
+    ExitThreshold,
+    StopLossPercent,
+    TakeProfitPercent,
+    PositionSizePercent,
+    TrailingStopPercent,
+}
+
+impl StrategyParam {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StrategyParam::EntryThreshold => "entry_threshold",
+            StrategyParam::ExitThreshold => "exit_threshold",
+            StrategyParam::StopLossPercent => "stop_loss_percent",
+            StrategyParam::TakeProfitPercent => "take_profit_percent",
+            StrategyParam::PositionSizePercent => "position_size_percent",
+            StrategyParam::TrailingStopPercent => "trailing_stop_percent",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "entry_threshold" => Some(StrategyParam::EntryThreshold),
+            "exit_threshold" => Some(StrategyParam::ExitThreshold),
+            "stop_loss_percent" => Some(StrategyParam::StopLossPercent),
+            "take_profit_percent" => Some(StrategyParam::TakeProfitPercent),
+            "position_size_percent" => Some(StrategyParam::PositionSizePercent),
+            "trailing_stop_percent" => Some(StrategyParam::TrailingStopPercent),
+            _ => None,
+        }
+    }
+
+    /// Set this parameter on `strategy`
+    pub fn apply(&self, strategy: &mut Strategy, value: f64) {
+        match self {
+            StrategyParam::EntryThreshold => strategy.entry_threshold = value,
+            StrategyParam::ExitThreshold => strategy.exit_threshold = value,
+            StrategyParam::StopLossPercent => strategy.stop_loss_percent = Some(value),
+            StrategyParam::TakeProfitPercent => strategy.take_profit_percent = Some(value),
+            StrategyParam::PositionSizePercent => strategy.position_size_percent = value,
+            StrategyParam::TrailingStopPercent => {
+                strategy.exit_rules.trailing_stop = Some(TrailingStop::Percent { percent: value })
+            }
+        }
+    }
+}
+
+/// Values tried for one parameter: `min` to `max` inclusive in `step`s
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParamRange {
+    pub param: StrategyParam,
+    pub min: f64,
+    pub max: f64,
+    pub step: f64,
+}
+
+impl ParamRange {
+    /// The range's values; more than `MAX_TRIALS` is rejected before any
+    /// are generated
+    pub fn values(&self) -> Result<Vec<f64>> {
+        if self.step <= 0.0 || self.max <= self.min {
+            return Ok(vec![self.min]);
+        }
+        let count = ((self.max - self.min) / self.step + 1e-9).floor() + 1.0;
+        if !count.is_finite() || count > MAX_TRIALS as f64 {
+            return Err(PipelineError::Config(format!(
+                "Range of {} has {} values (max {}); use a coarser step",
+                self.param.as_str(),
+                count,
+                MAX_TRIALS
+            )));
+        }
+        Ok((0..count as usize)
+            .map(|i| ((self.min + i as f64 * self.step) * 1e9).round() / 1e9)
+            .collect())
+    }
+}
+
+/// How parameter combinations are chosen
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SearchMethod {
+    /// Every combination of the ranges' values
+    #[default]
+    Grid,
+    /// `samples` distinct combinations drawn from the grid; the same seed
+    /// draws the same combinations
+    Random { samples: usize, seed: u64 },
+}
+
+impl SearchMethod {
+    /// Parameter sets to evaluate
+    pub fn combinations(&self, ranges: &[ParamRange]) -> Result<Vec<ParamSet>> {
+        let grids = ranges
+            .iter()
+            .map(ParamRange::values)
+            .collect::<Result<Vec<_>>>()?;
+        let total = grids
+            .iter()
+            .fold(1usize, |acc, values| acc.saturating_mul(values.len()));
+
+        let indices: Vec<Vec<usize>> = match *self {
+            SearchMethod::Grid => {
+                if total > MAX_TRIALS {
+                    return Err(PipelineError::Config(format!(
+                        "Parameter grid has {} combinations (max {}); use random search or coarser steps",
+                        total, MAX_TRIALS
+                    )));
+                }
+                (0..total)
+                    .map(|mut n| {
+                        grids
+                            .iter()
+                            .map(|values| {
+                                let i = n % values.len();
+                                n /= values.len();
+                                i
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+            SearchMethod::Random { samples, seed } => {
+                let target = samples.min(total).min(MAX_TRIALS);
+                let mut rng = SplitMix64(seed);
+                let mut seen = HashSet::new();
+                let mut drawn = Vec::new();
+                while drawn.len() < target {
+                    let pick: Vec<usize> = grids.iter().map(|v| rng.below(v.len())).collect();
+                    if seen.insert(pick.clone()) {
+                        drawn.push(pick);
+                    }
+                }
+                drawn
+            }
+        };
+
+        Ok(indices
+            .into_iter()
+            .map(|pick| {
+                ranges
+                    .iter()
+                    .zip(&grids)
+                    .zip(pick)
+                    .map(|((range, values), i)| (range.param.as_str().to_string(), values[i]))
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+/// Small deterministic generator for reproducible random search (also used
+/// by the Monte Carlo analysis)
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Score maximized by the search
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Objective {
+    #[default]
+    Sharpe,
+    /// CAGR (%) divided by max drawdown (%, floored at 1)
+    CagrOverDrawdown,
+    /// Gross profit / gross loss, capped at 100
+    ProfitFactor,
+}
+
+impl Objective {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Objective::Sharpe => "sharpe",
+            Objective::CagrOverDrawdown => "cagr_over_drawdown",
+            Objective::ProfitFactor => "profit_factor",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "sharpe" => Some(Objective::Sharpe),
+            "cagr_over_drawdown" => Some(Objective::CagrOverDrawdown),
+            "profit_factor" => Some(Objective::ProfitFactor),
+            _ => None,
+        }
+    }
+
+    pub fn score(&self, result: &BacktestResult) -> f64 {
+        let metrics = &result.metrics;
+        let score = match self {
+            Objective::Sharpe => metrics.sharpe_ratio,
+            Objective::CagrOverDrawdown => metrics.risk.cagr / metrics.max_drawdown.max(1.0),
+            Objective::ProfitFactor => metrics.profit_factor.min(MAX_PROFIT_FACTOR),
+        };
+        if score.is_nan() {
+            f64::NEG_INFINITY
+        } else {
+            score
+        }
+    }
+}
+
+/// How the history is divided into fitting and testing segments
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Validation {
+    /// First `in_sample_percent` of the bars to fit, the rest to test
+    Split { in_sample_percent: f64 },
+    /// Fit on `in_sample_bars`, test on the next `out_of_sample_bars`, then
+    /// roll both forward by `out_of_sample_bars`
+    WalkForward {
+        in_sample_bars: usize,
+        out_of_sample_bars: usize,
+    },
+}
+
+impl Default for Validation {
+    fn default() -> Self {
+        Validation::Split {
+            in_sample_percent: 70.0,
+        }
+    }
+}
+
+impl Validation {
+    /// (in-sample, out-of-sample) bar ranges over `bars` bars
+    pub fn windows(&self, bars: usize) -> Result<Vec<(Range<usize>, Range<usize>)>> {
+        let windows: Vec<(Range<usize>, Range<usize>)> = match *self {
+            Validation::Split { in_sample_percent } => {
+                let cut = (bars as f64 * in_sample_percent / 100.0).round() as usize;
+                vec![(0..cut.min(bars), cut.min(bars)..bars)]
+            }
+            Validation::WalkForward {
+                in_sample_bars,
+                out_of_sample_bars,
+            } => {
+                let step = out_of_sample_bars.max(1);
+                (0..)
+                    .map(|w| w * step)
+                    .take_while(|start| start + in_sample_bars + MIN_SEGMENT_BARS <= bars)
+                    .map(|start| {
+                        let split = start + in_sample_bars;
+                        (start..split, split..(split + out_of_sample_bars).min(bars))
+                    })
+                    .collect()
+            }
+        };
+
+        let usable = !windows.is_empty()
+            && windows
+                .iter()
+                .all(|(fit, test)| fit.len() >= MIN_SEGMENT_BARS && test.len() >= MIN_SEGMENT_BARS);
+        if !usable {
+            return Err(PipelineError::Config(format!(
+                "{} bars cannot be split into in-sample and out-of-sample segments of at least {} bars",
+                bars, MIN_SEGMENT_BARS
+            )));
+        }
+        Ok(windows)
+    }
+}
+
+fn default_overfit_threshold() -> f64 {
+    50.0
+}
+
+/// Settings of an optimization job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OptimizationConfig {
+    pub params: Vec<ParamRange>,
+    #[serde(default)]
+    pub search: SearchMethod,
+    #[serde(default)]
+    pub objective: Objective,
+    #[serde(default)]
+    pub validation: Validation,
+    /// In-sample to out-of-sample degradation (%) above which the result is
+    /// flagged as overfit
+    #[serde(default = "default_overfit_threshold")]
+    pub overfit_threshold_percent: f64,
+}
+
+impl Default for OptimizationConfig {
+    fn default() -> Self {
+        Self {
+            params: Vec::new(),
+            search: SearchMethod::default(),
+            objective: Objective::default(),
+            validation: Validation::default(),
+            overfit_threshold_percent: default_overfit_threshold(),
+        }
+    }
+}
+
+/// One parameter set evaluated in sample
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationTrial {
+    pub window: usize,
+    pub params: ParamSet,
+    pub score: f64,
+    pub result: BacktestResult,
+}
+
+/// In-sample winner of one window and its out-of-sample run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationWindow {
+    pub index: usize,
+    pub in_sample_start: NaiveDate,
+    pub in_sample_end: NaiveDate,
+    pub best_params: ParamSet,
+    pub in_sample_score: f64,
+    pub out_of_sample_score: f64,
+    pub out_of_sample: BacktestResult,
+}
+
+/// Outcome of an optimization job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationResult {
+    /// Database id once saved
+    pub job_id: i64,
+    pub strategy_id: i64,
+    pub strategy_name: String,
+    pub symbol: String,
+    pub objective: Objective,
+    pub trials: Vec<OptimizationTrial>,
+    pub windows: Vec<OptimizationWindow>,
+    /// Winner of the most recent window
+    pub best_params: ParamSet,
+    /// Mean of the windows' winning in-sample scores
+    pub in_sample_score: f64,
+    /// Mean of the windows' out-of-sample scores
+    pub out_of_sample_score: f64,
+    pub degradation_percent: f64,
+    pub overfit: bool,
+}
+
+/// Saved optimization job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationJob {
+    pub id: i64,
+    pub strategy_id: i64,
+    pub strategy_name: String,
+    pub symbol: String,
+    pub config: OptimizationConfig,
+    pub best_params: ParamSet,
+    pub in_sample_score: f64,
+    pub out_of_sample_score: f64,
+    pub degradation_percent: f64,
+    pub overfit: bool,
+    pub trial_count: usize,
+    pub created_at: String,
+}
+
+/// Backtest run belonging to an optimization job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationRun {
+    /// "in_sample" or "out_of_sample"
+    pub role: String,
+    pub window: usize,
+    pub params: ParamSet,
+    pub result: BacktestResult,
+}
+
+/// Drop from in-sample to out-of-sample score as a % of the in-sample score
+pub fn degradation_percent(in_sample: f64, out_of_sample: f64) -> f64 {
+    if !in_sample.is_finite() || !out_of_sample.is_finite() || in_sample.abs() < 1e-9 {
+        return 0.0;
+    }
+    (in_sample - out_of_sample) / in_sample.abs() * 100.0
+}
+
+/// `strategy` with `params` applied
+pub fn apply_params(strategy: &Strategy, params: &ParamSet) -> Strategy {
+    let mut strategy = strategy.clone();
+    for (name, &value) in params {
+        if let Some(param) = StrategyParam::from_str(name) {
+            param.apply(&mut strategy, value);
+        }
+    }
+    strategy
+}
+
+/// Prices in `range` and the indicators dated within them
+fn segment(
+    prices: &[DailyPrice],
+    indicators: &[TechnicalIndicator],
+    range: Range<usize>,
+) -> (Vec<DailyPrice>, Vec<TechnicalIndicator>) {
+    let prices = prices[range].to_vec();
+    let (first, last) = (prices[0].date, prices[prices.len() - 1].date);
+    let indicators = indicators
+        .iter()
+        .filter(|ind| ind.date >= first && ind.date <= last)
+        .cloned()
+        .collect();
+    (prices, indicators)
+}
+
+/// Search `config.params` for `strategy` on one symbol and validate the
+/// winners out of sample
+pub fn optimize(
+    engine: &BacktestEngine,
+    strategy: &Strategy,
+    symbol: &str,
+    prices: &[DailyPrice],
+    indicators: &[TechnicalIndicator],
+    config: &OptimizationConfig,
+) -> Result<OptimizationResult> {
+    if config.params.is_empty() {
+        return Err(PipelineError::Config(
+            "No parameters to optimize".to_string(),
+        ));
+    }
+    let combinations = config.search.combinations(&config.params)?;
+    let objective = config.objective;
+    // Windows are cut by position, so bars must be in date order (as `run` sorts them)
+    let mut sorted_prices = prices.to_vec();
+    sorted_prices.sort_by_key(|p| p.date);
+    let prices = sorted_prices.as_slice();
+    // Generate signal indicators once rather than in every trial
+    let indicators =
+        &*with_signal_indicators(engine.signal_engine(), strategy, symbol, prices, indicators);
+
+    let mut trials = Vec::new();
+    let mut windows = Vec::new();
+    for (index, (fit, test)) in config
+        .validation
+        .windows(prices.len())?
+        .into_iter()
+        .enumerate()
+    {
+        let (fit_prices, fit_indicators) = segment(prices, indicators, fit);
+
+        let first_trial = trials.len();
+        for params in &combinations {
+            let mut result = engine.run(
+                &apply_params(strategy, params),
+                symbol,
+                &fit_prices,
+                &fit_indicators,
+            );
+            // A search can run thousands of trials; only their summaries are kept
+            result.equity_curve = Vec::new();
+            trials.push(OptimizationTrial {
+                window: index,
+                params: params.clone(),
+                score: objective.score(&result),
+                result,
+            });
+        }
+        // Ties keep the earliest combination
+        let best = trials[first_trial..]
+            .iter()
+            .reduce(|best, trial| {
+                if trial.score > best.score {
+                    trial
+                } else {
+                    best
+                }
+            })
+            .expect("at least one combination");
+
+        let (test_prices, test_indicators) = segment(prices, indicators, test);
+        let out_of_sample = engine.run(
+            &apply_params(strategy, &best.params),
+            symbol,
+            &test_prices,
+            &test_indicators,
+        );
+        windows.push(OptimizationWindow {
+            index,
+            in_sample_start: fit_prices[0].date,
+            in_sample_end: fit_prices[fit_prices.len() - 1].date,
+            best_params: best.params.clone(),
+            in_sample_score: best.score,
+            out_of_sample_score: objective.score(&out_of_sample),
+            out_of_sample,
+        });
+    }
+
+    let mean = |score: fn(&OptimizationWindow) -> f64| {
+        windows.iter().map(score).sum::<f64>() / windows.len() as f64
+    };
+    let in_sample_score = mean(|w| w.in_sample_score);
+    let out_of_sample_score = mean(|w| w.out_of_sample_score);
+    let degradation = degradation_percent(in_sample_score, out_of_sample_score);
+
+    Ok(OptimizationResult {
+        job_id: 0,
+        strategy_id: strategy.id,
+        strategy_name: strategy.name.clone(),
+        symbol: symbol.to_string(),
+        objective,
+        best_params: windows
+            .last()
+            .map(|w| w.best_params.clone())
+            .unwrap_or_default(),
+        trials,
+        windows,
+        in_sample_score,
+        out_of_sample_score,
+        degradation_percent: degradation,
+        overfit: degradation > config.overfit_threshold_percent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ExitRules, PositionSizing, StrategyConditionType, StrategyDirection};
+
+    fn range(param: StrategyParam, min: f64, max: f64, step: f64) -> ParamRange {
+        ParamRange {
+            param,
+            min,
+            max,
+            step,
+        }
+    }
+
+    #[test]
+    fn test_grid_and_random_combinations() {
+        let ranges = vec![
+            range(StrategyParam::EntryThreshold, 20.0, 40.0, 10.0),
+            range(StrategyParam::StopLossPercent, 0.1, 0.3, 0.1),
+        ];
+        assert_eq!(ranges[1].values().unwrap(), vec![0.1, 0.2, 0.3]);
+
+        let grid = SearchMethod::Grid.combinations(&ranges).unwrap();
+        assert_eq!(grid.len(), 9);
+        assert_eq!(grid[0]["entry_threshold"], 20.0);
+        assert_eq!(grid[8]["stop_loss_percent"], 0.3);
+
+        let random = SearchMethod::Random {
+            samples: 5,
+            seed: 7,
+        };
+        let drawn = random.combinations(&ranges).unwrap();
+        assert_eq!(drawn.len(), 5);
+        assert_eq!(drawn, random.combinations(&ranges).unwrap());
+        assert!(drawn.iter().all(|p| grid.contains(p)));
+
+        // More samples than combinations: the whole grid, once
+        let all = SearchMethod::Random {
+            samples: 50,
+            seed: 1,
+        }
+        .combinations(&ranges)
+        .unwrap();
+        assert_eq!(all.len(), 9);
+
+        let huge = vec![range(StrategyParam::EntryThreshold, 0.0, 10_000.0, 1.0)];
+        assert!(SearchMethod::Grid.combinations(&huge).is_err());
+
+        // A tiny step is rejected without building its values
+        let tiny = range(StrategyParam::EntryThreshold, 0.0, 100.0, 1e-12);
+        assert!(tiny.values().is_err());
+        let random = SearchMethod::Random {
+            samples: 10,
+            seed: 1,
+        };
+        assert!(random.combinations(&[tiny]).is_err());
+    }
+
+    #[test]
+    fn test_walk_forward_windows_and_degradation() {
+        let walk = Validation::WalkForward {
+            in_sample_bars: 100,
+            out_of_sample_bars: 50,
+        };
+        let windows = walk.windows(260).unwrap();
+        // A fourth window would leave only 10 bars to test on
+        assert_eq!(
+            windows,
+            vec![
+                (0..100, 100..150),
+                (50..150, 150..200),
+                (100..200, 200..250)
+            ]
+        );
+        assert!(Validation::default().windows(30).is_err());
+
+        assert_eq!(degradation_percent(2.0, 0.5), 75.0);
+        assert_eq!(degradation_percent(-1.0, -2.0), 100.0);
+        assert_eq!(degradation_percent(0.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn test_optimize_picks_in_sample_winner() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        // Rises for 70 bars, then falls: the larger position wins in sample
+        // and loses more out of sample
+        let closes: Vec<f64> = (0..100)
+            .map(|i| {
+                if i < 70 {
+                    100.0 + i as f64
+                } else {
+                    170.0 - 2.0 * (i - 70) as f64
+                }
+            })
+            .collect();
+        let prices: Vec<DailyPrice> = closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| DailyPrice {
+                symbol: "TEST".to_string(),
+                date: start + chrono::Duration::days(i as i64),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 1000,
+                source: "test".to_string(),
+            })
+            .collect();
+        let indicators: Vec<TechnicalIndicator> = prices
+            .iter()
+            .map(|p| TechnicalIndicator {
+                symbol: "TEST".to_string(),
+                date: p.date,
+                indicator_name: "RSI_14".to_string(),
+                value: 20.0,
+            })
+            .collect();
+        let strategy = Strategy {
+            id: 3,
+            name: "rsi".to_string(),
+            description: None,
+            entry_condition: StrategyConditionType::RsiOversold,
+            entry_threshold: 30.0,
+            exit_condition: StrategyConditionType::RsiOverbought,
+            exit_threshold: 70.0,
+            stop_loss_percent: None,
+            take_profit_percent: None,
+            position_size_percent: 100.0,
+            entry_formula: None,
+            exit_formula: None,
+            rs_benchmark: None,
+            allowed_regimes: vec![],
+            entry_rule: None,
+            exit_rule: None,
+            direction: StrategyDirection::LongOnly,
+            exit_rules: ExitRules::default(),
+            position_sizing: PositionSizing::default(),
+            created_at: String::new(),
+        };
+        let config = OptimizationConfig {
+            params: vec![range(StrategyParam::PositionSizePercent, 50.0, 100.0, 50.0)],
+            objective: Objective::CagrOverDrawdown,
+            ..OptimizationConfig::default()
+        };
+
+        let result = optimize(
+            &BacktestEngine::default(),
+            &strategy,
+            "TEST",
+            &prices,
+            &indicators,
+            &config,
+        )
+        .unwrap();
+
+        assert_eq!(result.trials.len(), 2);
+        assert_eq!(result.windows.len(), 1);
+        assert_eq!(result.best_params["position_size_percent"], 100.0);
+        assert_eq!(result.windows[0].in_sample_end, prices[69].date);
+        // Profitable in sample, losing out of sample
+        assert!(result.in_sample_score > 0.0);
+        assert!(result.out_of_sample_score < 0.0);
+        assert!(result.overfit);
+
+        // Bars out of date order are windowed the same way
+        let mut shuffled = prices.clone();
+        shuffled.reverse();
+        let unsorted = optimize(
+            &BacktestEngine::default(),
+            &strategy,
+            "TEST",
+            &shuffled,
+            &indicators,
+            &config,
+        )
+        .unwrap();
+        assert_eq!(unsorted.windows[0].in_sample_end, prices[69].date);
+        assert_eq!(unsorted.in_sample_score, result.in_sample_score);
+    }
+}
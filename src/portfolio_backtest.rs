@@ -0,0 +1,628 @@
+//! Portfolio Backtesting
+//!
+//! Runs one strategy across several symbols with a shared cash pool:
+//! - Exits are processed first each day, then entries
+//! - At most `max_positions` positions are open at once; when more symbols
+//!   signal than there are free slots, `ranking` decides which are taken
+//! - Each new position is sized by the strategy's `position_sizing` model
+//!   (by default `position_size_percent` of current portfolio equity), with
+//!   equal-risk sizing spreading its budget over `max_positions` slots, and
+//!   capped by the buying power left after the margin held by open positions
+//! - Equity is marked to market daily using each symbol's latest close
+
+use crate::backtest::{
+    bar_open, symbol_trade_stats, with_signal_indicators, BacktestEngine, FillTiming, OpenPosition,
+};
+use crate::execution::FillAt;
+use crate::metrics;
+use crate::models::{
+    BacktestResult, BacktestTrade, DailyPrice, EquityPoint, ExecutionCosts, Strategy,
+    TechnicalIndicator, TradeDirection,
+};
+use crate::regime::{RegimeConfig, RegimeSeries};
+use crate::rules::RuleContext;
+use crate::sizing::SizingContext;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Prices and indicators of one symbol in the universe
+#[derive(Debug, Clone)]
+pub struct SymbolData {
+    pub symbol: String,
+    pub prices: Vec<DailyPrice>,
+    pub indicators: Vec<TechnicalIndicator>,
+}
+
+/// Order in which same-day entry signals are filled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "by", rename_all = "snake_case")]
+pub enum PortfolioRanking {
+    /// Highest return over `lookback` bars first
+    Momentum { lookback: usize },
+    /// Highest indicator value first (lowest first when `ascending`)
+    Indicator {
+        name: String,
+        #[serde(default)]
+        ascending: bool,
+    },
+    /// Alphabetical by symbol
+    Symbol,
+}
+
+impl Default for PortfolioRanking {
+    fn default() -> Self {
+        PortfolioRanking::Momentum { lookback: 20 }
+    }
+}
+
+impl PortfolioRanking {
+    /// Score of a candidate on the context's bar; higher is filled first
+    fn score(&self, ctx: &RuleContext) -> Option<f64> {
+        match self {
+            PortfolioRanking::Momentum { lookback } => {
+                let now = ctx.bars[ctx.index].close;
+                let then = ctx.bars.get(ctx.index.checked_sub(*lookback)?)?.close;
+                (then > 0.0).then(|| now / then - 1.0)
+            }
+            PortfolioRanking::Indicator { name, ascending } => {
+                let value = *ctx.indicators_at(0)?.get(name)?;
+                Some(if *ascending { -value } else { value })
+            }
+            PortfolioRanking::Symbol => Some(0.0),
+        }
+    }
+}
+
+/// Portfolio backtest settings
+#[derive(Debug, Clone)]
+pub struct PortfolioConfig {
+    /// Maximum concurrent positions
+    pub max_positions: usize,
+    pub ranking: PortfolioRanking,
+}
+
+impl Default for PortfolioConfig {
+    fn default() -> Self {
+        Self {
+            max_positions: 5,
+            ranking: PortfolioRanking::default(),
+        }
+    }
+}
+
+struct Series<'a> {
+    symbol: &'a str,
+    bars: Vec<DailyPrice>,
+    indicators: HashMap<NaiveDate, HashMap<String, f64>>,
+    index_by_date: HashMap<NaiveDate, usize>,
+    regimes: RegimeSeries,
+}
+
+impl<'a> Series<'a> {
+    fn context(&self, index: usize) -> RuleContext<'_> {
+        RuleContext {
+            bars: &self.bars,
+            index,
+            indicators: &self.indicators,
+        }
+    }
+}
+
+/// An entry signal, filled or queued for the symbol's next bar
+struct Candidate {
+    series: usize,
+    direction: TradeDirection,
+    reason: String,
+    score: f64,
+    /// ATR_14 on the signal bar, for volatility-based sizing
+    atr: Option<f64>,
+}
+
+/// Inputs shared by every step of a portfolio run
+struct Run<'a> {
+    engine: &'a BacktestEngine,
+    strategy: &'a Strategy,
+    config: &'a PortfolioConfig,
+    series: Vec<Series<'a>>,
+}
+
+/// Shared account state
+struct Book {
+    cash: f64,
+    /// Keyed by index into `Run::series`
+    positions: BTreeMap<usize, OpenPosition>,
+    last_close: Vec<Option<f64>>,
+    trades: Vec<BacktestTrade>,
+    costs: ExecutionCosts,
+    /// Exit orders to fill on each symbol's next bar
+    pending_exits: BTreeMap<usize, String>,
+    /// Entry orders to fill on each symbol's next bar
+    pending_entries: Vec<Candidate>,
+}
+
+impl Book {
+    /// Signed market value of each open position at its latest close
+    fn position_values(&self) -> Vec<f64> {
+        self.positions
+            .iter()
+            .map(|(&s, pos)| pos.market_value(self.last_close[s].unwrap_or(pos.entry_price)))
+            .collect()
+    }
+
+    fn close(&mut self, run: &Run, s: usize, i: usize, at: FillAt, reason: String) {
+        if let Some(pos) = self.positions.remove(&s) {
+            let data = &run.series[s];
+            let (cash_delta, trade) = run.engine.close_position(
+                data.symbol,
+                pos,
+                &data.bars[..=i],
+                at,
+                reason,
+                &mut self.costs,
+            );
+            self.cash += cash_delta;
+            self.trades.push(trade);
+        }
+    }
+
+    /// Run the stop check of the position in `s` on bar `i`
+    fn apply_stops(&mut self, run: &Run, s: usize, i: usize) {
+        let Some(mut pos) = self.positions.remove(&s) else {
+            return;
+        };
+        let data = &run.series[s];
+        let bars = &data.bars[..=i];
+        let atr = data
+            .context(i)
+            .indicators_at(0)
+            .and_then(|today| today.get("ATR_14").copied());
+        let exits = run.engine.check_stops(run.strategy, bars, atr, &mut pos);
+
+        let mut remaining = Some(pos);
+        for exit in exits {
+            let Some(open) = remaining.take() else {
+                break;
+            };
+            let (cash_delta, trade, rest) =
+                run.engine
+                    .stop_out(data.symbol, open, bars, exit, &mut self.costs);
+            self.cash += cash_delta;
+            self.trades.push(trade);
+            remaining = rest;
+        }
+        match remaining {
+            Some(pos) => {
+                self.positions.insert(s, pos);
+            }
+            None => {
+                self.pending_exits.remove(&s);
+            }
+        }
+    }
+
+    /// Fill entries best-ranked first into the free slots
+    fn enter(
+        &mut self,
+        run: &Run,
+        today: &[(usize, usize)],
+        mut candidates: Vec<Candidate>,
+        fill_at: fn(&DailyPrice) -> FillAt,
+    ) {
+        candidates.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| run.series[a.series].symbol.cmp(run.series[b.series].symbol))
+        });
+
+        let bt = run.engine.config();
+        for candidate in candidates {
+            let s = candidate.series;
+            if self.positions.len() >= run.config.max_positions || self.positions.contains_key(&s) {
+                continue;
+            }
+            let Some(&(_, i)) = today.iter().find(|&&(t, _)| t == s) else {
+                continue;
+            };
+
+            let values = self.position_values();
+            let equity = self.cash + values.iter().sum::<f64>();
+            let margin_used: f64 = self
+                .positions
+                .values()
+                .zip(&values)
+                .map(|(pos, value)| match pos.direction {
+                    TradeDirection::Long => *value,
+                    TradeDirection::Short => value.abs() * bt.short_margin_percent / 100.0,
+                })
+                .sum();
+            let bars = &run.series[s].bars[..=i];
+            let at = fill_at(&bars[i]);
+            let trade_returns: Vec<f64> =
+                self.trades.iter().filter_map(|t| t.profit_loss_percent).collect();
+            let sizing = SizingContext {
+                equity,
+                capital: equity,
+                percent: run.strategy.position_size_percent,
+                price: at.price(),
+                atr: candidate.atr,
+                stop_loss_percent: run.strategy.stop_loss_percent,
+                slots: run.config.max_positions,
+                margin_percent: match candidate.direction {
+                    TradeDirection::Long => 100.0,
+                    TradeDirection::Short => bt.short_margin_percent.max(1.0),
+                },
+                trade_returns: &trade_returns,
+            };
+            let Some(allocation) = run
+                .strategy
+                .position_sizing
+                .allocation(&sizing)
+                .map(|a| a.min(equity - margin_used))
+                .filter(|a| *a > 0.0)
+            else {
+                continue;
+            };
+
+            if let Some((cash_delta, pos)) = run.engine.open_position(
+                bars,
+                at,
+                candidate.direction,
+                candidate.reason,
+                allocation,
+                &mut self.costs,
+            ) {
+                self.cash += cash_delta;
+                self.positions.insert(s, pos);
+            }
+        }
+    }
+
+    /// Fill orders queued on earlier bars for the symbols trading today:
+    /// exits first, then entries
+    fn fill_pending(&mut self, run: &Run, today: &[(usize, usize)], fill_at: fn(&DailyPrice) -> FillAt) {
+        for &(s, i) in today {
+            if let Some(reason) = self.pending_exits.remove(&s) {
+                self.close(run, s, i, fill_at(&run.series[s].bars[i]), reason);
+            }
+        }
+        let (ready, waiting): (Vec<Candidate>, Vec<Candidate>) =
+            std::mem::take(&mut self.pending_entries)
+                .into_iter()
+                .partition(|c| today.iter().any(|&(s, _)| s == c.series));
+        self.pending_entries = waiting;
+        self.enter(run, today, ready, fill_at);
+    }
+}
+
+/// Run `strategy` over every symbol in `universe` with one shared account.
+/// The combined result's `symbol` lists the universe, comma-separated.
+pub fn run_portfolio_backtest(
+    engine: &BacktestEngine,
+    strategy: &Strategy,
+    universe: &[SymbolData],
+    config: &PortfolioConfig,
+) -> BacktestResult {
+    let bt = engine.config();
+    let series: Vec<Series> = universe
+        .iter()
+        .map(|data| {
+            let mut bars = data.prices.clone();
+            bars.sort_by_key(|p| p.date);
+            let regimes = if strategy.allowed_regimes.is_empty() {
+                RegimeSeries::default()
+            } else {
+                RegimeSeries::from_prices(&bars, &RegimeConfig::default())
+            };
+            Series {
+                symbol: &data.symbol,
+                index_by_date: bars.iter().enumerate().map(|(i, b)| (b.date, i)).collect(),
+                indicators: engine.build_indicator_map(&with_signal_indicators(
+                    engine.signal_engine(),
+                    strategy,
+                    &data.symbol,
+                    &bars,
+                    &data.indicators,
+                )),
+                bars,
+                regimes,
+            }
+        })
+        .collect();
+
+    let calendar: BTreeSet<NaiveDate> = series
+        .iter()
+        .flat_map(|s| s.bars.iter().map(|b| b.date))
+        .collect();
+
+    let timing = bt.fill_timing;
+    let run = Run {
+        engine,
+        strategy,
+        config,
+        series,
+    };
+    let mut book = Book {
+        cash: bt.initial_capital,
+        positions: BTreeMap::new(),
+        last_close: vec![None; run.series.len()],
+        trades: Vec::new(),
+        costs: ExecutionCosts::default(),
+        pending_exits: BTreeMap::new(),
+        pending_entries: Vec::new(),
+    };
+    let mut equity_curve: Vec<EquityPoint> = Vec::new();
+    // Buy-and-hold splits the capital equally, held as cash until a symbol's first bar
+    let hold_share = bt.initial_capital / run.series.len().max(1) as f64;
+
+    for &date in &calendar {
+        // (series, bar index) of symbols trading today
+        let today: Vec<(usize, usize)> = run
+            .series
+            .iter()
+            .enumerate()
+            .filter_map(|(s, data)| data.index_by_date.get(&date).map(|&i| (s, i)))
+            .collect();
+
+        if timing == FillTiming::NextOpen {
+            book.fill_pending(&run, &today, |bar| FillAt::Open(bar_open(bar)));
+        }
+        for &(s, i) in &today {
+            book.last_close[s] = Some(run.series[s].bars[i].close);
+        }
+
+        // Resting stop and target orders trade intrabar
+        for &(s, i) in &today {
+            book.apply_stops(&run, s, i);
+        }
+
+        if timing == FillTiming::NextClose {
+            book.fill_pending(&run, &today, |bar| FillAt::Close(bar.close));
+        }
+
+        // Signals need the bar's indicators
+        let tradable = |s: usize, i: usize| run.series[s].context(i).indicators_at(0).is_some();
+
+        // Exit signals
+        for &(s, i) in &today {
+            if !tradable(s, i) || book.pending_exits.contains_key(&s) {
+                continue;
+            }
+            let ctx = run.series[s].context(i);
+            let exit = book
+                .positions
+                .get(&s)
+                .and_then(|pos| engine.check_exit_condition(strategy, &ctx, pos));
+            if let Some(reason) = exit {
+                if timing == FillTiming::SameClose {
+                    book.close(&run, s, i, FillAt::Close(run.series[s].bars[i].close), reason);
+                } else {
+                    book.pending_exits.insert(s, reason);
+                }
+            }
+        }
+
+        // Entry signals on symbols that are flat or about to be
+        let candidates: Vec<Candidate> = today
+            .iter()
+            .filter(|&&(s, i)| {
+                (!book.positions.contains_key(&s) || book.pending_exits.contains_key(&s))
+                    && !book.pending_entries.iter().any(|c| c.series == s)
+                    && tradable(s, i)
+                    && run.series[s]
+                        .regimes
+                        .allows(date, &strategy.allowed_regimes)
+            })
+            .filter_map(|&(s, i)| {
+                let ctx = run.series[s].context(i);
+                let (direction, reason) = engine.check_open_signal(strategy, &ctx)?;
+                let score = config.ranking.score(&ctx).unwrap_or(f64::NEG_INFINITY);
+                Some(Candidate {
+                    series: s,
+                    direction,
+                    reason,
+                    score,
+                    atr: ctx.indicators_at(0).and_then(|day| day.get("ATR_14").copied()),
+                })
+            })
+            .collect();
+        if timing == FillTiming::SameClose {
+            book.enter(&run, &today, candidates, |bar| FillAt::Close(bar.close));
+        } else {
+            book.pending_entries.extend(candidates);
+        }
+
+        let values = book.position_values();
+        equity_curve.push(EquityPoint {
+            date,
+            equity: book.cash + values.iter().sum::<f64>(),
+            cash: book.cash,
+            exposure: values.iter().map(|v| v.abs()).sum(),
+            open_positions: book.positions.len(),
+            drawdown: 0.0,
+            buy_and_hold: Some(
+                run.series
+                    .iter()
+                    .zip(&book.last_close)
+                    .map(|(data, close)| match (data.bars.first(), close) {
+                        (Some(first), Some(close)) if first.close > 0.0 => {
+                            hold_share * close / first.close
+                        }
+                        _ => hold_share,
+                    })
+                    .sum(),
+            ),
+        });
+    }
+
+    // Close remaining positions at each symbol's last bar; unfilled orders lapse
+    for s in book.positions.keys().copied().collect::<Vec<_>>() {
+        let last = run.series[s].bars.len() - 1;
+        let at = FillAt::Close(run.series[s].bars[last].close);
+        book.close(&run, s, last, at, "end_of_data".to_string());
+    }
+    let Book {
+        cash,
+        mut trades,
+        costs,
+        ..
+    } = book;
+    trades.sort_by(|a, b| {
+        a.entry_date
+            .cmp(&b.entry_date)
+            .then_with(|| a.symbol.cmp(&b.symbol))
+    });
+
+    metrics::mark_drawdowns(&mut equity_curve, bt.initial_capital);
+    let metrics = engine.calculate_metrics(&trades, &equity_curve, costs);
+    let epoch = || NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+
+    BacktestResult {
+        id: 0,
+        strategy_id: strategy.id,
+        strategy_name: strategy.name.clone(),
+        symbol: universe
+            .iter()
+            .map(|d| d.symbol.as_str())
+            .collect::<Vec<_>>()
+            .join(","),
+        start_date: calendar.first().copied().unwrap_or_else(epoch),
+        end_date: calendar.last().copied().unwrap_or_else(epoch),
+        initial_capital: bt.initial_capital,
+        final_capital: cash,
+        metrics,
+        symbol_stats: symbol_trade_stats(&trades),
+        trades,
+        equity_curve,
+        created_at: String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::BacktestConfig;
+    use crate::models::{
+        CompareOp, ExitRules, PositionSizing, PriceField, RuleNode, RuleOperand, StrategyConditionType,
+        StrategyDirection,
+    };
+
+    fn symbol(name: &str, closes: &[f64]) -> SymbolData {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let prices: Vec<DailyPrice> = closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| DailyPrice {
+                symbol: name.to_string(),
+                date: start + chrono::Duration::days(i as i64),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: 1000,
+                source: "test".to_string(),
+            })
+            .collect();
+        let indicators = prices
+            .iter()
+            .map(|p| TechnicalIndicator {
+                symbol: name.to_string(),
+                date: p.date,
+                indicator_name: "RSI_14".to_string(),
+                value: 50.0,
+            })
+            .collect();
+        SymbolData {
+            symbol: name.to_string(),
+            prices,
+            indicators,
+        }
+    }
+
+    /// Enter when the close rose on the day; never exit
+    fn strategy(position_size_percent: f64) -> Strategy {
+        let close = RuleOperand::price(PriceField::Close);
+        Strategy {
+            id: 1,
+            name: "rising".to_string(),
+            description: None,
+            entry_condition: StrategyConditionType::RsiOversold,
+            entry_threshold: 30.0,
+            exit_condition: StrategyConditionType::RsiOverbought,
+            exit_threshold: 70.0,
+            stop_loss_percent: None,
+            take_profit_percent: None,
+            position_size_percent,
+            entry_formula: None,
+            exit_formula: None,
+            rs_benchmark: None,
+            allowed_regimes: vec![],
+            entry_rule: Some(RuleNode::compare(
+                close.clone(),
+                CompareOp::Gt,
+                close.ago(1),
+            )),
+            exit_rule: Some(RuleNode::any(vec![])),
+            direction: StrategyDirection::LongOnly,
+            exit_rules: ExitRules::default(),
+            position_sizing: PositionSizing::default(),
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_ranking_and_slot_limit() {
+        let universe = vec![
+            symbol("AAA", &[100.0, 101.0, 102.0]),
+            symbol("BBB", &[100.0, 110.0, 110.0]),
+            symbol("CCC", &[100.0, 105.0, 120.0]),
+        ];
+        let config = PortfolioConfig {
+            max_positions: 2,
+            ranking: PortfolioRanking::Momentum { lookback: 1 },
+        };
+        let engine = BacktestEngine::new(BacktestConfig::default());
+        let out = run_portfolio_backtest(&engine, &strategy(50.0), &universe, &config);
+
+        // Day 1: all three rise; BBB (+10%) and CCC (+5%) take the two slots
+        let symbols: Vec<&str> = out
+            .trades
+            .iter()
+            .map(|t| t.symbol.as_str())
+            .collect();
+        assert_eq!(symbols, vec!["BBB", "CCC"]);
+        assert_eq!(out.equity_curve.len(), 3);
+        assert_eq!(out.equity_curve[1].open_positions, 2);
+        assert!(out.equity_curve[1].cash.abs() < 1e-9);
+        // Equal thirds held from day 0: AAA +2%, BBB +10%, CCC +20%
+        let hold = out.equity_curve[2].buy_and_hold.unwrap();
+        assert!((hold - 10000.0 / 3.0 * (1.02 + 1.10 + 1.20)).abs() < 1e-6);
+        assert_eq!(out.equity_curve[2].drawdown, 0.0);
+
+        // CCC: 5000 / 105 shares gaining 15 each; BBB flat after entry
+        let expected = 10000.0 + 5000.0 / 105.0 * 15.0;
+        assert!((out.final_capital - expected).abs() < 1e-6);
+        assert!((out.equity_curve[2].equity - expected).abs() < 1e-6);
+        assert_eq!(out.symbol_stats.len(), 2);
+        assert_eq!(out.symbol, "AAA,BBB,CCC");
+    }
+
+    #[test]
+    fn test_allocation_capped_by_cash() {
+        let universe = vec![symbol("AAA", &[100.0, 101.0]), symbol("BBB", &[50.0, 52.0])];
+        let engine = BacktestEngine::new(BacktestConfig::default());
+        let config = PortfolioConfig {
+            ranking: PortfolioRanking::Momentum { lookback: 1 },
+            ..PortfolioConfig::default()
+        };
+        let out = run_portfolio_backtest(&engine, &strategy(80.0), &universe, &config);
+
+        // BBB (+4%) gets 80% of equity; AAA only the remaining 20%
+        let value = |sym: &str| {
+            let t = out.trades.iter().find(|t| t.symbol == sym).unwrap();
+            t.shares * t.entry_price
+        };
+        assert!((value("BBB") - 8000.0).abs() < 1e-6);
+        assert!((value("AAA") - 2000.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,363 @@
+//! Volume, gap and range anomaly signals
+//!
+//! Detected straight from OHLCV bars:
+//! - Volume spike: volume at/above `volume_spike_ratio` times its trailing
+//!   average, in the direction of the day's close-to-close move
+//! - Volume breakout/breakdown: close beyond the prior `breakout_lookback`
+//!   bars' high/low on at least `breakout_volume_ratio` times average volume
+//! - Gaps: open beyond the prior bar's high/low by `min_gap_percent`, plus a
+//!   gap fill when price trades back to the prior close within
+//!   `gap_fill_max_bars` (the gap day included)
+//! - NR7/WR7: narrowest/widest high-low range of the last `range_lookback` bars
+
+use crate::models::{DailyPrice, Signal, SignalDirection, SignalStatus, SignalType};
+
+/// Prefix of the per-date price action votes fed into confluence
+/// (e.g. "PA_VOLUME_SPIKE" = +strength for bullish, -strength for bearish)
+pub const PRICE_ACTION_VOTE_PREFIX: &str = "PA_";
+
+/// Price action detection thresholds
+#[derive(Debug, Clone)]
+pub struct PriceActionConfig {
+    /// Bars in the trailing volume average
+    pub volume_avg_period: usize,
+    /// Volume / average ratio that counts as a spike
+    pub volume_spike_ratio: f64,
+    /// Bars whose high/low a breakout must clear
+    pub breakout_lookback: usize,
+    /// Volume / average ratio required to confirm a breakout
+    pub breakout_volume_ratio: f64,
+    /// Minimum gap beyond the prior bar's range (%)
+    pub min_gap_percent: f64,
+    /// Bars after a gap within which a fill is reported
+    pub gap_fill_max_bars: usize,
+    /// Bars compared for NR7/WR7
+    pub range_lookback: usize,
+}
+
+impl Default for PriceActionConfig {
+    fn default() -> Self {
+        Self {
+            volume_avg_period: 20,
+            volume_spike_ratio: 2.0,
+            breakout_lookback: 20,
+            breakout_volume_ratio: 1.5,
+            min_gap_percent: 1.0,
+            gap_fill_max_bars: 20,
+            range_lookback: 7,
+        }
+    }
+}
+
+fn signal(
+    symbol: &str,
+    bar: &DailyPrice,
+    signal_type: SignalType,
+    direction: SignalDirection,
+    strength: f64,
+    triggered_by: &str,
+    trigger_value: f64,
+) -> Signal {
+    Signal {
+        id: 0,
+        symbol: symbol.to_string(),
+        signal_type,
+        direction,
+        strength: strength.clamp(0.0, 1.0),
+        price_at_signal: bar.close,
+        triggered_by: triggered_by.to_string(),
+        trigger_value,
+        timestamp: bar.date,
+        created_at: String::new(),
+        acknowledged: false,
+        status: SignalStatus::New,
+        end_date: None,
+    }
+}
+
+fn move_direction(from: f64, to: f64) -> SignalDirection {
+    if to > from {
+        SignalDirection::Bullish
+    } else if to < from {
+        SignalDirection::Bearish
+    } else {
+        SignalDirection::Neutral
+    }
+}
+
+/// Volume spikes and breakouts/breakdowns confirmed by volume
+pub fn detect_volume_signals(
+    symbol: &str,
+    prices: &[DailyPrice],
+    config: &PriceActionConfig,
+) -> Vec<Signal> {
+    let period = config.volume_avg_period.max(1);
+    let start = period.max(config.breakout_lookback);
+    let mut signals = Vec::new();
+
+    for i in start..prices.len() {
+        let bar = &prices[i];
+        let avg = prices[i - period..i]
+            .iter()
+            .map(|p| p.volume as f64)
+            .sum::<f64>()
+            / period as f64;
+        if avg <= 0.0 {
+            continue;
+        }
+        let ratio = bar.volume as f64 / avg;
+
+        if ratio >= config.volume_spike_ratio {
+            signals.push(signal(
+                symbol,
+                bar,
+                SignalType::VolumeSpike,
+                move_direction(prices[i - 1].close, bar.close),
+                ratio / (2.0 * config.volume_spike_ratio),
+                "VOLUME",
+                ratio,
+            ));
+        }
+
+        if config.breakout_lookback == 0 || ratio < config.breakout_volume_ratio {
+            continue;
+        }
+        let window = &prices[i - config.breakout_lookback..i];
+        let high = window.iter().map(|p| p.high).fold(f64::MIN, f64::max);
+        let low = window.iter().map(|p| p.low).fold(f64::MAX, f64::min);
+        let strength = ratio / (2.0 * config.breakout_volume_ratio);
+        if bar.close > high {
+            signals.push(signal(
+                symbol,
+                bar,
+                SignalType::VolumeBreakout,
+                SignalDirection::Bullish,
+                strength,
+                "BREAKOUT",
+                high,
+            ));
+        } else if bar.close < low {
+            signals.push(signal(
+                symbol,
+                bar,
+                SignalType::VolumeBreakdown,
+                SignalDirection::Bearish,
+                strength,
+                "BREAKOUT",
+                low,
+            ));
+        }
+    }
+
+    signals
+}
+
+/// Opening gaps and their fills
+pub fn detect_gap_signals(
+    symbol: &str,
+    prices: &[DailyPrice],
+    config: &PriceActionConfig,
+) -> Vec<Signal> {
+    let mut signals = Vec::new();
+
+    for i in 1..prices.len() {
+        let (prev, bar) = (&prices[i - 1], &prices[i]);
+        if prev.close <= 0.0 || prev.high <= 0.0 || prev.low <= 0.0 {
+            continue;
+        }
+        let up = (bar.open / prev.high - 1.0) * 100.0;
+        let down = (1.0 - bar.open / prev.low) * 100.0;
+        let (signal_type, direction, gap) = if up >= config.min_gap_percent {
+            (SignalType::GapUp, SignalDirection::Bullish, up)
+        } else if down >= config.min_gap_percent {
+            (SignalType::GapDown, SignalDirection::Bearish, down)
+        } else {
+            continue;
+        };
+        // A 5% gap is full strength
+        signals.push(signal(
+            symbol,
+            bar,
+            signal_type,
+            direction,
+            gap / 5.0,
+            "GAP",
+            gap,
+        ));
+
+        // Fill: price trades back to the close before the gap
+        let last = (i + config.gap_fill_max_bars).min(prices.len() - 1);
+        let filled = (i..=last).find(|&j| match direction {
+            SignalDirection::Bullish => prices[j].low <= prev.close,
+            _ => prices[j].high >= prev.close,
+        });
+        if let Some(j) = filled {
+            let fill_direction = match direction {
+                SignalDirection::Bullish => SignalDirection::Bearish,
+                _ => SignalDirection::Bullish,
+            };
+            signals.push(signal(
+                symbol,
+                &prices[j],
+                SignalType::GapFill,
+                fill_direction,
+                gap / 5.0,
+                "GAP",
+                (j - i) as f64,
+            ));
+        }
+    }
+
+    signals
+}
+
+/// NR7 (range contraction) and WR7 (range expansion) bars
+pub fn detect_range_signals(
+    symbol: &str,
+    prices: &[DailyPrice],
+    config: &PriceActionConfig,
+) -> Vec<Signal> {
+    let n = config.range_lookback;
+    if n < 2 {
+        return vec![];
+    }
+    let range = |p: &DailyPrice| p.high - p.low;
+    let mut signals = Vec::new();
+
+    for i in n - 1..prices.len() {
+        let bar = &prices[i];
+        let today = range(bar);
+        let prior = &prices[i + 1 - n..i];
+        let narrowest = prior.iter().map(range).fold(f64::MAX, f64::min);
+        let widest = prior.iter().map(range).fold(f64::MIN, f64::max);
+        let name = format!("RANGE_{}", n);
+
+        if today < narrowest {
+            // Contraction says a move is coming, not which way
+            let strength = 1.0 - today / narrowest.max(f64::EPSILON);
+            signals.push(signal(
+                symbol,
+                bar,
+                SignalType::NarrowRange7,
+                SignalDirection::Neutral,
+                strength,
+                &name,
+                today,
+            ));
+        } else if today > widest && widest > 0.0 {
+            signals.push(signal(
+                symbol,
+                bar,
+                SignalType::WideRange7,
+                move_direction(bar.open, bar.close),
+                today / widest - 1.0,
+                &name,
+                today,
+            ));
+        }
+    }
+
+    signals
+}
+
+/// All volume, gap and range signals. `prices` need not be sorted.
+pub fn detect_price_action_signals(
+    symbol: &str,
+    prices: &[DailyPrice],
+    config: &PriceActionConfig,
+) -> Vec<Signal> {
+    let mut sorted = prices.to_vec();
+    sorted.sort_by_key(|p| p.date);
+
+    let mut signals = detect_volume_signals(symbol, &sorted, config);
+    signals.extend(detect_gap_signals(symbol, &sorted, config));
+    signals.extend(detect_range_signals(symbol, &sorted, config));
+    signals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn bar(i: usize, open: f64, high: f64, low: f64, close: f64, volume: i64) -> DailyPrice {
+        DailyPrice {
+            symbol: "TEST".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + chrono::Duration::days(i as i64),
+            open,
+            high,
+            low,
+            close,
+            volume,
+            source: "test".to_string(),
+        }
+    }
+
+    fn quiet(n: usize) -> Vec<DailyPrice> {
+        (0..n)
+            .map(|i| bar(i, 100.0, 101.0, 99.0, 100.0, 1000))
+            .collect()
+    }
+
+    fn types(signals: &[Signal]) -> Vec<SignalType> {
+        signals.iter().map(|s| s.signal_type.clone()).collect()
+    }
+
+    #[test]
+    fn test_volume_spike_and_breakout() {
+        let mut prices = quiet(25);
+        prices.push(bar(25, 100.0, 104.0, 100.0, 103.0, 3000));
+
+        let signals = detect_volume_signals("TEST", &prices, &PriceActionConfig::default());
+        assert_eq!(
+            types(&signals),
+            vec![SignalType::VolumeSpike, SignalType::VolumeBreakout]
+        );
+        assert!(signals
+            .iter()
+            .all(|s| s.direction == SignalDirection::Bullish));
+        assert!((signals[0].trigger_value - 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_gap_up_then_fill() {
+        let mut prices = quiet(3);
+        prices.push(bar(3, 103.0, 104.0, 102.5, 103.5, 1000));
+        prices.push(bar(4, 103.0, 103.5, 101.0, 101.5, 1000));
+        prices.push(bar(5, 101.0, 101.5, 99.5, 100.0, 1000));
+
+        let signals = detect_gap_signals("TEST", &prices, &PriceActionConfig::default());
+        assert_eq!(
+            types(&signals),
+            vec![SignalType::GapUp, SignalType::GapFill]
+        );
+        assert_eq!(signals[1].direction, SignalDirection::Bearish);
+        assert_eq!(signals[1].timestamp, prices[5].date);
+        assert_eq!(signals[1].trigger_value, 2.0);
+    }
+
+    #[test]
+    fn test_no_gap_from_a_bar_without_a_range() {
+        let mut prices = quiet(3);
+        prices.push(bar(3, 100.0, 0.0, 0.0, 100.0, 1000));
+        prices.push(bar(4, 100.0, 101.0, 99.0, 100.0, 1000));
+
+        let signals = detect_gap_signals("TEST", &prices, &PriceActionConfig::default());
+        assert!(signals.is_empty());
+    }
+
+    #[test]
+    fn test_nr7_and_wr7() {
+        let mut prices = quiet(6);
+        prices.push(bar(6, 100.0, 100.5, 99.8, 100.2, 1000));
+        prices.push(bar(7, 100.0, 104.0, 99.0, 103.5, 1000));
+
+        let signals = detect_range_signals("TEST", &prices, &PriceActionConfig::default());
+        assert_eq!(
+            types(&signals),
+            vec![SignalType::NarrowRange7, SignalType::WideRange7]
+        );
+        assert_eq!(signals[0].direction, SignalDirection::Neutral);
+        assert_eq!(signals[1].direction, SignalDirection::Bullish);
+    }
+}
@@ -0,0 +1,264 @@
+//! Market regime detection
+//!
+//! Classifies each bar of a symbol into a regime:
+//! - High volatility: Yang-Zhang volatility above `high_vol_percentile` of its
+//!   trailing window (or, for the broad market, VIX at/above `vix_high`)
+//! - Trend up: ADX at/above `adx_trend`, +DI above -DI and close above its SMA
+//! - Trend down: ADX at/above `adx_trend`, -DI above +DI and close below its SMA
+//! - Range: everything else
+//!
+//! The broad market regime is the benchmark's regime combined with VIXCLS
+//! from `macro_data`, stored under `MARKET_REGIME_SYMBOL`.
+
+use crate::analytics::DEFAULT_BENCHMARK;
+use crate::db::Database;
+use crate::error::{PipelineError, Result};
+use crate::fred::indicators::VIX as VIX_INDICATOR;
+use crate::indicators::{calculate_adx, calculate_sma};
+use crate::models::{DailyPrice, MacroData, MarketRegime, RegimeRecord, TechnicalIndicator};
+use crate::volatility::{
+    classify_volatility_regime, VolatilityRegimeConfig, VOL_PERCENTILE_INDICATOR,
+};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+/// Symbol under which the broad market regime is stored
+pub const MARKET_REGIME_SYMBOL: &str = "MARKET";
+
+/// Regime classification thresholds
+#[derive(Debug, Clone)]
+pub struct RegimeConfig {
+    /// SMA period defining the trend side
+    pub sma_period: usize,
+    /// ADX period
+    pub adx_period: usize,
+    /// ADX level at/above which the market is trending
+    pub adx_trend: f64,
+    /// Volatility estimator window (bars)
+    pub vol_period: usize,
+    /// Trailing window for the volatility percentile (bars)
+    pub vol_lookback: usize,
+    /// Volatility percentile above which the regime is high volatility
+    pub high_vol_percentile: f64,
+    /// VIX level at/above which the market regime is high volatility
+    pub vix_high: f64,
+}
+
+impl Default for RegimeConfig {
+    fn default() -> Self {
+        Self {
+            sma_period: 50,
+            adx_period: 14,
+            adx_trend: 25.0,
+            vol_period: 20,
+            vol_lookback: 252,
+            high_vol_percentile: 80.0,
+            vix_high: 25.0,
+        }
+    }
+}
+
+/// Regime per date for one symbol, sorted by date
+#[derive(Debug, Clone, Default)]
+pub struct RegimeSeries {
+    records: Vec<RegimeRecord>,
+}
+
+impl RegimeSeries {
+    /// Classify every bar that has enough history for the SMA and ADX
+    pub fn from_prices(prices: &[DailyPrice], config: &RegimeConfig) -> Self {
+        let mut sorted = prices.to_vec();
+        sorted.sort_by_key(|p| p.date);
+        let Some(symbol) = sorted.first().map(|p| p.symbol.clone()) else {
+            return Self::default();
+        };
+
+        let by_name = |name: String, values: Vec<TechnicalIndicator>| {
+            values
+                .into_iter()
+                .filter(|i| i.indicator_name == name)
+                .map(|i| (i.date, i.value))
+                .collect::<HashMap<NaiveDate, f64>>()
+        };
+        let sma = by_name(format!("SMA_{}", config.sma_period), calculate_sma(&sorted, config.sma_period));
+        let adx_all = calculate_adx(&sorted, config.adx_period);
+        let adx = by_name(format!("ADX_{}", config.adx_period), adx_all.clone());
+        let plus_di = by_name(format!("+DI_{}", config.adx_period), adx_all.clone());
+        let minus_di = by_name(format!("-DI_{}", config.adx_period), adx_all);
+        let vol_config = VolatilityRegimeConfig {
+            period: config.vol_period,
+            lookback: config.vol_lookback,
+            high_percentile: config.high_vol_percentile,
+            ..VolatilityRegimeConfig::default()
+        };
+        let vol_percentile = by_name(
+            VOL_PERCENTILE_INDICATOR.to_string(),
+            classify_volatility_regime(&sorted, &vol_config),
+        );
+
+        let records = sorted
+            .iter()
+            .filter_map(|bar| {
+                let sma = *sma.get(&bar.date)?;
+                let adx = *adx.get(&bar.date)?;
+                let plus = plus_di.get(&bar.date).copied().unwrap_or(0.0);
+                let minus = minus_di.get(&bar.date).copied().unwrap_or(0.0);
+                let vol = vol_percentile.get(&bar.date).copied();
+
+                let regime = if vol.is_some_and(|v| v > config.high_vol_percentile) {
+                    MarketRegime::HighVolatility
+                } else if adx >= config.adx_trend && plus > minus && bar.close > sma {
+                    MarketRegime::TrendUp
+                } else if adx >= config.adx_trend && minus > plus && bar.close < sma {
+                    MarketRegime::TrendDown
+                } else {
+                    MarketRegime::Range
+                };
+
+                Some(RegimeRecord {
+                    symbol: symbol.clone(),
+                    date: bar.date,
+                    regime,
+                    adx: Some(adx),
+                    vol_percentile: vol,
+                    vix: None,
+                })
+            })
+            .collect();
+
+        Self { records }
+    }
+
+    /// Overlay VIX: dates where the latest VIX close is at/above `vix_high`
+    /// become high volatility. Records are relabelled as the market regime.
+    pub fn with_vix(mut self, vix: &[MacroData], config: &RegimeConfig) -> Self {
+        let mut vix: Vec<(NaiveDate, f64)> = vix.iter().map(|m| (m.date, m.value)).collect();
+        vix.sort_by_key(|v| v.0);
+
+        for record in &mut self.records {
+            record.symbol = MARKET_REGIME_SYMBOL.to_string();
+            let idx = vix.partition_point(|(d, _)| *d <= record.date);
+            if idx > 0 {
+                let level = vix[idx - 1].1;
+                record.vix = Some(level);
+                if level >= config.vix_high {
+                    record.regime = MarketRegime::HighVolatility;
+                }
+            }
+        }
+        self
+    }
+
+    pub fn from_records(mut records: Vec<RegimeRecord>) -> Self {
+        records.sort_by_key(|r| r.date);
+        Self { records }
+    }
+
+    pub fn records(&self) -> &[RegimeRecord] {
+        &self.records
+    }
+
+    /// Regime on the latest classified bar on or before `date`
+    pub fn as_of(&self, date: NaiveDate) -> Option<MarketRegime> {
+        let idx = self.records.partition_point(|r| r.date <= date);
+        (idx > 0).then(|| self.records[idx - 1].regime)
+    }
+
+    /// Whether `date` falls in one of the `allowed` regimes.
+    /// An empty list allows everything; dates without a classification pass.
+    pub fn allows(&self, date: NaiveDate, allowed: &[MarketRegime]) -> bool {
+        allowed.is_empty() || self.as_of(date).is_none_or(|r| allowed.contains(&r))
+    }
+}
+
+/// Classify a symbol's stored prices and save its regime history.
+/// Returns the number of records written.
+pub fn update_regime(db: &mut Database, symbol: &str, config: &RegimeConfig) -> Result<usize> {
+    let prices = db.get_prices(symbol)?;
+    let series = RegimeSeries::from_prices(&prices, config);
+    db.upsert_regime_history(series.records())
+}
+
+/// Classify the broad market from the benchmark and VIXCLS and save it under
+/// `MARKET_REGIME_SYMBOL`. Returns the number of records written.
+pub fn update_market_regime(db: &mut Database, benchmark: Option<&str>, config: &RegimeConfig) -> Result<usize> {
+    let benchmark = benchmark.unwrap_or(DEFAULT_BENCHMARK);
+    let prices = db.get_prices(benchmark)?;
+    if prices.is_empty() {
+        return Err(PipelineError::NoData(format!(
+            "No prices for benchmark {} - fetch it before classifying the market regime",
+            benchmark
+        )));
+    }
+
+    let vix = db.get_macro_history(VIX_INDICATOR)?;
+    let series = RegimeSeries::from_prices(&prices, config).with_vix(&vix, config);
+    db.upsert_regime_history(series.records())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bars(closes: &[f64]) -> Vec<DailyPrice> {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| DailyPrice {
+                symbol: "TEST".to_string(),
+                date: start + chrono::Duration::days(i as i64),
+                open: close,
+                high: close * 1.01,
+                low: close * 0.99,
+                close,
+                volume: 1000,
+                source: "test".to_string(),
+            })
+            .collect()
+    }
+
+    fn config() -> RegimeConfig {
+        RegimeConfig {
+            sma_period: 10,
+            high_vol_percentile: 101.0, // volatility never overrides
+            ..RegimeConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_trend_and_range_classification() {
+        let up: Vec<f64> = (0..80).map(|i| 100.0 * 1.01f64.powi(i)).collect();
+        let series = RegimeSeries::from_prices(&bars(&up), &config());
+        assert_eq!(series.records().last().unwrap().regime, MarketRegime::TrendUp);
+
+        let down: Vec<f64> = (0..80).map(|i| 100.0 * 0.99f64.powi(i)).collect();
+        let series = RegimeSeries::from_prices(&bars(&down), &config());
+        assert_eq!(series.records().last().unwrap().regime, MarketRegime::TrendDown);
+
+        let flat: Vec<f64> = (0..80u32).map(|i| 100.0 + if i.is_multiple_of(2) { 1.0 } else { -1.0 }).collect();
+        let series = RegimeSeries::from_prices(&bars(&flat), &config());
+        assert_eq!(series.records().last().unwrap().regime, MarketRegime::Range);
+    }
+
+    #[test]
+    fn test_vix_overlay_and_filter() {
+        let up: Vec<f64> = (0..80).map(|i| 100.0 * 1.01f64.powi(i)).collect();
+        let prices = bars(&up);
+        let last = prices.last().unwrap().date;
+        let vix = vec![MacroData {
+            indicator: VIX_INDICATOR.to_string(),
+            date: last - chrono::Duration::days(3),
+            value: 32.0,
+            source: "FRED".to_string(),
+        }];
+
+        let series = RegimeSeries::from_prices(&prices, &config()).with_vix(&vix, &config());
+        assert_eq!(series.as_of(last), Some(MarketRegime::HighVolatility));
+        assert_eq!(series.records().last().unwrap().symbol, MARKET_REGIME_SYMBOL);
+        assert!(!series.allows(last, &[MarketRegime::TrendUp, MarketRegime::Range]));
+        assert!(series.allows(last, &[]));
+        // Before any classification the filter passes
+        assert!(series.allows(prices[0].date, &[MarketRegime::Range]));
+    }
+}
@@ -0,0 +1,597 @@
+//! Strategy rule evaluation
+//!
+//! Evaluates `RuleNode` trees bar by bar. Operands resolve against the
+//! indicator map and price bars; a missing value (no indicator on that date,
+//! or a lookback before the first bar) makes the comparison false.
+//! Single-condition strategies are evaluated through `condition_met`, which
+//! also backs `RuleNode::Condition`. Signal and confluence conditions read the
+//! pseudo-indicators built by `SignalEngine::signal_indicators`.
+
+use crate::analytics::DEFAULT_BENCHMARK;
+use crate::error::Result;
+use crate::formula::formula_indicator_name;
+use crate::models::{
+    CompareOp, DailyPrice, PriceField, RuleNode, RuleOperand, SignalDirection,
+    StrategyConditionType,
+};
+use crate::signals::{is_signal_indicator, signal_indicator_name, CONFLUENCE_INDICATOR};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+/// Bar-level state a rule is evaluated against
+pub struct RuleContext<'a> {
+    /// Price bars sorted by date
+    pub bars: &'a [DailyPrice],
+    /// Index of the current bar in `bars`
+    pub index: usize,
+    /// Indicator values by date and name
+    pub indicators: &'a HashMap<NaiveDate, HashMap<String, f64>>,
+}
+
+impl<'a> RuleContext<'a> {
+    fn bar(&self, bars_ago: usize) -> Option<&'a DailyPrice> {
+        self.index
+            .checked_sub(bars_ago)
+            .and_then(|i| self.bars.get(i))
+    }
+
+    /// Indicator values on the bar `bars_ago` bars back
+    pub fn indicators_at(&self, bars_ago: usize) -> Option<&'a HashMap<String, f64>> {
+        self.bar(bars_ago)
+            .and_then(|b| self.indicators.get(&b.date))
+    }
+
+    /// Resolve an operand, shifted a further `shift` bars back
+    pub fn value(&self, operand: &RuleOperand, shift: usize) -> Option<f64> {
+        match operand {
+            RuleOperand::Indicator { name, bars_ago } => {
+                self.indicators_at(bars_ago + shift)?.get(name).copied()
+            }
+            RuleOperand::Price { field, bars_ago } => {
+                let bar = self.bar(bars_ago + shift)?;
+                Some(match field {
+                    PriceField::Open => bar.open,
+                    PriceField::High => bar.high,
+                    PriceField::Low => bar.low,
+                    PriceField::Close => bar.close,
+                    PriceField::Volume => bar.volume as f64,
+                })
+            }
+            RuleOperand::Constant { value } => Some(*value),
+        }
+    }
+}
+
+impl CompareOp {
+    pub fn apply(&self, left: f64, right: f64) -> bool {
+        match self {
+            CompareOp::Gt => left > right,
+            CompareOp::Gte => left >= right,
+            CompareOp::Lt => left < right,
+            CompareOp::Lte => left <= right,
+        }
+    }
+}
+
+impl RuleOperand {
+    pub fn indicator(name: &str) -> Self {
+        RuleOperand::Indicator {
+            name: name.to_string(),
+            bars_ago: 0,
+        }
+    }
+
+    pub fn price(field: PriceField) -> Self {
+        RuleOperand::Price { field, bars_ago: 0 }
+    }
+
+    pub fn constant(value: f64) -> Self {
+        RuleOperand::Constant { value }
+    }
+
+    /// The same operand `bars` bars earlier
+    pub fn ago(self, bars: usize) -> Self {
+        match self {
+            RuleOperand::Indicator { name, .. } => RuleOperand::Indicator {
+                name,
+                bars_ago: bars,
+            },
+            RuleOperand::Price { field, .. } => RuleOperand::Price {
+                field,
+                bars_ago: bars,
+            },
+            constant => constant,
+        }
+    }
+
+    fn describe(&self) -> String {
+        let (name, bars_ago) = match self {
+            RuleOperand::Indicator { name, bars_ago } => (name.clone(), *bars_ago),
+            RuleOperand::Price { field, bars_ago } => (field.as_str().to_string(), *bars_ago),
+            RuleOperand::Constant { value } => return value.to_string(),
+        };
+        if bars_ago == 0 {
+            name
+        } else {
+            format!("{}[{}]", name, bars_ago)
+        }
+    }
+}
+
+impl RuleNode {
+    pub fn compare(left: RuleOperand, op: CompareOp, right: RuleOperand) -> Self {
+        RuleNode::Compare { left, op, right }
+    }
+
+    pub fn all(rules: Vec<RuleNode>) -> Self {
+        RuleNode::All { rules }
+    }
+
+    pub fn any(rules: Vec<RuleNode>) -> Self {
+        RuleNode::Any { rules }
+    }
+
+    pub fn negate(rule: RuleNode) -> Self {
+        RuleNode::Not {
+            rule: Box::new(rule),
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Whether the rule holds on the context's current bar
+    pub fn evaluate(&self, ctx: &RuleContext) -> bool {
+        match self {
+            RuleNode::All { rules } => rules.iter().all(|r| r.evaluate(ctx)),
+            RuleNode::Any { rules } => rules.iter().any(|r| r.evaluate(ctx)),
+            RuleNode::Not { rule } => !rule.evaluate(ctx),
+            RuleNode::Compare { left, op, right } => {
+                match (ctx.value(left, 0), ctx.value(right, 0)) {
+                    (Some(l), Some(r)) => op.apply(l, r),
+                    _ => false,
+                }
+            }
+            RuleNode::CrossAbove { left, right } => crossed(ctx, left, right, CompareOp::Gt),
+            RuleNode::CrossBelow { left, right } => crossed(ctx, left, right, CompareOp::Lt),
+            RuleNode::Condition {
+                condition,
+                threshold,
+                formula,
+                benchmark,
+            } => {
+                let Some(today) = ctx.indicators_at(0) else {
+                    return false;
+                };
+                let price = ctx.bars[ctx.index].close;
+                condition_met(
+                    *condition,
+                    *threshold,
+                    formula.as_deref(),
+                    benchmark.as_deref(),
+                    price,
+                    today,
+                    ctx.indicators_at(1),
+                )
+            }
+            RuleNode::Confluence {
+                direction,
+                min_strength,
+            } => ctx
+                .indicators_at(0)
+                .and_then(|today| today.get(CONFLUENCE_INDICATOR))
+                .is_some_and(|&value| confluence_in(value, *direction, *min_strength)),
+            RuleNode::Signal {
+                signal_type,
+                direction,
+                min_strength,
+            } => {
+                let Some(today) = ctx.indicators_at(0) else {
+                    return false;
+                };
+                let any = [
+                    SignalDirection::Bullish,
+                    SignalDirection::Bearish,
+                    SignalDirection::Neutral,
+                ];
+                let directions = match direction {
+                    Some(direction) => std::slice::from_ref(direction),
+                    None => &any[..],
+                };
+                directions.iter().any(|&d| {
+                    today
+                        .get(&signal_indicator_name(signal_type, d))
+                        .is_some_and(|&strength| strength >= *min_strength)
+                })
+            }
+        }
+    }
+
+    /// Whether the rule reads signal or confluence pseudo-indicators
+    pub fn uses_signals(&self) -> bool {
+        let signal_operand = |operand: &RuleOperand| {
+            matches!(operand, RuleOperand::Indicator { name, .. } if is_signal_indicator(name))
+        };
+        match self {
+            RuleNode::All { rules } | RuleNode::Any { rules } => {
+                rules.iter().any(|r| r.uses_signals())
+            }
+            RuleNode::Not { rule } => rule.uses_signals(),
+            RuleNode::Compare { left, right, .. }
+            | RuleNode::CrossAbove { left, right }
+            | RuleNode::CrossBelow { left, right } => signal_operand(left) || signal_operand(right),
+            RuleNode::Condition { condition, .. } => condition.uses_signals(),
+            RuleNode::Confluence { .. } | RuleNode::Signal { .. } => true,
+        }
+    }
+
+    /// Human-readable form, e.g. `RSI_14 < 30 AND close > SMA_200`
+    pub fn describe(&self) -> String {
+        let group = |rules: &[RuleNode], joiner: &str| {
+            rules
+                .iter()
+                .map(|r| match r {
+                    RuleNode::All { rules } | RuleNode::Any { rules } if rules.len() > 1 => {
+                        format!("({})", r.describe())
+                    }
+                    _ => r.describe(),
+                })
+                .collect::<Vec<_>>()
+                .join(joiner)
+        };
+        match self {
+            RuleNode::All { rules } if rules.is_empty() => "true".to_string(),
+            RuleNode::Any { rules } if rules.is_empty() => "false".to_string(),
+            RuleNode::All { rules } => group(rules, " AND "),
+            RuleNode::Any { rules } => group(rules, " OR "),
+            RuleNode::Not { rule } => format!("NOT ({})", rule.describe()),
+            RuleNode::Compare { left, op, right } => {
+                format!("{} {} {}", left.describe(), op.as_str(), right.describe())
+            }
+            RuleNode::CrossAbove { left, right } => {
+                format!("{} crosses above {}", left.describe(), right.describe())
+            }
+            RuleNode::CrossBelow { left, right } => {
+                format!("{} crosses below {}", left.describe(), right.describe())
+            }
+            RuleNode::Condition {
+                condition,
+                threshold,
+                benchmark,
+                ..
+            } => match benchmark {
+                Some(benchmark) => format!("{}({}) vs {}", condition.as_str(), threshold, benchmark),
+                None => format!("{}({})", condition.as_str(), threshold),
+            },
+            RuleNode::Confluence {
+                direction,
+                min_strength,
+            } => {
+                format!("{} confluence >= {}", direction.as_str(), min_strength)
+            }
+            RuleNode::Signal {
+                signal_type,
+                direction,
+                min_strength,
+            } => match direction {
+                Some(d) => format!("{} {} signal >= {}", d.as_str(), signal_type.as_str(), min_strength),
+                None => format!("{} signal >= {}", signal_type.as_str(), min_strength),
+            },
+        }
+    }
+}
+
+/// A signed `CONFLUENCE` value is a confluence in `direction` at least
+/// `min_strength` strong
+fn confluence_in(value: f64, direction: SignalDirection, min_strength: f64) -> bool {
+    match direction {
+        SignalDirection::Bullish => value > 0.0 && value >= min_strength,
+        SignalDirection::Bearish => value < 0.0 && -value >= min_strength,
+        SignalDirection::Neutral => false,
+    }
+}
+
+/// `left` is beyond `right` (per `op`) now and was not on the previous bar
+fn crossed(ctx: &RuleContext, left: &RuleOperand, right: &RuleOperand, op: CompareOp) -> bool {
+    let values = (
+        ctx.value(left, 0),
+        ctx.value(right, 0),
+        ctx.value(left, 1),
+        ctx.value(right, 1),
+    );
+    match values {
+        (Some(l), Some(r), Some(prev_l), Some(prev_r)) => {
+            op.apply(l, r) && !op.apply(prev_l, prev_r)
+        }
+        _ => false,
+    }
+}
+
+/// `first` crossed `second` between the previous and current bar
+fn indicator_cross(
+    today: &HashMap<String, f64>,
+    prev: Option<&HashMap<String, f64>>,
+    first: &str,
+    second: &str,
+    up: bool,
+) -> bool {
+    let (Some(prev), Some(&a), Some(&b)) = (prev, today.get(first), today.get(second)) else {
+        return false;
+    };
+    let (Some(&prev_a), Some(&prev_b)) = (prev.get(first), prev.get(second)) else {
+        return false;
+    };
+    if up {
+        prev_a <= prev_b && a > b
+    } else {
+        prev_a >= prev_b && a < b
+    }
+}
+
+/// Evaluate a single strategy condition.
+/// StopLoss and TakeProfit depend on the open position and are never met here;
+/// the backtest engine places them as intrabar stop orders instead.
+pub fn condition_met(
+    condition: StrategyConditionType,
+    threshold: f64,
+    formula: Option<&str>,
+    benchmark: Option<&str>,
+    price: f64,
+    today: &HashMap<String, f64>,
+    prev: Option<&HashMap<String, f64>>,
+) -> bool {
+    let rs_rank = || {
+        let benchmark = benchmark.unwrap_or(DEFAULT_BENCHMARK).to_uppercase();
+        today.get(&format!("RS_RANK_{}", benchmark)).copied()
+    };
+    let formula_value = || {
+        formula
+            .and_then(|name| today.get(&formula_indicator_name(name)))
+            .copied()
+    };
+
+    match condition {
+        StrategyConditionType::RsiOversold => {
+            today.get("RSI_14").is_some_and(|&rsi| rsi < threshold)
+        }
+        StrategyConditionType::RsiOverbought => {
+            today.get("RSI_14").is_some_and(|&rsi| rsi > threshold)
+        }
+        StrategyConditionType::MacdCrossUp => {
+            indicator_cross(today, prev, "MACD_12_26", "MACD_SIGNAL_9", true)
+        }
+        StrategyConditionType::MacdCrossDown => {
+            indicator_cross(today, prev, "MACD_12_26", "MACD_SIGNAL_9", false)
+        }
+        StrategyConditionType::PriceAboveSma => today.get("SMA_20").is_some_and(|&sma| price > sma),
+        StrategyConditionType::PriceBelowSma => today.get("SMA_20").is_some_and(|&sma| price < sma),
+        StrategyConditionType::SmaCrossUp => indicator_cross(today, prev, "SMA_20", "SMA_50", true),
+        StrategyConditionType::SmaCrossDown => {
+            indicator_cross(today, prev, "SMA_20", "SMA_50", false)
+        }
+        StrategyConditionType::RsRankAbove => rs_rank().is_some_and(|rank| rank > threshold),
+        StrategyConditionType::RsRankBelow => rs_rank().is_some_and(|rank| rank < threshold),
+        StrategyConditionType::FormulaAbove => {
+            formula_value().is_some_and(|value| value > threshold)
+        }
+        StrategyConditionType::FormulaBelow => {
+            formula_value().is_some_and(|value| value < threshold)
+        }
+        StrategyConditionType::ConfluenceBullish => today
+            .get(CONFLUENCE_INDICATOR)
+            .is_some_and(|&value| confluence_in(value, SignalDirection::Bullish, threshold)),
+        StrategyConditionType::ConfluenceBearish => today
+            .get(CONFLUENCE_INDICATOR)
+            .is_some_and(|&value| confluence_in(value, SignalDirection::Bearish, threshold)),
+        StrategyConditionType::StopLoss | StrategyConditionType::TakeProfit => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SignalType;
+
+    fn setup() -> (Vec<DailyPrice>, HashMap<NaiveDate, HashMap<String, f64>>) {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let closes = [100.0, 99.0, 102.0];
+        let rsi = [35.0, 28.0, 25.0];
+        let sma = [100.5, 100.0, 101.0];
+        let mut bars = Vec::new();
+        let mut map: HashMap<NaiveDate, HashMap<String, f64>> = HashMap::new();
+        for i in 0..3 {
+            let date = start + chrono::Duration::days(i as i64);
+            bars.push(DailyPrice {
+                symbol: "TEST".to_string(),
+                date,
+                open: closes[i],
+                high: closes[i] + 1.0,
+                low: closes[i] - 1.0,
+                close: closes[i],
+                volume: 1000,
+                source: "test".to_string(),
+            });
+            let day = map.entry(date).or_default();
+            day.insert("RSI_14".to_string(), rsi[i]);
+            day.insert("SMA_200".to_string(), sma[i]);
+        }
+        (bars, map)
+    }
+
+    #[test]
+    fn test_all_any_not_and_cross() {
+        let (bars, map) = setup();
+        let at = |index| RuleContext {
+            bars: &bars,
+            index,
+            indicators: &map,
+        };
+
+        // RSI < 30 AND close > SMA_200
+        let rule = RuleNode::all(vec![
+            RuleNode::compare(
+                RuleOperand::indicator("RSI_14"),
+                CompareOp::Lt,
+                RuleOperand::constant(30.0),
+            ),
+            RuleNode::compare(
+                RuleOperand::price(PriceField::Close),
+                CompareOp::Gt,
+                RuleOperand::indicator("SMA_200"),
+            ),
+        ]);
+        assert!(!rule.evaluate(&at(0)));
+        assert!(!rule.evaluate(&at(1)));
+        assert!(rule.evaluate(&at(2)));
+        assert_eq!(rule.describe(), "RSI_14 < 30 AND close > SMA_200");
+
+        let cross = RuleNode::CrossAbove {
+            left: RuleOperand::price(PriceField::Close),
+            right: RuleOperand::indicator("SMA_200"),
+        };
+        assert!(!cross.evaluate(&at(0)));
+        assert!(cross.evaluate(&at(2)));
+
+        // Close higher than two bars ago; no history on the first bar
+        let rising = RuleNode::compare(
+            RuleOperand::price(PriceField::Close),
+            CompareOp::Gt,
+            RuleOperand::price(PriceField::Close).ago(2),
+        );
+        assert!(!rising.evaluate(&at(0)));
+        assert!(rising.evaluate(&at(2)));
+        assert!(RuleNode::negate(rising.clone()).evaluate(&at(0)));
+        assert!(RuleNode::any(vec![rising, cross]).evaluate(&at(2)));
+    }
+
+    #[test]
+    fn test_confluence_and_signal_nodes() {
+        let (bars, mut map) = setup();
+        let day = map.get_mut(&bars[2].date).unwrap();
+        day.insert(CONFLUENCE_INDICATOR.to_string(), -0.7);
+        day.insert(
+            signal_indicator_name(&SignalType::RsiOversold, SignalDirection::Bullish),
+            0.5,
+        );
+        let at = |index| RuleContext {
+            bars: &bars,
+            index,
+            indicators: &map,
+        };
+
+        let bearish = RuleNode::Confluence {
+            direction: SignalDirection::Bearish,
+            min_strength: 0.6,
+        };
+        assert!(bearish.evaluate(&at(2)));
+        assert!(!bearish.evaluate(&at(1)));
+        assert!(!RuleNode::Confluence {
+            direction: SignalDirection::Bullish,
+            min_strength: 0.0,
+        }
+        .evaluate(&at(2)));
+        assert!(!RuleNode::Confluence {
+            direction: SignalDirection::Bearish,
+            min_strength: 0.8,
+        }
+        .evaluate(&at(2)));
+
+        let today = at(2).indicators_at(0).unwrap();
+        assert!(condition_met(StrategyConditionType::ConfluenceBearish, 0.6, None, None, 102.0, today, None));
+        assert!(!condition_met(StrategyConditionType::ConfluenceBullish, 0.6, None, None, 102.0, today, None));
+
+        let oversold = |direction, min_strength| RuleNode::Signal {
+            signal_type: SignalType::RsiOversold,
+            direction,
+            min_strength,
+        };
+        assert!(oversold(None, 0.5).evaluate(&at(2)));
+        assert!(oversold(Some(SignalDirection::Bullish), 0.0).evaluate(&at(2)));
+        assert!(!oversold(Some(SignalDirection::Bearish), 0.0).evaluate(&at(2)));
+        assert!(!oversold(None, 0.6).evaluate(&at(2)));
+
+        assert!(bearish.uses_signals());
+        assert!(RuleNode::compare(
+            RuleOperand::indicator(CONFLUENCE_INDICATOR),
+            CompareOp::Gte,
+            RuleOperand::constant(0.6),
+        )
+        .uses_signals());
+        assert!(!RuleNode::compare(
+            RuleOperand::indicator("RSI_14"),
+            CompareOp::Lt,
+            RuleOperand::constant(30.0),
+        )
+        .uses_signals());
+        assert_eq!(bearish.describe(), "bearish confluence >= 0.6");
+    }
+
+    #[test]
+    fn test_rs_rank_benchmark_falls_back_to_spy() {
+        let mut today = HashMap::new();
+        today.insert("RS_RANK_SPY".to_string(), 40.0);
+        today.insert("RS_RANK_QQQ".to_string(), 90.0);
+        let above = |benchmark| {
+            condition_met(StrategyConditionType::RsRankAbove, 80.0, None, benchmark, 100.0, &today, None)
+        };
+        assert!(!above(None));
+        assert!(above(Some("qqq")));
+        assert!(!above(Some("IWM")));
+
+        let (bars, mut map) = setup();
+        map.get_mut(&bars[2].date).unwrap().extend(today.clone());
+        let rule = RuleNode::Condition {
+            condition: StrategyConditionType::RsRankAbove,
+            threshold: 80.0,
+            formula: None,
+            benchmark: Some("QQQ".to_string()),
+        };
+        let ctx = RuleContext {
+            bars: &bars,
+            index: 2,
+            indicators: &map,
+        };
+        assert!(rule.evaluate(&ctx));
+        assert_eq!(rule.describe(), "rs_rank_above(80) vs QQQ");
+    }
+
+    #[test]
+    fn test_json_round_trip_with_legacy_condition() {
+        let rule = RuleNode::any(vec![
+            RuleNode::Condition {
+                condition: StrategyConditionType::RsiOversold,
+                threshold: 30.0,
+                formula: None,
+                benchmark: None,
+            },
+            RuleNode::compare(
+                RuleOperand::indicator("RSI_14").ago(1),
+                CompareOp::Gte,
+                RuleOperand::constant(50.0),
+            ),
+        ]);
+        let parsed = RuleNode::from_json(&rule.to_json().unwrap()).unwrap();
+        assert_eq!(parsed, rule);
+
+        let parsed = RuleNode::from_json(
+            r#"{"type":"compare","left":{"kind":"indicator","name":"RSI_14"},"op":"lt","right":{"kind":"constant","value":30}}"#,
+        )
+        .unwrap();
+        let (bars, map) = setup();
+        assert!(parsed.evaluate(&RuleContext {
+            bars: &bars,
+            index: 1,
+            indicators: &map
+        }));
+        assert!(rule.evaluate(&RuleContext {
+            bars: &bars,
+            index: 1,
+            indicators: &map
+        }));
+    }
+}
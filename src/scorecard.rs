@@ -0,0 +1,349 @@
+//! Signal forward-return scorecard
+//!
+//! Joins historical signals with the prices that followed them to measure
+//! whether each signal type actually worked: hit rate, mean/median 1/5/20-day
+//! forward returns and maximum adverse/favorable excursion, per signal type,
+//! per symbol and per strength bucket.
+//!
+//! Signals are the stored episodes, so a condition that persists for several
+//! bars is scored once, from the bar it started.
+
+use crate::db::Database;
+use crate::error::Result;
+use crate::models::{DailyPrice, Signal, SignalDirection, SignalOutcome, SignalScorecardEntry};
+use std::collections::HashMap;
+
+/// Forward horizons (trading days)
+pub const HORIZONS: [usize; 3] = [1, 5, 20];
+
+/// Label used for pooled scorecard rows
+pub const ALL: &str = "ALL";
+
+/// Minimum samples before a symbol-specific row is preferred over the pooled row
+pub const MIN_SYMBOL_SAMPLES: usize = 10;
+
+/// Strength bucket label for a signal strength (0.0 - 1.0)
+pub fn strength_bucket(strength: f64) -> &'static str {
+    if strength < 1.0 / 3.0 {
+        "weak"
+    } else if strength < 2.0 / 3.0 {
+        "moderate"
+    } else {
+        "strong"
+    }
+}
+
+/// Measure what followed a signal. `prices` must be sorted by date.
+/// Returns None if the signal date has no price bar.
+pub fn evaluate_signal(signal: &Signal, prices: &[DailyPrice]) -> Option<SignalOutcome> {
+    let pending = SignalOutcome {
+        symbol: signal.symbol.clone(),
+        signal_type: signal.signal_type.clone(),
+        direction: signal.direction,
+        strength: signal.strength,
+        signal_date: signal.timestamp,
+        entry_price: 0.0,
+        return_1d: None,
+        return_5d: None,
+        return_20d: None,
+        mae: None,
+        mfe: None,
+    };
+    refresh_outcome(&pending, prices)
+}
+
+/// Whether an outcome is still missing its longest horizon
+pub fn is_pending(outcome: &SignalOutcome) -> bool {
+    outcome.return_20d.is_none()
+}
+
+/// Recompute an outcome from the prices that followed its signal date.
+/// `prices` must be sorted by date.
+pub fn refresh_outcome(outcome: &SignalOutcome, prices: &[DailyPrice]) -> Option<SignalOutcome> {
+    let idx = prices.binary_search_by_key(&outcome.signal_date, |p| p.date).ok()?;
+    let entry = prices[idx].close;
+    if entry <= 0.0 {
+        return None;
+    }
+
+    // Bearish signals are scored as if short
+    let sign = if outcome.direction == SignalDirection::Bearish { -1.0 } else { 1.0 };
+    let forward = |h: usize| {
+        prices
+            .get(idx + h)
+            .map(|p| sign * (p.close / entry - 1.0) * 100.0)
+    };
+
+    let horizon = *HORIZONS.last().unwrap();
+    let window = &prices[(idx + 1).min(prices.len())..(idx + 1 + horizon).min(prices.len())];
+    let (mae, mfe) = if window.is_empty() {
+        (None, None)
+    } else {
+        let best_high = window.iter().map(|p| p.high).fold(f64::MIN, f64::max);
+        let worst_low = window.iter().map(|p| p.low).fold(f64::MAX, f64::min);
+        let up = (best_high / entry - 1.0) * 100.0;
+        let down = (worst_low / entry - 1.0) * 100.0;
+        if sign > 0.0 {
+            (Some(down.min(0.0)), Some(up.max(0.0)))
+        } else {
+            (Some((-up).min(0.0)), Some((-down).max(0.0)))
+        }
+    };
+
+    Some(SignalOutcome {
+        entry_price: entry,
+        return_1d: forward(HORIZONS[0]),
+        return_5d: forward(HORIZONS[1]),
+        return_20d: forward(HORIZONS[2]),
+        mae,
+        mfe,
+        ..outcome.clone()
+    })
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+fn median(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+fn summarize(signal_type: &str, symbol: &str, bucket: &str, outcomes: &[&SignalOutcome]) -> SignalScorecardEntry {
+    let collect = |f: fn(&SignalOutcome) -> Option<f64>| -> Vec<f64> {
+        outcomes.iter().filter_map(|o| f(o)).collect()
+    };
+    let r1 = collect(|o| o.return_1d);
+    let r5 = collect(|o| o.return_5d);
+    let r20 = collect(|o| o.return_20d);
+
+    SignalScorecardEntry {
+        signal_type: signal_type.to_string(),
+        symbol: symbol.to_string(),
+        strength_bucket: bucket.to_string(),
+        sample_size: outcomes.len(),
+        hit_rate: if r5.is_empty() {
+            0.0
+        } else {
+            r5.iter().filter(|&&r| r > 0.0).count() as f64 / r5.len() as f64 * 100.0
+        },
+        hit_rate_samples: r5.len(),
+        mean_return_1d: mean(&r1),
+        median_return_1d: median(&r1),
+        mean_return_5d: mean(&r5),
+        median_return_5d: median(&r5),
+        mean_return_20d: mean(&r20),
+        median_return_20d: median(&r20),
+        mean_mae: mean(&collect(|o| o.mae)),
+        mean_mfe: mean(&collect(|o| o.mfe)),
+        updated_at: String::new(),
+    }
+}
+
+/// Aggregate outcomes per (signal type, symbol, strength bucket), plus pooled
+/// rows across symbols and/or buckets (labelled "ALL")
+pub fn build_scorecard(outcomes: &[SignalOutcome]) -> Vec<SignalScorecardEntry> {
+    let mut groups: HashMap<(String, String, String), Vec<&SignalOutcome>> = HashMap::new();
+
+    for o in outcomes {
+        let signal_type = o.signal_type.as_str().to_string();
+        let bucket = strength_bucket(o.strength).to_string();
+        for symbol in [o.symbol.as_str(), ALL] {
+            for b in [bucket.as_str(), ALL] {
+                groups
+                    .entry((signal_type.clone(), symbol.to_string(), b.to_string()))
+                    .or_default()
+                    .push(o);
+            }
+        }
+    }
+
+    let mut entries: Vec<SignalScorecardEntry> = groups
+        .iter()
+        .map(|((signal_type, symbol, bucket), group)| summarize(signal_type, symbol, bucket, group))
+        .collect();
+    entries.sort_by(|a, b| {
+        (&a.signal_type, &a.symbol, &a.strength_bucket).cmp(&(&b.signal_type, &b.symbol, &b.strength_bucket))
+    });
+    entries
+}
+
+/// Evaluate all stored signals against subsequent prices, record their
+/// outcomes and rebuild the scorecard table. Recorded outcomes that are still
+/// missing a horizon are recomputed too, even if their signal was cleaned up.
+/// Returns the number of scorecard rows.
+pub fn update_signal_scorecard(db: &mut Database) -> Result<usize> {
+    let signals = db.get_all_signal_history()?;
+    let pending: Vec<SignalOutcome> = db
+        .get_signal_outcomes()?
+        .into_iter()
+        .filter(is_pending)
+        .collect();
+
+    let mut by_symbol: HashMap<&str, (Vec<&Signal>, Vec<&SignalOutcome>)> = HashMap::new();
+    for signal in &signals {
+        by_symbol.entry(signal.symbol.as_str()).or_default().0.push(signal);
+    }
+    for outcome in &pending {
+        by_symbol.entry(outcome.symbol.as_str()).or_default().1.push(outcome);
+    }
+
+    let mut outcomes = Vec::new();
+    for (symbol, (symbol_signals, symbol_pending)) in by_symbol {
+        let mut prices = db.get_prices(symbol)?;
+        prices.sort_by_key(|p| p.date);
+        outcomes.extend(symbol_pending.into_iter().filter_map(|o| refresh_outcome(o, &prices)));
+        outcomes.extend(symbol_signals.into_iter().filter_map(|s| evaluate_signal(s, &prices)));
+    }
+    db.upsert_signal_outcomes(&outcomes)?;
+
+    // Aggregate over every recorded outcome, including signals already cleaned up
+    let entries = build_scorecard(&db.get_signal_outcomes()?);
+    db.save_signal_scorecard(&entries)
+}
+
+/// Best available track record for a signal type on a symbol: the symbol's
+/// own row if it has enough samples, otherwise the pooled row
+pub fn track_record(db: &Database, signal_type: &str, symbol: &str) -> Result<Option<SignalScorecardEntry>> {
+    let own = db
+        .get_signal_scorecard(Some(signal_type), Some(symbol))?
+        .into_iter()
+        .find(|e| e.strength_bucket == ALL && e.hit_rate_samples >= MIN_SYMBOL_SAMPLES);
+    if own.is_some() {
+        return Ok(own);
+    }
+
+    Ok(db
+        .get_signal_scorecard(Some(signal_type), Some(ALL))?
+        .into_iter()
+        .find(|e| e.strength_bucket == ALL))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SignalStatus, SignalType};
+    use chrono::NaiveDate;
+
+    fn prices(closes: &[f64]) -> Vec<DailyPrice> {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        closes
+            .iter()
+            .enumerate()
+            .map(|(i, &close)| DailyPrice {
+                symbol: "TEST".to_string(),
+                date: start + chrono::Duration::days(i as i64),
+                open: close,
+                high: close + 1.0,
+                low: close - 1.0,
+                close,
+                volume: 1000,
+                source: "test".to_string(),
+            })
+            .collect()
+    }
+
+    fn signal(date: NaiveDate, signal_type: SignalType, direction: SignalDirection, strength: f64) -> Signal {
+        Signal {
+            id: 0,
+            symbol: "TEST".to_string(),
+            signal_type,
+            direction,
+            strength,
+            price_at_signal: 0.0,
+            triggered_by: "TEST".to_string(),
+            trigger_value: 0.0,
+            timestamp: date,
+            created_at: String::new(),
+            acknowledged: false,
+            status: SignalStatus::New,
+            end_date: None,
+        }
+    }
+
+    #[test]
+    fn test_evaluate_signal_direction_adjusted() {
+        let closes: Vec<f64> = (0..30).map(|i| 100.0 + i as f64).collect();
+        let data = prices(&closes);
+
+        let bull = evaluate_signal(
+            &signal(data[0].date, SignalType::MacdBullishCross, SignalDirection::Bullish, 0.5),
+            &data,
+        )
+        .unwrap();
+        assert!((bull.return_1d.unwrap() - 1.0).abs() < 1e-9);
+        assert!((bull.return_5d.unwrap() - 5.0).abs() < 1e-9);
+        assert!(bull.mae.unwrap() <= 0.0);
+        assert!((bull.mfe.unwrap() - 21.0).abs() < 1e-9);
+
+        let bear = evaluate_signal(
+            &signal(data[0].date, SignalType::MacdBearishCross, SignalDirection::Bearish, 0.5),
+            &data,
+        )
+        .unwrap();
+        assert!((bear.return_5d.unwrap() + 5.0).abs() < 1e-9);
+        assert_eq!(bear.mfe, Some(0.0));
+
+        // Not enough bars for the 20-day horizon
+        let late = evaluate_signal(
+            &signal(data[25].date, SignalType::MacdBullishCross, SignalDirection::Bullish, 0.5),
+            &data,
+        )
+        .unwrap();
+        assert!(late.return_1d.is_some());
+        assert!(late.return_20d.is_none());
+        assert!(is_pending(&late));
+
+        // Once more bars arrive the pending outcome fills in
+        let more: Vec<f64> = (0..50).map(|i| 100.0 + i as f64).collect();
+        let refreshed = refresh_outcome(&late, &prices(&more)).unwrap();
+        assert!((refreshed.return_20d.unwrap() - 20.0 / 125.0 * 100.0).abs() < 1e-9);
+        assert!(!is_pending(&refreshed));
+        assert_eq!(refreshed.signal_type, late.signal_type);
+    }
+
+    #[test]
+    fn test_build_scorecard_groups_and_hit_rate() {
+        let closes = [100.0, 101.0, 102.0, 103.0, 104.0, 105.0, 104.0, 103.0, 102.0, 101.0, 100.0, 99.0];
+        let data = prices(&closes);
+        let outcomes: Vec<SignalOutcome> = [
+            signal(data[0].date, SignalType::RsiOversold, SignalDirection::Bullish, 0.9),
+            signal(data[5].date, SignalType::RsiOversold, SignalDirection::Bullish, 0.1),
+        ]
+        .iter()
+        .filter_map(|s| evaluate_signal(s, &data))
+        .collect();
+
+        let scorecard = build_scorecard(&outcomes);
+        let pooled = scorecard
+            .iter()
+            .find(|e| e.symbol == ALL && e.strength_bucket == ALL)
+            .unwrap();
+        assert_eq!(pooled.sample_size, 2);
+        assert_eq!(pooled.hit_rate_samples, 2);
+        assert!((pooled.hit_rate - 50.0).abs() < 1e-9);
+
+        let strong = scorecard
+            .iter()
+            .find(|e| e.symbol == "TEST" && e.strength_bucket == "strong")
+            .unwrap();
+        assert_eq!(strong.sample_size, 1);
+        assert!(strong.mean_return_5d > 0.0);
+        // {TEST, ALL} x {strong, weak, ALL}
+        assert_eq!(scorecard.len(), 6);
+    }
+}
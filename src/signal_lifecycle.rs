@@ -0,0 +1,274 @@
+//! Signal lifecycle: episodes, cooldowns, expiry and invalidation
+//!
+//! Raw detector output can repeat while a condition persists. This module
+//! turns it into episodes:
+//! - occurrences of the same signal type within `merge_gap_bars` of each other
+//!   extend one episode (start = `timestamp`, end = `end_date`)
+//! - after an episode ends, repeats within the type's cooldown are dropped
+//! - episodes expire `expiry_bars` after their last bar
+//! - an opposite-direction signal from the same indicator before expiry
+//!   invalidates the episode
+//!
+//! Status is assigned as of the last bar when signals are generated; stored
+//! episodes are re-aged with `refresh_signal_status` as new bars arrive.
+
+use crate::db::Database;
+use crate::error::Result;
+use crate::models::{Signal, SignalDirection, SignalStatus, SignalType};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+/// Lifecycle settings
+#[derive(Debug, Clone)]
+pub struct SignalLifecycleConfig {
+    /// Max bars between occurrences that still belong to the same episode
+    pub merge_gap_bars: usize,
+    /// Bars after an episode ends during which repeats are suppressed
+    pub default_cooldown_bars: usize,
+    /// Per-type cooldown overrides
+    pub cooldowns: HashMap<SignalType, usize>,
+    /// Bars after an episode's last bar until it expires
+    pub expiry_bars: usize,
+}
+
+impl Default for SignalLifecycleConfig {
+    fn default() -> Self {
+        Self {
+            merge_gap_bars: 1,
+            default_cooldown_bars: 5,
+            cooldowns: HashMap::from([
+                // Trend-state signals persist for long stretches
+                (SignalType::AdxTrendStrong, 20),
+                (SignalType::AdxTrendWeak, 20),
+            ]),
+            expiry_bars: 10,
+        }
+    }
+}
+
+impl SignalLifecycleConfig {
+    pub fn cooldown(&self, signal_type: SignalType) -> usize {
+        self.cooldowns
+            .get(&signal_type)
+            .copied()
+            .unwrap_or(self.default_cooldown_bars)
+    }
+}
+
+fn opposite(a: SignalDirection, b: SignalDirection) -> bool {
+    matches!(
+        (a, b),
+        (SignalDirection::Bullish, SignalDirection::Bearish)
+            | (SignalDirection::Bearish, SignalDirection::Bullish)
+    )
+}
+
+/// Merge raw signals into episodes and assign their status as of the last bar
+///
+/// `calendar` is the symbol's trading dates (used to count bars); signals on
+/// dates outside it are kept as single-bar episodes counted from the nearest
+/// following bar.
+pub fn apply_lifecycle(
+    signals: Vec<Signal>,
+    calendar: &[NaiveDate],
+    config: &SignalLifecycleConfig,
+) -> Vec<Signal> {
+    let mut calendar = calendar.to_vec();
+    calendar.sort();
+    calendar.dedup();
+    let bar = |date: NaiveDate| calendar.partition_point(|d| *d < date);
+
+    // Group by (symbol, type), oldest first
+    let mut groups: HashMap<(String, SignalType), Vec<Signal>> = HashMap::new();
+    for signal in signals {
+        groups
+            .entry((signal.symbol.clone(), signal.signal_type.clone()))
+            .or_default()
+            .push(signal);
+    }
+
+    let mut episodes: Vec<(usize, usize, Signal)> = Vec::new();
+    for ((_, signal_type), mut group) in groups {
+        group.sort_by_key(|s| s.timestamp);
+        let cooldown = config.cooldown(signal_type);
+        let mut current: Option<(usize, usize, Signal)> = None;
+
+        for signal in group {
+            let idx = bar(signal.timestamp);
+            match current.as_mut() {
+                Some((_, end, episode)) if idx - *end <= config.merge_gap_bars => {
+                    *end = idx;
+                    episode.end_date = Some(signal.timestamp);
+                    episode.strength = episode.strength.max(signal.strength);
+                }
+                Some((_, end, _)) if idx - *end <= cooldown => {}
+                _ => {
+                    if let Some(done) = current.take() {
+                        episodes.push(done);
+                    }
+                    current = Some((idx, idx, signal));
+                }
+            }
+        }
+        episodes.extend(current);
+    }
+
+    let mut result: Vec<Signal> = episodes.into_iter().map(|(_, _, episode)| episode).collect();
+    assign_status(&mut result, &calendar, config);
+
+    result.sort_by(|a, b| {
+        a.timestamp
+            .cmp(&b.timestamp)
+            .then(a.signal_type.as_str().cmp(b.signal_type.as_str()))
+    });
+    result
+}
+
+/// Set each episode's status as of the last bar of `calendar`
+pub fn assign_status(
+    episodes: &mut [Signal],
+    calendar: &[NaiveDate],
+    config: &SignalLifecycleConfig,
+) {
+    let mut calendar = calendar.to_vec();
+    calendar.sort();
+    calendar.dedup();
+    let bar = |date: NaiveDate| calendar.partition_point(|d| *d < date);
+    let last_bar = calendar.len().saturating_sub(1);
+
+    // Opposite signals from the same indicator, by symbol
+    let mut by_trigger: HashMap<(String, String), Vec<(usize, SignalDirection)>> = HashMap::new();
+    for s in episodes.iter() {
+        by_trigger
+            .entry((s.symbol.clone(), s.triggered_by.clone()))
+            .or_default()
+            .push((bar(s.timestamp), s.direction));
+    }
+
+    for signal in episodes.iter_mut() {
+        let start = bar(signal.timestamp);
+        let end = bar(signal.end_date.unwrap_or(signal.timestamp)).max(start);
+        let expires = end + config.expiry_bars;
+        let invalidated = by_trigger
+            .get(&(signal.symbol.clone(), signal.triggered_by.clone()))
+            .is_some_and(|others| {
+                others.iter().any(|(idx, dir)| {
+                    *idx > start && *idx <= expires.min(last_bar) && opposite(*dir, signal.direction)
+                })
+            });
+
+        signal.status = if invalidated {
+            SignalStatus::Invalidated
+        } else if last_bar >= expires {
+            SignalStatus::Expired
+        } else if end == last_bar && start == last_bar {
+            SignalStatus::New
+        } else {
+            SignalStatus::Active
+        };
+    }
+}
+
+/// Re-age a symbol's stored signals against its current price history, so
+/// episodes expire (and new ones become active) without regenerating signals.
+/// Returns the number of signals whose status changed.
+pub fn refresh_signal_status(
+    db: &Database,
+    symbol: &str,
+    config: &SignalLifecycleConfig,
+) -> Result<usize> {
+    let calendar: Vec<NaiveDate> = db.get_prices(symbol)?.iter().map(|p| p.date).collect();
+    if calendar.is_empty() {
+        return Ok(0);
+    }
+
+    let mut signals = db.get_signals(symbol, false)?;
+    let before: Vec<SignalStatus> = signals.iter().map(|s| s.status).collect();
+    assign_status(&mut signals, &calendar, config);
+
+    let changed: Vec<Signal> = signals
+        .into_iter()
+        .zip(before)
+        .filter(|(s, old)| s.status != *old)
+        .map(|(s, _)| s)
+        .collect();
+    db.update_signal_statuses(&changed)?;
+    Ok(changed.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn calendar(n: i64) -> Vec<NaiveDate> {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        (0..n).map(|i| start + chrono::Duration::days(i)).collect()
+    }
+
+    fn signal(date: NaiveDate, signal_type: SignalType, direction: SignalDirection) -> Signal {
+        Signal {
+            id: 0,
+            symbol: "TEST".to_string(),
+            signal_type,
+            direction,
+            strength: 0.5,
+            price_at_signal: 100.0,
+            triggered_by: "RSI_14".to_string(),
+            trigger_value: 25.0,
+            timestamp: date,
+            created_at: String::new(),
+            acknowledged: false,
+            status: SignalStatus::New,
+            end_date: None,
+        }
+    }
+
+    #[test]
+    fn test_consecutive_signals_merge_and_cooldown_suppresses() {
+        let days = calendar(30);
+        let raw: Vec<Signal> = [0, 1, 2, 5, 20]
+            .iter()
+            .map(|&i| signal(days[i], SignalType::RsiOversold, SignalDirection::Bullish))
+            .collect();
+
+        let episodes = apply_lifecycle(raw, &days, &SignalLifecycleConfig::default());
+        assert_eq!(episodes.len(), 2, "days 0-2 merge, day 5 is in cooldown, day 20 is new");
+        assert_eq!(episodes[0].timestamp, days[0]);
+        assert_eq!(episodes[0].end_date, Some(days[2]));
+        assert_eq!(episodes[0].status, SignalStatus::Expired);
+        assert_eq!(episodes[1].timestamp, days[20]);
+        assert_eq!(episodes[1].status, SignalStatus::Active);
+    }
+
+    #[test]
+    fn test_new_and_invalidated_status() {
+        let days = calendar(10);
+        let mut macd = signal(days[9], SignalType::MacdBullishCross, SignalDirection::Bullish);
+        macd.triggered_by = "MACD_12_26".to_string();
+        let raw = vec![
+            signal(days[3], SignalType::RsiOversold, SignalDirection::Bullish),
+            signal(days[6], SignalType::RsiOverbought, SignalDirection::Bearish),
+            macd,
+        ];
+
+        let episodes = apply_lifecycle(raw, &days, &SignalLifecycleConfig::default());
+        let status = |t: SignalType| episodes.iter().find(|s| s.signal_type == t).unwrap().status;
+        assert_eq!(status(SignalType::RsiOversold), SignalStatus::Invalidated);
+        assert_eq!(status(SignalType::RsiOverbought), SignalStatus::Active);
+        assert_eq!(status(SignalType::MacdBullishCross), SignalStatus::New);
+    }
+
+    #[test]
+    fn test_assign_status_reages_stored_episodes() {
+        let days = calendar(30);
+        let raw = vec![signal(days[9], SignalType::RsiOversold, SignalDirection::Bullish)];
+        let mut episodes = apply_lifecycle(raw, &days[..10], &SignalLifecycleConfig::default());
+        assert_eq!(episodes[0].status, SignalStatus::New);
+
+        assign_status(&mut episodes, &days[..12], &SignalLifecycleConfig::default());
+        assert_eq!(episodes[0].status, SignalStatus::Active);
+
+        assign_status(&mut episodes, &days, &SignalLifecycleConfig::default());
+        assert_eq!(episodes[0].status, SignalStatus::Expired);
+    }
+}
@@ -2,11 +2,21 @@
 //!
 //! Detects trading signals from technical indicators
 
+use crate::db::Database;
+use crate::error::Result;
 use crate::models::{
-    ConfluenceConfig, ConfluenceSignal, DailyPrice, IndicatorVote, Signal, SignalDirection,
-    SignalType, TechnicalIndicator,
+    ConfluenceConfig, ConfluenceSignal, DailyPrice, IndicatorVote, MarketRegime, Signal,
+    SignalDirection, SignalStatus, SignalType, TechnicalIndicator,
 };
+use crate::divergence::{find_divergences, DivergenceKind, DIVERGENCE_INDICATORS, DIVERGENCE_VOTE_PREFIX};
+use crate::indicators::calculate_all;
+use crate::detectors::{DetectorContext, DetectorRegistry, SignalDetector};
+use crate::price_action::{PriceActionConfig, PRICE_ACTION_VOTE_PREFIX};
+use crate::regime::{RegimeConfig, RegimeSeries};
+use crate::signal_lifecycle::{apply_lifecycle, SignalLifecycleConfig};
+use crate::timeframe::{resample, HigherTimeframeTrend, Timeframe};
 use chrono::NaiveDate;
+use std::borrow::Cow;
 use std::collections::HashMap;
 
 /// Configuration for signal detection thresholds
@@ -24,6 +34,23 @@ pub struct SignalConfig {
     pub cci_oversold: f64,
     pub mfi_overbought: f64,
     pub mfi_oversold: f64,
+    /// Higher timeframe whose trend must agree with a signal's direction (None = no filter)
+    pub trend_timeframe: Option<Timeframe>,
+    /// SMA period, in higher-timeframe bars, that defines the trend
+    pub trend_sma_period: usize,
+    /// Max bars between the two swings of a divergence
+    pub divergence_lookback: usize,
+    /// Bars on each side required to confirm a swing high/low
+    pub divergence_swing_window: usize,
+    /// Episode merging, cooldowns and expiry used by `generate_signal_episodes`
+    /// (None = store raw per-bar signals)
+    pub lifecycle: Option<SignalLifecycleConfig>,
+    /// Regimes in which each signal type may fire (types not listed are unfiltered)
+    pub regime_filters: HashMap<SignalType, Vec<MarketRegime>>,
+    /// Thresholds for the symbol regime used by `regime_filters`
+    pub regime: RegimeConfig,
+    /// Volume, gap and range signal thresholds
+    pub price_action: PriceActionConfig,
 }
 
 impl Default for SignalConfig {
@@ -41,154 +68,68 @@ impl Default for SignalConfig {
             cci_oversold: -100.0,
             mfi_overbought: 80.0,
             mfi_oversold: 20.0,
+            trend_timeframe: None,
+            trend_sma_period: 10,
+            divergence_lookback: 60,
+            divergence_swing_window: 5,
+            lifecycle: Some(SignalLifecycleConfig::default()),
+            regime_filters: HashMap::new(),
+            regime: RegimeConfig::default(),
+            price_action: PriceActionConfig::default(),
         }
     }
 }
 
-/// Main signal generator
-pub struct SignalEngine {
-    config: SignalConfig,
-    confluence_config: ConfluenceConfig,
-}
-
-impl Default for SignalEngine {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl SignalEngine {
-    pub fn new() -> Self {
-        Self {
-            config: SignalConfig::default(),
-            confluence_config: ConfluenceConfig::default(),
-        }
-    }
+// ============================================================================
+// Built-in Detectors
+// ============================================================================
 
-    pub fn with_config(config: SignalConfig) -> Self {
-        Self {
-            config,
-            confluence_config: ConfluenceConfig::default(),
-        }
-    }
-
-    pub fn with_confluence_config(mut self, confluence_config: ConfluenceConfig) -> Self {
-        self.confluence_config = confluence_config;
-        self
-    }
-
-    /// Build a map of indicators by date for O(1) lookups
-    fn build_indicator_map(
-        &self,
-        indicators: &[TechnicalIndicator],
-    ) -> HashMap<NaiveDate, HashMap<String, f64>> {
-        let mut map: HashMap<NaiveDate, HashMap<String, f64>> = HashMap::new();
-
-        for ind in indicators {
-            map.entry(ind.date)
-                .or_default()
-                .insert(ind.indicator_name.clone(), ind.value);
-        }
-
-        map
-    }
-
-    /// Generate all signals from indicators for a symbol
-    pub fn generate_signals(
+impl SignalConfig {
+    /// Detect regular and hidden divergences between price swings and
+    /// RSI, MACD histogram, OBV and MFI
+    pub(crate) fn detect_divergence_signals(
         &self,
         symbol: &str,
         indicators: &[TechnicalIndicator],
         prices: &[DailyPrice],
     ) -> Vec<Signal> {
-        if prices.is_empty() || indicators.is_empty() {
-            return vec![];
-        }
-
+        let closes: HashMap<NaiveDate, f64> = prices.iter().map(|p| (p.date, p.close)).collect();
         let mut signals = Vec::new();
-        let indicator_map = self.build_indicator_map(indicators);
-
-        // Get sorted dates from prices
-        let mut price_map: HashMap<NaiveDate, &DailyPrice> = HashMap::new();
-        for price in prices {
-            price_map.insert(price.date, price);
-        }
-
-        // Process each date
-        let mut dates: Vec<_> = indicator_map.keys().copied().collect();
-        dates.sort();
-
-        for (i, date) in dates.iter().enumerate() {
-            let Some(indicators_today) = indicator_map.get(date) else {
-                continue;
-            };
-            let indicators_prev = if i > 0 {
-                indicator_map.get(&dates[i - 1])
-            } else {
-                None
-            };
-            let price = price_map.get(date).map(|p| p.close).unwrap_or(0.0);
-
-            // RSI signals
-            if let Some(sig) =
-                self.detect_rsi_signal(symbol, *date, price, indicators_today, indicators_prev)
-            {
-                signals.push(sig);
-            }
-
-            // MACD signals
-            if let Some(sig) =
-                self.detect_macd_signal(symbol, *date, price, indicators_today, indicators_prev)
-            {
-                signals.push(sig);
-            }
-
-            // Bollinger Band signals
-            if let Some(sig) =
-                self.detect_bollinger_signal(symbol, *date, price, indicators_today)
-            {
-                signals.push(sig);
-            }
-
-            // MA Crossover signals
-            if let Some(sig) =
-                self.detect_ma_crossover_signal(symbol, *date, price, indicators_today, indicators_prev)
-            {
-                signals.push(sig);
-            }
-
-            // ADX signals
-            if let Some(sig) =
-                self.detect_adx_signal(symbol, *date, price, indicators_today, indicators_prev)
-            {
-                signals.push(sig);
-            }
 
-            // Stochastic signals
-            if let Some(sig) =
-                self.detect_stochastic_signal(symbol, *date, price, indicators_today, indicators_prev)
-            {
-                signals.push(sig);
-            }
-
-            // Williams %R signals
-            if let Some(sig) =
-                self.detect_willr_signal(symbol, *date, price, indicators_today, indicators_prev)
-            {
-                signals.push(sig);
-            }
-
-            // CCI signals
-            if let Some(sig) =
-                self.detect_cci_signal(symbol, *date, price, indicators_today, indicators_prev)
-            {
-                signals.push(sig);
-            }
-
-            // MFI signals
-            if let Some(sig) =
-                self.detect_mfi_signal(symbol, *date, price, indicators_today, indicators_prev)
-            {
-                signals.push(sig);
+        for name in DIVERGENCE_INDICATORS {
+            let values: Vec<(NaiveDate, f64)> = indicators
+                .iter()
+                .filter(|i| i.indicator_name == name)
+                .map(|i| (i.date, i.value))
+                .collect();
+
+            for div in find_divergences(
+                prices,
+                &values,
+                self.divergence_swing_window,
+                self.divergence_lookback,
+            ) {
+                let signal_type = match div.kind {
+                    DivergenceKind::RegularBullish => SignalType::BullishDivergence,
+                    DivergenceKind::HiddenBullish => SignalType::HiddenBullishDivergence,
+                    DivergenceKind::RegularBearish => SignalType::BearishDivergence,
+                    DivergenceKind::HiddenBearish => SignalType::HiddenBearishDivergence,
+                };
+                signals.push(Signal {
+                    id: 0,
+                    symbol: symbol.to_string(),
+                    signal_type,
+                    direction: div.kind.direction(),
+                    strength: div.strength,
+                    price_at_signal: closes.get(&div.confirmed_date).copied().unwrap_or(div.price),
+                    triggered_by: name.to_string(),
+                    trigger_value: div.indicator_value,
+                    timestamp: div.confirmed_date,
+                    created_at: String::new(),
+                    acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
+                });
             }
         }
 
@@ -196,7 +137,7 @@ impl SignalEngine {
     }
 
     /// Detect RSI overbought/oversold signals
-    fn detect_rsi_signal(
+    pub(crate) fn detect_rsi_signal(
         &self,
         symbol: &str,
         date: NaiveDate,
@@ -208,9 +149,9 @@ impl SignalEngine {
         let prev_rsi = prev.and_then(|p| p.get("RSI_14").copied());
 
         // Detect crossing into overbought
-        if rsi > self.config.rsi_overbought {
-            if prev_rsi.map_or(true, |p| p <= self.config.rsi_overbought) {
-                let strength = ((rsi - self.config.rsi_overbought) / 30.0).min(1.0);
+        if rsi > self.rsi_overbought {
+            if prev_rsi.map_or(true, |p| p <= self.rsi_overbought) {
+                let strength = ((rsi - self.rsi_overbought) / 30.0).min(1.0);
                 return Some(Signal {
                     id: 0,
                     symbol: symbol.to_string(),
@@ -223,13 +164,15 @@ impl SignalEngine {
                     timestamp: date,
                     created_at: String::new(),
                     acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
                 });
             }
         }
         // Detect crossing into oversold
-        else if rsi < self.config.rsi_oversold {
-            if prev_rsi.map_or(true, |p| p >= self.config.rsi_oversold) {
-                let strength = ((self.config.rsi_oversold - rsi) / 30.0).min(1.0);
+        else if rsi < self.rsi_oversold {
+            if prev_rsi.map_or(true, |p| p >= self.rsi_oversold) {
+                let strength = ((self.rsi_oversold - rsi) / 30.0).min(1.0);
                 return Some(Signal {
                     id: 0,
                     symbol: symbol.to_string(),
@@ -242,6 +185,8 @@ impl SignalEngine {
                     timestamp: date,
                     created_at: String::new(),
                     acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
                 });
             }
         }
@@ -250,7 +195,7 @@ impl SignalEngine {
     }
 
     /// Detect MACD crossover signals
-    fn detect_macd_signal(
+    pub(crate) fn detect_macd_signal(
         &self,
         symbol: &str,
         date: NaiveDate,
@@ -278,6 +223,8 @@ impl SignalEngine {
                 timestamp: date,
                 created_at: String::new(),
                 acknowledged: false,
+                status: SignalStatus::New,
+                end_date: None,
             });
         }
         // Bearish crossover: MACD crosses below signal
@@ -295,6 +242,8 @@ impl SignalEngine {
                 timestamp: date,
                 created_at: String::new(),
                 acknowledged: false,
+                status: SignalStatus::New,
+                end_date: None,
             });
         }
 
@@ -302,7 +251,7 @@ impl SignalEngine {
     }
 
     /// Detect Bollinger Band breakout signals
-    fn detect_bollinger_signal(
+    pub(crate) fn detect_bollinger_signal(
         &self,
         symbol: &str,
         date: NaiveDate,
@@ -328,6 +277,8 @@ impl SignalEngine {
                 timestamp: date,
                 created_at: String::new(),
                 acknowledged: false,
+                status: SignalStatus::New,
+                end_date: None,
             });
         }
         // Price breaks below lower band (oversold/potential bounce)
@@ -345,6 +296,8 @@ impl SignalEngine {
                 timestamp: date,
                 created_at: String::new(),
                 acknowledged: false,
+                status: SignalStatus::New,
+                end_date: None,
             });
         }
 
@@ -352,7 +305,7 @@ impl SignalEngine {
     }
 
     /// Detect MA crossover signals (SMA 20/50)
-    fn detect_ma_crossover_signal(
+    pub(crate) fn detect_ma_crossover_signal(
         &self,
         symbol: &str,
         date: NaiveDate,
@@ -380,6 +333,8 @@ impl SignalEngine {
                 timestamp: date,
                 created_at: String::new(),
                 acknowledged: false,
+                status: SignalStatus::New,
+                end_date: None,
             });
         }
         // Death cross: fast MA crosses below slow MA
@@ -397,6 +352,8 @@ impl SignalEngine {
                 timestamp: date,
                 created_at: String::new(),
                 acknowledged: false,
+                status: SignalStatus::New,
+                end_date: None,
             });
         }
 
@@ -404,7 +361,7 @@ impl SignalEngine {
     }
 
     /// Detect ADX trend strength signals
-    fn detect_adx_signal(
+    pub(crate) fn detect_adx_signal(
         &self,
         symbol: &str,
         date: NaiveDate,
@@ -416,9 +373,9 @@ impl SignalEngine {
         let prev_adx = prev.and_then(|p| p.get("ADX_14").copied());
 
         // Trend strengthening: ADX crosses above 25
-        if adx > self.config.adx_strong_trend {
-            if prev_adx.map_or(true, |p| p <= self.config.adx_strong_trend) {
-                let strength = ((adx - self.config.adx_strong_trend) / 25.0).min(1.0);
+        if adx > self.adx_strong_trend {
+            if prev_adx.map_or(true, |p| p <= self.adx_strong_trend) {
+                let strength = ((adx - self.adx_strong_trend) / 25.0).min(1.0);
                 return Some(Signal {
                     id: 0,
                     symbol: symbol.to_string(),
@@ -431,13 +388,15 @@ impl SignalEngine {
                     timestamp: date,
                     created_at: String::new(),
                     acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
                 });
             }
         }
         // Trend weakening: ADX crosses below 20
-        else if adx < self.config.adx_weak_trend {
-            if prev_adx.map_or(true, |p| p >= self.config.adx_weak_trend) {
-                let strength = ((self.config.adx_weak_trend - adx) / 20.0).min(1.0);
+        else if adx < self.adx_weak_trend {
+            if prev_adx.map_or(true, |p| p >= self.adx_weak_trend) {
+                let strength = ((self.adx_weak_trend - adx) / 20.0).min(1.0);
                 return Some(Signal {
                     id: 0,
                     symbol: symbol.to_string(),
@@ -450,6 +409,8 @@ impl SignalEngine {
                     timestamp: date,
                     created_at: String::new(),
                     acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
                 });
             }
         }
@@ -458,7 +419,7 @@ impl SignalEngine {
     }
 
     /// Detect Stochastic crossover signals
-    fn detect_stochastic_signal(
+    pub(crate) fn detect_stochastic_signal(
         &self,
         symbol: &str,
         date: NaiveDate,
@@ -472,7 +433,7 @@ impl SignalEngine {
         let prev_d = prev.and_then(|p| p.get("STOCH_D_3").copied())?;
 
         // Bullish crossover from oversold
-        if prev_k <= prev_d && k > d && k < self.config.stoch_oversold + 20.0 {
+        if prev_k <= prev_d && k > d && k < self.stoch_oversold + 20.0 {
             let strength = ((d - k).abs() / 20.0).min(1.0);
             return Some(Signal {
                 id: 0,
@@ -486,10 +447,12 @@ impl SignalEngine {
                 timestamp: date,
                 created_at: String::new(),
                 acknowledged: false,
+                status: SignalStatus::New,
+                end_date: None,
             });
         }
         // Bearish crossover from overbought
-        else if prev_k >= prev_d && k < d && k > self.config.stoch_overbought - 20.0 {
+        else if prev_k >= prev_d && k < d && k > self.stoch_overbought - 20.0 {
             let strength = ((k - d).abs() / 20.0).min(1.0);
             return Some(Signal {
                 id: 0,
@@ -503,6 +466,8 @@ impl SignalEngine {
                 timestamp: date,
                 created_at: String::new(),
                 acknowledged: false,
+                status: SignalStatus::New,
+                end_date: None,
             });
         }
 
@@ -510,7 +475,7 @@ impl SignalEngine {
     }
 
     /// Detect Williams %R signals
-    fn detect_willr_signal(
+    pub(crate) fn detect_willr_signal(
         &self,
         symbol: &str,
         date: NaiveDate,
@@ -522,9 +487,9 @@ impl SignalEngine {
         let prev_willr = prev.and_then(|p| p.get("WILLR_14").copied());
 
         // Overbought (Williams %R > -20)
-        if willr > self.config.willr_overbought {
-            if prev_willr.map_or(true, |p| p <= self.config.willr_overbought) {
-                let strength = ((willr - self.config.willr_overbought) / 20.0).min(1.0);
+        if willr > self.willr_overbought {
+            if prev_willr.map_or(true, |p| p <= self.willr_overbought) {
+                let strength = ((willr - self.willr_overbought) / 20.0).min(1.0);
                 return Some(Signal {
                     id: 0,
                     symbol: symbol.to_string(),
@@ -537,13 +502,15 @@ impl SignalEngine {
                     timestamp: date,
                     created_at: String::new(),
                     acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
                 });
             }
         }
         // Oversold (Williams %R < -80)
-        else if willr < self.config.willr_oversold {
-            if prev_willr.map_or(true, |p| p >= self.config.willr_oversold) {
-                let strength = ((self.config.willr_oversold - willr) / 20.0).min(1.0);
+        else if willr < self.willr_oversold {
+            if prev_willr.map_or(true, |p| p >= self.willr_oversold) {
+                let strength = ((self.willr_oversold - willr) / 20.0).min(1.0);
                 return Some(Signal {
                     id: 0,
                     symbol: symbol.to_string(),
@@ -556,6 +523,8 @@ impl SignalEngine {
                     timestamp: date,
                     created_at: String::new(),
                     acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
                 });
             }
         }
@@ -564,7 +533,7 @@ impl SignalEngine {
     }
 
     /// Detect CCI signals
-    fn detect_cci_signal(
+    pub(crate) fn detect_cci_signal(
         &self,
         symbol: &str,
         date: NaiveDate,
@@ -576,9 +545,9 @@ impl SignalEngine {
         let prev_cci = prev.and_then(|p| p.get("CCI_20").copied());
 
         // Overbought (CCI > 100)
-        if cci > self.config.cci_overbought {
-            if prev_cci.map_or(true, |p| p <= self.config.cci_overbought) {
-                let strength = ((cci - self.config.cci_overbought) / 100.0).min(1.0);
+        if cci > self.cci_overbought {
+            if prev_cci.map_or(true, |p| p <= self.cci_overbought) {
+                let strength = ((cci - self.cci_overbought) / 100.0).min(1.0);
                 return Some(Signal {
                     id: 0,
                     symbol: symbol.to_string(),
@@ -591,13 +560,15 @@ impl SignalEngine {
                     timestamp: date,
                     created_at: String::new(),
                     acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
                 });
             }
         }
         // Oversold (CCI < -100)
-        else if cci < self.config.cci_oversold {
-            if prev_cci.map_or(true, |p| p >= self.config.cci_oversold) {
-                let strength = ((self.config.cci_oversold - cci) / 100.0).min(1.0);
+        else if cci < self.cci_oversold {
+            if prev_cci.map_or(true, |p| p >= self.cci_oversold) {
+                let strength = ((self.cci_oversold - cci) / 100.0).min(1.0);
                 return Some(Signal {
                     id: 0,
                     symbol: symbol.to_string(),
@@ -610,6 +581,8 @@ impl SignalEngine {
                     timestamp: date,
                     created_at: String::new(),
                     acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
                 });
             }
         }
@@ -618,7 +591,7 @@ impl SignalEngine {
     }
 
     /// Detect MFI signals
-    fn detect_mfi_signal(
+    pub(crate) fn detect_mfi_signal(
         &self,
         symbol: &str,
         date: NaiveDate,
@@ -630,9 +603,9 @@ impl SignalEngine {
         let prev_mfi = prev.and_then(|p| p.get("MFI_14").copied());
 
         // Overbought (MFI > 80)
-        if mfi > self.config.mfi_overbought {
-            if prev_mfi.map_or(true, |p| p <= self.config.mfi_overbought) {
-                let strength = ((mfi - self.config.mfi_overbought) / 20.0).min(1.0);
+        if mfi > self.mfi_overbought {
+            if prev_mfi.map_or(true, |p| p <= self.mfi_overbought) {
+                let strength = ((mfi - self.mfi_overbought) / 20.0).min(1.0);
                 return Some(Signal {
                     id: 0,
                     symbol: symbol.to_string(),
@@ -645,13 +618,15 @@ impl SignalEngine {
                     timestamp: date,
                     created_at: String::new(),
                     acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
                 });
             }
         }
         // Oversold (MFI < 20)
-        else if mfi < self.config.mfi_oversold {
-            if prev_mfi.map_or(true, |p| p >= self.config.mfi_oversold) {
-                let strength = ((self.config.mfi_oversold - mfi) / 20.0).min(1.0);
+        else if mfi < self.mfi_oversold {
+            if prev_mfi.map_or(true, |p| p >= self.mfi_oversold) {
+                let strength = ((self.mfi_oversold - mfi) / 20.0).min(1.0);
                 return Some(Signal {
                     id: 0,
                     symbol: symbol.to_string(),
@@ -664,54 +639,229 @@ impl SignalEngine {
                     timestamp: date,
                     created_at: String::new(),
                     acknowledged: false,
+                    status: SignalStatus::New,
+                    end_date: None,
                 });
             }
         }
 
         None
     }
+}
+
+/// Pseudo-indicator carrying a day's confluence signal for strategy rules:
+/// +strength when bullish, -strength when bearish
+pub const CONFLUENCE_INDICATOR: &str = "CONFLUENCE";
+
+/// Prefix of the pseudo-indicators carrying individual signals for strategy
+/// rules, named `SIGNAL_<TYPE>_<DIRECTION>`
+pub const SIGNAL_INDICATOR_PREFIX: &str = "SIGNAL_";
+
+/// Pseudo-indicator holding the strongest `signal_type` signal in `direction`
+/// that fired on a day
+pub fn signal_indicator_name(signal_type: &SignalType, direction: SignalDirection) -> String {
+    format!(
+        "{}{}_{}",
+        SIGNAL_INDICATOR_PREFIX,
+        signal_type.as_str(),
+        direction.as_str().to_uppercase()
+    )
+}
+
+/// Whether `name` is a signal or confluence pseudo-indicator
+pub fn is_signal_indicator(name: &str) -> bool {
+    name == CONFLUENCE_INDICATOR || name.starts_with(SIGNAL_INDICATOR_PREFIX)
+}
+
+/// Signal engine with the saved detector settings and the active
+/// confluence profile's vote weights applied
+pub fn configured_signal_engine(db: &Database) -> Result<SignalEngine> {
+    let mut engine = SignalEngine::new();
+    engine.registry_mut().apply_settings(&db.get_detector_settings()?)?;
+    if let Some(profile) = db.get_active_confluence_profile()? {
+        engine = engine.with_confluence_config(ConfluenceConfig::default().with_profile(&profile));
+    }
+    Ok(engine)
+}
+
+/// Main signal generator
+pub struct SignalEngine {
+    confluence_config: ConfluenceConfig,
+    registry: DetectorRegistry,
+}
+
+impl Default for SignalEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SignalEngine {
+    pub fn new() -> Self {
+        Self::with_config(SignalConfig::default())
+    }
+
+    pub fn with_config(config: SignalConfig) -> Self {
+        Self {
+            registry: DetectorRegistry::builtin(&config),
+            confluence_config: ConfluenceConfig::default(),
+        }
+    }
+
+    /// Thresholds in effect, including detector parameters tuned through
+    /// `registry_mut`
+    pub fn config(&self) -> &SignalConfig {
+        self.registry.config()
+    }
+
+    pub fn with_confluence_config(mut self, confluence_config: ConfluenceConfig) -> Self {
+        self.confluence_config = confluence_config;
+        self
+    }
+
+    /// Register an additional detector (replacing any with the same id)
+    pub fn with_detector(mut self, detector: Box<dyn SignalDetector>) -> Self {
+        self.registry.register(detector);
+        self
+    }
+
+    /// Detectors run by `generate_signals`
+    pub fn registry(&self) -> &DetectorRegistry {
+        &self.registry
+    }
+
+    /// Enable, disable or tune detectors at runtime
+    pub fn registry_mut(&mut self) -> &mut DetectorRegistry {
+        &mut self.registry
+    }
+
+    /// Build a map of indicators by date for O(1) lookups
+    fn build_indicator_map(
+        &self,
+        indicators: &[TechnicalIndicator],
+    ) -> HashMap<NaiveDate, HashMap<String, f64>> {
+        let mut map: HashMap<NaiveDate, HashMap<String, f64>> = HashMap::new();
+
+        for ind in indicators {
+            map.entry(ind.date)
+                .or_default()
+                .insert(ind.indicator_name.clone(), ind.value);
+        }
+
+        map
+    }
+
+    /// Generate all raw per-bar signals from indicators for a symbol
+    pub fn generate_signals(
+        &self,
+        symbol: &str,
+        indicators: &[TechnicalIndicator],
+        prices: &[DailyPrice],
+    ) -> Vec<Signal> {
+        if prices.is_empty() || indicators.is_empty() {
+            return vec![];
+        }
+
+        let indicator_map = self.build_indicator_map(indicators);
+        let mut dates: Vec<_> = indicator_map.keys().copied().collect();
+        dates.sort();
+        let mut sorted_prices = prices.to_vec();
+        sorted_prices.sort_by_key(|p| p.date);
+
+        let ctx = DetectorContext {
+            symbol,
+            prices: &sorted_prices,
+            indicators,
+            indicator_map: &indicator_map,
+            dates: &dates,
+        };
+        let mut signals = self.registry.detect(&ctx);
+
+        // Require higher-timeframe trend alignment if configured
+        if let Some(trend) = self.higher_timeframe_trend(prices) {
+            signals.retain(|s| trend.allows(s.timestamp, s.direction));
+        }
+
+        // Drop signal types outside their allowed regimes
+        let config = self.config();
+        if !config.regime_filters.is_empty() {
+            let regimes = RegimeSeries::from_prices(prices, &config.regime);
+            signals.retain(|s| match config.regime_filters.get(&s.signal_type) {
+                Some(allowed) => regimes.allows(s.timestamp, allowed),
+                None => true,
+            });
+        }
+
+        signals
+    }
+
+    /// `generate_signals` merged into episodes by the configured lifecycle,
+    /// as stored and shown to the AI trader
+    pub fn generate_signal_episodes(
+        &self,
+        symbol: &str,
+        indicators: &[TechnicalIndicator],
+        prices: &[DailyPrice],
+    ) -> Vec<Signal> {
+        let signals = self.generate_signals(symbol, indicators, prices);
+        match &self.config().lifecycle {
+            Some(lifecycle) => {
+                let calendar: Vec<NaiveDate> = prices.iter().map(|p| p.date).collect();
+                apply_lifecycle(signals, &calendar, lifecycle)
+            }
+            None => signals,
+        }
+    }
+
+    /// Detect regular and hidden divergences between price swings and
+    /// RSI, MACD histogram, OBV and MFI
+    pub fn detect_divergence_signals(
+        &self,
+        symbol: &str,
+        indicators: &[TechnicalIndicator],
+        prices: &[DailyPrice],
+    ) -> Vec<Signal> {
+        self.config().detect_divergence_signals(symbol, indicators, prices)
+    }
+
+    /// Build the higher-timeframe trend filter, if one is configured
+    fn higher_timeframe_trend(&self, prices: &[DailyPrice]) -> Option<HigherTimeframeTrend> {
+        let config = self.config();
+        config
+            .trend_timeframe
+            .map(|tf| HigherTimeframeTrend::from_prices(prices, tf, config.trend_sma_period))
+    }
 
     // ========================================================================
     // Confluence Signal Detection
     // ========================================================================
 
-    /// Detect confluence signal when 3+ indicators agree on direction
-    /// Returns ConfluenceSignal if enough indicators agree, None otherwise
-    pub fn detect_confluence_signal(
+    /// Collect every indicator's directional vote for one bar, before
+    /// weighting and correlated-vote de-duplication
+    pub fn collect_confluence_votes(
         &self,
-        symbol: &str,
-        date: NaiveDate,
         price: f64,
         indicators: &HashMap<String, f64>,
-    ) -> Option<ConfluenceSignal> {
+    ) -> Vec<IndicatorVote> {
+        let cfg = &self.confluence_config;
         let mut votes: Vec<IndicatorVote> = Vec::new();
-        let mut bullish_count = 0usize;
-        let mut bearish_count = 0usize;
-        let mut bullish_strength_sum = 0.0f64;
-        let mut bearish_strength_sum = 0.0f64;
+        let mut vote = |name: &str, direction: SignalDirection, strength: f64, value: f64| {
+            votes.push(IndicatorVote {
+                indicator_name: name.to_string(),
+                direction,
+                strength,
+                value,
+            });
+        };
 
         // RSI vote
         if let Some(&rsi) = indicators.get("RSI_14") {
-            if rsi < self.confluence_config.rsi_oversold {
-                let strength = ((self.confluence_config.rsi_oversold - rsi) / 30.0).min(1.0);
-                votes.push(IndicatorVote {
-                    indicator_name: "RSI_14".to_string(),
-                    direction: SignalDirection::Bullish,
-                    strength,
-                    value: rsi,
-                });
-                bullish_count += 1;
-                bullish_strength_sum += strength;
-            } else if rsi > self.confluence_config.rsi_overbought {
-                let strength = ((rsi - self.confluence_config.rsi_overbought) / 30.0).min(1.0);
-                votes.push(IndicatorVote {
-                    indicator_name: "RSI_14".to_string(),
-                    direction: SignalDirection::Bearish,
-                    strength,
-                    value: rsi,
-                });
-                bearish_count += 1;
-                bearish_strength_sum += strength;
+            if rsi < cfg.rsi_oversold {
+                let strength = ((cfg.rsi_oversold - rsi) / 30.0).min(1.0);
+                vote("RSI_14", SignalDirection::Bullish, strength, rsi);
+            } else if rsi > cfg.rsi_overbought {
+                let strength = ((rsi - cfg.rsi_overbought) / 30.0).min(1.0);
+                vote("RSI_14", SignalDirection::Bearish, strength, rsi);
             }
         }
 
@@ -721,26 +871,11 @@ impl SignalEngine {
             indicators.get("MACD_SIGNAL_9"),
         ) {
             let diff = macd - signal;
+            let strength = (diff.abs() / price.max(1.0) * 100.0).min(1.0);
             if diff > 0.0 {
-                let strength = (diff.abs() / price.max(1.0) * 100.0).min(1.0);
-                votes.push(IndicatorVote {
-                    indicator_name: "MACD".to_string(),
-                    direction: SignalDirection::Bullish,
-                    strength,
-                    value: macd,
-                });
-                bullish_count += 1;
-                bullish_strength_sum += strength;
+                vote("MACD", SignalDirection::Bullish, strength, macd);
             } else if diff < 0.0 {
-                let strength = (diff.abs() / price.max(1.0) * 100.0).min(1.0);
-                votes.push(IndicatorVote {
-                    indicator_name: "MACD".to_string(),
-                    direction: SignalDirection::Bearish,
-                    strength,
-                    value: macd,
-                });
-                bearish_count += 1;
-                bearish_strength_sum += strength;
+                vote("MACD", SignalDirection::Bearish, strength, macd);
             }
         }
 
@@ -749,99 +884,134 @@ impl SignalEngine {
             indicators.get("BB_UPPER_20"),
             indicators.get("BB_LOWER_20"),
         ) {
+            let middle = (upper + lower) / 2.0;
             if price < lower {
-                let middle = (upper + lower) / 2.0;
                 let strength = ((lower - price) / (middle - lower).max(0.01)).min(1.0);
-                votes.push(IndicatorVote {
-                    indicator_name: "BB_LOWER".to_string(),
-                    direction: SignalDirection::Bullish,
-                    strength,
-                    value: price,
-                });
-                bullish_count += 1;
-                bullish_strength_sum += strength;
+                vote("BB_LOWER", SignalDirection::Bullish, strength, price);
             } else if price > upper {
-                let middle = (upper + lower) / 2.0;
                 let strength = ((price - upper) / (upper - middle).max(0.01)).min(1.0);
-                votes.push(IndicatorVote {
-                    indicator_name: "BB_UPPER".to_string(),
-                    direction: SignalDirection::Bearish,
-                    strength,
-                    value: price,
-                });
-                bearish_count += 1;
-                bearish_strength_sum += strength;
+                vote("BB_UPPER", SignalDirection::Bearish, strength, price);
             }
         }
 
         // Stochastic vote
         if let Some(&stoch_k) = indicators.get("STOCH_K_14") {
-            if stoch_k < self.confluence_config.stoch_oversold {
-                let strength =
-                    ((self.confluence_config.stoch_oversold - stoch_k) / 20.0).min(1.0);
-                votes.push(IndicatorVote {
-                    indicator_name: "STOCH_K".to_string(),
-                    direction: SignalDirection::Bullish,
-                    strength,
-                    value: stoch_k,
-                });
-                bullish_count += 1;
-                bullish_strength_sum += strength;
-            } else if stoch_k > self.confluence_config.stoch_overbought {
-                let strength =
-                    ((stoch_k - self.confluence_config.stoch_overbought) / 20.0).min(1.0);
-                votes.push(IndicatorVote {
-                    indicator_name: "STOCH_K".to_string(),
-                    direction: SignalDirection::Bearish,
-                    strength,
-                    value: stoch_k,
-                });
-                bearish_count += 1;
-                bearish_strength_sum += strength;
+            if stoch_k < cfg.stoch_oversold {
+                let strength = ((cfg.stoch_oversold - stoch_k) / 20.0).min(1.0);
+                vote("STOCH_K", SignalDirection::Bullish, strength, stoch_k);
+            } else if stoch_k > cfg.stoch_overbought {
+                let strength = ((stoch_k - cfg.stoch_overbought) / 20.0).min(1.0);
+                vote("STOCH_K", SignalDirection::Bearish, strength, stoch_k);
+            }
+        }
+
+        // Williams %R vote (opt-in)
+        if let Some(&willr) = indicators.get("WILLR_14").filter(|_| cfg.willr_vote) {
+            if willr < cfg.willr_oversold {
+                let strength = ((cfg.willr_oversold - willr) / 20.0).min(1.0);
+                vote("WILLR_14", SignalDirection::Bullish, strength, willr);
+            } else if willr > cfg.willr_overbought {
+                let strength = ((willr - cfg.willr_overbought) / 20.0).min(1.0);
+                vote("WILLR_14", SignalDirection::Bearish, strength, willr);
             }
         }
 
         // CCI vote
         if let Some(&cci) = indicators.get("CCI_20") {
-            if cci < self.confluence_config.cci_oversold {
-                let strength =
-                    ((self.confluence_config.cci_oversold - cci) / 100.0).abs().min(1.0);
-                votes.push(IndicatorVote {
-                    indicator_name: "CCI_20".to_string(),
-                    direction: SignalDirection::Bullish,
-                    strength,
-                    value: cci,
-                });
-                bullish_count += 1;
-                bullish_strength_sum += strength;
-            } else if cci > self.confluence_config.cci_overbought {
-                let strength =
-                    ((cci - self.confluence_config.cci_overbought) / 100.0).abs().min(1.0);
-                votes.push(IndicatorVote {
-                    indicator_name: "CCI_20".to_string(),
-                    direction: SignalDirection::Bearish,
-                    strength,
-                    value: cci,
-                });
-                bearish_count += 1;
-                bearish_strength_sum += strength;
+            if cci < cfg.cci_oversold {
+                let strength = ((cfg.cci_oversold - cci) / 100.0).abs().min(1.0);
+                vote("CCI_20", SignalDirection::Bullish, strength, cci);
+            } else if cci > cfg.cci_overbought {
+                let strength = ((cci - cfg.cci_overbought) / 100.0).abs().min(1.0);
+                vote("CCI_20", SignalDirection::Bearish, strength, cci);
+            }
+        }
+
+        // Signal votes: "DIV_<indicator>" and "PA_<signal type>" =
+        // +strength (bullish) / -strength (bearish)
+        let mut signal_keys: Vec<&String> = indicators
+            .keys()
+            .filter(|k| k.starts_with(DIVERGENCE_VOTE_PREFIX) || k.starts_with(PRICE_ACTION_VOTE_PREFIX))
+            .collect();
+        signal_keys.sort();
+        for key in signal_keys {
+            let value = indicators[key];
+            if value > 0.0 {
+                vote(key, SignalDirection::Bullish, value.min(1.0), value);
+            } else if value < 0.0 {
+                vote(key, SignalDirection::Bearish, value.abs().min(1.0), value);
             }
         }
 
+        votes
+    }
+
+    /// Keep only the strongest vote within each correlated group
+    fn dedupe_correlated_votes(&self, votes: Vec<IndicatorVote>) -> Vec<IndicatorVote> {
+        let groups = &self.confluence_config.correlated_groups;
+        let group_of = |name: &str| groups.iter().position(|g| g.iter().any(|n| n == name));
+
+        let mut strongest: HashMap<usize, usize> = HashMap::new();
+        for (i, vote) in votes.iter().enumerate() {
+            if let Some(g) = group_of(&vote.indicator_name) {
+                let best = strongest.entry(g).or_insert(i);
+                if vote.strength > votes[*best].strength {
+                    *best = i;
+                }
+            }
+        }
+
+        votes
+            .into_iter()
+            .enumerate()
+            .filter(|(i, vote)| match group_of(&vote.indicator_name) {
+                Some(g) => strongest.get(&g) == Some(i),
+                None => true,
+            })
+            .map(|(_, vote)| vote)
+            .collect()
+    }
+
+    /// Detect confluence signal when enough weighted indicator votes agree on direction
+    /// Returns ConfluenceSignal if enough indicators agree, None otherwise
+    pub fn detect_confluence_signal(
+        &self,
+        symbol: &str,
+        date: NaiveDate,
+        price: f64,
+        indicators: &HashMap<String, f64>,
+    ) -> Option<ConfluenceSignal> {
+        let votes: Vec<IndicatorVote> = self
+            .dedupe_correlated_votes(self.collect_confluence_votes(price, indicators))
+            .into_iter()
+            .filter(|v| self.confluence_config.vote_weight(&v.indicator_name) > 0.0)
+            .collect();
+
+        // (count, summed weight, weighted strength sum) per direction
+        let tally = |direction: SignalDirection| {
+            votes
+                .iter()
+                .filter(|v| v.direction == direction)
+                .fold((0usize, 0.0f64, 0.0f64), |(n, w, s), v| {
+                    let weight = self.confluence_config.vote_weight(&v.indicator_name);
+                    (n + 1, w + weight, s + weight * v.strength)
+                })
+        };
+        let (bullish_count, bullish_weight, bullish_strength_sum) = tally(SignalDirection::Bullish);
+        let (bearish_count, bearish_weight, bearish_strength_sum) = tally(SignalDirection::Bearish);
+
         // ADX - confidence multiplier (doesn't vote on direction)
         let adx_confidence = indicators.get("ADX_14").copied().filter(|&adx| {
             adx > self.confluence_config.adx_strong_trend
         });
 
         // Determine if we have confluence
-        let min_required = self.confluence_config.min_agreeing_indicators;
-
-        let (direction, base_strength) = if bullish_count >= min_required {
-            let avg_strength = bullish_strength_sum / bullish_count as f64;
-            (SignalDirection::Bullish, avg_strength)
-        } else if bearish_count >= min_required {
-            let avg_strength = bearish_strength_sum / bearish_count as f64;
-            (SignalDirection::Bearish, avg_strength)
+        let min_required = self.confluence_config.min_agreeing_indicators as f64;
+
+        let (direction, base_strength) = if bullish_count > 0 && bullish_weight >= min_required {
+            (SignalDirection::Bullish, bullish_strength_sum / bullish_weight)
+        } else if bearish_count > 0 && bearish_weight >= min_required {
+            (SignalDirection::Bearish, bearish_strength_sum / bearish_weight)
         } else {
             return None; // Not enough agreement
         };
@@ -886,18 +1056,103 @@ impl SignalEngine {
             .map(|p| (p.date, p.close))
             .collect();
 
+        // Divergences vote on the date they are confirmed, price action on
+        // the bar it happened
+        let mut signal_votes: HashMap<NaiveDate, Vec<(String, f64)>> = HashMap::new();
+        for sig in &individual_signals {
+            let key = if sig.signal_type.is_divergence() {
+                format!("{}{}", DIVERGENCE_VOTE_PREFIX, sig.triggered_by)
+            } else if sig.signal_type.is_price_action() {
+                format!("{}{}", PRICE_ACTION_VOTE_PREFIX, sig.signal_type.as_str())
+            } else {
+                continue;
+            };
+            let signed = match sig.direction {
+                SignalDirection::Bullish => sig.strength,
+                SignalDirection::Bearish => -sig.strength,
+                SignalDirection::Neutral => continue,
+            };
+            signal_votes.entry(sig.timestamp).or_default().push((key, signed));
+        }
+
         // Check for confluence on each date
         for (date, day_indicators) in &indicator_map {
             let price = price_map.get(date).copied().unwrap_or(0.0);
+            let day_indicators = match signal_votes.get(date) {
+                Some(votes) => {
+                    let mut with_votes = day_indicators.clone();
+                    with_votes.extend(votes.iter().cloned());
+                    Cow::Owned(with_votes)
+                }
+                None => Cow::Borrowed(day_indicators),
+            };
             if let Some(confluence) =
-                self.detect_confluence_signal(symbol, *date, price, day_indicators)
+                self.detect_confluence_signal(symbol, *date, price, &day_indicators)
             {
                 confluence_signals.push(confluence);
             }
         }
 
+        if let Some(trend) = self.higher_timeframe_trend(prices) {
+            confluence_signals.retain(|c| trend.allows(c.date, c.direction));
+        }
+
         (individual_signals, confluence_signals)
     }
+
+    /// Signals and confluence as per-day pseudo-indicators, so strategy rules
+    /// can trade them in backtests: `CONFLUENCE` and `SIGNAL_<TYPE>_<DIRECTION>`.
+    /// Signals count on every bar they fire.
+    pub fn signal_indicators(
+        &self,
+        symbol: &str,
+        indicators: &[TechnicalIndicator],
+        prices: &[DailyPrice],
+    ) -> Vec<TechnicalIndicator> {
+        let (signals, confluence) = self.generate_signals_with_confluence(symbol, indicators, prices);
+
+        let mut strongest: HashMap<(NaiveDate, String), f64> = HashMap::new();
+        for sig in &signals {
+            let name = signal_indicator_name(&sig.signal_type, sig.direction);
+            let value = strongest.entry((sig.timestamp, name)).or_insert(sig.strength);
+            *value = value.max(sig.strength);
+        }
+        let confluence = confluence.into_iter().map(|c| {
+            let value = match c.direction {
+                SignalDirection::Bearish => -c.strength,
+                _ => c.strength,
+            };
+            ((c.date, CONFLUENCE_INDICATOR.to_string()), value)
+        });
+
+        let mut out: Vec<TechnicalIndicator> = strongest
+            .into_iter()
+            .chain(confluence)
+            .map(|((date, indicator_name), value)| TechnicalIndicator {
+                symbol: symbol.to_string(),
+                date,
+                indicator_name,
+                value,
+            })
+            .collect();
+        out.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.indicator_name.cmp(&b.indicator_name)));
+        out
+    }
+
+    /// Generate signals and confluence on a resampled timeframe
+    ///
+    /// Daily prices are resampled, standard indicators are recomputed on the
+    /// resampled bars, and signals are dated with each bar's last trading day.
+    pub fn generate_signals_for_timeframe(
+        &self,
+        symbol: &str,
+        prices: &[DailyPrice],
+        timeframe: Timeframe,
+    ) -> (Vec<Signal>, Vec<ConfluenceSignal>) {
+        let bars = resample(prices, timeframe);
+        let indicators = calculate_all(&bars);
+        self.generate_signals_with_confluence(symbol, &indicators, &bars)
+    }
 }
 
 #[cfg(test)]
@@ -981,6 +1236,229 @@ mod tests {
         assert!(result.is_none(), "Confluence should NOT fire with only 2 agreeing indicators");
     }
 
+    #[test]
+    fn test_divergence_votes_count_toward_confluence() {
+        let engine = SignalEngine::new();
+        let date = NaiveDate::from_ymd_opt(2026, 1, 21).unwrap();
+
+        // Two bullish indicators alone are not enough
+        let mut indicators = HashMap::new();
+        indicators.insert("RSI_14".to_string(), 25.0);
+        indicators.insert("STOCH_K_14".to_string(), 15.0);
+        assert!(engine.detect_confluence_signal("TEST", date, 100.0, &indicators).is_none());
+
+        // A bullish RSI divergence adds the third vote
+        indicators.insert("DIV_RSI_14".to_string(), 0.6);
+        let confluence = engine
+            .detect_confluence_signal("TEST", date, 100.0, &indicators)
+            .expect("divergence should complete confluence");
+        assert_eq!(confluence.direction, SignalDirection::Bullish);
+        assert_eq!(confluence.bullish_count, 3);
+        assert!(confluence
+            .contributing_indicators
+            .iter()
+            .any(|v| v.indicator_name == "DIV_RSI_14"));
+    }
+
+    #[test]
+    fn test_correlated_votes_deduplicated_and_weighted() {
+        let date = NaiveDate::from_ymd_opt(2026, 1, 21).unwrap();
+
+        // Stochastic and Williams %R agree but only count once
+        let mut indicators = HashMap::new();
+        indicators.insert("RSI_14".to_string(), 25.0);
+        indicators.insert("STOCH_K_14".to_string(), 10.0);
+        indicators.insert("WILLR_14".to_string(), -95.0);
+
+        let mut config = ConfluenceConfig { willr_vote: true, ..ConfluenceConfig::default() };
+        let engine = SignalEngine::new().with_confluence_config(config.clone());
+        assert_eq!(engine.collect_confluence_votes(100.0, &indicators).len(), 3);
+        assert!(engine.detect_confluence_signal("TEST", date, 100.0, &indicators).is_none());
+
+        // Doubling RSI's weight reaches the threshold with the same two votes
+        config.weights.insert("RSI_14".to_string(), 2.0);
+        let weighted = SignalEngine::new().with_confluence_config(config);
+        let confluence = weighted
+            .detect_confluence_signal("TEST", date, 100.0, &indicators)
+            .expect("weighted votes should reach the threshold");
+        assert_eq!(confluence.bullish_count, 2);
+    }
+
+    #[test]
+    fn test_willr_vote_only_counts_without_stochastic() {
+        let date = NaiveDate::from_ymd_opt(2026, 1, 21).unwrap();
+        let mut indicators = HashMap::new();
+        indicators.insert("RSI_14".to_string(), 25.0);
+        indicators.insert("CCI_20".to_string(), -150.0);
+        indicators.insert("WILLR_14".to_string(), -95.0);
+
+        // Off by default: Williams %R does not change the count
+        let engine = SignalEngine::new();
+        assert!(engine.detect_confluence_signal("TEST", date, 100.0, &indicators).is_none());
+
+        let engine = SignalEngine::new().with_confluence_config(ConfluenceConfig {
+            willr_vote: true,
+            ..ConfluenceConfig::default()
+        });
+        let confluence = engine
+            .detect_confluence_signal("TEST", date, 100.0, &indicators)
+            .expect("Williams %R should add a third vote");
+        assert_eq!(confluence.bullish_count, 3);
+
+        // With Stochastic agreeing, the pair still counts once
+        indicators.insert("STOCH_K_14".to_string(), 10.0);
+        let confluence = engine
+            .detect_confluence_signal("TEST", date, 100.0, &indicators)
+            .unwrap();
+        assert_eq!(confluence.bullish_count, 3);
+
+        // A profile that weights Williams %R opts in as well
+        let profile = crate::models::ConfluenceProfile {
+            id: 0,
+            name: "willr".to_string(),
+            weights: HashMap::from([("WILLR_14".to_string(), 1.0)]),
+            intercept: 0.0,
+            horizon_days: 5,
+            sample_size: 100,
+            accuracy: 60.0,
+            symbols: vec!["TEST".to_string()],
+            active: true,
+            created_at: String::new(),
+        };
+        assert!(ConfluenceConfig::default().with_profile(&profile).willr_vote);
+    }
+
+    #[test]
+    fn test_configured_engine_uses_active_profile() {
+        let mut db = Database::open_in_memory().unwrap();
+        db.init_schema().unwrap();
+        let date = NaiveDate::from_ymd_opt(2026, 1, 21).unwrap();
+        let mut indicators = HashMap::new();
+        indicators.insert("RSI_14".to_string(), 25.0);
+
+        let profile = crate::models::ConfluenceProfile {
+            id: 0,
+            name: "rsi_heavy".to_string(),
+            weights: HashMap::from([("RSI_14".to_string(), 3.0)]),
+            intercept: 0.0,
+            horizon_days: 5,
+            sample_size: 100,
+            accuracy: 60.0,
+            symbols: vec!["TEST".to_string()],
+            active: false,
+            created_at: String::new(),
+        };
+        let id = db.save_confluence_profile(&profile).unwrap();
+        assert_eq!(db.save_confluence_profile(&profile).unwrap(), id);
+
+        let engine = configured_signal_engine(&db).unwrap();
+        assert!(engine.detect_confluence_signal("TEST", date, 100.0, &indicators).is_none());
+
+        db.set_active_confluence_profile(Some("rsi_heavy")).unwrap();
+        let engine = configured_signal_engine(&db).unwrap();
+        assert!(engine.detect_confluence_signal("TEST", date, 100.0, &indicators).is_some());
+
+        assert!(db.set_active_confluence_profile(Some("missing")).is_err());
+        db.set_active_confluence_profile(None).unwrap();
+        assert!(db.get_active_confluence_profile().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_weekly_trend_filter_drops_counter_trend_signals() {
+        // Steady uptrend over ~40 weeks of weekdays
+        let start = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let prices: Vec<DailyPrice> = (0..280)
+            .map(|i| start + chrono::Duration::days(i))
+            .filter(|d| chrono::Datelike::weekday(d).number_from_monday() <= 5)
+            .enumerate()
+            .map(|(i, date)| {
+                let close = 100.0 + i as f64;
+                DailyPrice {
+                    symbol: "TEST".to_string(),
+                    date,
+                    open: close,
+                    high: close + 1.0,
+                    low: close - 1.0,
+                    close,
+                    volume: 1000,
+                    source: "test".to_string(),
+                }
+            })
+            .collect();
+
+        // RSI crosses into overbought (bearish) then oversold (bullish) near the end
+        let n = prices.len();
+        let rsi = |idx: usize, value: f64| TechnicalIndicator {
+            symbol: "TEST".to_string(),
+            date: prices[idx].date,
+            indicator_name: "RSI_14".to_string(),
+            value,
+        };
+        let indicators = vec![
+            rsi(n - 4, 50.0),
+            rsi(n - 3, 75.0),
+            rsi(n - 2, 50.0),
+            rsi(n - 1, 25.0),
+        ];
+
+        let unfiltered = SignalEngine::new().generate_signals("TEST", &indicators, &prices);
+        assert_eq!(unfiltered.len(), 2);
+
+        let engine = SignalEngine::with_config(SignalConfig {
+            trend_timeframe: Some(Timeframe::Weekly),
+            ..SignalConfig::default()
+        });
+        let filtered = engine.generate_signals("TEST", &indicators, &prices);
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].signal_type, SignalType::RsiOversold);
+    }
+
+    #[test]
+    fn test_generate_signals_stays_raw_and_episodes_merge() {
+        let start = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let prices: Vec<DailyPrice> = (0..10)
+            .map(|i| DailyPrice {
+                symbol: "TEST".to_string(),
+                date: start + chrono::Duration::days(i),
+                open: 100.0,
+                high: 101.0,
+                low: 99.0,
+                close: 100.0,
+                volume: 1000,
+                source: "test".to_string(),
+            })
+            .collect();
+        // Crosses into overbought three times, every other bar
+        let indicators: Vec<TechnicalIndicator> = prices
+            .iter()
+            .enumerate()
+            .map(|(i, p)| TechnicalIndicator {
+                symbol: "TEST".to_string(),
+                date: p.date,
+                indicator_name: "RSI_14".to_string(),
+                value: if i % 2 == 0 && (2..=6).contains(&i) { 75.0 } else { 50.0 },
+            })
+            .collect();
+
+        let engine = SignalEngine::new();
+        let raw = engine.generate_signals("TEST", &indicators, &prices);
+        let overbought = |s: &&Signal| s.signal_type == SignalType::RsiOverbought;
+        assert_eq!(raw.iter().filter(overbought).count(), 3);
+
+        let episodes = engine.generate_signal_episodes("TEST", &indicators, &prices);
+        let merged: Vec<&Signal> = episodes.iter().filter(overbought).collect();
+        assert_eq!(merged.len(), 1);
+        // Repeats inside the cooldown are dropped
+        assert_eq!(merged[0].timestamp, prices[2].date);
+
+        let no_lifecycle = SignalEngine::with_config(SignalConfig {
+            lifecycle: None,
+            ..SignalConfig::default()
+        });
+        let unmerged = no_lifecycle.generate_signal_episodes("TEST", &indicators, &prices);
+        assert_eq!(unmerged.len(), raw.len());
+    }
+
     #[test]
     fn test_confluence_adx_multiplier() {
         let engine = SignalEngine::new();
@@ -0,0 +1,201 @@
+//! Position Sizing
+//!
+//! Turns a strategy's (or the AI trader's) `PositionSizing` model into the
+//! capital committed to a new position: the cost of a long, or the margin
+//! held against a short. Share-based models (fixed shares, volatility
+//! target, equal risk) convert their share count at the entry price, before
+//! execution costs.
+
+use crate::models::PositionSizing;
+
+/// ATR multiple used as the loss per share by equal-risk sizing when there is
+/// no fixed stop loss
+pub const DEFAULT_STOP_ATR_MULTIPLE: f64 = 2.0;
+
+/// Account and market state a new position is sized from
+#[derive(Debug, Clone, Default)]
+pub struct SizingContext<'a> {
+    /// Account value (cash plus open positions)
+    pub equity: f64,
+    /// Capital the percent model applies to
+    pub capital: f64,
+    /// Percent of `capital` used by percent sizing and the Kelly fallback
+    pub percent: f64,
+    /// Expected entry price
+    pub price: f64,
+    /// ATR_14 on the signal bar
+    pub atr: Option<f64>,
+    /// Fixed stop-loss distance from entry (%)
+    pub stop_loss_percent: Option<f64>,
+    /// Most positions held at once
+    pub slots: usize,
+    /// Capital committed per dollar of position value (%): 100 for longs,
+    /// the short margin requirement for shorts
+    pub margin_percent: f64,
+    /// Returns (%) of closed trades, for Kelly sizing
+    pub trade_returns: &'a [f64],
+}
+
+impl SizingContext<'_> {
+    /// Capital committed to `shares` shares at the entry price
+    fn shares_allocation(&self, shares: f64) -> f64 {
+        shares * self.price * self.margin_percent / 100.0
+    }
+}
+
+impl PositionSizing {
+    /// Capital to commit to a new position, or None when an input the model
+    /// needs (ATR, price) is missing. Callers cap the result by the buying
+    /// power available and skip entries sized at zero.
+    pub fn allocation(&self, ctx: &SizingContext) -> Option<f64> {
+        let percent_of_capital = ctx.capital * ctx.percent / 100.0;
+        let allocation = match self {
+            PositionSizing::PercentOfCapital => percent_of_capital,
+            PositionSizing::FixedDollar { amount } => *amount,
+            PositionSizing::FixedShares { shares } => ctx.shares_allocation(*shares),
+            PositionSizing::VolatilityTarget {
+                risk_percent,
+                atr_multiple,
+            } => {
+                let risk_per_share = ctx.atr.filter(|atr| *atr > 0.0)? * atr_multiple;
+                if risk_per_share <= 0.0 {
+                    return None;
+                }
+                ctx.shares_allocation(ctx.equity * risk_percent / 100.0 / risk_per_share)
+            }
+            PositionSizing::Kelly {
+                fraction,
+                max_percent,
+                min_trades,
+            } => {
+                if ctx.trade_returns.len() < *min_trades {
+                    percent_of_capital
+                } else {
+                    let kelly = kelly_fraction(ctx.trade_returns).unwrap_or(0.0);
+                    let percent = (kelly * fraction * 100.0).clamp(0.0, *max_percent);
+                    ctx.equity * percent / 100.0
+                }
+            }
+            PositionSizing::EqualRisk {
+                portfolio_risk_percent,
+            } => {
+                let risk = ctx.equity * portfolio_risk_percent / 100.0 / ctx.slots.max(1) as f64;
+                let risk_per_share = match ctx.stop_loss_percent.filter(|p| *p > 0.0) {
+                    Some(stop) => ctx.price * stop / 100.0,
+                    None => ctx.atr.filter(|atr| *atr > 0.0)? * DEFAULT_STOP_ATR_MULTIPLE,
+                };
+                if risk_per_share <= 0.0 {
+                    return None;
+                }
+                ctx.shares_allocation(risk / risk_per_share)
+            }
+        };
+        (ctx.price > 0.0).then_some(allocation.max(0.0))
+    }
+}
+
+/// Kelly fraction `W - (1 - W) / R` from trade returns (%), where `W` is the
+/// win rate and `R` the average win over the average loss. None without any
+/// trades; a record with no losses bets the win rate.
+pub fn kelly_fraction(returns: &[f64]) -> Option<f64> {
+    if returns.is_empty() {
+        return None;
+    }
+    let (wins, losses): (Vec<f64>, Vec<f64>) = returns.iter().partition(|r| **r > 0.0);
+    let win_rate = wins.len() as f64 / returns.len() as f64;
+    if wins.is_empty() {
+        return Some(0.0);
+    }
+    let avg_loss = losses.iter().map(|r| r.abs()).sum::<f64>() / losses.len().max(1) as f64;
+    if avg_loss <= 0.0 {
+        return Some(win_rate);
+    }
+    let avg_win = wins.iter().sum::<f64>() / wins.len() as f64;
+    Some(win_rate - (1.0 - win_rate) / (avg_win / avg_loss))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx(trade_returns: &[f64]) -> SizingContext<'_> {
+        SizingContext {
+            equity: 100_000.0,
+            capital: 100_000.0,
+            percent: 50.0,
+            price: 50.0,
+            atr: Some(2.0),
+            stop_loss_percent: None,
+            slots: 4,
+            margin_percent: 100.0,
+            trade_returns,
+        }
+    }
+
+    #[test]
+    fn test_share_based_sizing() {
+        let c = ctx(&[]);
+        assert_eq!(
+            PositionSizing::PercentOfCapital.allocation(&c),
+            Some(50_000.0)
+        );
+        assert_eq!(
+            PositionSizing::FixedShares { shares: 100.0 }.allocation(&c),
+            Some(5_000.0)
+        );
+
+        // 1% of equity at risk over 2 x ATR: 1000 / 4 = 250 shares
+        let vol = PositionSizing::VolatilityTarget {
+            risk_percent: 1.0,
+            atr_multiple: 2.0,
+        };
+        assert_eq!(vol.allocation(&c), Some(12_500.0));
+        let short = SizingContext {
+            margin_percent: 50.0,
+            ..c.clone()
+        };
+        assert_eq!(vol.allocation(&short), Some(6_250.0));
+        let no_atr = SizingContext {
+            atr: None,
+            ..c.clone()
+        };
+        assert_eq!(vol.allocation(&no_atr), None);
+
+        // 4% across 4 slots = 1000 risked per position, to a 5% stop ($2.50)
+        let equal = PositionSizing::EqualRisk {
+            portfolio_risk_percent: 4.0,
+        };
+        let stopped = SizingContext {
+            stop_loss_percent: Some(5.0),
+            ..c.clone()
+        };
+        assert_eq!(equal.allocation(&stopped), Some(20_000.0));
+        // Falls back to 2 x ATR without a stop
+        assert_eq!(equal.allocation(&c), Some(12_500.0));
+    }
+
+    #[test]
+    fn test_kelly_sizing() {
+        // 60% winners averaging 2x the average loss: 0.6 - 0.4 / 2 = 0.4
+        let returns = [10.0, 10.0, 10.0, -5.0, -5.0];
+        assert!((kelly_fraction(&returns).unwrap() - 0.4).abs() < 1e-9);
+        assert_eq!(kelly_fraction(&[-1.0, -2.0]), Some(0.0));
+        assert_eq!(kelly_fraction(&[]), None);
+
+        let kelly = PositionSizing::Kelly {
+            fraction: 0.5,
+            max_percent: 25.0,
+            min_trades: 5,
+        };
+        assert!((kelly.allocation(&ctx(&returns)).unwrap() - 20_000.0).abs() < 1e-6);
+        // Too little history: percent sizing
+        assert_eq!(kelly.allocation(&ctx(&returns[..3])), Some(50_000.0));
+        // Capped at max_percent
+        let full = PositionSizing::Kelly {
+            fraction: 1.0,
+            max_percent: 25.0,
+            min_trades: 5,
+        };
+        assert!((full.allocation(&ctx(&returns)).unwrap() - 25_000.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,410 @@
+//! Stop Management
+//!
+//! Bar-by-bar protective and profit-taking exits for an open position:
+//! fixed stop-loss / take-profit, trailing stops (percent, ATR, chandelier),
+//! break-even stops, maximum holding periods and scale-outs. Shared by the
+//! backtesters and the paper-trading stop check.
+//!
+//! Ordering within a bar is conservative:
+//! 1. A bar opening beyond the stop exits at the open; targets the open gapped
+//!    through are taken at the open
+//! 2. The stop (tightest of fixed, trailing and break-even) is assumed to
+//!    trade before any target inside the same bar's range
+//! 3. Remaining targets inside the range fill at their levels
+//! 4. A time stop closes whatever is left at the close
+//! 5. The best price, trailing level and break-even arm update last, so a bar
+//!    never tightens its own stop
+
+use crate::backtest::bar_open;
+use crate::execution::FillAt;
+use crate::models::{DailyPrice, ExitRules, ScaleOut, TradeDirection, TrailingStop};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// An exit triggered on a bar
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopExit {
+    pub price: f64,
+    /// Fraction of the remaining position to close, in (0, 1]
+    pub fraction: f64,
+    /// stop_loss, trailing_stop, break_even_stop, take_profit, scale_out or
+    /// time_stop
+    pub reason: String,
+}
+
+impl StopExit {
+    pub fn closes_position(&self) -> bool {
+        self.fraction >= 1.0 - 1e-9
+    }
+
+    /// Where the exit fills: time stops at the close, the rest intrabar
+    pub fn fill_at(&self) -> FillAt {
+        if self.reason == "time_stop" {
+            FillAt::Close(self.price)
+        } else {
+            FillAt::Stop(self.price)
+        }
+    }
+}
+
+/// Fixed stop-loss / take-profit distances from entry (%)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FixedStops {
+    pub stop_loss_percent: Option<f64>,
+    pub take_profit_percent: Option<f64>,
+}
+
+/// Stop state of one open position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StopTracker {
+    pub direction: TradeDirection,
+    pub entry_price: f64,
+    pub entry_date: NaiveDate,
+    /// Most favourable price since entry (highest high for longs, lowest low
+    /// for shorts)
+    pub best_price: f64,
+    /// Trailing stop level as of the last bar
+    pub trailing_level: Option<f64>,
+    pub break_even_armed: bool,
+    pub scale_outs_taken: usize,
+}
+
+impl StopTracker {
+    pub fn new(direction: TradeDirection, entry_price: f64, entry_date: NaiveDate) -> Self {
+        Self {
+            direction,
+            entry_price,
+            entry_date,
+            best_price: entry_price,
+            trailing_level: None,
+            break_even_armed: false,
+            scale_outs_taken: 0,
+        }
+    }
+
+    /// +1 for longs, -1 for shorts: multiplying a price move by this makes
+    /// favourable moves positive
+    fn sign(&self) -> f64 {
+        match self.direction {
+            TradeDirection::Long => 1.0,
+            TradeDirection::Short => -1.0,
+        }
+    }
+
+    /// Price at `percent` gain from entry (negative for a loss)
+    fn level(&self, percent: f64) -> f64 {
+        self.entry_price + self.sign() * self.entry_price * percent / 100.0
+    }
+
+    /// Tightest protective stop and its reason
+    fn stop_level(&self, fixed: FixedStops) -> Option<(f64, &'static str)> {
+        let sign = self.sign();
+        [
+            fixed
+                .stop_loss_percent
+                .map(|pct| (self.level(-pct), "stop_loss")),
+            self.trailing_level.map(|level| (level, "trailing_stop")),
+            self.break_even_armed
+                .then_some((self.entry_price, "break_even_stop")),
+        ]
+        .into_iter()
+        .flatten()
+        .max_by(|a, b| (sign * a.0).total_cmp(&(sign * b.0)))
+    }
+
+    /// Untaken scale-outs and the final target as (level, fraction of the
+    /// remaining position, reason), nearest first
+    fn targets(&self, rules: &ExitRules, fixed: FixedStops) -> Vec<(f64, f64, &'static str)> {
+        let mut scale_outs: Vec<&ScaleOut> = rules.scale_outs.iter().collect();
+        scale_outs.sort_by(|a, b| a.gain_percent.total_cmp(&b.gain_percent));
+
+        let mut remaining = 100.0
+            - scale_outs
+                .iter()
+                .take(self.scale_outs_taken)
+                .map(|s| s.size_percent)
+                .sum::<f64>();
+        let mut targets = Vec::new();
+        for scale_out in scale_outs.iter().skip(self.scale_outs_taken) {
+            if remaining <= 0.0 {
+                break;
+            }
+            let fraction = (scale_out.size_percent / remaining).min(1.0);
+            targets.push((self.level(scale_out.gain_percent), fraction, "scale_out"));
+            remaining -= scale_out.size_percent;
+        }
+        if let Some(pct) = fixed.take_profit_percent {
+            targets.push((self.level(pct), 1.0, "take_profit"));
+        }
+
+        let sign = self.sign();
+        targets.sort_by(|a, b| (sign * a.0).total_cmp(&(sign * b.0)));
+        targets
+    }
+
+    /// Mark the nearest untaken scale-out as sold. `check` reports scale-outs
+    /// without consuming them, so callers record one only once shares were
+    /// actually sold; a scale-out too small to sell a whole share stays open.
+    pub fn record_scale_out(&mut self) {
+        self.scale_outs_taken += 1;
+    }
+
+    fn push(&self, exits: &mut Vec<StopExit>, price: f64, fraction: f64, reason: &str) {
+        exits.push(StopExit {
+            price,
+            fraction,
+            reason: reason.to_string(),
+        });
+    }
+
+    /// Exits triggered on the last of `bars` (history up to the current bar),
+    /// in execution order. `atr` is the current ATR_14, used by ATR and
+    /// chandelier trailing stops.
+    pub fn check(
+        &mut self,
+        rules: &ExitRules,
+        fixed: FixedStops,
+        bars: &[DailyPrice],
+        atr: Option<f64>,
+    ) -> Vec<StopExit> {
+        let Some(bar) = bars.last() else {
+            return Vec::new();
+        };
+        let sign = self.sign();
+        let (worst, best) = match self.direction {
+            TradeDirection::Long => (bar.low, bar.high),
+            TradeDirection::Short => (bar.high, bar.low),
+        };
+        let against = |price: f64, level: f64| sign * (price - level) <= 0.0;
+        let favours = |price: f64, level: f64| sign * (price - level) >= 0.0;
+        let open = bar_open(bar);
+
+        let mut exits = Vec::new();
+        let stop = self.stop_level(fixed);
+        let mut targets = self.targets(rules, fixed).into_iter().peekable();
+
+        if let Some((_, reason)) = stop.filter(|&(level, _)| against(open, level)) {
+            self.push(&mut exits, open, 1.0, reason);
+            return exits;
+        }
+        while let Some((_, fraction, reason)) =
+            targets.next_if(|&(level, _, _)| favours(open, level))
+        {
+            self.push(&mut exits, open, fraction, reason);
+            if fraction >= 1.0 {
+                return exits;
+            }
+        }
+        if let Some((level, reason)) = stop.filter(|&(level, _)| against(worst, level)) {
+            self.push(&mut exits, level, 1.0, reason);
+            return exits;
+        }
+        while let Some((level, fraction, reason)) =
+            targets.next_if(|&(level, _, _)| favours(best, level))
+        {
+            self.push(&mut exits, level, fraction, reason);
+            if fraction >= 1.0 {
+                return exits;
+            }
+        }
+        if let Some(days) = rules.max_holding_days {
+            if (bar.date - self.entry_date).num_days() >= days as i64 {
+                self.push(&mut exits, bar.close, 1.0, "time_stop");
+                return exits;
+            }
+        }
+
+        self.update(rules, bars, atr);
+        exits
+    }
+
+    /// Advance the best price, break-even arm and trailing level past the
+    /// last of `bars`
+    fn update(&mut self, rules: &ExitRules, bars: &[DailyPrice], atr: Option<f64>) {
+        let Some(bar) = bars.last() else {
+            return;
+        };
+        let sign = self.sign();
+        let bar_best = match self.direction {
+            TradeDirection::Long => bar.high,
+            TradeDirection::Short => bar.low,
+        };
+        if sign * (bar_best - self.best_price) > 0.0 {
+            self.best_price = bar_best;
+        }
+
+        if let Some(pct) = rules.break_even_after_percent {
+            let gain = sign * (self.best_price - self.entry_price) / self.entry_price * 100.0;
+            if gain >= pct {
+                self.break_even_armed = true;
+            }
+        }
+
+        let candidate = match rules.trailing_stop {
+            None => None,
+            Some(TrailingStop::Percent { percent }) => {
+                Some(self.best_price * (1.0 - sign * percent / 100.0))
+            }
+            Some(TrailingStop::Atr { multiple }) => {
+                atr.map(|atr| self.best_price - sign * multiple * atr)
+            }
+            Some(TrailingStop::Chandelier { period, multiple }) => atr.map(|atr| {
+                let window = &bars[bars.len().saturating_sub(period.max(1))..];
+                let extreme = match self.direction {
+                    TradeDirection::Long => window.iter().map(|b| b.high).fold(f64::MIN, f64::max),
+                    TradeDirection::Short => window.iter().map(|b| b.low).fold(f64::MAX, f64::min),
+                };
+                extreme - sign * multiple * atr
+            }),
+        };
+        if let Some(candidate) = candidate {
+            self.trailing_level = Some(match self.trailing_level {
+                Some(current) if sign * (current - candidate) > 0.0 => current,
+                _ => candidate,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(day: i64, open: f64, high: f64, low: f64, close: f64) -> DailyPrice {
+        DailyPrice {
+            symbol: "TEST".to_string(),
+            date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap() + chrono::Duration::days(day),
+            open,
+            high,
+            low,
+            close,
+            volume: 1000,
+            source: "test".to_string(),
+        }
+    }
+
+    fn tracker() -> StopTracker {
+        StopTracker::new(
+            TradeDirection::Long,
+            100.0,
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        )
+    }
+
+    fn reasons(exits: &[StopExit]) -> Vec<(f64, f64, &str)> {
+        exits
+            .iter()
+            .map(|e| (e.price, e.fraction, e.reason.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_trailing_stop_ratchets_and_triggers() {
+        let rules = ExitRules {
+            trailing_stop: Some(TrailingStop::Percent { percent: 10.0 }),
+            ..ExitRules::default()
+        };
+        let mut t = tracker();
+        let mut bars = vec![bar(1, 100.0, 120.0, 99.0, 118.0)];
+        assert!(t
+            .check(&rules, FixedStops::default(), &bars, None)
+            .is_empty());
+        assert_eq!(t.trailing_level, Some(108.0));
+
+        // A lower high does not loosen the stop
+        bars.push(bar(2, 115.0, 116.0, 110.0, 112.0));
+        assert!(t
+            .check(&rules, FixedStops::default(), &bars, None)
+            .is_empty());
+        assert_eq!(t.trailing_level, Some(108.0));
+
+        bars.push(bar(3, 111.0, 112.0, 105.0, 106.0));
+        let exits = t.check(&rules, FixedStops::default(), &bars, None);
+        assert_eq!(reasons(&exits), vec![(108.0, 1.0, "trailing_stop")]);
+    }
+
+    #[test]
+    fn test_break_even_beats_wider_fixed_stop() {
+        let rules = ExitRules {
+            break_even_after_percent: Some(5.0),
+            ..ExitRules::default()
+        };
+        let fixed = FixedStops {
+            stop_loss_percent: Some(8.0),
+            take_profit_percent: None,
+        };
+        let mut t = tracker();
+        let bars = vec![
+            bar(1, 100.0, 106.0, 99.0, 104.0),
+            bar(2, 103.0, 104.0, 95.0, 96.0),
+        ];
+        assert!(t.check(&rules, fixed, &bars[..1], None).is_empty());
+        assert!(t.break_even_armed);
+        let exits = t.check(&rules, fixed, &bars, None);
+        assert_eq!(reasons(&exits), vec![(100.0, 1.0, "break_even_stop")]);
+    }
+
+    #[test]
+    fn test_scale_out_then_target_and_time_stop() {
+        let rules = ExitRules {
+            scale_outs: vec![ScaleOut {
+                gain_percent: 5.0,
+                size_percent: 50.0,
+            }],
+            max_holding_days: Some(10),
+            ..ExitRules::default()
+        };
+        let fixed = FixedStops {
+            stop_loss_percent: None,
+            take_profit_percent: Some(10.0),
+        };
+
+        // Target 1 and the final target inside one bar: half, then the rest
+        let mut t = tracker();
+        let exits = t.check(&rules, fixed, &[bar(1, 101.0, 111.0, 100.0, 109.0)], None);
+        assert_eq!(
+            reasons(&exits),
+            vec![(105.0, 0.5, "scale_out"), (110.0, 1.0, "take_profit")]
+        );
+
+        // A scale-out that wasn't sold fires again
+        let mut t = tracker();
+        let first = t.check(&rules, fixed, &[bar(1, 101.0, 106.0, 100.0, 104.0)], None);
+        assert_eq!(reasons(&first), vec![(105.0, 0.5, "scale_out")]);
+        let again = t.check(&rules, fixed, &[bar(2, 104.0, 106.0, 103.0, 104.0)], None);
+        assert_eq!(reasons(&again), vec![(105.0, 0.5, "scale_out")]);
+
+        // Once sold it fires once; the remainder hits the time stop
+        t.record_scale_out();
+        assert!(t
+            .check(&rules, fixed, &[bar(2, 104.0, 106.0, 103.0, 104.0)], None)
+            .is_empty());
+        let late = t.check(&rules, fixed, &[bar(10, 104.0, 105.0, 103.0, 104.5)], None);
+        assert_eq!(reasons(&late), vec![(104.5, 1.0, "time_stop")]);
+    }
+
+    #[test]
+    fn test_chandelier_short_uses_lowest_low() {
+        let rules = ExitRules {
+            trailing_stop: Some(TrailingStop::Chandelier {
+                period: 2,
+                multiple: 2.0,
+            }),
+            ..ExitRules::default()
+        };
+        let mut t = StopTracker::new(
+            TradeDirection::Short,
+            100.0,
+            NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        );
+        let bars = vec![
+            bar(1, 99.0, 99.0, 90.0, 92.0),
+            bar(2, 92.0, 95.0, 91.0, 94.0),
+        ];
+        assert!(t
+            .check(&rules, FixedStops::default(), &bars, Some(1.5))
+            .is_empty());
+        // Lowest low of the last two bars (90) + 2 x ATR
+        assert_eq!(t.trailing_level, Some(93.0));
+    }
+}
@@ -0,0 +1,267 @@
+//! Multi-timeframe support
+//!
+//! Resamples daily OHLCV bars into weekly, monthly or N-day bars so that
+//! indicators and confluence can be computed on higher timeframes, and
+//! provides the higher-timeframe trend used to filter daily signals.
+
+use crate::indicators::{calculate_all, calculate_sma};
+use crate::models::{DailyPrice, SignalDirection, TechnicalIndicator};
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// Bar timeframe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Timeframe {
+    Daily,
+    Weekly,
+    Monthly,
+    /// Fixed number of trading days per bar
+    Days(u32),
+}
+
+impl Timeframe {
+    /// Short label, e.g. "1D", "1W", "1M", "5D"
+    pub fn label(&self) -> String {
+        match self {
+            Timeframe::Daily => "1D".to_string(),
+            Timeframe::Weekly => "1W".to_string(),
+            Timeframe::Monthly => "1M".to_string(),
+            Timeframe::Days(n) => format!("{}D", n),
+        }
+    }
+
+    /// Suffix appended to indicator names computed on this timeframe
+    /// (empty for daily so existing names like "RSI_14" are unchanged)
+    pub fn indicator_suffix(&self) -> String {
+        match self {
+            Timeframe::Daily => String::new(),
+            Timeframe::Weekly => "_W".to_string(),
+            Timeframe::Monthly => "_M".to_string(),
+            Timeframe::Days(n) => format!("_{}D", n),
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "daily" | "d" | "1d" => Some(Timeframe::Daily),
+            "weekly" | "w" | "1w" => Some(Timeframe::Weekly),
+            "monthly" | "m" | "1m" | "1mo" => Some(Timeframe::Monthly),
+            other => {
+                let n: u32 = other.strip_suffix('d')?.parse().ok()?;
+                match n {
+                    0 => None,
+                    1 => Some(Timeframe::Daily),
+                    _ => Some(Timeframe::Days(n)),
+                }
+            }
+        }
+    }
+}
+
+/// Resample daily bars into the given timeframe
+///
+/// Open = first open, High = max high, Low = min low, Close = last close,
+/// Volume = sum of volume. Each bar is dated with the last trading day it
+/// contains, so a bar's date is the first day its values are fully known.
+/// The final bar may be an incomplete (in-progress) period.
+pub fn resample(prices: &[DailyPrice], timeframe: Timeframe) -> Vec<DailyPrice> {
+    let mut sorted = prices.to_vec();
+    sorted.sort_by_key(|p| p.date);
+
+    if timeframe == Timeframe::Daily {
+        return sorted;
+    }
+
+    let mut bars: Vec<DailyPrice> = Vec::new();
+    let mut current_key: Option<(i32, u32)> = None;
+
+    for (i, price) in sorted.iter().enumerate() {
+        let key = match timeframe {
+            Timeframe::Daily => unreachable!(),
+            Timeframe::Weekly => {
+                let week = price.date.iso_week();
+                (week.year(), week.week())
+            }
+            Timeframe::Monthly => (price.date.year(), price.date.month()),
+            Timeframe::Days(n) => (0, (i / n.max(1) as usize) as u32),
+        };
+
+        match bars.last_mut() {
+            Some(bar) if current_key == Some(key) => {
+                bar.date = price.date;
+                bar.high = bar.high.max(price.high);
+                bar.low = bar.low.min(price.low);
+                bar.close = price.close;
+                bar.volume += price.volume;
+            }
+            _ => {
+                bars.push(price.clone());
+                current_key = Some(key);
+            }
+        }
+    }
+
+    bars
+}
+
+/// Calculate all standard indicators on a resampled series
+///
+/// Indicator names carry the timeframe suffix (e.g. "RSI_14_W") so they can
+/// be stored in `technical_indicators` next to the daily values.
+pub fn calculate_all_for_timeframe(
+    prices: &[DailyPrice],
+    timeframe: Timeframe,
+) -> Vec<TechnicalIndicator> {
+    let bars = resample(prices, timeframe);
+    let suffix = timeframe.indicator_suffix();
+
+    calculate_all(&bars)
+        .into_iter()
+        .map(|mut ind| {
+            ind.indicator_name.push_str(&suffix);
+            ind
+        })
+        .collect()
+}
+
+/// Trend of a higher timeframe, used to require alignment of daily signals
+#[derive(Debug, Clone)]
+pub struct HigherTimeframeTrend {
+    /// (bar date, trend direction), sorted by date
+    bars: Vec<(NaiveDate, SignalDirection)>,
+}
+
+impl HigherTimeframeTrend {
+    /// Classify each higher-timeframe bar as bullish (close above its SMA),
+    /// bearish (close below) or neutral
+    pub fn from_prices(prices: &[DailyPrice], timeframe: Timeframe, sma_period: usize) -> Self {
+        let bars = resample(prices, timeframe);
+        let bars = calculate_sma(&bars, sma_period)
+            .iter()
+            .zip(bars.iter().skip(sma_period.saturating_sub(1)))
+            .map(|(sma, bar)| {
+                let direction = if bar.close > sma.value {
+                    SignalDirection::Bullish
+                } else if bar.close < sma.value {
+                    SignalDirection::Bearish
+                } else {
+                    SignalDirection::Neutral
+                };
+                (bar.date, direction)
+            })
+            .collect();
+
+        Self { bars }
+    }
+
+    /// Trend of the last completed higher-timeframe bar before `date`
+    ///
+    /// The bar containing `date` is dated on or after it, so only bars
+    /// strictly earlier are considered - no look-ahead into the current period.
+    pub fn as_of(&self, date: NaiveDate) -> Option<SignalDirection> {
+        let idx = self.bars.partition_point(|(d, _)| *d < date);
+        if idx == 0 {
+            None
+        } else {
+            Some(self.bars[idx - 1].1)
+        }
+    }
+
+    /// Whether a signal in `direction` on `date` agrees with the trend.
+    /// Neutral signals always pass; directional signals need a matching trend.
+    pub fn allows(&self, date: NaiveDate, direction: SignalDirection) -> bool {
+        match direction {
+            SignalDirection::Neutral => true,
+            _ => self.as_of(date) == Some(direction),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bar(date: NaiveDate, open: f64, high: f64, low: f64, close: f64, volume: i64) -> DailyPrice {
+        DailyPrice {
+            symbol: "TEST".to_string(),
+            date,
+            open,
+            high,
+            low,
+            close,
+            volume,
+            source: "test".to_string(),
+        }
+    }
+
+    fn trending_prices(days: i64, step: f64) -> Vec<DailyPrice> {
+        let start = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap(); // Monday
+        (0..days)
+            .map(|i| start + chrono::Duration::days(i))
+            .filter(|d| d.weekday().number_from_monday() <= 5)
+            .enumerate()
+            .map(|(i, d)| {
+                let close = 100.0 + step * i as f64;
+                bar(d, close, close + 1.0, close - 1.0, close, 1000)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resample_weekly_aggregation() {
+        let monday = NaiveDate::from_ymd_opt(2025, 1, 6).unwrap();
+        let prices: Vec<DailyPrice> = (0..5)
+            .map(|i| {
+                let d = monday + chrono::Duration::days(i);
+                let base = 10.0 + i as f64;
+                bar(d, base, base + 2.0, base - 1.0, base + 0.5, 100)
+            })
+            .collect();
+
+        let weekly = resample(&prices, Timeframe::Weekly);
+        assert_eq!(weekly.len(), 1);
+        let w = &weekly[0];
+        assert_eq!(w.date, NaiveDate::from_ymd_opt(2025, 1, 10).unwrap());
+        assert_eq!(w.open, 10.0);
+        assert_eq!(w.high, 16.0);
+        assert_eq!(w.low, 9.0);
+        assert_eq!(w.close, 14.5);
+        assert_eq!(w.volume, 500);
+    }
+
+    #[test]
+    fn test_resample_monthly_and_n_day() {
+        let prices = trending_prices(70, 1.0);
+
+        let monthly = resample(&prices, Timeframe::Monthly);
+        assert_eq!(monthly.len(), 3); // Jan, Feb, Mar
+        assert_eq!(monthly[0].date.month(), 1);
+
+        let five_day = resample(&prices, Timeframe::Days(5));
+        assert_eq!(five_day.len(), prices.len().div_ceil(5));
+        assert_eq!(five_day[0].close, prices[4].close);
+    }
+
+    #[test]
+    fn test_timeframe_from_str() {
+        assert_eq!(Timeframe::from_str("weekly"), Some(Timeframe::Weekly));
+        assert_eq!(Timeframe::from_str("1M"), Some(Timeframe::Monthly));
+        assert_eq!(Timeframe::from_str("3d"), Some(Timeframe::Days(3)));
+        assert_eq!(Timeframe::from_str("0d"), None);
+        assert_eq!(Timeframe::Days(3).indicator_suffix(), "_3D");
+    }
+
+    #[test]
+    fn test_higher_timeframe_trend_alignment() {
+        let prices = trending_prices(200, 1.0);
+        let trend = HigherTimeframeTrend::from_prices(&prices, Timeframe::Weekly, 5);
+        let last = prices.last().unwrap().date;
+
+        assert_eq!(trend.as_of(last), Some(SignalDirection::Bullish));
+        assert!(trend.allows(last, SignalDirection::Bullish));
+        assert!(!trend.allows(last, SignalDirection::Bearish));
+        assert!(trend.allows(last, SignalDirection::Neutral));
+        // Before enough weekly bars exist there is no trend to align with
+        assert_eq!(trend.as_of(prices[0].date), None);
+    }
+}
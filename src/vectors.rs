@@ -384,7 +384,9 @@ mod tests {
 
     #[test]
     fn test_vector_store() {
-        let store = VectorStore::new("./test_vectors.db").unwrap();
+        let path = std::env::temp_dir().join(format!("test_vectors_{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let store = VectorStore::new(path.to_str().unwrap()).unwrap();
 
         let event = MarketEvent {
             id: "test1".to_string(),
@@ -402,5 +404,8 @@ mod tests {
         let results = store.search_events("Apple iPhone sales", 5).unwrap();
         assert!(!results.is_empty());
         assert!(results[0].score > 0.0);
+
+        drop(store);
+        let _ = std::fs::remove_file(&path);
     }
 }
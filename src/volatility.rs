@@ -0,0 +1,504 @@
+//! Volatility estimators and regime classification
+//!
+//! Range-based and close-to-close historical volatility estimators, EWMA and
+//! GARCH(1,1) forecasts, and a low/normal/high volatility regime per symbol.
+//! All volatilities are annualized (252 trading days) and expressed in percent.
+
+use crate::models::{DailyPrice, TechnicalIndicator};
+use serde::{Deserialize, Serialize};
+
+/// Trading days per year used for annualization
+const TRADING_DAYS: f64 = 252.0;
+
+/// Indicator name for the stored regime code
+pub const VOL_REGIME_INDICATOR: &str = "VOL_REGIME";
+
+/// Indicator name for the stored volatility percentile
+pub const VOL_PERCENTILE_INDICATOR: &str = "VOL_PERCENTILE";
+
+fn annualize(daily_variance: f64) -> f64 {
+    (daily_variance.max(0.0) * TRADING_DAYS).sqrt() * 100.0
+}
+
+fn sample_variance(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64
+}
+
+fn valid_bar(p: &DailyPrice) -> bool {
+    p.open > 0.0 && p.high > 0.0 && p.low > 0.0 && p.close > 0.0
+}
+
+/// Rolling estimator over `period` bars. `per_bar` sees the bar and the previous bar.
+fn rolling_estimator(
+    prices: &[DailyPrice],
+    period: usize,
+    name: String,
+    estimate: impl Fn(&[DailyPrice], &[DailyPrice]) -> f64,
+) -> Vec<TechnicalIndicator> {
+    if period < 2 || prices.len() < period + 1 {
+        return vec![];
+    }
+
+    (period..prices.len())
+        .filter(|&i| prices[(i - period)..=i].iter().all(valid_bar))
+        .map(|i| TechnicalIndicator {
+            symbol: prices[0].symbol.clone(),
+            date: prices[i].date,
+            indicator_name: name.clone(),
+            value: annualize(estimate(&prices[(i + 1 - period)..=i], &prices[(i - period)..i])),
+        })
+        .collect()
+}
+
+/// Close-to-close historical volatility (sample std dev of log returns)
+pub fn calculate_historical_volatility(prices: &[DailyPrice], period: usize) -> Vec<TechnicalIndicator> {
+    rolling_estimator(prices, period, format!("HV_{}", period), |window, prev| {
+        let returns: Vec<f64> = window
+            .iter()
+            .zip(prev)
+            .map(|(p, q)| (p.close / q.close).ln())
+            .collect();
+        sample_variance(&returns)
+    })
+}
+
+/// Parkinson high-low range estimator
+pub fn calculate_parkinson_volatility(prices: &[DailyPrice], period: usize) -> Vec<TechnicalIndicator> {
+    rolling_estimator(prices, period, format!("PARK_VOL_{}", period), |window, _| {
+        let sum: f64 = window.iter().map(|p| (p.high / p.low).ln().powi(2)).sum();
+        sum / (4.0 * 2.0_f64.ln() * window.len() as f64)
+    })
+}
+
+/// Garman-Klass OHLC estimator
+pub fn calculate_garman_klass_volatility(prices: &[DailyPrice], period: usize) -> Vec<TechnicalIndicator> {
+    rolling_estimator(prices, period, format!("GK_VOL_{}", period), |window, _| {
+        let sum: f64 = window
+            .iter()
+            .map(|p| {
+                0.5 * (p.high / p.low).ln().powi(2)
+                    - (2.0 * 2.0_f64.ln() - 1.0) * (p.close / p.open).ln().powi(2)
+            })
+            .sum();
+        sum / window.len() as f64
+    })
+}
+
+fn rogers_satchell_term(p: &DailyPrice) -> f64 {
+    (p.high / p.close).ln() * (p.high / p.open).ln() + (p.low / p.close).ln() * (p.low / p.open).ln()
+}
+
+/// Rogers-Satchell estimator (drift independent)
+pub fn calculate_rogers_satchell_volatility(prices: &[DailyPrice], period: usize) -> Vec<TechnicalIndicator> {
+    rolling_estimator(prices, period, format!("RS_VOL_{}", period), |window, _| {
+        window.iter().map(rogers_satchell_term).sum::<f64>() / window.len() as f64
+    })
+}
+
+/// Yang-Zhang estimator (overnight + open-to-close + Rogers-Satchell)
+pub fn calculate_yang_zhang_volatility(prices: &[DailyPrice], period: usize) -> Vec<TechnicalIndicator> {
+    rolling_estimator(prices, period, format!("YZ_VOL_{}", period), |window, prev| {
+        let n = window.len() as f64;
+        let overnight: Vec<f64> = window
+            .iter()
+            .zip(prev)
+            .map(|(p, q)| (p.open / q.close).ln())
+            .collect();
+        let open_close: Vec<f64> = window.iter().map(|p| (p.close / p.open).ln()).collect();
+        let rs = window.iter().map(rogers_satchell_term).sum::<f64>() / n;
+        let k = 0.34 / (1.34 + (n + 1.0) / (n - 1.0));
+        sample_variance(&overnight) + k * sample_variance(&open_close) + (1.0 - k) * rs
+    })
+}
+
+/// Daily log returns paired with the date they end on
+fn log_returns(prices: &[DailyPrice]) -> Vec<(chrono::NaiveDate, f64)> {
+    prices
+        .windows(2)
+        .filter(|w| w[0].close > 0.0 && w[1].close > 0.0)
+        .map(|w| (w[1].date, (w[1].close / w[0].close).ln()))
+        .collect()
+}
+
+/// EWMA (RiskMetrics) one-day-ahead volatility forecast
+/// Each value is the forecast for the next bar made at the close of that date.
+pub fn calculate_ewma_volatility(prices: &[DailyPrice], lambda: f64, warmup: usize) -> Vec<TechnicalIndicator> {
+    let returns = log_returns(prices);
+    if returns.len() <= warmup || warmup < 2 {
+        return vec![];
+    }
+
+    let initial: Vec<f64> = returns[..warmup].iter().map(|r| r.1).collect();
+    let mut variance = sample_variance(&initial);
+    let mut indicators = Vec::new();
+
+    for (date, r) in &returns[warmup..] {
+        variance = lambda * variance + (1.0 - lambda) * r * r;
+        indicators.push(TechnicalIndicator {
+            symbol: prices[0].symbol.clone(),
+            date: *date,
+            indicator_name: "EWMA_VOL".to_string(),
+            value: annualize(variance),
+        });
+    }
+
+    indicators
+}
+
+/// GARCH(1,1) model: sigma2_t = omega + alpha * r2_{t-1} + beta * sigma2_{t-1}
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GarchModel {
+    pub omega: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl GarchModel {
+    /// Fit by grid search over (alpha, beta) with variance targeting,
+    /// maximizing the Gaussian log-likelihood. Returns None if there are too
+    /// few observations or the returns have no variance.
+    pub fn fit(returns: &[f64]) -> Option<Self> {
+        if returns.len() < 30 {
+            return None;
+        }
+        let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+        let demeaned: Vec<f64> = returns.iter().map(|r| r - mean).collect();
+        let long_run = sample_variance(&demeaned);
+        if long_run <= 0.0 {
+            return None;
+        }
+
+        let mut best: Option<(f64, Self)> = None;
+        for a in 1..=15 {
+            for b in 30..=49 {
+                let alpha = a as f64 * 0.02;
+                let beta = b as f64 * 0.02;
+                if alpha + beta >= 0.999 {
+                    continue;
+                }
+                let model = Self {
+                    omega: long_run * (1.0 - alpha - beta),
+                    alpha,
+                    beta,
+                };
+                let ll = model.log_likelihood(&demeaned, long_run);
+                if best.is_none_or(|(best_ll, _)| ll > best_ll) {
+                    best = Some((ll, model));
+                }
+            }
+        }
+
+        best.map(|(_, model)| model)
+    }
+
+    fn log_likelihood(&self, returns: &[f64], initial_variance: f64) -> f64 {
+        let mut variance = initial_variance;
+        let mut ll = 0.0;
+        for r in returns {
+            ll -= variance.ln() + r * r / variance;
+            variance = self.next_variance(variance, *r);
+        }
+        ll
+    }
+
+    /// One-step variance update
+    pub fn next_variance(&self, variance: f64, ret: f64) -> f64 {
+        self.omega + self.alpha * ret * ret + self.beta * variance
+    }
+
+    /// Unconditional (long-run) daily variance
+    pub fn long_run_variance(&self) -> f64 {
+        self.omega / (1.0 - self.alpha - self.beta)
+    }
+
+    /// Filter a return series, returning the conditional variance after each return
+    pub fn filter(&self, returns: &[f64]) -> Vec<f64> {
+        let mut variance = self.long_run_variance();
+        returns
+            .iter()
+            .map(|r| {
+                variance = self.next_variance(variance, *r);
+                variance
+            })
+            .collect()
+    }
+
+    /// Annualized volatility forecast (percent) for each of the next `horizon` days,
+    /// starting from next-day variance `next_var`
+    pub fn forecast(&self, next_var: f64, horizon: usize) -> Vec<f64> {
+        let persistence = self.alpha + self.beta;
+        let long_run = self.long_run_variance();
+        (0..horizon)
+            .map(|h| annualize(long_run + persistence.powi(h as i32) * (next_var - long_run)))
+            .collect()
+    }
+}
+
+/// Bars between GARCH refits
+pub const GARCH_REFIT_INTERVAL: usize = 21;
+
+/// Returns needed before the first GARCH fit
+pub const GARCH_MIN_RETURNS: usize = 60;
+
+/// GARCH(1,1) one-day-ahead volatility forecast. The model is refitted every
+/// `GARCH_REFIT_INTERVAL` bars on the returns known at that bar (an expanding
+/// window), so each value only uses past data.
+pub fn calculate_garch_volatility(prices: &[DailyPrice]) -> Vec<TechnicalIndicator> {
+    let returns = log_returns(prices);
+    let values: Vec<f64> = returns.iter().map(|r| r.1).collect();
+
+    let mut fitted: Option<(GarchModel, f64)> = None;
+    let mut variance = 0.0;
+    let mut indicators = Vec::new();
+    for (i, (date, ret)) in returns.iter().enumerate() {
+        let known = i + 1;
+        let refit = known >= GARCH_MIN_RETURNS
+            && (known - GARCH_MIN_RETURNS).is_multiple_of(GARCH_REFIT_INTERVAL);
+        if refit {
+            let window = &values[..known];
+            if let Some(model) = GarchModel::fit(window) {
+                let mean = window.iter().sum::<f64>() / window.len() as f64;
+                if fitted.is_none() {
+                    // Warm the variance up over the returns before this one
+                    variance = model.long_run_variance();
+                    for r in &values[..i] {
+                        variance = model.next_variance(variance, r - mean);
+                    }
+                }
+                fitted = Some((model, mean));
+            }
+        }
+
+        let Some((model, mean)) = fitted else {
+            continue;
+        };
+        variance = model.next_variance(variance, ret - mean);
+        indicators.push(TechnicalIndicator {
+            symbol: prices[0].symbol.clone(),
+            date: *date,
+            indicator_name: "GARCH_VOL".to_string(),
+            value: annualize(variance),
+        });
+    }
+
+    indicators
+}
+
+/// Volatility regime of a symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VolatilityRegime {
+    Low,
+    Normal,
+    High,
+}
+
+impl VolatilityRegime {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VolatilityRegime::Low => "low",
+            VolatilityRegime::Normal => "normal",
+            VolatilityRegime::High => "high",
+        }
+    }
+
+    pub fn from_str(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "low" => Some(VolatilityRegime::Low),
+            "normal" => Some(VolatilityRegime::Normal),
+            "high" => Some(VolatilityRegime::High),
+            _ => None,
+        }
+    }
+
+    /// Numeric code stored in `technical_indicators` (0 = low, 1 = normal, 2 = high)
+    pub fn code(&self) -> f64 {
+        match self {
+            VolatilityRegime::Low => 0.0,
+            VolatilityRegime::Normal => 1.0,
+            VolatilityRegime::High => 2.0,
+        }
+    }
+
+    pub fn from_code(code: f64) -> Option<Self> {
+        match code.round() as i64 {
+            0 => Some(VolatilityRegime::Low),
+            1 => Some(VolatilityRegime::Normal),
+            2 => Some(VolatilityRegime::High),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration for volatility regime classification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolatilityRegimeConfig {
+    /// Estimator window (bars)
+    pub period: usize,
+    /// Trailing window for percentile ranking (bars)
+    pub lookback: usize,
+    /// Percentile below which volatility is "low"
+    pub low_percentile: f64,
+    /// Percentile above which volatility is "high"
+    pub high_percentile: f64,
+}
+
+impl Default for VolatilityRegimeConfig {
+    fn default() -> Self {
+        Self {
+            period: 20,
+            lookback: 252,
+            low_percentile: 25.0,
+            high_percentile: 75.0,
+        }
+    }
+}
+
+/// Classify the volatility regime per date from the Yang-Zhang estimator's
+/// percentile within its trailing lookback window.
+/// Emits "VOL_PERCENTILE" (0-100) and "VOL_REGIME" (see `VolatilityRegime::code`).
+pub fn classify_volatility_regime(
+    prices: &[DailyPrice],
+    config: &VolatilityRegimeConfig,
+) -> Vec<TechnicalIndicator> {
+    let vols = calculate_yang_zhang_volatility(prices, config.period);
+    let min_history = (config.lookback / 4).max(20);
+    let mut indicators = Vec::new();
+
+    for i in 0..vols.len() {
+        let start = (i + 1).saturating_sub(config.lookback);
+        let window = &vols[start..=i];
+        if window.len() < min_history {
+            continue;
+        }
+
+        let current = vols[i].value;
+        let below = window.iter().filter(|v| v.value < current).count() as f64;
+        let percentile = below / (window.len() - 1) as f64 * 100.0;
+
+        let regime = if percentile < config.low_percentile {
+            VolatilityRegime::Low
+        } else if percentile > config.high_percentile {
+            VolatilityRegime::High
+        } else {
+            VolatilityRegime::Normal
+        };
+
+        indicators.push(TechnicalIndicator {
+            symbol: vols[i].symbol.clone(),
+            date: vols[i].date,
+            indicator_name: VOL_PERCENTILE_INDICATOR.to_string(),
+            value: percentile,
+        });
+        indicators.push(TechnicalIndicator {
+            symbol: vols[i].symbol.clone(),
+            date: vols[i].date,
+            indicator_name: VOL_REGIME_INDICATOR.to_string(),
+            value: regime.code(),
+        });
+    }
+
+    indicators
+}
+
+/// Calculate the full volatility suite with default parameters
+pub fn calculate_volatility_suite(prices: &[DailyPrice]) -> Vec<TechnicalIndicator> {
+    let mut all = Vec::new();
+    all.extend(calculate_historical_volatility(prices, 20));
+    all.extend(calculate_parkinson_volatility(prices, 20));
+    all.extend(calculate_garman_klass_volatility(prices, 20));
+    all.extend(calculate_rogers_satchell_volatility(prices, 20));
+    all.extend(calculate_yang_zhang_volatility(prices, 20));
+    all.extend(calculate_ewma_volatility(prices, 0.94, 20));
+    all.extend(calculate_garch_volatility(prices));
+    all.extend(classify_volatility_regime(prices, &VolatilityRegimeConfig::default()));
+    all
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Deterministic series whose daily range widens after `calm_days`
+    fn prices(days: usize, calm_days: usize) -> Vec<DailyPrice> {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let mut close = 100.0;
+        (0..days)
+            .map(|i| {
+                let amp = if i < calm_days { 0.005 } else { 0.03 };
+                let sign = if i % 3 == 0 { -1.0 } else { 1.0 };
+                let open = close;
+                close = open * (1.0 + sign * amp * (1.0 + (i % 4) as f64 / 4.0));
+                DailyPrice {
+                    symbol: "TEST".to_string(),
+                    date: start + chrono::Duration::days(i as i64),
+                    open,
+                    high: open.max(close) * (1.0 + amp / 2.0),
+                    low: open.min(close) * (1.0 - amp / 2.0),
+                    close,
+                    volume: 1000,
+                    source: "test".to_string(),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_estimators_positive_and_rise_with_range() {
+        let data = prices(120, 60);
+        for series in [
+            calculate_historical_volatility(&data, 20),
+            calculate_parkinson_volatility(&data, 20),
+            calculate_garman_klass_volatility(&data, 20),
+            calculate_rogers_satchell_volatility(&data, 20),
+            calculate_yang_zhang_volatility(&data, 20),
+        ] {
+            assert_eq!(series.len(), 100);
+            let first = series.first().unwrap().value;
+            let last = series.last().unwrap().value;
+            assert!(first > 0.0);
+            assert!(last > first * 2.0, "{} should rise", series[0].indicator_name);
+        }
+    }
+
+    #[test]
+    fn test_garch_fit_is_stationary() {
+        let data = prices(300, 150);
+        let returns: Vec<f64> = log_returns(&data).iter().map(|r| r.1).collect();
+        let model = GarchModel::fit(&returns).unwrap();
+        assert!(model.alpha + model.beta < 1.0);
+        assert!(model.omega > 0.0);
+
+        let forecast = model.forecast(model.long_run_variance() * 4.0, 10);
+        assert!(forecast[0] > forecast[9], "Forecast should mean-revert");
+    }
+
+    #[test]
+    fn test_garch_series_uses_only_past_returns() {
+        let data = prices(300, 150);
+        let full = calculate_garch_volatility(&data);
+        let partial = calculate_garch_volatility(&data[..200]);
+        assert_eq!(full.len(), data.len() - GARCH_MIN_RETURNS);
+        assert!(!partial.is_empty());
+        for (a, b) in partial.iter().zip(&full) {
+            assert_eq!(a.date, b.date);
+            assert!((a.value - b.value).abs() < 1e-12, "{} changed with later data", a.date);
+        }
+    }
+
+    #[test]
+    fn test_regime_turns_high_after_volatility_jump() {
+        let data = prices(200, 150);
+        let regimes: Vec<_> = classify_volatility_regime(&data, &VolatilityRegimeConfig::default())
+            .into_iter()
+            .filter(|i| i.indicator_name == VOL_REGIME_INDICATOR)
+            .collect();
+
+        let last = regimes.last().unwrap();
+        assert_eq!(VolatilityRegime::from_code(last.value), Some(VolatilityRegime::High));
+    }
+}
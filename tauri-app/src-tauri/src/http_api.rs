@@ -3,13 +3,17 @@
 
 use axum::{
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{header, StatusCode},
     response::Json,
     routing::{get, post},
     Router,
 };
 use financial_pipeline::models::PaperTradeAction;
-use financial_pipeline::{Database, QueuedTrade, QueueLogEntry, YahooFinance};
+use financial_pipeline::monte_carlo::{self, MonteCarloConfig, MonteCarloResult};
+use financial_pipeline::{
+    export_equity_curve, Database, ExportFormat, PipelineError, QueuedTrade, QueueLogEntry,
+    YahooFinance,
+};
 use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 use tower_http::cors::{Any, CorsLayer};
@@ -299,6 +303,12 @@ pub fn build_router(db: SharedDb) -> Router {
         .route("/api/reports/content", get(get_report_content))
         // Watchlists
         .route("/api/watchlists", get(get_watchlists))
+        // Backtest analysis
+        .route(
+            "/api/backtests/:id/monte-carlo",
+            get(get_monte_carlo_runs).post(run_monte_carlo),
+        )
+        .route("/api/backtests/:id/equity", get(get_backtest_equity))
         // Trade queue management
         .route("/api/queue", get(get_trade_queue))
         .route("/api/queue/add", post(add_to_queue))
@@ -1074,6 +1084,91 @@ async fn get_watchlists(State(db): State<SharedDb>) -> Result<Json<Vec<Watchlist
     Ok(Json(result))
 }
 
+// ============================================================================
+// Backtest Analysis Handlers
+// ============================================================================
+
+/// Get stored Monte Carlo analyses of a backtest
+async fn get_monte_carlo_runs(
+    State(db): State<SharedDb>,
+    Path(id): Path<i64>,
+) -> Result<Json<Vec<MonteCarloResult>>, StatusCode> {
+    let db = db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let runs = db
+        .get_monte_carlo_runs(id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(runs))
+}
+
+/// Run and store a Monte Carlo analysis of a backtest
+async fn run_monte_carlo(
+    State(db): State<SharedDb>,
+    Path(id): Path<i64>,
+    Json(config): Json<MonteCarloConfig>,
+) -> Result<Json<MonteCarloResult>, StatusCode> {
+    let status = |e: PipelineError| {
+        log::error!("Monte Carlo for backtest {} failed: {}", id, e);
+        match e {
+            PipelineError::NoData(_) => StatusCode::NOT_FOUND,
+            PipelineError::Config(_) => StatusCode::BAD_REQUEST,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    };
+
+    let (backtest, daily_equity) = {
+        let db = db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        monte_carlo::load_backtest(&db, id, &config.method).map_err(status)?
+    };
+
+    // Simulate off the async runtime without holding the DB lock
+    let mut result = tokio::task::spawn_blocking(move || {
+        monte_carlo::analyze(&backtest, &daily_equity, &config)
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .map_err(status)?;
+
+    let db = db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    db.save_monte_carlo(&mut result).map_err(status)?;
+
+    Ok(Json(result))
+}
+
+/// Query params for an equity curve export
+#[derive(Deserialize)]
+pub struct EquityExportQuery {
+    pub format: Option<String>,
+}
+
+/// Daily equity curve of a backtest as CSV (default) or JSON
+async fn get_backtest_equity(
+    State(db): State<SharedDb>,
+    Path(id): Path<i64>,
+    Query(params): Query<EquityExportQuery>,
+) -> Result<([(header::HeaderName, &'static str); 1], String), StatusCode> {
+    let format = match params.format.as_deref() {
+        Some(f) => ExportFormat::from_str(f).ok_or(StatusCode::BAD_REQUEST)?,
+        None => ExportFormat::default(),
+    };
+    let db = db.lock().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let curve = db
+        .get_backtest_equity(id)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if curve.is_empty() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let body = export_equity_curve(&curve, format).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let content_type = match format {
+        ExportFormat::Csv => "text/csv",
+        ExportFormat::Json => "application/json",
+    };
+
+    Ok(([(header::CONTENT_TYPE, content_type)], body))
+}
+
 // ============================================================================
 // Trade Queue Handlers
 // ============================================================================
@@ -4,8 +4,8 @@ mod http_api;
 mod scheduler;
 
 use financial_pipeline::{
-    calculate_all, AlertCondition, BacktestConfig, BacktestEngine, Database, Fred, GoogleTrends,
-    IndicatorAlert, IndicatorAlertCondition, IndicatorAlertType, PositionType, SignalEngine,
+    calculate_all, AlertCondition, BacktestConfig, BacktestEngine, Database, FillTiming, Fred, GoogleTrends,
+    IndicatorAlert, IndicatorAlertCondition, IndicatorAlertType, PositionType, configured_signal_engine, refresh_signal_status,
     Strategy, StrategyConditionType, YahooFinance,
     VectorStore, MarketEvent, PricePattern,
     ClaudeClient, FinancialContext, PriceContext as ClaudePriceContext,
@@ -17,8 +17,24 @@ use financial_pipeline::{
     DcWallet, DcPosition, DcTrade, PortfolioSnapshot, TeamConfig, ImportResult, CompetitionStats,
 };
 use financial_pipeline::ollama::{OllamaClient, SentimentResult, PatternExplanation};
-use chrono::Utc;
+use financial_pipeline::analytics::{self, AnalyticsConfig, CorrelationMatrix};
+use financial_pipeline::{confluence, formula, macro_signals, regime, scorecard, Formula, SignalScorecardEntry};
+use financial_pipeline::{MacroSignalConfig, MarketRegime, RegimeConfig, RegimeRecord};
+use financial_pipeline::models::{ConfluenceProfile, DetectorSetting};
+use financial_pipeline::{DetectorInfo, DirectionMetrics, ExecutionCosts, ExecutionModel, ExitRules, PositionSizing, RuleNode, StrategyDirection};
+use financial_pipeline::{export_equity_curve, BacktestResult, DailyPrice, EquityPoint, ExportFormat, PortfolioConfig, PortfolioRanking, SymbolData, SymbolTradeStats};
+use financial_pipeline::monte_carlo::{self, MonteCarloConfig, MonteCarloResult};
+use financial_pipeline::metrics::{self, Benchmark, DEFAULT_BENCHMARK_SYMBOL};
+use financial_pipeline::RiskMetrics;
+use financial_pipeline::{calculate_all_for_timeframe, Timeframe};
+use financial_pipeline::{
+    save_replay_backtest, OllamaDecisionModel, ReplayConfig, ReplayData, ReplayDecision,
+    RuleBasedModel,
+};
+use financial_pipeline::{optimize, Objective, OptimizationConfig, OptimizationJob, ParamRange, SearchMethod, Validation};
+use chrono::{NaiveDate, Utc};
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
 use tauri::{State, Emitter, Manager};
 
@@ -262,17 +278,40 @@ fn fetch_fred(state: State<AppState>, indicators: String) -> Result<CommandResul
         }
     }
 
+    // Refresh market-wide signals from the updated series; the fetched data
+    // is already stored, so a failure here is reported rather than returned
+    let macro_result = macro_signals::update_macro_signals(&mut db, &MacroSignalConfig::default());
+    let macro_message = match &macro_result {
+        Ok(count) => format!("{} macro signals", count),
+        Err(e) => format!("macro signal update failed: {}", e),
+    };
+
     Ok(CommandResult {
-        success: fail_count == 0,
+        success: fail_count == 0 && macro_result.is_ok(),
         message: format!(
-            "Fetched {} indicators ({} success, {} failed)",
+            "Fetched {} indicators ({} success, {} failed), {}",
             indicator_list.len(),
             success_count,
-            fail_count
+            fail_count,
+            macro_message
         ),
     })
 }
 
+/// Detect market-wide signals from stored FRED series
+#[tauri::command]
+fn generate_macro_signals(state: State<AppState>) -> Result<CommandResult, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let count = macro_signals::update_macro_signals(&mut db, &MacroSignalConfig::default())
+        .map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: format!("Detected {} macro signals", count),
+    })
+}
+
 /// Get macro data summary (latest value for each indicator)
 #[tauri::command]
 fn get_macro_data(state: State<AppState>) -> Result<Vec<MacroDataResponse>, String> {
@@ -299,11 +338,22 @@ fn get_price(state: State<AppState>, symbol: String) -> Result<Option<f64>, Stri
         .map_err(|e| e.to_string())
 }
 
-/// Calculate indicators for a symbol
+/// Calculate indicators for a symbol, plus the same indicators on any
+/// higher `timeframes` ("weekly", "monthly", "5d") under suffixed names
+/// such as "RSI_14_W"
 #[tauri::command]
-fn calculate_indicators(state: State<AppState>, symbol: String) -> Result<CommandResult, String> {
+fn calculate_indicators(
+    state: State<AppState>,
+    symbol: String,
+    timeframes: Option<Vec<String>>,
+) -> Result<CommandResult, String> {
     let mut db = state.db.lock().map_err(|e| e.to_string())?;
     let symbol = symbol.to_uppercase();
+    let timeframes = timeframes
+        .unwrap_or_default()
+        .iter()
+        .map(|tf| Timeframe::from_str(tf).ok_or_else(|| format!("Unknown timeframe '{}'", tf)))
+        .collect::<Result<Vec<_>, String>>()?;
 
     // Get price history
     let prices = db.get_prices(&symbol).map_err(|e| e.to_string())?;
@@ -316,18 +366,31 @@ fn calculate_indicators(state: State<AppState>, symbol: String) -> Result<Comman
     }
 
     // Calculate all indicators
-    let indicators = calculate_all(&prices);
+    let mut indicators = calculate_all(&prices);
+    for timeframe in timeframes.into_iter().filter(|tf| *tf != Timeframe::Daily) {
+        indicators.extend(calculate_all_for_timeframe(&prices, timeframe));
+    }
     let count = indicators.len();
 
     // Store them
     db.upsert_indicators(&indicators)
         .map_err(|e| e.to_string())?;
 
+    // Saved formulas can reference the indicators just stored; a broken
+    // formula is skipped rather than failing the whole symbol
+    let formulas = formula::update_formula_indicators(&mut db, &symbol).map_err(|e| e.to_string())?;
+    let count = count + formulas.stored;
+
     println!("[OK] Calculated {} indicator values for {}", count, symbol);
 
+    let mut message = format!("Calculated {} indicator values for {}", count, symbol);
+    if !formulas.failed.is_empty() {
+        message.push_str(&format!(" ({} formulas failed)", formulas.failed.len()));
+    }
+
     Ok(CommandResult {
         success: true,
-        message: format!("Calculated {} indicator values for {}", count, symbol),
+        message,
     })
 }
 
@@ -375,6 +438,116 @@ fn get_indicator_history(
         .collect())
 }
 
+/// Calculate beta, correlation and relative strength vs a benchmark
+#[tauri::command]
+fn calculate_relative_analytics(
+    state: State<AppState>,
+    symbols: Vec<String>,
+    benchmark: Option<String>,
+) -> Result<CommandResult, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let symbols: Vec<String> = symbols.iter().map(|s| s.to_uppercase()).collect();
+
+    let mut config = AnalyticsConfig::default();
+    if let Some(bench) = benchmark {
+        config.benchmark = bench.to_uppercase();
+    }
+
+    let count = analytics::update_relative_analytics(&mut db, &symbols, &config)
+        .map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: format!(
+            "Calculated {} relative analytics values vs {}",
+            count, config.benchmark
+        ),
+    })
+}
+
+/// Get the return correlation matrix for a watchlist
+#[tauri::command]
+fn get_correlation_matrix(
+    state: State<AppState>,
+    watchlist: String,
+    lookback: usize,
+) -> Result<CorrelationMatrix, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    analytics::watchlist_correlation_matrix(&db, &watchlist, lookback).map_err(|e| e.to_string())
+}
+
+/// Save a named indicator formula, e.g. "(close - SMA(50)) / ATR(14)"
+#[tauri::command]
+fn save_formula(
+    state: State<AppState>,
+    name: String,
+    expression: String,
+    description: Option<String>,
+) -> Result<CommandResult, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let name = formula::normalize_formula_name(&name).map_err(|e| e.to_string())?;
+    formula::parse(&expression).map_err(|e| e.to_string())?;
+
+    db.save_formula(&name, &expression, description.as_deref())
+        .map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: format!("Formula '{}' saved", name),
+    })
+}
+
+/// Get all saved formulas
+#[tauri::command]
+fn get_formulas(state: State<AppState>) -> Result<Vec<Formula>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    db.get_formulas().map_err(|e| e.to_string())
+}
+
+/// Delete a formula and its stored values
+#[tauri::command]
+fn delete_formula(state: State<AppState>, name: String) -> Result<CommandResult, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    db.delete_formula(&name.to_uppercase()).map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: format!("Formula '{}' deleted", name),
+    })
+}
+
+/// Evaluate all saved formulas for a symbol
+#[tauri::command]
+fn calculate_formulas(state: State<AppState>, symbol: String) -> Result<CommandResult, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let symbol = symbol.to_uppercase();
+
+    let update = formula::update_formula_indicators(&mut db, &symbol).map_err(|e| e.to_string())?;
+
+    let mut message = format!("Calculated {} formula values for {}", update.stored, symbol);
+    if !update.failed.is_empty() {
+        let failed: Vec<String> = update
+            .failed
+            .iter()
+            .map(|(name, error)| format!("{}: {}", name, error))
+            .collect();
+        message.push_str(&format!(
+            "; {} formulas failed ({})",
+            update.failed.len(),
+            failed.join("; ")
+        ));
+    }
+
+    Ok(CommandResult {
+        success: true,
+        message,
+    })
+}
+
 /// Price point for charting
 #[derive(Serialize)]
 struct PricePoint {
@@ -911,6 +1084,8 @@ struct SignalData {
     timestamp: String,
     created_at: String,
     acknowledged: bool,
+    status: String,
+    end_date: Option<String>,
 }
 
 /// Generate signals for a symbol
@@ -938,8 +1113,8 @@ fn generate_signals(state: State<AppState>, symbol: String) -> Result<CommandRes
     }
 
     // Generate signals
-    let engine = SignalEngine::new();
-    let signals = engine.generate_signals(&symbol, &indicators, &prices);
+    let engine = configured_signal_engine(&db).map_err(|e| e.to_string())?;
+    let signals = engine.generate_signal_episodes(&symbol, &indicators, &prices);
     let count = signals.len();
 
     // Store signals
@@ -953,6 +1128,70 @@ fn generate_signals(state: State<AppState>, symbol: String) -> Result<CommandRes
     })
 }
 
+/// Update a detector's saved settings after checking them against the registry
+fn update_detector_setting(
+    db: &Database,
+    detector_id: &str,
+    update: impl FnOnce(&mut DetectorSetting),
+) -> Result<DetectorInfo, String> {
+    let mut setting = db
+        .get_detector_setting(detector_id)
+        .map_err(|e| e.to_string())?
+        .unwrap_or_else(|| DetectorSetting {
+            detector_id: detector_id.to_string(),
+            enabled: true,
+            params: Default::default(),
+        });
+    update(&mut setting);
+
+    let mut engine = configured_signal_engine(db).map_err(|e| e.to_string())?;
+    engine
+        .registry_mut()
+        .apply_settings(std::slice::from_ref(&setting))
+        .map_err(|e| e.to_string())?;
+    let info = engine
+        .registry()
+        .info()
+        .into_iter()
+        .find(|d| d.id == detector_id)
+        .ok_or_else(|| format!("Unknown signal detector '{}'", detector_id))?;
+
+    db.save_detector_setting(&setting).map_err(|e| e.to_string())?;
+    Ok(info)
+}
+
+/// List signal detectors with their enabled state and parameters
+#[tauri::command]
+fn get_signal_detectors(state: State<AppState>) -> Result<Vec<DetectorInfo>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(configured_signal_engine(&db).map_err(|e| e.to_string())?.registry().info())
+}
+
+/// Enable or disable a signal detector
+#[tauri::command]
+fn set_signal_detector_enabled(
+    state: State<AppState>,
+    detector_id: String,
+    enabled: bool,
+) -> Result<DetectorInfo, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    update_detector_setting(&db, &detector_id, |s| s.enabled = enabled)
+}
+
+/// Set a numeric parameter of a signal detector
+#[tauri::command]
+fn set_signal_detector_param(
+    state: State<AppState>,
+    detector_id: String,
+    name: String,
+    value: f64,
+) -> Result<DetectorInfo, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    update_detector_setting(&db, &detector_id, |s| {
+        s.params.insert(name, value);
+    })
+}
+
 /// Get signals for a symbol
 #[tauri::command]
 fn get_signals(
@@ -963,6 +1202,12 @@ fn get_signals(
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let symbol = symbol.to_uppercase();
 
+    // Re-age stored episodes against the latest prices before showing them
+    let engine = configured_signal_engine(&db).map_err(|e| e.to_string())?;
+    if let Some(lifecycle) = &engine.config().lifecycle {
+        refresh_signal_status(&db, &symbol, lifecycle).map_err(|e| e.to_string())?;
+    }
+
     let signals = db
         .get_signals(&symbol, only_unacknowledged)
         .map_err(|e| e.to_string())?;
@@ -981,6 +1226,8 @@ fn get_signals(
             timestamp: s.timestamp.to_string(),
             created_at: s.created_at,
             acknowledged: s.acknowledged,
+            status: s.status.as_str().to_string(),
+            end_date: s.end_date.map(|d| d.to_string()),
         })
         .collect())
 }
@@ -1006,6 +1253,8 @@ fn get_all_signals(state: State<AppState>, limit: usize) -> Result<Vec<SignalDat
             timestamp: s.timestamp.to_string(),
             created_at: s.created_at,
             acknowledged: s.acknowledged,
+            status: s.status.as_str().to_string(),
+            end_date: s.end_date.map(|d| d.to_string()),
         })
         .collect())
 }
@@ -1042,6 +1291,135 @@ fn acknowledge_all_signals(
     })
 }
 
+/// Score every stored signal against the prices that followed it
+#[tauri::command]
+fn evaluate_signal_scorecard(state: State<AppState>) -> Result<CommandResult, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let count = scorecard::update_signal_scorecard(&mut db).map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: format!("Signal scorecard updated ({} rows)", count),
+    })
+}
+
+/// Get signal scorecard rows (symbol "ALL" for pooled rows)
+#[tauri::command]
+fn get_signal_scorecard(
+    state: State<AppState>,
+    signal_type: Option<String>,
+    symbol: Option<String>,
+) -> Result<Vec<SignalScorecardEntry>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let symbol = symbol.map(|s| s.to_uppercase());
+
+    db.get_signal_scorecard(signal_type.as_deref(), symbol.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// Fit confluence vote weights from the history of the given symbols
+#[tauri::command]
+fn fit_confluence_profile(
+    state: State<AppState>,
+    name: String,
+    symbols: Vec<String>,
+    horizon_days: Option<usize>,
+) -> Result<ConfluenceProfile, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let symbols: Vec<String> = symbols.iter().map(|s| s.to_uppercase()).collect();
+
+    confluence::fit_and_save_profile(
+        &db,
+        &configured_signal_engine(&db).map_err(|e| e.to_string())?,
+        &name,
+        &symbols,
+        horizon_days.unwrap_or(5),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Get all saved confluence profiles
+#[tauri::command]
+fn get_confluence_profiles(state: State<AppState>) -> Result<Vec<ConfluenceProfile>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    db.get_confluence_profiles().map_err(|e| e.to_string())
+}
+
+/// Delete a confluence profile
+#[tauri::command]
+fn delete_confluence_profile(state: State<AppState>, name: String) -> Result<CommandResult, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    db.delete_confluence_profile(&name).map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: format!("Confluence profile '{}' deleted", name),
+    })
+}
+
+/// Use a confluence profile's weights for signals, backtests and the AI
+/// trader (None = plain vote counting)
+#[tauri::command]
+fn set_active_confluence_profile(
+    state: State<AppState>,
+    name: Option<String>,
+) -> Result<CommandResult, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    db.set_active_confluence_profile(name.as_deref())
+        .map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: match name {
+            Some(name) => format!("Confluence profile '{}' is now active", name),
+            None => "Confluence profiles deactivated".to_string(),
+        },
+    })
+}
+
+/// Classify and store the regime history of a symbol
+#[tauri::command]
+fn update_regime(state: State<AppState>, symbol: String) -> Result<CommandResult, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let symbol = symbol.to_uppercase();
+
+    let count = regime::update_regime(&mut db, &symbol, &RegimeConfig::default())
+        .map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: format!("Classified {} regime bars for {}", count, symbol),
+    })
+}
+
+/// Classify and store the broad market regime (benchmark + VIXCLS)
+#[tauri::command]
+fn update_market_regime(state: State<AppState>, benchmark: Option<String>) -> Result<CommandResult, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let benchmark = benchmark.map(|b| b.to_uppercase());
+
+    let count = regime::update_market_regime(&mut db, benchmark.as_deref(), &RegimeConfig::default())
+        .map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: format!("Classified {} market regime bars", count),
+    })
+}
+
+/// Get the regime history of a symbol ("MARKET" for the broad market)
+#[tauri::command]
+fn get_regime_history(state: State<AppState>, symbol: String) -> Result<Vec<RegimeRecord>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    db.get_regime_history(&symbol.to_uppercase())
+        .map_err(|e| e.to_string())
+}
+
 // ============================================================================
 // Indicator Alert Commands
 // ============================================================================
@@ -1199,6 +1577,15 @@ struct StrategyData {
     stop_loss_percent: Option<f64>,
     take_profit_percent: Option<f64>,
     position_size_percent: f64,
+    entry_formula: Option<String>,
+    exit_formula: Option<String>,
+    rs_benchmark: Option<String>,
+    allowed_regimes: Vec<String>,
+    entry_rule: Option<RuleNode>,
+    exit_rule: Option<RuleNode>,
+    direction: String,
+    exit_rules: ExitRules,
+    position_sizing: PositionSizing,
     created_at: String,
 }
 
@@ -1234,6 +1621,10 @@ struct MetricsData {
     avg_loss_percent: f64,
     profit_factor: f64,
     avg_trade_duration_days: f64,
+    long: DirectionMetrics,
+    short: DirectionMetrics,
+    costs: ExecutionCosts,
+    risk: RiskMetrics,
 }
 
 /// Backtest result data for frontend
@@ -1249,9 +1640,137 @@ struct BacktestResultData {
     final_capital: f64,
     metrics: MetricsData,
     trades: Vec<BacktestTradeData>,
+    symbol_stats: Vec<SymbolTradeStats>,
+    equity_curve: Vec<EquityPoint>,
     created_at: String,
 }
 
+/// In-sample trial of an optimization for frontend
+#[derive(Serialize)]
+struct OptimizationTrialData {
+    window: usize,
+    params: BTreeMap<String, f64>,
+    score: f64,
+    total_return: f64,
+    max_drawdown: f64,
+    sharpe_ratio: f64,
+    total_trades: usize,
+}
+
+/// Optimization window and its out-of-sample run for frontend
+#[derive(Serialize)]
+struct OptimizationWindowData {
+    index: usize,
+    in_sample_start: String,
+    in_sample_end: String,
+    best_params: BTreeMap<String, f64>,
+    in_sample_score: f64,
+    out_of_sample_score: f64,
+    out_of_sample: BacktestResultData,
+}
+
+/// Optimization job result for frontend
+#[derive(Serialize)]
+struct OptimizationData {
+    job_id: i64,
+    strategy_name: String,
+    symbol: String,
+    objective: String,
+    best_params: BTreeMap<String, f64>,
+    in_sample_score: f64,
+    out_of_sample_score: f64,
+    degradation_percent: f64,
+    overfit: bool,
+    trials: Vec<OptimizationTrialData>,
+    windows: Vec<OptimizationWindowData>,
+}
+
+/// Backtest run of an optimization job for frontend
+#[derive(Serialize)]
+struct OptimizationRunData {
+    role: String,
+    window: usize,
+    params: BTreeMap<String, f64>,
+    backtest: BacktestResultData,
+}
+
+/// First and last date of some price bars
+fn price_range<'a>(prices: impl IntoIterator<Item = &'a DailyPrice>) -> (NaiveDate, NaiveDate) {
+    let dates: Vec<NaiveDate> = prices.into_iter().map(|p| p.date).collect();
+    (
+        dates.iter().min().copied().unwrap_or_default(),
+        dates.iter().max().copied().unwrap_or_default(),
+    )
+}
+
+/// Risk-free rate (average DFF) over a backtest period and the benchmark
+/// prices, defaulting to SPY
+fn risk_inputs(
+    db: &Database,
+    (start, end): (NaiveDate, NaiveDate),
+    benchmark_symbol: Option<String>,
+) -> Result<(f64, Option<Benchmark>), String> {
+    let risk_free = metrics::risk_free_rate_percent(db, start, end).map_err(|e| e.to_string())?;
+    let symbol = benchmark_symbol
+        .unwrap_or_else(|| DEFAULT_BENCHMARK_SYMBOL.to_string())
+        .to_uppercase();
+    let benchmark = metrics::load_benchmark(db, &symbol).map_err(|e| e.to_string())?;
+    Ok((risk_free, benchmark))
+}
+
+/// Convert a backtest result into the frontend format
+fn backtest_result_data(result: BacktestResult) -> BacktestResultData {
+    BacktestResultData {
+        id: result.id,
+        strategy_id: result.strategy_id,
+        strategy_name: result.strategy_name,
+        symbol: result.symbol,
+        start_date: result.start_date.to_string(),
+        end_date: result.end_date.to_string(),
+        initial_capital: result.initial_capital,
+        final_capital: result.final_capital,
+        metrics: MetricsData {
+            total_return: result.metrics.total_return,
+            total_return_dollars: result.metrics.total_return_dollars,
+            max_drawdown: result.metrics.max_drawdown,
+            sharpe_ratio: result.metrics.sharpe_ratio,
+            win_rate: result.metrics.win_rate,
+            total_trades: result.metrics.total_trades,
+            winning_trades: result.metrics.winning_trades,
+            losing_trades: result.metrics.losing_trades,
+            avg_win_percent: result.metrics.avg_win_percent,
+            avg_loss_percent: result.metrics.avg_loss_percent,
+            profit_factor: result.metrics.profit_factor,
+            avg_trade_duration_days: result.metrics.avg_trade_duration_days,
+            long: result.metrics.long,
+            short: result.metrics.short,
+            costs: result.metrics.costs,
+            risk: result.metrics.risk,
+        },
+        trades: result
+            .trades
+            .into_iter()
+            .map(|t| BacktestTradeData {
+                id: t.id,
+                symbol: t.symbol,
+                direction: t.direction.as_str().to_string(),
+                entry_date: t.entry_date.to_string(),
+                entry_price: t.entry_price,
+                entry_reason: t.entry_reason,
+                exit_date: t.exit_date.map(|d| d.to_string()),
+                exit_price: t.exit_price,
+                exit_reason: t.exit_reason,
+                shares: t.shares,
+                profit_loss: t.profit_loss,
+                profit_loss_percent: t.profit_loss_percent,
+            })
+            .collect(),
+        symbol_stats: result.symbol_stats,
+        equity_curve: result.equity_curve,
+        created_at: result.created_at,
+    }
+}
+
 /// Save a strategy
 #[tauri::command]
 fn save_strategy(
@@ -1265,6 +1784,15 @@ fn save_strategy(
     stop_loss_percent: Option<f64>,
     take_profit_percent: Option<f64>,
     position_size_percent: f64,
+    entry_formula: Option<String>,
+    exit_formula: Option<String>,
+    rs_benchmark: Option<String>,
+    allowed_regimes: Option<Vec<String>>,
+    entry_rule: Option<RuleNode>,
+    exit_rule: Option<RuleNode>,
+    direction: Option<String>,
+    exit_rules: Option<ExitRules>,
+    position_sizing: Option<PositionSizing>,
 ) -> Result<CommandResult, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
 
@@ -1272,6 +1800,15 @@ fn save_strategy(
         .ok_or_else(|| format!("Invalid entry condition: {}", entry_condition))?;
     let exit_cond = StrategyConditionType::from_str(&exit_condition)
         .ok_or_else(|| format!("Invalid exit condition: {}", exit_condition))?;
+    let allowed_regimes = allowed_regimes
+        .unwrap_or_default()
+        .iter()
+        .map(|r| MarketRegime::from_str(r).ok_or_else(|| format!("Invalid regime: {}", r)))
+        .collect::<Result<Vec<_>, String>>()?;
+    let direction = match direction {
+        Some(d) => StrategyDirection::from_str(&d).ok_or_else(|| format!("Invalid direction: {}", d))?,
+        None => StrategyDirection::LongOnly,
+    };
 
     let strategy = Strategy {
         id: 0,
@@ -1284,6 +1821,15 @@ fn save_strategy(
         stop_loss_percent,
         take_profit_percent,
         position_size_percent,
+        entry_formula: entry_formula.map(|f| f.to_uppercase()),
+        exit_formula: exit_formula.map(|f| f.to_uppercase()),
+        rs_benchmark: rs_benchmark.map(|b| b.to_uppercase()),
+        allowed_regimes,
+        entry_rule,
+        exit_rule,
+        direction,
+        exit_rules: exit_rules.unwrap_or_default(),
+        position_sizing: position_sizing.unwrap_or_default(),
         created_at: String::new(),
     };
 
@@ -1317,6 +1863,15 @@ fn get_strategies(state: State<AppState>) -> Result<Vec<StrategyData>, String> {
             stop_loss_percent: s.stop_loss_percent,
             take_profit_percent: s.take_profit_percent,
             position_size_percent: s.position_size_percent,
+            entry_formula: s.entry_formula,
+            exit_formula: s.exit_formula,
+            rs_benchmark: s.rs_benchmark,
+            allowed_regimes: s.allowed_regimes.iter().map(|r| r.as_str().to_string()).collect(),
+            entry_rule: s.entry_rule,
+            exit_rule: s.exit_rule,
+            direction: s.direction.as_str().to_string(),
+            exit_rules: s.exit_rules,
+            position_sizing: s.position_sizing,
             created_at: s.created_at,
         })
         .collect())
@@ -1335,6 +1890,14 @@ fn delete_strategy(state: State<AppState>, name: String) -> Result<CommandResult
     })
 }
 
+/// Parse an optional fill timing ("same_close", "next_open", "next_close")
+fn parse_fill_timing(fill_timing: Option<String>) -> Result<FillTiming, String> {
+    match fill_timing {
+        Some(t) => FillTiming::from_str(&t).ok_or_else(|| format!("Invalid fill timing: {}", t)),
+        None => Ok(FillTiming::default()),
+    }
+}
+
 /// Run a backtest
 #[tauri::command]
 fn run_backtest(
@@ -1342,9 +1905,15 @@ fn run_backtest(
     strategy_name: String,
     symbol: String,
     initial_capital: f64,
+    short_margin_percent: Option<f64>,
+    borrow_rate_percent: Option<f64>,
+    execution: Option<ExecutionModel>,
+    fill_timing: Option<String>,
+    benchmark_symbol: Option<String>,
 ) -> Result<BacktestResultData, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
     let symbol = symbol.to_uppercase();
+    let fill_timing = parse_fill_timing(fill_timing)?;
 
     // Get strategy
     let strategy = db
@@ -1368,11 +1937,20 @@ fn run_backtest(
     }
 
     // Run backtest
+    let (risk_free_rate_percent, benchmark) =
+        risk_inputs(&db, price_range(&prices), benchmark_symbol)?;
+    let defaults = BacktestConfig::default();
     let config = BacktestConfig {
         initial_capital,
-        commission_per_trade: 0.0,
+        execution: execution.unwrap_or_default(),
+        short_margin_percent: short_margin_percent.unwrap_or(defaults.short_margin_percent),
+        borrow_rate_percent: borrow_rate_percent.unwrap_or(defaults.borrow_rate_percent),
+        fill_timing,
+        risk_free_rate_percent,
+        benchmark,
     };
-    let engine = BacktestEngine::new(config);
+    let signal_engine = configured_signal_engine(&db).map_err(|e| e.to_string())?;
+    let engine = BacktestEngine::new(config).with_signal_engine(signal_engine);
     let result = engine.run(&strategy, &symbol, &prices, &indicators);
 
     // Save result
@@ -1383,50 +1961,89 @@ fn run_backtest(
         strategy_name, symbol, result.metrics.total_return
     );
 
-    // Convert to frontend format
-    Ok(BacktestResultData {
-        id: result.id,
-        strategy_id: result.strategy_id,
-        strategy_name: result.strategy_name,
-        symbol: result.symbol,
-        start_date: result.start_date.to_string(),
-        end_date: result.end_date.to_string(),
-        initial_capital: result.initial_capital,
-        final_capital: result.final_capital,
-        metrics: MetricsData {
-            total_return: result.metrics.total_return,
-            total_return_dollars: result.metrics.total_return_dollars,
-            max_drawdown: result.metrics.max_drawdown,
-            sharpe_ratio: result.metrics.sharpe_ratio,
-            win_rate: result.metrics.win_rate,
-            total_trades: result.metrics.total_trades,
-            winning_trades: result.metrics.winning_trades,
-            losing_trades: result.metrics.losing_trades,
-            avg_win_percent: result.metrics.avg_win_percent,
-            avg_loss_percent: result.metrics.avg_loss_percent,
-            profit_factor: result.metrics.profit_factor,
-            avg_trade_duration_days: result.metrics.avg_trade_duration_days,
-        },
-        trades: result
-            .trades
-            .into_iter()
-            .map(|t| BacktestTradeData {
-                id: t.id,
-                symbol: t.symbol,
-                direction: t.direction.as_str().to_string(),
-                entry_date: t.entry_date.to_string(),
-                entry_price: t.entry_price,
-                entry_reason: t.entry_reason,
-                exit_date: t.exit_date.map(|d| d.to_string()),
-                exit_price: t.exit_price,
-                exit_reason: t.exit_reason,
-                shares: t.shares,
-                profit_loss: t.profit_loss,
-                profit_loss_percent: t.profit_loss_percent,
-            })
-            .collect(),
-        created_at: result.created_at,
-    })
+    Ok(backtest_result_data(result))
+}
+
+/// Run a portfolio backtest across several symbols with shared capital
+#[tauri::command]
+fn run_portfolio_backtest(
+    state: State<AppState>,
+    strategy_name: String,
+    symbols: Option<Vec<String>>,
+    watchlist: Option<String>,
+    initial_capital: f64,
+    max_positions: Option<usize>,
+    ranking: Option<PortfolioRanking>,
+    execution: Option<ExecutionModel>,
+    fill_timing: Option<String>,
+    benchmark_symbol: Option<String>,
+) -> Result<BacktestResultData, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let fill_timing = parse_fill_timing(fill_timing)?;
+
+    let strategy = db
+        .get_strategy(&strategy_name)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Strategy '{}' not found", strategy_name))?;
+
+    let mut symbols = match (symbols, watchlist) {
+        (Some(symbols), _) if !symbols.is_empty() => symbols,
+        (_, Some(name)) => db.get_watchlist(&name).map_err(|e| e.to_string())?,
+        _ => return Err("Provide a list of symbols or a watchlist".to_string()),
+    };
+    for symbol in symbols.iter_mut() {
+        *symbol = symbol.to_uppercase();
+    }
+    symbols.sort();
+    symbols.dedup();
+
+    let mut universe = Vec::with_capacity(symbols.len());
+    for symbol in symbols {
+        let prices = db.get_prices(&symbol).map_err(|e| e.to_string())?;
+        let indicators = db.get_all_indicators(&symbol).map_err(|e| e.to_string())?;
+        if prices.is_empty() || indicators.is_empty() {
+            println!("[WARN] Skipping {} in portfolio backtest: missing data", symbol);
+            continue;
+        }
+        universe.push(SymbolData {
+            symbol,
+            prices,
+            indicators,
+        });
+    }
+
+    if universe.is_empty() {
+        return Err("No symbols with price and indicator data".to_string());
+    }
+
+    let period = price_range(universe.iter().flat_map(|d| &d.prices));
+    let (risk_free_rate_percent, benchmark) = risk_inputs(&db, period, benchmark_symbol)?;
+    let engine = BacktestEngine::new(BacktestConfig {
+        initial_capital,
+        execution: execution.unwrap_or_default(),
+        fill_timing,
+        risk_free_rate_percent,
+        benchmark,
+        ..BacktestConfig::default()
+    })
+    .with_signal_engine(configured_signal_engine(&db).map_err(|e| e.to_string())?);
+    let defaults = PortfolioConfig::default();
+    let config = PortfolioConfig {
+        max_positions: max_positions.unwrap_or(defaults.max_positions),
+        ranking: ranking.unwrap_or(defaults.ranking),
+    };
+    let result = financial_pipeline::run_portfolio_backtest(&engine, &strategy, &universe, &config);
+
+    db.save_backtest_result(&result).map_err(|e| e.to_string())?;
+
+    println!(
+        "[OK] Portfolio backtest completed for {} on {} symbols: {:.2}% return",
+        strategy_name,
+        universe.len(),
+        result.metrics.total_return
+    );
+
+    Ok(backtest_result_data(result))
 }
 
 /// Get backtest history
@@ -1449,31 +2066,9 @@ fn get_backtest_results(
 
     Ok(results
         .into_iter()
-        .map(|r| BacktestResultData {
-            id: r.id,
-            strategy_id: r.strategy_id,
-            strategy_name: r.strategy_name,
-            symbol: r.symbol,
-            start_date: r.start_date.to_string(),
-            end_date: r.end_date.to_string(),
-            initial_capital: r.initial_capital,
-            final_capital: r.final_capital,
-            metrics: MetricsData {
-                total_return: r.metrics.total_return,
-                total_return_dollars: r.metrics.total_return_dollars,
-                max_drawdown: r.metrics.max_drawdown,
-                sharpe_ratio: r.metrics.sharpe_ratio,
-                win_rate: r.metrics.win_rate,
-                total_trades: r.metrics.total_trades,
-                winning_trades: r.metrics.winning_trades,
-                losing_trades: r.metrics.losing_trades,
-                avg_win_percent: r.metrics.avg_win_percent,
-                avg_loss_percent: r.metrics.avg_loss_percent,
-                profit_factor: r.metrics.profit_factor,
-                avg_trade_duration_days: r.metrics.avg_trade_duration_days,
-            },
-            trades: Vec::new(), // Trades not loaded in list view
-            created_at: r.created_at,
+        .map(|mut r| {
+            r.trades.clear(); // Trades not loaded in list view
+            backtest_result_data(r)
         })
         .collect())
 }
@@ -1490,49 +2085,259 @@ fn get_backtest_detail(
         .get_backtest_detail(backtest_id)
         .map_err(|e| e.to_string())?;
 
-    Ok(result.map(|r| BacktestResultData {
-        id: r.id,
-        strategy_id: r.strategy_id,
-        strategy_name: r.strategy_name,
-        symbol: r.symbol,
-        start_date: r.start_date.to_string(),
-        end_date: r.end_date.to_string(),
-        initial_capital: r.initial_capital,
-        final_capital: r.final_capital,
-        metrics: MetricsData {
-            total_return: r.metrics.total_return,
-            total_return_dollars: r.metrics.total_return_dollars,
-            max_drawdown: r.metrics.max_drawdown,
-            sharpe_ratio: r.metrics.sharpe_ratio,
-            win_rate: r.metrics.win_rate,
-            total_trades: r.metrics.total_trades,
-            winning_trades: r.metrics.winning_trades,
-            losing_trades: r.metrics.losing_trades,
-            avg_win_percent: r.metrics.avg_win_percent,
-            avg_loss_percent: r.metrics.avg_loss_percent,
-            profit_factor: r.metrics.profit_factor,
-            avg_trade_duration_days: r.metrics.avg_trade_duration_days,
-        },
-        trades: r
-            .trades
+    Ok(result.map(backtest_result_data))
+}
+
+/// Search strategy parameters on one symbol with out-of-sample validation
+#[tauri::command]
+async fn run_optimization(
+    state: State<'_, AppState>,
+    strategy_name: String,
+    symbol: String,
+    initial_capital: f64,
+    params: Vec<ParamRange>,
+    search: Option<SearchMethod>,
+    objective: Option<String>,
+    validation: Option<Validation>,
+    overfit_threshold_percent: Option<f64>,
+    execution: Option<ExecutionModel>,
+    fill_timing: Option<String>,
+    benchmark_symbol: Option<String>,
+) -> Result<OptimizationData, String> {
+    let symbol = symbol.to_uppercase();
+    let fill_timing = parse_fill_timing(fill_timing)?;
+    let objective = match objective {
+        Some(o) => Objective::from_str(&o).ok_or_else(|| format!("Invalid objective: {}", o))?,
+        None => Objective::default(),
+    };
+
+    // Load under the lock, search without it
+    let (engine, strategy, prices, indicators, config) = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let strategy = db
+            .get_strategy(&strategy_name)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Strategy '{}' not found", strategy_name))?;
+
+        let prices = db.get_prices(&symbol).map_err(|e| e.to_string())?;
+        let indicators = db.get_all_indicators(&symbol).map_err(|e| e.to_string())?;
+        if prices.is_empty() {
+            return Err(format!("No price data for {}", symbol));
+        }
+        if indicators.is_empty() {
+            return Err(format!(
+                "No indicator data for {}. Calculate indicators first.",
+                symbol
+            ));
+        }
+
+        let defaults = OptimizationConfig::default();
+        let config = OptimizationConfig {
+            params,
+            search: search.unwrap_or_default(),
+            objective,
+            validation: validation.unwrap_or_default(),
+            overfit_threshold_percent: overfit_threshold_percent
+                .unwrap_or(defaults.overfit_threshold_percent),
+        };
+        let (risk_free_rate_percent, benchmark) =
+            risk_inputs(&db, price_range(&prices), benchmark_symbol)?;
+        let engine = BacktestEngine::new(BacktestConfig {
+            initial_capital,
+            execution: execution.unwrap_or_default(),
+            fill_timing,
+            risk_free_rate_percent,
+            benchmark,
+            ..BacktestConfig::default()
+        })
+        .with_signal_engine(configured_signal_engine(&db).map_err(|e| e.to_string())?);
+        (engine, strategy, prices, indicators, config)
+    };
+
+    let search_symbol = symbol.clone();
+    let (mut result, config) = tokio::task::spawn_blocking(move || {
+        optimize(&engine, &strategy, &search_symbol, &prices, &indicators, &config)
+            .map(|result| (result, config))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_optimization(&mut result, &config).map_err(|e| e.to_string())?;
+
+    println!(
+        "[OK] Optimization {} of {} on {}: {} trials, degradation {:.1}%{}",
+        result.job_id,
+        strategy_name,
+        symbol,
+        result.trials.len(),
+        result.degradation_percent,
+        if result.overfit { " (overfit)" } else { "" }
+    );
+
+    Ok(OptimizationData {
+        job_id: result.job_id,
+        strategy_name: result.strategy_name,
+        symbol: result.symbol,
+        objective: result.objective.as_str().to_string(),
+        best_params: result.best_params,
+        in_sample_score: result.in_sample_score,
+        out_of_sample_score: result.out_of_sample_score,
+        degradation_percent: result.degradation_percent,
+        overfit: result.overfit,
+        trials: result
+            .trials
             .into_iter()
-            .map(|t| BacktestTradeData {
-                id: t.id,
-                symbol: t.symbol,
-                direction: t.direction.as_str().to_string(),
-                entry_date: t.entry_date.to_string(),
-                entry_price: t.entry_price,
-                entry_reason: t.entry_reason,
-                exit_date: t.exit_date.map(|d| d.to_string()),
-                exit_price: t.exit_price,
-                exit_reason: t.exit_reason,
-                shares: t.shares,
-                profit_loss: t.profit_loss,
-                profit_loss_percent: t.profit_loss_percent,
+            .map(|t| OptimizationTrialData {
+                window: t.window,
+                params: t.params,
+                score: t.score,
+                total_return: t.result.metrics.total_return,
+                max_drawdown: t.result.metrics.max_drawdown,
+                sharpe_ratio: t.result.metrics.sharpe_ratio,
+                total_trades: t.result.metrics.total_trades,
             })
             .collect(),
-        created_at: r.created_at,
-    }))
+        windows: result
+            .windows
+            .into_iter()
+            .map(|w| OptimizationWindowData {
+                index: w.index,
+                in_sample_start: w.in_sample_start.to_string(),
+                in_sample_end: w.in_sample_end.to_string(),
+                best_params: w.best_params,
+                in_sample_score: w.in_sample_score,
+                out_of_sample_score: w.out_of_sample_score,
+                out_of_sample: backtest_result_data(w.out_of_sample),
+            })
+            .collect(),
+    })
+}
+
+/// Get saved optimization jobs
+#[tauri::command]
+fn get_optimization_jobs(
+    state: State<AppState>,
+    strategy_name: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<OptimizationJob>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    db.get_optimization_jobs(strategy_name.as_deref(), limit.unwrap_or(50))
+        .map_err(|e| e.to_string())
+}
+
+/// Get the backtest runs of an optimization job
+#[tauri::command]
+fn get_optimization_runs(
+    state: State<AppState>,
+    job_id: i64,
+) -> Result<Vec<OptimizationRunData>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let runs = db.get_optimization_runs(job_id).map_err(|e| e.to_string())?;
+
+    Ok(runs
+        .into_iter()
+        .map(|r| OptimizationRunData {
+            role: r.role,
+            window: r.window,
+            params: r.params,
+            backtest: backtest_result_data(r.result),
+        })
+        .collect())
+}
+
+/// Delete an optimization job and its runs
+#[tauri::command]
+fn delete_optimization_job(state: State<AppState>, job_id: i64) -> Result<CommandResult, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    db.delete_optimization_job(job_id).map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: "Optimization job deleted".to_string(),
+    })
+}
+
+/// Run and store a Monte Carlo robustness analysis of a saved backtest
+#[tauri::command]
+async fn run_monte_carlo(
+    state: State<'_, AppState>,
+    backtest_id: i64,
+    config: Option<MonteCarloConfig>,
+) -> Result<MonteCarloResult, String> {
+    let config = config.unwrap_or_default();
+    let (backtest, daily_equity) = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        monte_carlo::load_backtest(&db, backtest_id, &config.method).map_err(|e| e.to_string())?
+    };
+
+    // Simulate on a blocking thread without holding the DB lock
+    let mut result = tokio::task::spawn_blocking(move || {
+        monte_carlo::analyze(&backtest, &daily_equity, &config)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_monte_carlo(&mut result).map_err(|e| e.to_string())?;
+
+    println!(
+        "[OK] Monte Carlo on backtest {}: median return {:.2}%, risk of ruin {:.1}%",
+        backtest_id, result.final_return.p50, result.risk_of_ruin_percent
+    );
+
+    Ok(result)
+}
+
+/// Get stored Monte Carlo analyses of a backtest
+#[tauri::command]
+fn get_monte_carlo_runs(
+    state: State<AppState>,
+    backtest_id: i64,
+) -> Result<Vec<MonteCarloResult>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    db.get_monte_carlo_runs(backtest_id).map_err(|e| e.to_string())
+}
+
+/// Export a backtest's daily equity, cash, exposure and drawdown series
+/// ("csv" or "json") to the exports directory
+#[tauri::command]
+fn export_backtest_equity(
+    state: State<AppState>,
+    backtest_id: i64,
+    format: Option<String>,
+) -> Result<CommandResult, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let format = match format {
+        Some(f) => ExportFormat::from_str(&f).ok_or_else(|| format!("Invalid export format: {}", f))?,
+        None => ExportFormat::default(),
+    };
+
+    let curve = db.get_backtest_equity(backtest_id).map_err(|e| e.to_string())?;
+    if curve.is_empty() {
+        return Ok(CommandResult {
+            success: false,
+            message: format!("No equity curve stored for backtest {}", backtest_id),
+        });
+    }
+    let content = export_equity_curve(&curve, format).map_err(|e| e.to_string())?;
+
+    std::fs::create_dir_all("exports").ok();
+    let file = format!("exports/backtest_{}_equity.{}", backtest_id, format.as_str());
+    std::fs::write(&file, content).map_err(|e| e.to_string())?;
+
+    println!("[OK] Exported equity curve of backtest {}", backtest_id);
+
+    Ok(CommandResult {
+        success: true,
+        message: format!("Exported to {}", file),
+    })
 }
 
 /// Delete a backtest result
@@ -3123,6 +3928,24 @@ fn get_portfolio_snapshots(
         .collect())
 }
 
+/// Get risk and benchmark-relative statistics of a team's portfolio
+/// ("KALIC" or "DC") from its daily snapshots
+#[tauri::command]
+fn get_portfolio_metrics(
+    state: State<AppState>,
+    team: String,
+    days: Option<i32>,
+    benchmark_symbol: Option<String>,
+) -> Result<RiskMetrics, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let benchmark_symbol = benchmark_symbol
+        .unwrap_or_else(|| DEFAULT_BENCHMARK_SYMBOL.to_string())
+        .to_uppercase();
+
+    metrics::portfolio_metrics(&db, &team.to_uppercase(), days.unwrap_or(365), &benchmark_symbol)
+        .map_err(|e| e.to_string())
+}
+
 /// Save team configuration
 #[tauri::command]
 fn save_team_config(
@@ -3374,6 +4197,8 @@ struct AiConfigResponse {
     session_duration_minutes: u32,
     benchmark_symbol: String,
     model_priority: Vec<String>,
+    exit_rules: ExitRules,
+    position_sizing: PositionSizing,
 }
 
 /// Get AI trader status
@@ -3414,6 +4239,8 @@ fn ai_trader_get_config(state: State<AppState>) -> Result<AiConfigResponse, Stri
         session_duration_minutes: config.session_duration_minutes,
         benchmark_symbol: config.benchmark_symbol,
         model_priority: config.model_priority,
+        exit_rules: config.exit_rules,
+        position_sizing: config.position_sizing,
     })
 }
 
@@ -3529,6 +4356,22 @@ fn ai_trader_get_benchmark_comparison(state: State<AppState>) -> Result<AiBenchm
     })
 }
 
+/// Get risk and benchmark-relative statistics of the AI portfolio
+#[tauri::command]
+fn ai_trader_get_performance_metrics(
+    state: State<AppState>,
+    days: Option<u32>,
+) -> Result<RiskMetrics, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let config = db.get_ai_trader_config().map_err(|e| e.to_string())?;
+    let trader = AiTrader::new(config);
+
+    trader
+        .get_performance_metrics(&db, days.unwrap_or(365))
+        .map_err(|e| e.to_string())
+}
+
 /// Get compounding forecast
 #[tauri::command]
 fn ai_trader_get_compounding_forecast(state: State<AppState>) -> Result<AiForecastResponse, String> {
@@ -3712,6 +4555,145 @@ fn ai_trader_update_circuit_breaker(
     })
 }
 
+/// Set trailing, break-even, time and scale-out exits for AI paper positions
+#[tauri::command]
+fn ai_trader_set_exit_rules(
+    state: State<AppState>,
+    exit_rules: ExitRules,
+) -> Result<CommandResult, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut config = db.get_ai_trader_config().map_err(|e| e.to_string())?;
+    config.exit_rules = exit_rules;
+    db.update_ai_trader_config(&config).map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: "Exit rules updated".to_string(),
+    })
+}
+
+/// Set the sizing model used for AI trader BUY decisions
+#[tauri::command]
+fn ai_trader_set_position_sizing(
+    state: State<AppState>,
+    position_sizing: PositionSizing,
+) -> Result<CommandResult, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut config = db.get_ai_trader_config().map_err(|e| e.to_string())?;
+    config.position_sizing = position_sizing;
+    db.update_ai_trader_config(&config).map_err(|e| e.to_string())?;
+
+    Ok(CommandResult {
+        success: true,
+        message: "Position sizing updated".to_string(),
+    })
+}
+
+/// AI trader replay result for frontend
+#[derive(Serialize)]
+struct AiReplayData {
+    backtest: BacktestResultData,
+    decisions: Vec<ReplayDecision>,
+    failed_responses: usize,
+}
+
+/// Replay the AI trader over past dates in an isolated simulated portfolio.
+/// `model` is an Ollama model name or "rule_based"; by default the configured
+/// model priority is used.
+#[tauri::command]
+async fn ai_trader_replay(
+    start_date: String,
+    end_date: String,
+    symbols: Vec<String>,
+    step_days: Option<usize>,
+    model: Option<String>,
+    enforce_guardrails: Option<bool>,
+) -> Result<AiReplayData, String> {
+    let start = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid start_date: {}", e))?;
+    let end = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid end_date: {}", e))?;
+    let symbols: Vec<String> = symbols.iter().map(|s| s.to_uppercase()).collect();
+    if symbols.is_empty() {
+        return Err("Provide at least one symbol".to_string());
+    }
+
+    // Load everything up front: the replay itself needs no connection
+    let db_path = get_data_path("finance.db");
+    let db = Database::open(&db_path).map_err(|e| e.to_string())?;
+    let trader_config = db.get_ai_trader_config().map_err(|e| e.to_string())?;
+    let mut config = ReplayConfig::new(start, end, symbols);
+    config.step_days = step_days.unwrap_or(config.step_days);
+    config.enforce_guardrails = enforce_guardrails.unwrap_or(config.enforce_guardrails);
+    let data = ReplayData::load(&db, &config, &trader_config.benchmark_symbol)
+        .map_err(|e| e.to_string())?;
+    let signal_engine = configured_signal_engine(&db).map_err(|e| e.to_string())?;
+    drop(db);
+
+    let trader = AiTrader::new(trader_config).with_signal_engine(signal_engine);
+    let mut result = match model.as_deref() {
+        Some("rule_based") => trader
+            .replay(&data, &config, &mut RuleBasedModel::default())
+            .await,
+        _ => {
+            if !trader.check_ollama().await {
+                return Err("Ollama is not available. Start it with: ollama serve".to_string());
+            }
+            let models = model.map_or_else(|| trader.config.model_priority.clone(), |m| vec![m]);
+            trader
+                .replay(&data, &config, &mut OllamaDecisionModel::new(models))
+                .await
+        }
+    }
+    .map_err(|e| e.to_string())?;
+
+    // Save result
+    let db = Database::open(&db_path).map_err(|e| e.to_string())?;
+    save_replay_backtest(&db, &mut result.backtest).map_err(|e| e.to_string())?;
+
+    println!(
+        "[AI Trader] Replay {} to {}: {} decisions, {:.2}% return",
+        start,
+        end,
+        result.decisions.len(),
+        result.backtest.metrics.total_return
+    );
+
+    Ok(AiReplayData {
+        backtest: backtest_result_data(result.backtest),
+        decisions: result.decisions,
+        failed_responses: result.failed_responses,
+    })
+}
+
+/// Apply stops, targets and exit rules to open paper positions now
+#[tauri::command]
+fn ai_trader_check_stops(state: State<AppState>) -> Result<Vec<PaperTradeResponse>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let config = db.get_ai_trader_config().map_err(|e| e.to_string())?;
+    let trader = AiTrader::new(config);
+    let trades = trader.enforce_stops(&db).map_err(|e| e.to_string())?;
+
+    println!("[AI Trader] Stop check executed {} exit(s)", trades.len());
+
+    Ok(trades
+        .into_iter()
+        .map(|t| PaperTradeResponse {
+            id: t.id,
+            symbol: t.symbol,
+            action: t.action.as_str().to_string(),
+            quantity: t.quantity,
+            price: t.price,
+            pnl: t.pnl,
+            timestamp: t.timestamp,
+            notes: t.notes,
+        })
+        .collect())
+}
+
 /// Get recent trade rejections
 #[tauri::command]
 fn ai_trader_get_rejections(
@@ -4144,6 +5126,12 @@ pub fn run() {
             calculate_indicators,
             get_indicators,
             get_indicator_history,
+            calculate_relative_analytics,
+            get_correlation_matrix,
+            save_formula,
+            get_formulas,
+            delete_formula,
+            calculate_formulas,
             get_price_history,
             export_csv,
             search_symbol,
@@ -4158,10 +5146,23 @@ pub fn run() {
             get_trends,
             // Signal commands
             generate_signals,
+            generate_macro_signals,
+            get_signal_detectors,
+            set_signal_detector_enabled,
+            set_signal_detector_param,
             get_signals,
             get_all_signals,
             acknowledge_signal,
             acknowledge_all_signals,
+            evaluate_signal_scorecard,
+            get_signal_scorecard,
+            fit_confluence_profile,
+            get_confluence_profiles,
+            delete_confluence_profile,
+            set_active_confluence_profile,
+            update_regime,
+            update_market_regime,
+            get_regime_history,
             // Indicator alert commands
             add_indicator_alert,
             get_indicator_alerts,
@@ -4172,9 +5173,17 @@ pub fn run() {
             get_strategies,
             delete_strategy,
             run_backtest,
+            run_portfolio_backtest,
             get_backtest_results,
             get_backtest_detail,
+            export_backtest_equity,
             delete_backtest,
+            run_monte_carlo,
+            get_monte_carlo_runs,
+            run_optimization,
+            get_optimization_jobs,
+            get_optimization_runs,
+            delete_optimization_job,
             // Watchlist/Symbol Group commands
             create_watchlist,
             get_all_watchlists,
@@ -4228,6 +5237,7 @@ pub fn run() {
             lookup_current_price,
             record_portfolio_snapshot,
             get_portfolio_snapshots,
+            get_portfolio_metrics,
             save_team_config,
             load_team_config,
             list_team_configs,
@@ -4241,6 +5251,7 @@ pub fn run() {
             ai_trader_get_decisions,
             ai_trader_get_performance_history,
             ai_trader_get_benchmark_comparison,
+            ai_trader_get_performance_metrics,
             ai_trader_get_compounding_forecast,
             ai_trader_get_prediction_accuracy,
             ai_trader_evaluate_predictions,
@@ -4250,6 +5261,10 @@ pub fn run() {
             ai_trader_switch_mode,
             ai_trader_get_circuit_breaker,
             ai_trader_update_circuit_breaker,
+            ai_trader_set_exit_rules,
+            ai_trader_set_position_sizing,
+            ai_trader_replay,
+            ai_trader_check_stops,
             ai_trader_get_rejections,
             ai_trader_get_circuit_breaker_events,
             // Reports commands
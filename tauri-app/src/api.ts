@@ -136,8 +136,8 @@ export async function getMacroData(): Promise<MacroData[]> {
     return invoke('get_macro_data');
 }
 
-export async function calculateIndicators(symbol: string): Promise<CommandResult> {
-    return invoke('calculate_indicators', { symbol });
+export async function calculateIndicators(symbol: string, timeframes?: string[]): Promise<CommandResult> {
+    return invoke('calculate_indicators', { symbol, timeframes });
 }
 
 export async function getIndicators(symbol: string): Promise<IndicatorData[]> {